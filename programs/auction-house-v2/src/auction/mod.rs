@@ -0,0 +1,1190 @@
+//! Timed English (ascending) auctions over a regular listing.
+//! `create_auction` lists the token through `sell_logic` at a seller trade state keyed by `AUCTION` in place of a
+//! price, so no other sale path can fill it, and records an `AuctionState` PDA keyed by that trade state. Each
+//! `place_auction_bid` moves the bid out of the bidder's escrow payment account into an escrow payment account
+//! derived from the auction state, where it stays locked until outbid. Once the auction ends anyone can call
+//! `settle_auction`, which hands the winning bid to `execute_sale_logic`. `cancel_auction` pulls the listing,
+//! after which settling returns the highest bid.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program::invoke, program_option::COption},
+    AnchorDeserialize,
+};
+
+use crate::{
+    cancel::*, constants::*, errors::*, execute_sale::*, sell::*, utils::*, AuctionHouse, *,
+};
+
+/// Accounts for the [`create_auction` handler](auction_house/fn.create_auction.html).
+#[derive(Accounts, Clone)]
+#[instruction(
+    trade_state_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    token_size: u64
+)]
+pub struct CreateAuction<'info> {
+    /// CHECK: Signs as the payer for the auction state.
+    /// Seller wallet account.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in the trade state seeds, owned by either token program.
+    /// SPL token account containing token for sale.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Metaplex metadata account decorating SPL mint account.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Auction House authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Account<'info, AuctionHouse>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account encoding the auction listing, which has no fixed price.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            AUCTION.as_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Free seller trade state PDA account encoding a free sell order.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            &0u64.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub free_seller_trade_state: UncheckedAccount<'info>,
+
+    /// Auction state PDA account.
+    #[account(
+        init,
+        payer=wallet,
+        space=AUCTION_STATE_SIZE,
+        seeds = [
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            seller_trade_state.key().as_ref()
+        ],
+        bump
+    )]
+    pub auction_state: Box<Account<'info, AuctionState>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow payment account PDA locking the highest bid.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            auction_house.key().as_ref(),
+            auction_state.key().as_ref()
+        ],
+        bump
+    )]
+    pub auction_escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account of a dutch listing of the same token, which must not be open.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            DUTCH.as_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub dutch_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> From<CreateAuction<'info>> for Sell<'info> {
+    fn from(a: CreateAuction<'info>) -> Sell<'info> {
+        Sell {
+            wallet: a.wallet,
+            token_account: a.token_account,
+            metadata: a.metadata,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            seller_trade_state: a.seller_trade_state,
+            free_seller_trade_state: a.free_seller_trade_state,
+            token_program: a.token_program,
+            system_program: a.system_program,
+            program_as_signer: a.program_as_signer,
+            rent: a.rent,
+        }
+    }
+}
+
+/// Accounts for the [`place_auction_bid` handler](auction_house/fn.place_auction_bid.html).
+#[derive(Accounts)]
+#[instruction(escrow_payment_bump: u8, bid_price: u64)]
+pub struct PlaceAuctionBid<'info> {
+    /// Bidder wallet account.
+    pub wallet: Signer<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Bidder escrow payment account funding the bid.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            wallet.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in place_auction_bid_logic.
+    /// Escrow payment account of the current highest bidder, refunded when outbid.
+    /// Ignored while the auction has no bids.
+    #[account(mut)]
+    pub previous_bidder_escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=treasury_mint
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Checked against the auction state.
+    /// Seller trade state PDA account encoding the auction listing.
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the auction state.
+    /// Token account where the SPL token is stored.
+    pub token_account: UncheckedAccount<'info>,
+
+    /// Auction state PDA account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            seller_trade_state.key().as_ref()
+        ],
+        bump=auction_state.bump,
+        has_one=auction_house,
+        has_one=seller_trade_state,
+        has_one=token_account
+    )]
+    pub auction_state: Box<Account<'info, AuctionState>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow payment account PDA locking the highest bid.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            auction_house.key().as_ref(),
+            auction_state.key().as_ref()
+        ],
+        bump=auction_state.escrow_payment_bump
+    )]
+    pub auction_escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+}
+
+/// Accounts for the [`settle_auction` handler](auction_house/fn.settle_auction.html).
+#[derive(Accounts, Clone)]
+#[instruction(
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8
+)]
+pub struct SettleAuction<'info> {
+    /// CHECK: Validated in settle_auction_logic.
+    /// Highest bidder wallet account. Any account when the auction has no bids.
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the auction state.
+    /// Seller user wallet account.
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the auction state.
+    /// Token account where the SPL token is stored.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the auction state.
+    /// Token mint account for the SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow token account receiving the auctioned token.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            seller.key().as_ref(),
+            token_mint.key().as_ref(),
+            buyer.key().as_ref(),
+            NFT.as_bytes()
+        ],
+        bump
+    )]
+    pub escrow_nft_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow NFT record, created with the escrow NFT account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            ESCROW_NFT_RECORD.as_bytes(),
+            escrow_nft_account.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_nft_record: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Metaplex metadata account decorating SPL mint account.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Auction House treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            buyer.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Seller SOL or SPL account to receive payment at.
+    #[account(mut)]
+    pub seller_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer SPL token account to receive purchased item at.
+    #[account(mut)]
+    pub buyer_receipt_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Auction House instance authority.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_treasury,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance treasury account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            TREASURY.as_bytes()
+        ],
+        bump=auction_house.treasury_bump
+    )]
+    pub auction_house_treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in settle_auction_logic.
+    /// Buyer trade state PDA account for the winning bid, created during settlement.
+    #[account(mut)]
+    pub buyer_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the auction state.
+    /// Seller trade state PDA account encoding the auction listing.
+    #[account(mut)]
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Free seller trade state PDA account encoding a free sell order.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            &0u64.to_le_bytes(),
+            &auction_state.token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub free_trade_state: UncheckedAccount<'info>,
+
+    /// Auction state PDA account, closed to the seller once settled.
+    #[account(
+        mut,
+        close=seller,
+        seeds = [
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            seller_trade_state.key().as_ref()
+        ],
+        bump=auction_state.bump,
+        has_one=auction_house,
+        has_one=seller,
+        has_one=seller_trade_state,
+        has_one=token_account,
+        has_one=token_mint
+    )]
+    pub auction_state: Box<Account<'info, AuctionState>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow payment account PDA locking the highest bid.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            auction_house.key().as_ref(),
+            auction_state.key().as_ref()
+        ],
+        bump=auction_state.escrow_payment_bump
+    )]
+    pub auction_escrow_payment_account: UncheckedAccount<'info>,
+
+    /// Account paying for settlement; anyone can settle an ended auction.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> From<SettleAuction<'info>> for ExecuteSale<'info> {
+    fn from(a: SettleAuction<'info>) -> ExecuteSale<'info> {
+        ExecuteSale {
+            buyer: a.buyer,
+            seller: a.seller,
+            token_account: a.token_account,
+            token_mint: a.token_mint,
+            escrow_nft_account: a.escrow_nft_account,
+            escrow_nft_record: a.escrow_nft_record,
+            metadata: a.metadata,
+            treasury_mint: a.treasury_mint,
+            escrow_payment_account: a.escrow_payment_account,
+            seller_payment_receipt_account: a.seller_payment_receipt_account,
+            buyer_receipt_token_account: a.buyer_receipt_token_account,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            auction_house_treasury: a.auction_house_treasury,
+            buyer_trade_state: a.buyer_trade_state,
+            seller_trade_state: a.seller_trade_state,
+            free_trade_state: a.free_trade_state,
+            token_program: a.token_program,
+            system_program: a.system_program,
+            ata_program: a.ata_program,
+            program_as_signer: a.program_as_signer,
+            rent: a.rent,
+        }
+    }
+}
+
+/// Accounts for the [`cancel_auction` handler](auction_house/fn.cancel_auction.html).
+#[derive(Accounts, Clone)]
+pub struct CancelAuction<'info> {
+    /// CHECK: Checked against the auction state, validated as a signer in cancel_logic.
+    /// Seller wallet account.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in cancel_logic, owned by either token program.
+    /// SPL token account containing the auctioned token.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the token account mint in cancel_logic.
+    /// Token mint account of SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in cancel_logic.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the auction state, validated in cancel_logic.
+    /// Seller trade state PDA account encoding the auction listing.
+    #[account(mut)]
+    pub trade_state: UncheckedAccount<'info>,
+
+    /// Auction state PDA account, left open for `settle_auction` to return the highest bid.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            trade_state.key().as_ref()
+        ],
+        bump=auction_state.bump,
+        has_one=auction_house,
+        has_one=token_account
+    )]
+    pub auction_state: Box<Account<'info, AuctionState>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+}
+
+impl<'info> From<CancelAuction<'info>> for Cancel<'info> {
+    fn from(a: CancelAuction<'info>) -> Cancel<'info> {
+        Cancel {
+            wallet: a.wallet,
+            token_account: a.token_account,
+            token_mint: a.token_mint,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            trade_state: a.trade_state,
+            token_program: a.token_program,
+        }
+    }
+}
+
+/// List a token for an English auction running from `start_time` to `end_time` (unix seconds).
+/// Bids within `extension_window` seconds of the end push the end back to `extension_window` seconds after the bid.
+#[allow(clippy::too_many_arguments)]
+pub fn create_auction<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateAuction<'info>>,
+    trade_state_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    token_size: u64,
+    start_time: i64,
+    end_time: i64,
+    reserve_price: u64,
+    min_increment: u64,
+    extension_window: i64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // Auction listings are sell orders, so they follow a delegated Sell scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Sell as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let clock = Clock::get()?;
+    if end_time <= start_time || end_time <= clock.unix_timestamp || extension_window < 0 {
+        return Err(AuctionHouseError::InvalidAuctionTimes.into());
+    }
+
+    let trade_state_canonical_bump = *ctx
+        .bumps
+        .get("seller_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let free_trade_state_canonical_bump = *ctx
+        .bumps
+        .get("free_seller_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let auction_state_bump = *ctx
+        .bumps
+        .get("auction_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let escrow_payment_bump = *ctx
+        .bumps
+        .get("auction_escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if (trade_state_canonical_bump != trade_state_bump)
+        || (free_trade_state_canonical_bump != free_trade_state_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    // Whichever listing sold first would leave the other one's buyers without the token.
+    if trade_state_is_open(&ctx.accounts.dutch_trade_state)? {
+        return Err(AuctionHouseError::ListingConflict.into());
+    }
+
+    let mut accounts: Sell<'info> = (*ctx.accounts).clone().into();
+    sell_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        ctx.program_id,
+        trade_state_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        u64::MAX,
+        token_size,
+        None,
+        None,
+        Some(AUCTION),
+    )?;
+
+    let wallet = &ctx.accounts.wallet;
+    let auction_escrow_payment_account = &ctx.accounts.auction_escrow_payment_account;
+    let auction_house = &ctx.accounts.auction_house;
+    let auction_house_key = auction_house.key();
+    let auction_state_key = ctx.accounts.auction_state.key();
+    let escrow_signer_seeds = [
+        PREFIX.as_bytes(),
+        AUCTION.as_bytes(),
+        auction_house_key.as_ref(),
+        auction_state_key.as_ref(),
+        &[escrow_payment_bump],
+    ];
+    let is_native = auction_house.treasury_mint == spl_token::native_mint::id();
+
+    // The seller pays for the locking escrow up front so that it stays rent exempt
+    // however the bids move through it; the rent is returned on settlement.
+    create_program_token_account_if_not_present(
+        auction_escrow_payment_account,
+        &ctx.accounts.system_program,
+        &wallet.to_account_info(),
+        &ctx.accounts.token_program,
+        &ctx.accounts.treasury_mint,
+        &auction_house.to_account_info(),
+        &ctx.accounts.rent,
+        &escrow_signer_seeds,
+        &[],
+        is_native,
+    )?;
+    if is_native {
+        let rent_shortfall = verify_deposit(auction_escrow_payment_account.to_account_info(), 0)?;
+        if rent_shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    &wallet.key(),
+                    &auction_escrow_payment_account.key(),
+                    rent_shortfall,
+                ),
+                &[
+                    wallet.to_account_info(),
+                    auction_escrow_payment_account.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+    }
+
+    let auction_state = &mut ctx.accounts.auction_state;
+    auction_state.auction_house = auction_house_key;
+    auction_state.seller = wallet.key();
+    auction_state.seller_trade_state = ctx.accounts.seller_trade_state.key();
+    auction_state.token_account = ctx.accounts.token_account.key();
+    auction_state.token_mint = get_mint_from_token_account(&ctx.accounts.token_account)?;
+    auction_state.token_size = token_size;
+    auction_state.start_time = start_time;
+    auction_state.end_time = end_time;
+    auction_state.reserve_price = reserve_price;
+    auction_state.min_increment = min_increment;
+    auction_state.extension_window = extension_window;
+    auction_state.highest_bidder = Pubkey::default();
+    auction_state.highest_bid = 0;
+    auction_state.bump = auction_state_bump;
+    auction_state.escrow_payment_bump = escrow_payment_bump;
+
+    Ok(())
+}
+
+pub fn place_auction_bid<'info>(
+    ctx: Context<'_, '_, '_, 'info, PlaceAuctionBid<'info>>,
+    escrow_payment_bump: u8,
+    bid_price: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // Auction bids are buy orders, so they follow a delegated Buy scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Buy as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let escrow_canonical_bump = *ctx
+        .bumps
+        .get("escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if escrow_canonical_bump != escrow_payment_bump {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    place_auction_bid_logic(ctx.accounts, escrow_payment_bump, bid_price)
+}
+
+/// Lock `bid_price` from the bidder's escrow as the new highest bid, releasing the previous highest bid back to its bidder's escrow.
+fn place_auction_bid_logic(
+    accounts: &mut PlaceAuctionBid,
+    escrow_payment_bump: u8,
+    bid_price: u64,
+) -> Result<()> {
+    let wallet = &accounts.wallet;
+    let escrow_payment_account = &accounts.escrow_payment_account;
+    let previous_bidder_escrow_payment_account = &accounts.previous_bidder_escrow_payment_account;
+    let auction_house = &accounts.auction_house;
+    let auction_escrow_payment_account = &accounts.auction_escrow_payment_account;
+    let treasury_mint = &accounts.treasury_mint;
+    let token_program = &accounts.token_program;
+    let system_program = &accounts.system_program;
+    let auction_state = &mut accounts.auction_state;
+
+    let clock = Clock::get()?;
+    if clock.unix_timestamp < auction_state.start_time
+        || clock.unix_timestamp >= auction_state.end_time
+    {
+        return Err(AuctionHouseError::AuctionNotActive.into());
+    }
+    // A seller bidding up their own auction would only inflate the price others pay.
+    if wallet.key() == auction_state.seller {
+        return Err(AuctionHouseError::SellerCannotBid.into());
+    }
+    // A canceled listing can no longer sell, so it takes no new bids.
+    if !listing_is_live(
+        &accounts.seller_trade_state,
+        &accounts.token_account,
+        &accounts.program_as_signer,
+        auction_state.token_size,
+    )? {
+        return Err(AuctionHouseError::AuctionListingClosed.into());
+    }
+
+    let has_bid = auction_state.highest_bidder != Pubkey::default();
+    let minimum_bid = if has_bid {
+        auction_state
+            .highest_bid
+            .checked_add(auction_state.min_increment.max(1))
+            .ok_or(AuctionHouseError::NumericalOverflow)?
+    } else {
+        auction_state.reserve_price.max(1)
+    };
+    if bid_price < minimum_bid {
+        return Err(AuctionHouseError::BidTooLow.into());
+    }
+
+    let auction_house_key = auction_house.key();
+    let auction_state_key = auction_state.key();
+
+    if has_bid {
+        assert_derivation(
+            &crate::id(),
+            &previous_bidder_escrow_payment_account.to_account_info(),
+            &[
+                PREFIX.as_bytes(),
+                auction_house_key.as_ref(),
+                auction_state.highest_bidder.as_ref(),
+            ],
+        )?;
+        let auction_escrow_signer_seeds = [
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            auction_house_key.as_ref(),
+            auction_state_key.as_ref(),
+            &[auction_state.escrow_payment_bump],
+        ];
+        transfer_between_escrows(
+            auction_house,
+            &auction_escrow_payment_account.to_account_info(),
+            &previous_bidder_escrow_payment_account.to_account_info(),
+            &auction_escrow_signer_seeds,
+            treasury_mint,
+            token_program,
+            system_program,
+            auction_state.highest_bid,
+        )?;
+    }
+
+    let wallet_key = wallet.key();
+    let escrow_signer_seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        wallet_key.as_ref(),
+        &[escrow_payment_bump],
+    ];
+    transfer_between_escrows(
+        auction_house,
+        &escrow_payment_account.to_account_info(),
+        &auction_escrow_payment_account.to_account_info(),
+        &escrow_signer_seeds,
+        treasury_mint,
+        token_program,
+        system_program,
+        bid_price,
+    )?;
+
+    auction_state.highest_bidder = wallet_key;
+    auction_state.highest_bid = bid_price;
+
+    // Anti-sniping: a late bid keeps the auction open for another extension window.
+    let extended_end_time = clock
+        .unix_timestamp
+        .checked_add(auction_state.extension_window)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    if extended_end_time > auction_state.end_time {
+        auction_state.end_time = extended_end_time;
+    }
+
+    Ok(())
+}
+
+pub fn settle_auction<'info>(
+    ctx: Context<'_, '_, '_, 'info, SettleAuction<'info>>,
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // Settling sells the listing, so it follows a delegated ExecuteSale scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::ExecuteSale as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let escrow_canonical_bump = *ctx
+        .bumps
+        .get("escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let free_trade_state_canonical_bump = *ctx
+        .bumps
+        .get("free_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let escrow_nft_canonical_bump = *ctx
+        .bumps
+        .get("escrow_nft_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if (escrow_canonical_bump != escrow_payment_bump)
+        || (free_trade_state_canonical_bump != free_trade_state_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+        || (escrow_nft_canonical_bump != escrow_nft_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    settle_auction_logic(
+        ctx.accounts,
+        ctx.remaining_accounts,
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+    )
+}
+
+/// Settle an ended auction: sell to the highest bidder through `execute_sale_logic` if the listing is still
+/// live, otherwise release the highest bid back to its bidder and close the listing. The locking escrow is
+/// emptied to the seller.
+fn settle_auction_logic<'info>(
+    accounts: &mut SettleAuction<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    if clock.unix_timestamp < accounts.auction_state.end_time {
+        return Err(AuctionHouseError::AuctionNotEnded.into());
+    }
+
+    let auction_house = &accounts.auction_house;
+    let auction_state = &accounts.auction_state;
+    let auction_escrow_payment_account = &accounts.auction_escrow_payment_account;
+    let treasury_mint = &accounts.treasury_mint;
+    let token_program = &accounts.token_program;
+    let system_program = &accounts.system_program;
+
+    let auction_house_key = auction_house.key();
+    let auction_state_key = auction_state.key();
+    let auction_escrow_signer_seeds = [
+        PREFIX.as_bytes(),
+        AUCTION.as_bytes(),
+        auction_house_key.as_ref(),
+        auction_state_key.as_ref(),
+        &[auction_state.escrow_payment_bump],
+    ];
+
+    let has_bid = auction_state.highest_bidder != Pubkey::default();
+    if has_bid {
+        assert_keys_equal(accounts.buyer.key(), auction_state.highest_bidder)?;
+
+        // The winning bid goes back to the winner's escrow, which execute_sale_logic pays out of.
+        // If the seller pulled the listing it simply stays there for the bidder to withdraw.
+        transfer_between_escrows(
+            auction_house,
+            &auction_escrow_payment_account.to_account_info(),
+            &accounts.escrow_payment_account.to_account_info(),
+            &auction_escrow_signer_seeds,
+            treasury_mint,
+            token_program,
+            system_program,
+            auction_state.highest_bid,
+        )?;
+    }
+
+    // The sale closes its trade states by moving lamports directly, so the escrow is released first.
+    release_auction_escrow(accounts, &auction_escrow_signer_seeds)?;
+
+    let sold = has_bid
+        && listing_is_live(
+            &accounts.seller_trade_state,
+            &accounts.token_account,
+            &accounts.program_as_signer,
+            accounts.auction_state.token_size,
+        )?;
+    if sold {
+        let price = accounts.auction_state.highest_bid;
+        let token_size = accounts.auction_state.token_size;
+        let payer = accounts.payer.to_account_info();
+        let mut sale: ExecuteSale<'info> = accounts.clone().into();
+        create_buyer_trade_state(&sale, payer.clone(), AUCTION_BID, token_size)?;
+        execute_sale_logic(
+            &mut sale,
+            remaining_accounts,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            escrow_nft_bump,
+            price,
+            token_size,
+            None,
+            None,
+            None,
+            Some(payer),
+            Some(AUCTION_BID),
+        )?;
+    }
+
+    // An unsold listing is closed along with the auction, returning its rent to whoever paid it.
+    // This moves lamports directly, so it has to come after the last CPI.
+    let seller_trade_state = accounts.seller_trade_state.to_account_info();
+    if !sold && seller_trade_state.lamports() > 0 {
+        let seller = accounts.seller.to_account_info();
+        let rent_payer = trade_state_rent_payer_info(
+            &seller_trade_state,
+            &[
+                seller.clone(),
+                accounts.auction_house_fee_account.to_account_info(),
+            ],
+            &seller,
+        )?;
+        close_account(&seller_trade_state, &rent_payer)?;
+    }
+
+    Ok(())
+}
+
+/// Pull an auction listing. The auction state stays open, and once the auction ends `settle_auction` returns the
+/// highest bid to its bidder and closes it.
+pub fn cancel_auction<'info>(ctx: Context<'_, '_, '_, 'info, CancelAuction<'info>>) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auction_state = &ctx.accounts.auction_state;
+
+    // If the Cancel scope is delegated, cancels must go through auctioneer_cancel.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Cancel as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    assert_keys_equal(ctx.accounts.wallet.key(), auction_state.seller)?;
+    let trade_state = &ctx.accounts.trade_state;
+    if !trade_state_is_open(trade_state)? {
+        return Err(AuctionHouseError::AuctionListingClosed.into());
+    }
+
+    let token_size = auction_state.token_size;
+    let mut accounts: Cancel<'info> = (*ctx.accounts).clone().into();
+    cancel_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        u64::MAX,
+        token_size,
+        Some(AUCTION),
+    )
+}
+
+/// Whether the seller trade state is still open and the program can still move `token_size` listed tokens.
+fn listing_is_live(
+    seller_trade_state: &UncheckedAccount,
+    token_account: &UncheckedAccount,
+    program_as_signer: &UncheckedAccount,
+    token_size: u64,
+) -> Result<bool> {
+    if !trade_state_is_open(seller_trade_state)? {
+        return Ok(false);
+    }
+
+    let token_account = match unpack_token_account(token_account) {
+        Ok(token_account) => token_account,
+        Err(_) => return Ok(false),
+    };
+
+    Ok(
+        token_account.delegate == COption::Some(program_as_signer.key())
+            && token_account.delegated_amount >= token_size
+            && token_account.amount >= token_size,
+    )
+}
+
+/// Empty the locking escrow to the seller, who paid its rent in `create_auction`.
+fn release_auction_escrow(
+    accounts: &SettleAuction,
+    auction_escrow_signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let auction_house = &accounts.auction_house;
+    let auction_escrow_payment_account = &accounts.auction_escrow_payment_account;
+    let seller = &accounts.seller;
+
+    if auction_house.treasury_mint == spl_token::native_mint::id() {
+        invoke_signed(
+            &system_instruction::transfer(
+                auction_escrow_payment_account.key,
+                seller.key,
+                auction_escrow_payment_account.lamports(),
+            ),
+            &[
+                auction_escrow_payment_account.to_account_info(),
+                seller.to_account_info(),
+                accounts.system_program.to_account_info(),
+            ],
+            &[auction_escrow_signer_seeds],
+        )?;
+    } else {
+        let ah_seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            &[auction_house.bump],
+        ];
+        invoke_signed(
+            &spl_token_2022::instruction::close_account(
+                accounts.token_program.key,
+                auction_escrow_payment_account.key,
+                seller.key,
+                &auction_house.key(),
+                &[],
+            )?,
+            &[
+                auction_escrow_payment_account.to_account_info(),
+                seller.to_account_info(),
+                auction_house.to_account_info(),
+                accounts.token_program.to_account_info(),
+            ],
+            &[&ah_seeds],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Move `amount` from one escrow payment account to another. Native escrows sign for
+/// themselves with `escrow_signer_seeds`; token escrows are owned by the Auction House.
+#[allow(clippy::too_many_arguments)]
+fn transfer_between_escrows<'info>(
+    auction_house: &Account<'info, AuctionHouse>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    escrow_signer_seeds: &[&[u8]],
+    treasury_mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if auction_house.treasury_mint == spl_token::native_mint::id() {
+        // The source escrow must stay rent exempt.
+        if verify_withdrawal(from.clone(), amount)? > 0 {
+            return Err(AuctionHouseError::InsufficientFunds.into());
+        }
+        invoke_signed(
+            &system_instruction::transfer(from.key, to.key, amount),
+            &[from.clone(), to.clone(), system_program.to_account_info()],
+            &[escrow_signer_seeds],
+        )?;
+    } else {
+        let from_data = unpack_token_account(from)?;
+        if from_data.amount < amount {
+            return Err(AuctionHouseError::InsufficientFunds.into());
+        }
+        let ah_seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            &[auction_house.bump],
+        ];
+        transfer_tokens(
+            token_program,
+            from,
+            treasury_mint,
+            to,
+            &auction_house.to_account_info(),
+            amount,
+            &[&ah_seeds],
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! Delegate a subset of Auction House actions to an external Auctioneer authority.
+//! While a scope is delegated, the matching plain handler is rejected and only its `auctioneer_*` variant, co-signed by the Auctioneer authority, can be used.
+
+use anchor_lang::prelude::*;
+
+use crate::{constants::*, errors::*, AuctionHouse, Auctioneer, AuthorityScope};
+
+/// Accounts for the [`delegate_auctioneer` handler](auction_house/fn.delegate_auctioneer.html).
+#[derive(Accounts)]
+pub struct DelegateAuctioneer<'info> {
+    /// Auction House instance PDA account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Auction House instance authority account, paying for the Auctioneer PDA.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: The Auction House authority can delegate to any external address.
+    /// Auctioneer authority account that must co-sign the delegated actions.
+    pub auctioneer_authority: UncheckedAccount<'info>,
+
+    /// Auctioneer PDA account binding the Auctioneer authority to this Auction House.
+    #[account(
+        init,
+        payer=authority,
+        space=AUCTIONEER_SIZE,
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for the [`update_auctioneer` handler](auction_house/fn.update_auctioneer.html).
+#[derive(Accounts)]
+pub struct UpdateAuctioneer<'info> {
+    /// Auction House instance PDA account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Auction House instance authority account.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Used as a seed for the Auctioneer PDA.
+    /// Auctioneer authority account.
+    pub auctioneer_authority: UncheckedAccount<'info>,
+
+    /// Auctioneer PDA account.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        bump=ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for the [`revoke_auctioneer` handler](auction_house/fn.revoke_auctioneer.html).
+#[derive(Accounts)]
+pub struct RevokeAuctioneer<'info> {
+    /// Auction House instance PDA account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Auction House instance authority account, receiving the Auctioneer PDA rent.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Used as a seed for the Auctioneer PDA.
+    /// Auctioneer authority account.
+    pub auctioneer_authority: UncheckedAccount<'info>,
+
+    /// Auctioneer PDA account.
+    #[account(
+        mut,
+        close=authority,
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        bump=ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn delegate_auctioneer<'info>(
+    ctx: Context<'_, '_, '_, 'info, DelegateAuctioneer<'info>>,
+    scopes: Vec<AuthorityScope>,
+) -> Result<()> {
+    let auction_house = &mut ctx.accounts.auction_house;
+    let ah_auctioneer_pda = &mut ctx.accounts.ah_auctioneer_pda;
+
+    if auction_house.has_auctioneer {
+        return Err(AuctionHouseError::AuctionHouseAlreadyDelegated.into());
+    }
+
+    ah_auctioneer_pda.auctioneer_authority = ctx.accounts.auctioneer_authority.key();
+    ah_auctioneer_pda.auction_house = auction_house.key();
+    ah_auctioneer_pda.bump = *ctx
+        .bumps
+        .get("ah_auctioneer_pda")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    auction_house.scopes = scopes_to_array(scopes)?;
+    auction_house.has_auctioneer = true;
+    auction_house.auctioneer_address = ah_auctioneer_pda.key();
+
+    Ok(())
+}
+
+pub fn update_auctioneer<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpdateAuctioneer<'info>>,
+    scopes: Vec<AuthorityScope>,
+) -> Result<()> {
+    let auction_house = &mut ctx.accounts.auction_house;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::AuctionHouseNotDelegated.into());
+    }
+    if auction_house.auctioneer_address != ah_auctioneer_pda.key() {
+        return Err(AuctionHouseError::AuctioneerAuthorityMismatch.into());
+    }
+
+    auction_house.scopes = scopes_to_array(scopes)?;
+
+    Ok(())
+}
+
+pub fn revoke_auctioneer<'info>(
+    ctx: Context<'_, '_, '_, 'info, RevokeAuctioneer<'info>>,
+) -> Result<()> {
+    let auction_house = &mut ctx.accounts.auction_house;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::AuctionHouseNotDelegated.into());
+    }
+    if auction_house.auctioneer_address != ah_auctioneer_pda.key() {
+        return Err(AuctionHouseError::AuctioneerAuthorityMismatch.into());
+    }
+
+    auction_house.scopes = [false; MAX_NUM_SCOPES];
+    auction_house.has_auctioneer = false;
+    auction_house.auctioneer_address = Pubkey::default();
+
+    Ok(())
+}
+
+/// Set exactly the given scopes, clearing every other one.
+fn scopes_to_array(scopes: Vec<AuthorityScope>) -> Result<[bool; MAX_NUM_SCOPES]> {
+    if scopes.len() > MAX_NUM_SCOPES {
+        return Err(AuctionHouseError::TooManyScopes.into());
+    }
+
+    let mut scopes_array = [false; MAX_NUM_SCOPES];
+    for scope in scopes {
+        scopes_array[scope as usize] = true;
+    }
+
+    Ok(scopes_array)
+}
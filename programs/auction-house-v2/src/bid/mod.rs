@@ -6,137 +6,338 @@ use anchor_lang::{
     solana_program::{program::invoke, system_instruction},
     AnchorDeserialize,
 };
-use anchor_spl::token::{Mint, Token, TokenAccount};
-use solana_program::program_memory::sol_memset;
 
 use crate::{
-    constants::*, errors::AuctionHouseError, utils::*, AuctionHouse,
-    TRADE_STATE_SIZE,
+    constants::*, errors::AuctionHouseError, events::*, utils::*, AuctionHouse, Auctioneer,
+    AuthorityScope, Referral, TRADE_STATE_SIZE,
 };
 
 /// Accounts for the [`public_bid` handler](fn.public_bid.html).
-// #[derive(Accounts)]
-// #[instruction(
-//     trade_state_bump: u8,
-//     escrow_payment_bump: u8,
-//     buyer_price: u64,
-//     token_size: u64
-// )]
-// pub struct PublicBuy<'info> {
-//     wallet: Signer<'info>,
-
-//     /// CHECK: Validated in public_bid_logic.
-//     #[account(mut)]
-//     payment_account: UncheckedAccount<'info>,
-
-//     /// CHECK: Validated in public_bid_logic.
-//     transfer_authority: UncheckedAccount<'info>,
-
-//     treasury_mint: Box<Account<'info, Mint>>,
-//     token_account: Box<Account<'info, TokenAccount>>,
-
-//     /// CHECK: Validated in public_bid_logic.
-//     metadata: UncheckedAccount<'info>,
-
-//     /// CHECK: Not dangerous. Account seeds checked in constraint.
-//     #[account(
-//         mut,
-//         seeds = [
-//             PREFIX.as_bytes(),
-//             auction_house.key().as_ref(),
-//             wallet.key().as_ref()
-//         ],
-//         bump
-//     )]
-//     escrow_payment_account: UncheckedAccount<'info>,
-
-//     /// CHECK: Verified with has_one constraint on auction house account.
-//     authority: UncheckedAccount<'info>,
-
-//     /// CHECK: Not dangerous. Account seeds checked in constraint.
-//     #[account(
-//         seeds = [
-//             PREFIX.as_bytes(),
-//             auction_house.creator.as_ref(),
-//             auction_house.treasury_mint.as_ref()
-//         ],
-//         bump = auction_house.bump,
-//         has_one = authority,
-//         has_one = treasury_mint,
-//         has_one = auction_house_fee_account
-//     )]
-//     auction_house: Box<Account<'info, AuctionHouse>>,
-
-//     /// CHECK: Not dangerous. Account seeds checked in constraint.
-//     #[account(
-//         mut,
-//         seeds = [
-//             PREFIX.as_bytes(),
-//             auction_house.key().as_ref(),
-//             FEE_PAYER.as_bytes()
-//         ],
-//         bump = auction_house.fee_payer_bump
-//     )]
-//     auction_house_fee_account: UncheckedAccount<'info>,
-
-//     /// CHECK: Not dangerous. Account seeds checked in constraint.
-//     #[account(
-//         mut,
-//         seeds = [
-//             PREFIX.as_bytes(),
-//             wallet.key().as_ref(),
-//             auction_house.key().as_ref(),
-//             treasury_mint.key().as_ref(),
-//             token_account.mint.as_ref(),
-//             buyer_price.to_le_bytes().as_ref(),
-//             token_size.to_le_bytes().as_ref()
-//         ],
-//         bump
-//     )]
-//     buyer_trade_state: UncheckedAccount<'info>,
-
-//     token_program: Program<'info, Token>,
-//     system_program: Program<'info, System>,
-//     rent: Sysvar<'info, Rent>,
-// }
+#[derive(Accounts)]
+#[instruction(
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    buyer_price: u64,
+    token_size: u64
+)]
+pub struct PublicBuy<'info> {
+    /// User wallet account.
+    wallet: Signer<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// User SOL or SPL account to transfer funds from.
+    #[account(mut)]
+    payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// SPL token account transfer authority.
+    transfer_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House instance treasury mint account.
+    treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in the trade state seeds, owned by either token program.
+    /// SPL token account of any holder of the mint, used to look up the mint and metadata.
+    token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// SPL token account metadata.
+    metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            wallet.key().as_ref()
+        ],
+        bump
+    )]
+    escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// Auction House instance authority account.
+    authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump = auction_house.bump,
+        has_one = authority,
+        has_one = treasury_mint,
+        has_one = auction_house_fee_account
+    )]
+    auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump = auction_house.fee_payer_bump
+    )]
+    auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer trade state PDA. Public bids leave the token account out of the seeds.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            treasury_mint.key().as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            buyer_price.to_le_bytes().as_ref(),
+            token_size.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    buyer_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    token_program: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
 
 /// Create a bid on a specific SPL token.
-/// Public bids are specific to the token itself, rather than the auction, and remain open indefinitely until either the user closes it or the requirements for the bid are met and it is matched with a counter bid and closed as a transaction.
-// pub fn public_bid(
-//     ctx: Context<PublicBuy>,
-//     trade_state_bump: u8,
-//     escrow_payment_bump: u8,
-//     buyer_price: u64,
-//     token_size: u64,
-// ) -> Result<()> {
-//     bid_logic(
-//         ctx.accounts.wallet.to_owned(),
-//         ctx.accounts.payment_account.to_owned(),
-//         ctx.accounts.transfer_authority.to_owned(),
-//         *ctx.accounts.treasury_mint.to_owned(),
-//         *ctx.accounts.token_account.to_owned(),
-//         ctx.accounts.metadata.to_owned(),
-//         ctx.accounts.escrow_payment_account.to_owned(),
-//         ctx.accounts.authority.to_owned(),
-//         *ctx.accounts.auction_house.to_owned(),
-//         ctx.accounts.auction_house_fee_account.to_owned(),
-//         ctx.accounts.buyer_trade_state.to_owned(),
-//         ctx.accounts.token_program.to_owned(),
-//         ctx.accounts.system_program.to_owned(),
-//         ctx.accounts.rent.to_owned(),
-//         trade_state_bump,
-//         escrow_payment_bump,
-//         buyer_price,
-//         token_size,
-//         true,
-//         *ctx.bumps
-//             .get("escrow_payment_account")
-//             .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
-//         *ctx.bumps
-//             .get("buyer_trade_state")
-//             .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
-//     )
-// }
+/// Public bids are specific to the token itself, rather than the auction, and remain open until either the user closes it, it passes its optional expiry, or the requirements for the bid are met and it is matched with a counter bid and closed as a transaction.
+#[allow(clippy::too_many_arguments)]
+pub fn public_bid<'info>(
+    ctx: Context<'_, '_, '_, 'info, PublicBuy<'info>>,
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
+    royalty_choice: Option<u16>,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // If the PublicBuy scope is delegated, public bids must go through the Auctioneer.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::PublicBuy as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    bid_logic(
+        ctx.accounts.wallet.to_owned(),
+        ctx.accounts.payment_account.to_owned(),
+        ctx.accounts.transfer_authority.to_owned(),
+        ctx.accounts.treasury_mint.to_owned(),
+        ctx.accounts.token_account.to_owned(),
+        ctx.accounts.metadata.to_owned(),
+        ctx.accounts.escrow_payment_account.to_owned(),
+        ctx.accounts.authority.to_owned(),
+        *ctx.accounts.auction_house.to_owned(),
+        ctx.accounts.auction_house_fee_account.to_owned(),
+        ctx.accounts.buyer_trade_state.to_owned(),
+        ctx.accounts.token_program.to_owned(),
+        ctx.accounts.system_program.to_owned(),
+        ctx.accounts.rent.to_owned(),
+        trade_state_bump,
+        escrow_payment_bump,
+        buyer_price,
+        token_size,
+        expiry,
+        referral,
+        royalty_choice,
+        true,
+        *ctx.bumps
+            .get("escrow_payment_account")
+            .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
+        *ctx.bumps
+            .get("buyer_trade_state")
+            .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
+    )
+}
+
+/// Accounts for the [`auctioneer_public_bid` handler](fn.auctioneer_public_bid.html).
+#[derive(Accounts)]
+#[instruction(
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    buyer_price: u64,
+    token_size: u64
+)]
+pub struct AuctioneerPublicBuy<'info> {
+    /// User wallet account.
+    wallet: Signer<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// User SOL or SPL account to transfer funds from.
+    #[account(mut)]
+    payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// SPL token account transfer authority.
+    transfer_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House instance treasury mint account.
+    treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in the trade state seeds, owned by either token program.
+    /// SPL token account of any holder of the mint, used to look up the mint and metadata.
+    token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// SPL token account metadata.
+    metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            wallet.key().as_ref()
+        ],
+        bump
+    )]
+    escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// Auction House instance authority account.
+    authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump = auction_house.bump,
+        has_one = authority,
+        has_one = treasury_mint,
+        has_one = auction_house_fee_account
+    )]
+    auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump = auction_house.fee_payer_bump
+    )]
+    auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer trade state PDA. Public bids leave the token account out of the seeds.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            treasury_mint.key().as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            buyer_price.to_le_bytes().as_ref(),
+            token_size.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    buyer_trade_state: UncheckedAccount<'info>,
+
+    /// The Auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    auctioneer_authority: Signer<'info>,
+
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        bump = ah_auctioneer_pda.bump
+    )]
+    ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    token_program: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Create a public bid through the Auctioneer delegated the PublicBuy scope.
+pub fn auctioneer_public_bid<'info>(
+    ctx: Context<'_, '_, '_, 'info, AuctioneerPublicBuy<'info>>,
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auctioneer_authority = &ctx.accounts.auctioneer_authority;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::NoAuctioneerProgramSet.into());
+    }
+
+    assert_valid_auctioneer_and_scope(
+        auction_house,
+        &auctioneer_authority.key(),
+        ah_auctioneer_pda,
+        AuthorityScope::PublicBuy,
+    )?;
+
+    bid_logic(
+        ctx.accounts.wallet.to_owned(),
+        ctx.accounts.payment_account.to_owned(),
+        ctx.accounts.transfer_authority.to_owned(),
+        ctx.accounts.treasury_mint.to_owned(),
+        ctx.accounts.token_account.to_owned(),
+        ctx.accounts.metadata.to_owned(),
+        ctx.accounts.escrow_payment_account.to_owned(),
+        ctx.accounts.authority.to_owned(),
+        *ctx.accounts.auction_house.to_owned(),
+        ctx.accounts.auction_house_fee_account.to_owned(),
+        ctx.accounts.buyer_trade_state.to_owned(),
+        ctx.accounts.token_program.to_owned(),
+        ctx.accounts.system_program.to_owned(),
+        ctx.accounts.rent.to_owned(),
+        trade_state_bump,
+        escrow_payment_bump,
+        buyer_price,
+        token_size,
+        None,
+        None,
+        None,
+        true,
+        *ctx.bumps
+            .get("escrow_payment_account")
+            .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
+        *ctx.bumps
+            .get("buyer_trade_state")
+            .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
+    )
+}
 
 /// Accounts for the [`private_bid` handler](fn.private_bid.html).
 #[derive(Accounts)]
@@ -159,11 +360,13 @@ pub struct Buy<'info> {
     /// SPL token account transfer authority.
     transfer_authority: UncheckedAccount<'info>,
 
+    /// CHECK: Checked against the Auction House treasury mint.
     /// Auction House instance treasury mint account.
-    treasury_mint: Account<'info, Mint>,
+    treasury_mint: UncheckedAccount<'info>,
 
+    /// CHECK: Unpacked in the trade state seeds, owned by either token program.
     /// SPL token account.
-    token_account: Box<Account<'info, TokenAccount>>,
+    token_account: UncheckedAccount<'info>,
 
     /// CHECK: Validated in bid_logic.
     /// SPL token account metadata.
@@ -223,7 +426,7 @@ pub struct Buy<'info> {
             auction_house.key().as_ref(),
             token_account.key().as_ref(),
             treasury_mint.key().as_ref(),
-            token_account.mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
             buyer_price.to_le_bytes().as_ref(),
             token_size.to_le_bytes().as_ref()
         ],
@@ -231,25 +434,40 @@ pub struct Buy<'info> {
     )]
     buyer_trade_state: UncheckedAccount<'info>,
 
-    token_program: Program<'info, Token>,
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    token_program: UncheckedAccount<'info>,
     system_program: Program<'info, System>,
     rent: Sysvar<'info, Rent>,
 }
 
 /// Create a private bid on a specific SPL token that is *held by a specific wallet*.
+#[allow(clippy::too_many_arguments)]
 pub fn private_bid<'info>(
     ctx: Context<'_, '_, '_, 'info, Buy<'info>>,
     trade_state_bump: u8,
     escrow_payment_bump: u8,
     buyer_price: u64,
     token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
+    royalty_choice: Option<u16>,
 ) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // If the Buy scope is delegated, private bids must go through the Auctioneer.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Buy as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
     bid_logic(
         ctx.accounts.wallet.to_owned(),
         ctx.accounts.payment_account.to_owned(),
         ctx.accounts.transfer_authority.to_owned(),
         ctx.accounts.treasury_mint.to_owned(),
-        *ctx.accounts.token_account.to_owned(),
+        ctx.accounts.token_account.to_owned(),
         ctx.accounts.metadata.to_owned(),
         ctx.accounts.escrow_payment_account.to_owned(),
         ctx.accounts.authority.to_owned(),
@@ -263,6 +481,9 @@ pub fn private_bid<'info>(
         escrow_payment_bump,
         buyer_price,
         token_size,
+        expiry,
+        referral,
+        royalty_choice,
         false,
         *ctx.bumps
             .get("escrow_payment_account")
@@ -274,31 +495,208 @@ pub fn private_bid<'info>(
 }
 
 
+/// Accounts for the [`auctioneer_private_bid` handler](fn.auctioneer_private_bid.html).
+#[derive(Accounts)]
+#[instruction(
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    buyer_price: u64,
+    token_size: u64
+)]
+pub struct AuctioneerBuy<'info> {
+    /// User wallet account.
+    wallet: Signer<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// User SOL or SPL account to transfer funds from.
+    #[account(mut)]
+    payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// SPL token account transfer authority.
+    transfer_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House instance treasury mint account.
+    treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in the trade state seeds, owned by either token program.
+    /// SPL token account.
+    token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// SPL token account metadata.
+    metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            wallet.key().as_ref()
+        ],
+        bump
+    )]
+    escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in bid_logic.
+    /// Auction House instance authority account.
+    authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump = auction_house.bump,
+        has_one = authority,
+        has_one = treasury_mint,
+        has_one = auction_house_fee_account
+    )]
+    auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump = auction_house.fee_payer_bump
+    )]
+    auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer trade state PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            treasury_mint.key().as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            buyer_price.to_le_bytes().as_ref(),
+            token_size.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    buyer_trade_state: UncheckedAccount<'info>,
+
+    /// The Auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    auctioneer_authority: Signer<'info>,
+
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        bump = ah_auctioneer_pda.bump
+    )]
+    ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    token_program: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Create a private bid through the Auctioneer delegated the Buy scope.
+pub fn auctioneer_private_bid<'info>(
+    ctx: Context<'_, '_, '_, 'info, AuctioneerBuy<'info>>,
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auctioneer_authority = &ctx.accounts.auctioneer_authority;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::NoAuctioneerProgramSet.into());
+    }
+
+    assert_valid_auctioneer_and_scope(
+        auction_house,
+        &auctioneer_authority.key(),
+        ah_auctioneer_pda,
+        AuthorityScope::Buy,
+    )?;
+
+    bid_logic(
+        ctx.accounts.wallet.to_owned(),
+        ctx.accounts.payment_account.to_owned(),
+        ctx.accounts.transfer_authority.to_owned(),
+        ctx.accounts.treasury_mint.to_owned(),
+        ctx.accounts.token_account.to_owned(),
+        ctx.accounts.metadata.to_owned(),
+        ctx.accounts.escrow_payment_account.to_owned(),
+        ctx.accounts.authority.to_owned(),
+        *ctx.accounts.auction_house.to_owned(),
+        ctx.accounts.auction_house_fee_account.to_owned(),
+        ctx.accounts.buyer_trade_state.to_owned(),
+        ctx.accounts.token_program.to_owned(),
+        ctx.accounts.system_program.to_owned(),
+        ctx.accounts.rent.to_owned(),
+        trade_state_bump,
+        escrow_payment_bump,
+        buyer_price,
+        token_size,
+        None,
+        None,
+        None,
+        false,
+        *ctx.bumps
+            .get("escrow_payment_account")
+            .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
+        *ctx.bumps
+            .get("buyer_trade_state")
+            .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
+    )
+}
+
 /// Handles the bid logic for both private and public bids.
 #[allow(clippy::too_many_arguments)]
 pub fn bid_logic<'info>(
     wallet: Signer<'info>,
     payment_account: UncheckedAccount<'info>,
     transfer_authority: UncheckedAccount<'info>,
-    treasury_mint: Account<'info, Mint>,
-    token_account: Account<'info, TokenAccount>,
+    treasury_mint: UncheckedAccount<'info>,
+    token_account: UncheckedAccount<'info>,
     metadata: UncheckedAccount<'info>,
     escrow_payment_account: UncheckedAccount<'info>,
     authority: UncheckedAccount<'info>,
     auction_house: Account<'info, AuctionHouse>,
     auction_house_fee_account: UncheckedAccount<'info>,
     buyer_trade_state: UncheckedAccount<'info>,
-    token_program: Program<'info, Token>,
+    token_program: UncheckedAccount<'info>,
     system_program: Program<'info, System>,
     rent: Sysvar<'info, Rent>,
     trade_state_bump: u8,
     escrow_payment_bump: u8,
     buyer_price: u64,
     token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
+    royalty_choice: Option<u16>,
     public: bool,
     escrow_canonical_bump: u8,
     trade_state_canonical_bump: u8,
 ) -> Result<()> {
+    let token_mint = get_mint_from_token_account(&token_account)?;
 
     assert_valid_trade_state(
         &wallet.key(),
@@ -306,7 +704,7 @@ pub fn bid_logic<'info>(
         buyer_price,
         token_size,
         &buyer_trade_state,
-        &token_account.mint.key(),
+        &token_mint,
         &token_account.key(),
         trade_state_bump,
     )?;
@@ -354,60 +752,19 @@ pub fn bid_logic<'info>(
         fee_seeds,
         is_native,
     )?;
-    if is_native {
-        assert_keys_equal(wallet.key(), payment_account.key())?;
-
-        if escrow_payment_account.lamports()
-            < buyer_price
-                .checked_add(rent.minimum_balance(escrow_payment_account.data_len()))
-                .ok_or(AuctionHouseError::NumericalOverflow)?
-        {
-            let diff = buyer_price
-                .checked_add(rent.minimum_balance(escrow_payment_account.data_len()))
-                .ok_or(AuctionHouseError::NumericalOverflow)?
-                .checked_sub(escrow_payment_account.lamports())
-                .ok_or(AuctionHouseError::NumericalOverflow)?;
-
-            invoke(
-                &system_instruction::transfer(
-                    &payment_account.key(),
-                    &escrow_payment_account.key(),
-                    diff,
-                ),
-                &[
-                    payment_account.to_account_info(),
-                    escrow_payment_account.to_account_info(),
-                    system_program.to_account_info(),
-                ],
-            )?;
-        }
-    } else {
-        let escrow_payment_loaded: spl_token::state::Account =
-            assert_initialized(&escrow_payment_account)?;
-
-        if escrow_payment_loaded.amount < buyer_price {
-            let diff = buyer_price
-                .checked_sub(escrow_payment_loaded.amount)
-                .ok_or(AuctionHouseError::NumericalOverflow)?;
-            invoke(
-                &spl_token::instruction::transfer(
-                    &token_program.key(),
-                    &payment_account.key(),
-                    &escrow_payment_account.key(),
-                    &transfer_authority.key(),
-                    &[],
-                    diff,
-                )?,
-                &[
-                    transfer_authority.to_account_info(),
-                    payment_account.to_account_info(),
-                    escrow_payment_account.to_account_info(),
-                    token_program.to_account_info(),
-                ],
-            )?;
-        }
-    }
-    assert_metadata_valid(&metadata, &token_account)?;
+    fund_escrow_payment_account(
+        &wallet,
+        &payment_account,
+        &transfer_authority,
+        &escrow_payment_account,
+        &treasury_mint,
+        &token_program,
+        &system_program,
+        &rent,
+        is_native,
+        buyer_price,
+    )?;
+    assert_metadata_valid(&metadata, &token_mint)?;
 
     let ts_info = buyer_trade_state.to_account_info();
     if ts_info.data_is_empty() {
@@ -427,7 +784,7 @@ pub fn bid_logic<'info>(
                     wallet_key.as_ref(),
                     auction_house_key.as_ref(),
                     auction_house.treasury_mint.as_ref(),
-                    token_account.mint.as_ref(),
+                    token_mint.as_ref(),
                     &buyer_price.to_le_bytes(),
                     &token_size.to_le_bytes(),
                     &[trade_state_bump],
@@ -448,19 +805,102 @@ pub fn bid_logic<'info>(
                     auction_house_key.as_ref(),
                     token_account_key.as_ref(),
                     auction_house.treasury_mint.as_ref(),
-                    token_account.mint.as_ref(),
+                    token_mint.as_ref(),
                     &buyer_price.to_le_bytes(),
                     &token_size.to_le_bytes(),
                     &[trade_state_bump],
                 ],
             )?;
         }
-        sol_memset(
-            *ts_info.try_borrow_mut_data()?,
-            trade_state_bump,
-            TRADE_STATE_SIZE,
-        );
+        init_trade_state(&ts_info, trade_state_bump, fee_payer.key)?;
     }
-    // Allow The same bid to be sent with no issues
+    // Allow The same bid to be sent with no issues, updating its expiry, referral and royalty choice
+    set_trade_state_expiry(&ts_info, expiry)?;
+    set_trade_state_referral(
+        &ts_info,
+        &wallet.key(),
+        referral,
+        auction_house.max_referral_basis_points,
+    )?;
+    set_trade_state_royalty_choice(&ts_info, royalty_choice, &auction_house.royalty_policy)?;
+
+    emit!(BidPlaced {
+        auction_house: auction_house_key,
+        trade_state: buyer_trade_state.key(),
+        buyer: wallet.key(),
+        token_account: token_account.key(),
+        token_mint,
+        price: buyer_price,
+        token_size,
+        public,
+        expiry,
+        referral,
+        royalty_choice,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Top up the escrow payment account from `payment_account` so it holds at least `amount`.
+#[allow(clippy::too_many_arguments)]
+pub fn fund_escrow_payment_account<'info>(
+    wallet: &Signer<'info>,
+    payment_account: &UncheckedAccount<'info>,
+    transfer_authority: &UncheckedAccount<'info>,
+    escrow_payment_account: &UncheckedAccount<'info>,
+    treasury_mint: &UncheckedAccount<'info>,
+    token_program: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    rent: &Sysvar<'info, Rent>,
+    is_native: bool,
+    amount: u64,
+) -> Result<()> {
+    if is_native {
+        assert_keys_equal(wallet.key(), payment_account.key())?;
+
+        if escrow_payment_account.lamports()
+            < amount
+                .checked_add(rent.minimum_balance(escrow_payment_account.data_len()))
+                .ok_or(AuctionHouseError::NumericalOverflow)?
+        {
+            let diff = amount
+                .checked_add(rent.minimum_balance(escrow_payment_account.data_len()))
+                .ok_or(AuctionHouseError::NumericalOverflow)?
+                .checked_sub(escrow_payment_account.lamports())
+                .ok_or(AuctionHouseError::NumericalOverflow)?;
+
+            invoke(
+                &system_instruction::transfer(
+                    &payment_account.key(),
+                    &escrow_payment_account.key(),
+                    diff,
+                ),
+                &[
+                    payment_account.to_account_info(),
+                    escrow_payment_account.to_account_info(),
+                    system_program.to_account_info(),
+                ],
+            )?;
+        }
+    } else {
+        let escrow_payment_loaded = unpack_token_account(escrow_payment_account)?;
+
+        if escrow_payment_loaded.amount < amount {
+            let diff = amount
+                .checked_sub(escrow_payment_loaded.amount)
+                .ok_or(AuctionHouseError::NumericalOverflow)?;
+            transfer_tokens(
+                token_program,
+                payment_account,
+                treasury_mint,
+                escrow_payment_account,
+                transfer_authority,
+                diff,
+                &[],
+            )?;
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,699 @@
+//! Bundle listings.
+//! `create_bundle_listing` delegates one of each of up to `MAX_BUNDLE_ITEMS` tokens to the program as signer and
+//! records a `BundleListing` PDA selling them together at one price. `execute_bundle_sale` funds the buyer's escrow
+//! payment account with the price, moves every token to the buyer and pays each token's royalties on its weighted
+//! share of the price. `cancel_bundle_listing` revokes every delegate and closes the listing.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        program::{invoke, invoke_signed},
+        program_option::COption,
+        system_instruction,
+    },
+    AnchorDeserialize,
+};
+use spl_token_2022::instruction::{approve, revoke};
+
+use crate::{
+    bid::*, constants::*, errors::*, events::*, utils::*, AuctionHouse, BundleItem, BundleListing,
+    *,
+};
+
+/// Accounts for the [`create_bundle_listing` handler](auction_house/fn.create_bundle_listing.html).
+/// The remaining accounts are the token account and metadata of each item, in order.
+#[derive(Accounts)]
+pub struct CreateBundleListing<'info> {
+    /// Seller wallet account, paying for the bundle listing PDA.
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// CHECK: Checked against the first item in create_bundle_listing.
+    /// Token account of the first item, which keys the bundle listing.
+    pub first_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked for a signature in create_bundle_listing if the Auction House requires sign off.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Bundle listing PDA account.
+    #[account(
+        init,
+        payer=wallet,
+        space=BUNDLE_LISTING_SIZE,
+        seeds = [
+            PREFIX.as_bytes(),
+            BUNDLE.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            first_token_account.key().as_ref()
+        ],
+        bump
+    )]
+    pub bundle_listing: Box<Account<'info, BundleListing>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+}
+
+/// Accounts for the [`execute_bundle_sale` handler](auction_house/fn.execute_bundle_sale.html).
+/// The remaining accounts are, for each item in order, its token account, mint, metadata and the buyer's
+/// associated token account for it, followed by the creator accounts `pay_creator_fees` expects for it.
+#[derive(Accounts)]
+#[instruction(escrow_payment_bump: u8, program_as_signer_bump: u8)]
+pub struct ExecuteBundleSale<'info> {
+    /// Buyer wallet account.
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: Validated in fund_escrow_payment_account.
+    /// Buyer SOL or SPL account to transfer funds from.
+    #[account(mut)]
+    pub payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in fund_escrow_payment_account.
+    /// SPL token account transfer authority.
+    pub transfer_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the bundle listing.
+    /// Seller wallet account, receiving the bundle listing rent.
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            buyer.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_bundle_sale.
+    /// Seller SOL or SPL account to receive payment at.
+    #[account(mut)]
+    pub seller_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House instance treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in get_fee_payer.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_treasury,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance treasury account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            TREASURY.as_bytes()
+        ],
+        bump=auction_house.treasury_bump
+    )]
+    pub auction_house_treasury: UncheckedAccount<'info>,
+
+    /// Bundle listing PDA account being bought.
+    #[account(
+        mut,
+        close=seller,
+        seeds = [
+            PREFIX.as_bytes(),
+            BUNDLE.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            bundle_listing.items[0].token_account.as_ref()
+        ],
+        bump=bundle_listing.bump,
+        has_one=auction_house,
+        has_one=seller
+    )]
+    pub bundle_listing: Box<Account<'info, BundleListing>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for the [`cancel_bundle_listing` handler](auction_house/fn.cancel_bundle_listing.html).
+/// The remaining accounts are the token account of each item, in order, whose delegates are revoked if the seller
+/// signs.
+#[derive(Accounts)]
+pub struct CancelBundleListing<'info> {
+    /// CHECK: Checked against the bundle listing.
+    /// Seller wallet account, receiving the bundle listing rent.
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in cancel_bundle_listing.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Bundle listing PDA account.
+    #[account(
+        mut,
+        close=seller,
+        seeds = [
+            PREFIX.as_bytes(),
+            BUNDLE.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            bundle_listing.items[0].token_account.as_ref()
+        ],
+        bump=bundle_listing.bump,
+        has_one=auction_house,
+        has_one=seller
+    )]
+    pub bundle_listing: Box<Account<'info, BundleListing>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+}
+
+/// List one of each of the tokens in the remaining accounts together at `price`, delegating each to the program as
+/// signer. `weights` holds each item's share of the price, which its royalties are paid on.
+pub fn create_bundle_listing<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateBundleListing<'info>>,
+    price: u64,
+    weights: Vec<u64>,
+) -> Result<()> {
+    let wallet = &ctx.accounts.wallet;
+    let auction_house = &ctx.accounts.auction_house;
+    let token_program = &ctx.accounts.token_program;
+    let program_as_signer = &ctx.accounts.program_as_signer;
+
+    // Bundles put the seller's tokens up like a listing, so they follow a delegated Sell scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Sell as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    if auction_house.requires_sign_off && !ctx.accounts.authority.is_signer {
+        return Err(AuctionHouseError::CannotTakeThisActionWithoutAuctionHouseSignOff.into());
+    }
+
+    if weights.is_empty()
+        || weights.len() > MAX_BUNDLE_ITEMS
+        || weights.contains(&0)
+        || ctx.remaining_accounts.len() != 2 * weights.len()
+    {
+        return Err(AuctionHouseError::InvalidBundle.into());
+    }
+
+    let mut items: Vec<BundleItem> = Vec::with_capacity(weights.len());
+    for (accounts, weight) in ctx.remaining_accounts.chunks(2).zip(weights) {
+        let (token_account, metadata) = (&accounts[0], &accounts[1]);
+        if items
+            .iter()
+            .any(|item| item.token_account == token_account.key())
+        {
+            return Err(AuctionHouseError::InvalidBundle.into());
+        }
+
+        let token_account_data = unpack_token_account(token_account)?;
+        assert_is_ata(token_account, &wallet.key(), &token_account_data.mint)?;
+        assert_metadata_valid(metadata, &token_account_data.mint)?;
+        if token_account_data.amount < 1 {
+            return Err(AuctionHouseError::InvalidTokenAmount.into());
+        }
+
+        invoke(
+            &approve(
+                &token_program.key(),
+                &token_account.key(),
+                &program_as_signer.key(),
+                &wallet.key(),
+                &[],
+                1,
+            )?,
+            &[
+                token_program.to_account_info(),
+                token_account.clone(),
+                program_as_signer.to_account_info(),
+                wallet.to_account_info(),
+            ],
+        )?;
+
+        items.push(BundleItem {
+            token_mint: token_account_data.mint,
+            token_account: token_account.key(),
+            weight,
+        });
+    }
+    if items[0].token_account != ctx.accounts.first_token_account.key() {
+        return Err(AuctionHouseError::BundleItemMismatch.into());
+    }
+
+    let bundle_listing = &mut ctx.accounts.bundle_listing;
+    bundle_listing.auction_house = auction_house.key();
+    bundle_listing.seller = wallet.key();
+    bundle_listing.price = price;
+    bundle_listing.items = items;
+    bundle_listing.bump = *ctx
+        .bumps
+        .get("bundle_listing")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    emit!(BundleListed {
+        auction_house: auction_house.key(),
+        bundle_listing: bundle_listing.key(),
+        seller: wallet.key(),
+        token_mints: bundle_listing
+            .items
+            .iter()
+            .map(|item| item.token_mint)
+            .collect(),
+        price,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Buy a bundle listing at `price`, funding the buyer's escrow payment account with it. Each item's royalties are
+/// paid on its weighted share of the price, the last item taking any rounding remainder, and the Auction House fee
+/// on the whole price.
+pub fn execute_bundle_sale<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteBundleSale<'info>>,
+    escrow_payment_bump: u8,
+    program_as_signer_bump: u8,
+    price: u64,
+) -> Result<()> {
+    let buyer = &ctx.accounts.buyer;
+    let seller = &ctx.accounts.seller;
+    let escrow_payment_account = &ctx.accounts.escrow_payment_account;
+    let seller_payment_receipt_account = &ctx.accounts.seller_payment_receipt_account;
+    let treasury_mint = &ctx.accounts.treasury_mint;
+    let authority = &ctx.accounts.authority;
+    let auction_house = &ctx.accounts.auction_house;
+    let auction_house_fee_account = &ctx.accounts.auction_house_fee_account;
+    let auction_house_treasury = &ctx.accounts.auction_house_treasury;
+    let bundle_listing = &ctx.accounts.bundle_listing;
+    let token_program = &ctx.accounts.token_program;
+    let system_program = &ctx.accounts.system_program;
+    let ata_program = &ctx.accounts.ata_program;
+    let program_as_signer = &ctx.accounts.program_as_signer;
+    let rent = &ctx.accounts.rent;
+
+    // Bundle sales settle like any other sale, so they follow a delegated ExecuteSale scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::ExecuteSale as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let escrow_canonical_bump = *ctx
+        .bumps
+        .get("escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    if (escrow_canonical_bump != escrow_payment_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    // The price is passed in so a seller cannot relist the same items higher ahead of the buyer.
+    if price != bundle_listing.price {
+        return Err(AuctionHouseError::BundlePriceMismatch.into());
+    }
+
+    let auction_house_key = auction_house.key();
+    let seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        FEE_PAYER.as_bytes(),
+        &[auction_house.fee_payer_bump],
+    ];
+    let (fee_payer, fee_payer_seeds) = get_fee_payer(
+        authority,
+        auction_house,
+        buyer.to_account_info(),
+        auction_house_fee_account.to_account_info(),
+        &seeds,
+    )?;
+
+    let is_native = treasury_mint.key() == spl_token::native_mint::id();
+    let buyer_key = buyer.key();
+    let escrow_signer_seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        buyer_key.as_ref(),
+        &[escrow_payment_bump],
+    ];
+    let ah_seeds = [
+        PREFIX.as_bytes(),
+        auction_house.creator.as_ref(),
+        auction_house.treasury_mint.as_ref(),
+        &[auction_house.bump],
+    ];
+    create_program_token_account_if_not_present(
+        escrow_payment_account,
+        system_program,
+        &fee_payer,
+        token_program,
+        treasury_mint,
+        &auction_house.to_account_info(),
+        rent,
+        &escrow_signer_seeds,
+        fee_payer_seeds,
+        is_native,
+    )?;
+    fund_escrow_payment_account(
+        buyer,
+        &ctx.accounts.payment_account,
+        &ctx.accounts.transfer_authority,
+        escrow_payment_account,
+        treasury_mint,
+        token_program,
+        system_program,
+        rent,
+        is_native,
+        price,
+    )?;
+
+    // with the native account, the escrow is its own owner,
+    // whereas with token, it is the auction house that is owner.
+    let (escrow_owner, signer_seeds) = if is_native {
+        (
+            escrow_payment_account.to_account_info(),
+            escrow_signer_seeds,
+        )
+    } else {
+        (auction_house.to_account_info(), ah_seeds)
+    };
+
+    let program_as_signer_seeds = [
+        PREFIX.as_bytes(),
+        SIGNER.as_bytes(),
+        &[program_as_signer_bump],
+    ];
+    let total_weight: u128 = bundle_listing
+        .items
+        .iter()
+        .map(|item| item.weight as u128)
+        .sum();
+    let mut allocated: u64 = 0;
+    let mut leftover_after_royalties: u64 = 0;
+    let remaining_accounts = &mut ctx.remaining_accounts.iter();
+    for (index, item) in bundle_listing.items.iter().enumerate() {
+        let token_account = next_account_info(remaining_accounts)?;
+        let token_mint = next_account_info(remaining_accounts)?;
+        let metadata = next_account_info(remaining_accounts)?;
+        let buyer_receipt_token_account = next_account_info(remaining_accounts)?;
+        if token_account.key() != item.token_account || token_mint.key() != item.token_mint {
+            return Err(AuctionHouseError::BundleItemMismatch.into());
+        }
+        assert_supported_mint(token_mint)?;
+        assert_metadata_valid(metadata, &item.token_mint)?;
+
+        let token_account_data = assert_is_ata(token_account, &seller.key(), &item.token_mint)?;
+        if token_account_data.delegate != COption::Some(program_as_signer.key())
+            || token_account_data.delegated_amount < 1
+        {
+            return Err(AuctionHouseError::BothPartiesNeedToAgreeToSale.into());
+        }
+
+        let item_price = if index + 1 == bundle_listing.items.len() {
+            price
+                .checked_sub(allocated)
+                .ok_or(AuctionHouseError::NumericalOverflow)?
+        } else {
+            (price as u128 * item.weight as u128 / total_weight) as u64
+        };
+        allocated = allocated
+            .checked_add(item_price)
+            .ok_or(AuctionHouseError::NumericalOverflow)?;
+        let item_leftover = pay_creator_fees(
+            remaining_accounts,
+            metadata,
+            escrow_payment_account,
+            &escrow_owner,
+            &fee_payer,
+            treasury_mint,
+            ata_program,
+            token_program,
+            system_program,
+            &rent.to_account_info(),
+            &signer_seeds,
+            fee_payer_seeds,
+            royalty_basis_points(auction_house, metadata, None)?,
+            auction_house.unverified_creator_policy,
+            item_price,
+            is_native,
+        )?;
+        leftover_after_royalties = leftover_after_royalties
+            .checked_add(item_leftover)
+            .ok_or(AuctionHouseError::NumericalOverflow)?;
+
+        if buyer_receipt_token_account.data_is_empty() {
+            make_ata(
+                buyer_receipt_token_account.clone(),
+                buyer.to_account_info(),
+                token_mint.clone(),
+                fee_payer.clone(),
+                ata_program.to_account_info(),
+                token_program.to_account_info(),
+                system_program.to_account_info(),
+                rent.to_account_info(),
+                fee_payer_seeds,
+            )?;
+        }
+        let buyer_rec_acct =
+            assert_is_ata(buyer_receipt_token_account, &buyer_key, &item.token_mint)?;
+        // make sure you cant get rugged
+        if buyer_rec_acct.delegate.is_some() {
+            return Err(AuctionHouseError::BuyerATACannotHaveDelegate.into());
+        }
+
+        transfer_tokens(
+            token_program,
+            token_account,
+            token_mint,
+            buyer_receipt_token_account,
+            program_as_signer,
+            1,
+            &[&program_as_signer_seeds],
+        )?;
+    }
+
+    // Items may come from different collections, so bundles pay the house rate rather than any fee schedule.
+    let auction_house_fee_paid = pay_auction_house_fees(
+        auction_house,
+        auction_house_treasury,
+        escrow_payment_account,
+        treasury_mint,
+        token_program,
+        system_program,
+        &signer_seeds,
+        auction_house.seller_fee_basis_points,
+        price,
+        0,
+        is_native,
+    )?;
+    let seller_proceeds = leftover_after_royalties
+        .checked_sub(auction_house_fee_paid)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+
+    if !is_native {
+        if seller_payment_receipt_account.data_is_empty() {
+            make_ata(
+                seller_payment_receipt_account.to_account_info(),
+                seller.to_account_info(),
+                treasury_mint.to_account_info(),
+                fee_payer.clone(),
+                ata_program.to_account_info(),
+                token_program.to_account_info(),
+                system_program.to_account_info(),
+                rent.to_account_info(),
+                fee_payer_seeds,
+            )?;
+        }
+        let seller_rec_acct = assert_is_ata(
+            seller_payment_receipt_account,
+            &seller.key(),
+            &treasury_mint.key(),
+        )?;
+        // make sure you cant get rugged
+        if seller_rec_acct.delegate.is_some() {
+            return Err(AuctionHouseError::SellerATACannotHaveDelegate.into());
+        }
+
+        transfer_tokens(
+            token_program,
+            escrow_payment_account,
+            treasury_mint,
+            seller_payment_receipt_account,
+            &escrow_owner,
+            seller_proceeds,
+            &[&signer_seeds],
+        )?;
+    } else {
+        assert_keys_equal(seller_payment_receipt_account.key(), seller.key())?;
+        invoke_signed(
+            &system_instruction::transfer(
+                escrow_payment_account.key,
+                seller_payment_receipt_account.key,
+                seller_proceeds,
+            ),
+            &[
+                escrow_payment_account.to_account_info(),
+                seller_payment_receipt_account.to_account_info(),
+                system_program.to_account_info(),
+            ],
+            &[&signer_seeds],
+        )?;
+    }
+
+    emit!(BundleSold {
+        auction_house: auction_house_key,
+        bundle_listing: bundle_listing.key(),
+        buyer: buyer_key,
+        seller: seller.key(),
+        price,
+        royalties: price
+            .checked_sub(leftover_after_royalties)
+            .ok_or(AuctionHouseError::NumericalOverflow)?,
+        auction_house_fee: auction_house_fee_paid,
+        seller_proceeds,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Close a bundle listing, returning its rent to the seller. If the seller signs, the delegate over each item's
+/// token account is revoked as well.
+pub fn cancel_bundle_listing<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelBundleListing<'info>>,
+) -> Result<()> {
+    let seller = &ctx.accounts.seller;
+    let auction_house = &ctx.accounts.auction_house;
+    let bundle_listing = &ctx.accounts.bundle_listing;
+    let token_program = &ctx.accounts.token_program;
+
+    // If the Cancel scope is delegated, bundles can only be canceled through the Auctioneer.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Cancel as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    if !seller.is_signer && !ctx.accounts.authority.is_signer {
+        return Err(AuctionHouseError::NoValidSignerPresent.into());
+    }
+
+    if seller.is_signer {
+        if ctx.remaining_accounts.len() != bundle_listing.items.len() {
+            return Err(AuctionHouseError::BundleItemMismatch.into());
+        }
+        for (token_account, item) in ctx.remaining_accounts.iter().zip(&bundle_listing.items) {
+            if token_account.key() != item.token_account {
+                return Err(AuctionHouseError::BundleItemMismatch.into());
+            }
+            // Items the seller has since moved on keep whatever delegate their new owner set.
+            if unpack_token_account(token_account)?.owner != seller.key() {
+                continue;
+            }
+            invoke(
+                &revoke(
+                    &token_program.key(),
+                    &token_account.key(),
+                    &seller.key(),
+                    &[],
+                )?,
+                &[
+                    token_program.to_account_info(),
+                    token_account.clone(),
+                    seller.to_account_info(),
+                ],
+            )?;
+        }
+    }
+
+    emit!(BundleCanceled {
+        auction_house: auction_house.key(),
+        bundle_listing: bundle_listing.key(),
+        seller: seller.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
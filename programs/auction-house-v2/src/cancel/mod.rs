@@ -1,10 +1,10 @@
 use anchor_lang::{prelude::*, solana_program::program::invoke, AnchorDeserialize};
 use solana_program::program_memory::sol_memset;
 
-use crate::{constants::*, errors::*, utils::*, AuctionHouse, *};
+use crate::{constants::*, errors::*, events::*, utils::*, AuctionHouse, *};
 
 /// Accounts for the [`cancel` handler](auction_house/fn.cancel.html).
-#[derive(Accounts)]
+#[derive(Accounts, Clone)]
 #[instruction(buyer_price: u64, token_size: u64)]
 pub struct Cancel<'info> {
     /// CHECK: Verified in cancel_logic.
@@ -12,12 +12,14 @@ pub struct Cancel<'info> {
     #[account(mut)]
     pub wallet: UncheckedAccount<'info>,
 
+    /// CHECK: Unpacked in cancel_logic, owned by either token program.
     /// SPL token account containing the token of the sale to be canceled.
     #[account(mut)]
-    pub token_account: Box<Account<'info, TokenAccount>>,
+    pub token_account: UncheckedAccount<'info>,
 
+    /// CHECK: Checked against the token account mint in cancel_logic.
     /// Token mint account of SPL token.
-    pub token_mint: Box<Account<'info, Mint>>,
+    pub token_mint: UncheckedAccount<'info>,
 
     /// CHECK: Validated as a signer in cancel_logic.
     /// Auction House instance authority account.
@@ -54,7 +56,141 @@ pub struct Cancel<'info> {
     #[account(mut)]
     pub trade_state: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+}
+
+/// Accounts for the [`auctioneer_cancel` handler](auction_house/fn.auctioneer_cancel.html).
+#[derive(Accounts, Clone)]
+#[instruction(buyer_price: u64, token_size: u64)]
+pub struct AuctioneerCancel<'info> {
+    /// CHECK: Verified in cancel_logic.
+    /// User wallet account.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in cancel_logic, owned by either token program.
+    /// SPL token account containing the token of the sale to be canceled.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the token account mint in cancel_logic.
+    /// Token mint account of SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in cancel_logic.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in cancel_logic.
+    /// Trade state PDA account representing the bid or ask to be canceled.
+    #[account(mut)]
+    pub trade_state: UncheckedAccount<'info>,
+
+    /// The Auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    pub auctioneer_authority: Signer<'info>,
+
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        bump=ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+}
+
+/// Accounts for the [`close_expired_trade_state` handler](auction_house/fn.close_expired_trade_state.html).
+#[derive(Accounts)]
+#[instruction(buyer_price: u64, token_size: u64)]
+pub struct CloseExpiredTradeState<'info> {
+    /// CHECK: Used as a trade state seed, and only needs to sign to have the token delegate revoked.
+    /// User wallet account that placed the bid or ask.
+    pub wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in close_expired_trade_state, owned by either token program.
+    /// SPL token account of the expired bid or ask.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Validated in close_expired_trade_state.
+    /// Expired trade state PDA account.
+    #[account(mut)]
+    pub trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the rent payer recorded in the trade state.
+    /// Account that paid the trade state rent, receiving it back.
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+}
+
+impl<'info> From<AuctioneerCancel<'info>> for Cancel<'info> {
+    fn from(a: AuctioneerCancel<'info>) -> Cancel<'info> {
+        Cancel {
+            wallet: a.wallet,
+            token_account: a.token_account,
+            token_mint: a.token_mint,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            trade_state: a.trade_state,
+            token_program: a.token_program,
+        }
+    }
 }
 
 // Cancel a bid or ask by revoking the token delegate, transferring all lamports from the trade state account to the fee payer, and setting the trade state account data to zero so it can be garbage collected.
@@ -63,14 +199,64 @@ pub fn cancel<'info>(
     buyer_price: u64,
     token_size: u64,
 ) -> Result<()> {
-    cancel_logic(ctx.accounts, buyer_price, token_size)
+    let auction_house = &ctx.accounts.auction_house;
+
+    // If the Cancel scope is delegated, cancels must go through auctioneer_cancel.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Cancel as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    cancel_logic(
+        ctx.accounts,
+        ctx.remaining_accounts,
+        buyer_price,
+        token_size,
+        None,
+    )
+}
+
+/// Cancel a bid or ask through the Auctioneer delegated the Cancel scope.
+pub fn auctioneer_cancel<'info>(
+    ctx: Context<'_, '_, '_, 'info, AuctioneerCancel<'info>>,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auctioneer_authority = &ctx.accounts.auctioneer_authority;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::NoAuctioneerProgramSet.into());
+    }
+
+    assert_valid_auctioneer_and_scope(
+        auction_house,
+        &auctioneer_authority.key(),
+        ah_auctioneer_pda,
+        AuthorityScope::Cancel,
+    )?;
+
+    let mut accounts: Cancel<'info> = (*ctx.accounts).clone().into();
+
+    cancel_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        buyer_price,
+        token_size,
+        None,
+    )
 }
 
-#[allow(clippy::needless_lifetimes)]
-fn cancel_logic<'info>(
+/// Cancel a bid or ask by closing its trade state, revoking the program as signer delegate when the seller signs.
+/// A programmable NFT listing is revoked through Token Metadata and takes the [`ProgrammableAccounts`] followed
+/// by the metadata, program as signer and system program accounts as remaining accounts.
+/// Listings priced at settlement pass the `listing_seed` keying their trade state in place of `buyer_price`.
+pub fn cancel_logic<'info>(
     accounts: &mut Cancel<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
     buyer_price: u64,
     token_size: u64,
+    listing_seed: Option<&str>,
 ) -> Result<()> {
     let wallet = &accounts.wallet;
     let token_account = &accounts.token_account;
@@ -81,18 +267,34 @@ fn cancel_logic<'info>(
     let trade_state = &accounts.trade_state;
     let token_program = &accounts.token_program;
 
-    let ts_bump = trade_state.try_borrow_data()?[0];
-    assert_valid_trade_state(
-        &wallet.key(),
-        auction_house,
-        buyer_price,
-        token_size,
-        &trade_state.to_account_info(),
-        &token_account.mint.key(),
-        &token_account.key(),
-        ts_bump,
-    )?;
-    assert_keys_equal(token_mint.key(), token_account.mint)?;
+    let token_account_data = unpack_token_account(token_account)?;
+    let ts_bump = *trade_state
+        .try_borrow_data()?
+        .first()
+        .ok_or(AuctionHouseError::TradeStateDoesntExist)?;
+    match listing_seed {
+        Some(listing_seed) => assert_valid_seeded_trade_state(
+            &wallet.key(),
+            auction_house,
+            listing_seed,
+            token_size,
+            &trade_state.to_account_info(),
+            &token_account_data.mint,
+            &token_account.key(),
+            ts_bump,
+        )?,
+        None => assert_valid_trade_state(
+            &wallet.key(),
+            auction_house,
+            buyer_price,
+            token_size,
+            &trade_state.to_account_info(),
+            &token_account_data.mint,
+            &token_account.key(),
+            ts_bump,
+        )?,
+    };
+    assert_keys_equal(token_mint.key(), token_account_data.mint)?;
     if !wallet.to_account_info().is_signer && !authority.to_account_info().is_signer {
         return Err(AuctionHouseError::NoValidSignerPresent.into());
     }
@@ -112,8 +314,54 @@ fn cancel_logic<'info>(
         auction_house_fee_account.to_account_info(),
         &seeds,
     )?;
+    // Orders are paid for by their wallet or the fee account, and the rent goes back to whichever did. Trade states
+    // from before the payer was recorded refund the fee payer as they always have.
+    let rent_payer = trade_state_rent_payer_info(
+        trade_state,
+        &[
+            wallet.to_account_info(),
+            auction_house_fee_account.to_account_info(),
+        ],
+        &fee_payer,
+    )?;
 
-    if token_account.owner == wallet.key() && wallet.is_signer {
+    let is_seller = token_account_data.owner == wallet.key() && wallet.is_signer;
+    if is_seller && !remaining_accounts.is_empty() {
+        let remaining_accounts = &mut remaining_accounts.iter();
+        let programmable = ProgrammableAccounts::next(remaining_accounts)?;
+        let metadata = next_account_info(remaining_accounts)?;
+        let program_as_signer = next_account_info(remaining_accounts)?;
+        let system_program = next_account_info(remaining_accounts)?;
+        assert_derivation(
+            &mpl_token_metadata::id(),
+            metadata,
+            &[
+                mpl_token_metadata::state::PREFIX.as_bytes(),
+                mpl_token_metadata::id().as_ref(),
+                token_account_data.mint.as_ref(),
+            ],
+        )?;
+        assert_derivation(
+            &crate::id(),
+            program_as_signer,
+            &[PREFIX.as_bytes(), SIGNER.as_bytes()],
+        )?;
+        assert_keys_equal(system_program.key(), System::id())?;
+        if !is_programmable(metadata)? {
+            return Err(AuctionHouseError::NotProgrammableNft.into());
+        }
+
+        revoke_programmable_sale(
+            &programmable,
+            program_as_signer,
+            metadata,
+            token_mint,
+            token_account,
+            wallet,
+            system_program,
+            token_program,
+        )?;
+    } else if is_seller {
         invoke(
             &revoke(
                 &token_program.key(),
@@ -133,11 +381,85 @@ fn cancel_logic<'info>(
     let curr_lamp = trade_state.lamports();
     **trade_state.lamports.borrow_mut() = 0;
 
-    **fee_payer.lamports.borrow_mut() = fee_payer
+    **rent_payer.lamports.borrow_mut() = rent_payer
         .lamports()
         .checked_add(curr_lamp)
         .ok_or(AuctionHouseError::NumericalOverflow)?;
-    sol_memset(*trade_state.try_borrow_mut_data()?, 0, TRADE_STATE_SIZE);
+    let ts_len = trade_state.data_len();
+    sol_memset(*trade_state.try_borrow_mut_data()?, 0, ts_len);
+
+    emit!(OrderCanceled {
+        auction_house: auction_house_key,
+        trade_state: trade_state.key(),
+        wallet: wallet.key(),
+        token_mint: token_account_data.mint,
+        price: buyer_price,
+        token_size,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
 
     Ok(())
 }
+
+/// Close a bid or ask past its expiry, returning the trade state rent to whoever paid it.
+/// Anyone can call this. Revoking the token delegate needs the token owner's signature, so the delegate is only revoked when the wallet signs; otherwise it is left in place but can no longer be used, as no trade state remains to execute a sale against.
+pub fn close_expired_trade_state<'info>(
+    ctx: Context<'_, '_, '_, 'info, CloseExpiredTradeState<'info>>,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let wallet = &ctx.accounts.wallet;
+    let token_account = &ctx.accounts.token_account;
+    let auction_house = &ctx.accounts.auction_house;
+    let trade_state = &ctx.accounts.trade_state.to_account_info();
+    let rent_payer = &ctx.accounts.rent_payer;
+    let token_program = &ctx.accounts.token_program;
+
+    match get_trade_state_expiry(trade_state)? {
+        Some(expiry) if Clock::get()?.unix_timestamp >= expiry => {}
+        _ => return Err(AuctionHouseError::TradeStateNotExpired.into()),
+    }
+
+    let token_account_data = unpack_token_account(token_account)?;
+    let ts_bump = *trade_state
+        .try_borrow_data()?
+        .first()
+        .ok_or(AuctionHouseError::TradeStateDoesntExist)?;
+    assert_valid_trade_state(
+        &wallet.key(),
+        auction_house,
+        buyer_price,
+        token_size,
+        trade_state,
+        &token_account_data.mint,
+        &token_account.key(),
+        ts_bump,
+    )?;
+    let recorded_rent_payer =
+        get_trade_state_rent_payer(trade_state)?.ok_or(AuctionHouseError::DerivedKeyInvalid)?;
+    assert_keys_equal(rent_payer.key(), recorded_rent_payer)?;
+
+    // Programmable NFT accounts stay frozen, so their owner revokes the Sale delegate through
+    // Token Metadata directly.
+    if token_account_data.owner == wallet.key()
+        && wallet.is_signer
+        && !token_account_data.is_frozen()
+    {
+        invoke(
+            &revoke(
+                &token_program.key(),
+                &token_account.key(),
+                &wallet.key(),
+                &[],
+            )
+            .unwrap(),
+            &[
+                token_program.to_account_info(),
+                token_account.to_account_info(),
+                wallet.to_account_info(),
+            ],
+        )?;
+    }
+
+    close_account(trade_state, &rent_payer.to_account_info())
+}
@@ -0,0 +1,1107 @@
+//! Off-chain builders for Auction House instructions and decoders for its accounts.
+//!
+//! Builders derive every PDA and bump from the addresses they are given, so callers never
+//! order accounts by hand. Each builder takes what it cannot derive in `new`, offers setters
+//! for the rest, and turns into an [`Instruction`] with `instruction`.
+use anchor_lang::{
+    prelude::*,
+    solana_program::{instruction::Instruction, system_program, sysvar},
+    AccountDeserialize, InstructionData,
+};
+use mpl_token_metadata::pda::find_metadata_account;
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+};
+
+use crate::{
+    accounts, instruction,
+    pda::*,
+    receipt::{BidReceipt, ListingReceipt, PurchaseReceipt},
+    AuctionHouse, Referral,
+};
+
+/// Addresses of an Auction House instance that its instructions take.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuctionHouseKeys {
+    pub address: Pubkey,
+    pub authority: Pubkey,
+    pub treasury_mint: Pubkey,
+    pub fee_account: Pubkey,
+    pub treasury: Pubkey,
+}
+
+impl AuctionHouseKeys {
+    /// Keys of the instance `creator` creates for `treasury_mint`, while `creator` is still its
+    /// authority.
+    pub fn new(creator: &Pubkey, treasury_mint: &Pubkey) -> Self {
+        let (address, _) = find_auction_house_address(creator, treasury_mint);
+        Self::with_authority(address, *creator, *treasury_mint)
+    }
+
+    /// Keys of a decoded instance, whose authority may have changed since it was created.
+    pub fn from_account(address: Pubkey, auction_house: &AuctionHouse) -> Self {
+        Self::with_authority(
+            address,
+            auction_house.authority,
+            auction_house.treasury_mint,
+        )
+    }
+
+    fn with_authority(address: Pubkey, authority: Pubkey, treasury_mint: Pubkey) -> Self {
+        Self {
+            address,
+            authority,
+            treasury_mint,
+            fee_account: find_auction_house_fee_account_address(&address).0,
+            treasury: find_auction_house_treasury_address(&address).0,
+        }
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.treasury_mint == spl_token::native_mint::id()
+    }
+
+    /// Account `wallet` pays from or is paid to in the treasury mint.
+    pub fn payment_account(&self, wallet: &Pubkey) -> Pubkey {
+        if self.is_native() {
+            *wallet
+        } else {
+            get_associated_token_address(wallet, &self.treasury_mint)
+        }
+    }
+
+    /// Trade state of `wallet`'s order on the tokens `token_account` holds.
+    pub fn trade_state(
+        &self,
+        wallet: &Pubkey,
+        token_account: &Pubkey,
+        token_mint: &Pubkey,
+        price: u64,
+        token_size: u64,
+    ) -> (Pubkey, u8) {
+        find_trade_state_address(
+            wallet,
+            &self.address,
+            token_account,
+            &self.treasury_mint,
+            token_mint,
+            price,
+            token_size,
+        )
+    }
+
+    /// Trade state of `wallet`'s public bid on `token_mint`.
+    pub fn public_bid_trade_state(
+        &self,
+        wallet: &Pubkey,
+        token_mint: &Pubkey,
+        price: u64,
+        token_size: u64,
+    ) -> (Pubkey, u8) {
+        find_public_bid_trade_state_address(
+            wallet,
+            &self.address,
+            &self.treasury_mint,
+            token_mint,
+            price,
+            token_size,
+        )
+    }
+}
+
+/// Tokens of `token_mint` held in `owner`'s associated token account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenKeys {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub metadata: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl TokenKeys {
+    /// Tokens of an SPL Token mint.
+    pub fn new(owner: &Pubkey, mint: &Pubkey) -> Self {
+        Self::with_token_program(owner, mint, &spl_token::id())
+    }
+
+    /// Tokens of a mint owned by `token_program`, SPL Token or Token-2022.
+    pub fn with_token_program(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Self {
+        Self {
+            owner: *owner,
+            mint: *mint,
+            token_account: get_associated_token_address_with_program_id(owner, mint, token_program),
+            metadata: find_metadata_account(mint).0,
+            token_program: *token_program,
+        }
+    }
+
+    /// Associated token account of `wallet` for the same mint.
+    pub fn token_account_of(&self, wallet: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(wallet, &self.mint, &self.token_program)
+    }
+}
+
+fn set_signer(accounts: &mut [AccountMeta], signer: &Pubkey, writable: bool) {
+    for meta in accounts.iter_mut().filter(|meta| meta.pubkey == *signer) {
+        meta.is_signer = true;
+        meta.is_writable |= writable;
+    }
+}
+
+fn program_instruction(accounts: Vec<AccountMeta>, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts,
+        data: data.data(),
+    }
+}
+
+/// Builds `create_auction_house`, signed by the payer.
+pub struct CreateAuctionHouseBuilder {
+    payer: Pubkey,
+    authority: Pubkey,
+    treasury_mint: Pubkey,
+    fee_withdrawal_destination: Pubkey,
+    treasury_withdrawal_destination_owner: Pubkey,
+    seller_fee_basis_points: u16,
+    requires_sign_off: bool,
+    can_change_sale_price: bool,
+    token_program: Pubkey,
+}
+
+impl CreateAuctionHouseBuilder {
+    /// Fees and treasury withdrawals go to `authority` until set otherwise.
+    pub fn new(payer: &Pubkey, authority: &Pubkey, treasury_mint: &Pubkey) -> Self {
+        Self {
+            payer: *payer,
+            authority: *authority,
+            treasury_mint: *treasury_mint,
+            fee_withdrawal_destination: *authority,
+            treasury_withdrawal_destination_owner: *authority,
+            seller_fee_basis_points: 0,
+            requires_sign_off: false,
+            can_change_sale_price: false,
+            token_program: spl_token::id(),
+        }
+    }
+
+    pub fn fee_withdrawal_destination(mut self, destination: &Pubkey) -> Self {
+        self.fee_withdrawal_destination = *destination;
+        self
+    }
+
+    pub fn treasury_withdrawal_destination_owner(mut self, owner: &Pubkey) -> Self {
+        self.treasury_withdrawal_destination_owner = *owner;
+        self
+    }
+
+    pub fn seller_fee_basis_points(mut self, seller_fee_basis_points: u16) -> Self {
+        self.seller_fee_basis_points = seller_fee_basis_points;
+        self
+    }
+
+    pub fn requires_sign_off(mut self, requires_sign_off: bool) -> Self {
+        self.requires_sign_off = requires_sign_off;
+        self
+    }
+
+    pub fn can_change_sale_price(mut self, can_change_sale_price: bool) -> Self {
+        self.can_change_sale_price = can_change_sale_price;
+        self
+    }
+
+    /// Token program owning the treasury mint.
+    pub fn token_program(mut self, token_program: &Pubkey) -> Self {
+        self.token_program = *token_program;
+        self
+    }
+
+    pub fn keys(&self) -> AuctionHouseKeys {
+        AuctionHouseKeys::new(&self.authority, &self.treasury_mint)
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let (auction_house, bump) =
+            find_auction_house_address(&self.authority, &self.treasury_mint);
+        let (auction_house_fee_account, fee_payer_bump) =
+            find_auction_house_fee_account_address(&auction_house);
+        let (auction_house_treasury, treasury_bump) =
+            find_auction_house_treasury_address(&auction_house);
+        let treasury_withdrawal_destination = if self.keys().is_native() {
+            self.treasury_withdrawal_destination_owner
+        } else {
+            get_associated_token_address_with_program_id(
+                &self.treasury_withdrawal_destination_owner,
+                &self.treasury_mint,
+                &self.token_program,
+            )
+        };
+
+        let accounts = accounts::CreateAuctionHouse {
+            treasury_mint: self.treasury_mint,
+            payer: self.payer,
+            authority: self.authority,
+            fee_withdrawal_destination: self.fee_withdrawal_destination,
+            treasury_withdrawal_destination,
+            treasury_withdrawal_destination_owner: self.treasury_withdrawal_destination_owner,
+            auction_house,
+            auction_house_fee_account,
+            auction_house_treasury,
+            token_program: self.token_program,
+            system_program: system_program::id(),
+            ata_program: spl_associated_token_account::id(),
+            rent: sysvar::rent::id(),
+        }
+        .to_account_metas(None);
+        program_instruction(
+            accounts,
+            instruction::CreateAuctionHouse {
+                _bump: bump,
+                fee_payer_bump,
+                treasury_bump,
+                seller_fee_basis_points: self.seller_fee_basis_points,
+                requires_sign_off: self.requires_sign_off,
+                can_change_sale_price: self.can_change_sale_price,
+            },
+        )
+    }
+}
+
+/// Builds `sell`, signed by the seller, who pays for the trade state.
+pub struct SellBuilder {
+    auction_house: AuctionHouseKeys,
+    tokens: TokenKeys,
+    price: u64,
+    token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
+}
+
+impl SellBuilder {
+    /// List the tokens in `tokens.owner`'s token account, one token unless set otherwise.
+    pub fn new(auction_house: &AuctionHouseKeys, tokens: &TokenKeys, price: u64) -> Self {
+        Self {
+            auction_house: *auction_house,
+            tokens: *tokens,
+            price,
+            token_size: 1,
+            expiry: None,
+            referral: None,
+        }
+    }
+
+    pub fn token_size(mut self, token_size: u64) -> Self {
+        self.token_size = token_size;
+        self
+    }
+
+    pub fn expiry(mut self, expiry: i64) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    /// Pay `referrer` `basis_points` of the house fee when the order settles.
+    pub fn referral(mut self, referrer: &Pubkey, basis_points: u16) -> Self {
+        self.referral = Some(Referral {
+            referrer: *referrer,
+            basis_points,
+        });
+        self
+    }
+
+    pub fn trade_state(&self) -> Pubkey {
+        self.auction_house
+            .trade_state(
+                &self.tokens.owner,
+                &self.tokens.token_account,
+                &self.tokens.mint,
+                self.price,
+                self.token_size,
+            )
+            .0
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let ah = &self.auction_house;
+        let tokens = &self.tokens;
+        let (seller_trade_state, trade_state_bump) = ah.trade_state(
+            &tokens.owner,
+            &tokens.token_account,
+            &tokens.mint,
+            self.price,
+            self.token_size,
+        );
+        let (free_seller_trade_state, free_trade_state_bump) = ah.trade_state(
+            &tokens.owner,
+            &tokens.token_account,
+            &tokens.mint,
+            0,
+            self.token_size,
+        );
+        let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+
+        let mut accounts = accounts::Sell {
+            wallet: tokens.owner,
+            token_account: tokens.token_account,
+            metadata: tokens.metadata,
+            authority: ah.authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account,
+            seller_trade_state,
+            free_seller_trade_state,
+            token_program: tokens.token_program,
+            system_program: system_program::id(),
+            program_as_signer,
+            rent: sysvar::rent::id(),
+        }
+        .to_account_metas(None);
+        set_signer(&mut accounts, &tokens.owner, true);
+        program_instruction(
+            accounts,
+            instruction::Sell {
+                trade_state_bump,
+                free_trade_state_bump,
+                program_as_signer_bump,
+                buyer_price: self.price,
+                token_size: self.token_size,
+                expiry: self.expiry,
+                referral: self.referral,
+            },
+        )
+    }
+}
+
+/// Builds `buy` or `public_buy`, signed by the buyer, who pays for the trade state.
+pub struct BuyBuilder {
+    auction_house: AuctionHouseKeys,
+    buyer: Pubkey,
+    tokens: TokenKeys,
+    price: u64,
+    token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
+    royalty_choice: Option<u16>,
+    public: bool,
+}
+
+impl BuyBuilder {
+    /// Bid on the tokens in `tokens.owner`'s token account, one token unless set otherwise.
+    pub fn new(
+        auction_house: &AuctionHouseKeys,
+        buyer: &Pubkey,
+        tokens: &TokenKeys,
+        price: u64,
+    ) -> Self {
+        Self {
+            auction_house: *auction_house,
+            buyer: *buyer,
+            tokens: *tokens,
+            price,
+            token_size: 1,
+            expiry: None,
+            referral: None,
+            royalty_choice: None,
+            public: false,
+        }
+    }
+
+    pub fn token_size(mut self, token_size: u64) -> Self {
+        self.token_size = token_size;
+        self
+    }
+
+    pub fn expiry(mut self, expiry: i64) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    /// Pay `referrer` `basis_points` of the house fee when the order settles.
+    pub fn referral(mut self, referrer: &Pubkey, basis_points: u16) -> Self {
+        self.referral = Some(Referral {
+            referrer: *referrer,
+            basis_points,
+        });
+        self
+    }
+
+    /// Pay creators `share`, in basis points, of the metadata royalty, where the house's royalty policy allows it.
+    pub fn royalty_choice(mut self, share: u16) -> Self {
+        self.royalty_choice = Some(share);
+        self
+    }
+
+    /// Bid on the mint wherever it is held; the token account then only supplies the mint.
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    pub fn trade_state(&self) -> Pubkey {
+        self.trade_state_and_bump().0
+    }
+
+    fn trade_state_and_bump(&self) -> (Pubkey, u8) {
+        if self.public {
+            self.auction_house.public_bid_trade_state(
+                &self.buyer,
+                &self.tokens.mint,
+                self.price,
+                self.token_size,
+            )
+        } else {
+            self.auction_house.trade_state(
+                &self.buyer,
+                &self.tokens.token_account,
+                &self.tokens.mint,
+                self.price,
+                self.token_size,
+            )
+        }
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let ah = &self.auction_house;
+        let tokens = &self.tokens;
+        let (buyer_trade_state, trade_state_bump) = self.trade_state_and_bump();
+        let (escrow_payment_account, escrow_payment_bump) =
+            find_escrow_payment_address(&ah.address, &self.buyer);
+
+        let mut accounts = if self.public {
+            accounts::PublicBuy {
+                wallet: self.buyer,
+                payment_account: ah.payment_account(&self.buyer),
+                transfer_authority: self.buyer,
+                treasury_mint: ah.treasury_mint,
+                token_account: tokens.token_account,
+                metadata: tokens.metadata,
+                escrow_payment_account,
+                authority: ah.authority,
+                auction_house: ah.address,
+                auction_house_fee_account: ah.fee_account,
+                buyer_trade_state,
+                token_program: tokens.token_program,
+                system_program: system_program::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None)
+        } else {
+            accounts::Buy {
+                wallet: self.buyer,
+                payment_account: ah.payment_account(&self.buyer),
+                transfer_authority: self.buyer,
+                treasury_mint: ah.treasury_mint,
+                token_account: tokens.token_account,
+                metadata: tokens.metadata,
+                escrow_payment_account,
+                authority: ah.authority,
+                auction_house: ah.address,
+                auction_house_fee_account: ah.fee_account,
+                buyer_trade_state,
+                token_program: tokens.token_program,
+                system_program: system_program::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None)
+        };
+        set_signer(&mut accounts, &self.buyer, true);
+        if self.public {
+            program_instruction(
+                accounts,
+                instruction::PublicBuy {
+                    trade_state_bump,
+                    escrow_payment_bump,
+                    buyer_price: self.price,
+                    token_size: self.token_size,
+                    expiry: self.expiry,
+                    referral: self.referral,
+                    royalty_choice: self.royalty_choice,
+                },
+            )
+        } else {
+            program_instruction(
+                accounts,
+                instruction::Buy {
+                    trade_state_bump,
+                    escrow_payment_bump,
+                    buyer_price: self.price,
+                    token_size: self.token_size,
+                    expiry: self.expiry,
+                    referral: self.referral,
+                    royalty_choice: self.royalty_choice,
+                },
+            )
+        }
+    }
+}
+
+/// Builds `execute_sale`, or `execute_partial_sale` for a partial fill, signed by the auction
+/// house authority unless set otherwise.
+pub struct ExecuteSaleBuilder {
+    auction_house: AuctionHouseKeys,
+    buyer: Pubkey,
+    tokens: TokenKeys,
+    price: u64,
+    token_size: u64,
+    public: bool,
+    partial_order: Option<(u64, u64)>,
+    creators: Vec<Pubkey>,
+    collection: Option<Pubkey>,
+    referrers: Vec<Pubkey>,
+    signer: Pubkey,
+}
+
+impl ExecuteSaleBuilder {
+    /// Match `buyer`'s private bid with the listing of the tokens in `tokens.owner`'s token
+    /// account, both at `price` for one token unless set otherwise.
+    pub fn new(
+        auction_house: &AuctionHouseKeys,
+        buyer: &Pubkey,
+        tokens: &TokenKeys,
+        price: u64,
+    ) -> Self {
+        Self {
+            auction_house: *auction_house,
+            buyer: *buyer,
+            tokens: *tokens,
+            price,
+            token_size: 1,
+            public: false,
+            partial_order: None,
+            creators: vec![],
+            collection: None,
+            referrers: vec![],
+            signer: auction_house.authority,
+        }
+    }
+
+    pub fn token_size(mut self, token_size: u64) -> Self {
+        self.token_size = token_size;
+        self
+    }
+
+    /// Match the buyer's public bid instead of a private one.
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    /// Fill `size` of the listed tokens for `price` through `execute_partial_sale`, matching
+    /// the buyer's bid for that size and price.
+    pub fn partial(mut self, size: u64, price: u64) -> Self {
+        self.partial_order = Some((size, price));
+        self
+    }
+
+    /// Creators listed in the metadata, in order, who are paid royalties.
+    pub fn creators(mut self, creators: &[Pubkey]) -> Self {
+        self.creators = creators.to_vec();
+        self
+    }
+
+    /// Verified collection of the tokens, whose fee schedule sets the house fee and whose stats the sale updates.
+    pub fn collection(mut self, collection: &Pubkey) -> Self {
+        self.collection = Some(*collection);
+        self
+    }
+
+    /// Referrers of the listing and then the bid, leaving out either side without a referral.
+    pub fn referrers(mut self, referrers: &[Pubkey]) -> Self {
+        self.referrers = referrers.to_vec();
+        self
+    }
+
+    /// Sign as the buyer or the seller instead of the auction house authority.
+    pub fn signer(mut self, signer: &Pubkey) -> Self {
+        self.signer = *signer;
+        self
+    }
+
+    pub fn buyer_trade_state(&self) -> Pubkey {
+        self.bid().trade_state()
+    }
+
+    pub fn seller_trade_state(&self) -> Pubkey {
+        SellBuilder::new(&self.auction_house, &self.tokens, self.price)
+            .token_size(self.token_size)
+            .trade_state()
+    }
+
+    fn bid(&self) -> BuyBuilder {
+        let (size, price) = self.partial_order.unwrap_or((self.token_size, self.price));
+        let bid =
+            BuyBuilder::new(&self.auction_house, &self.buyer, &self.tokens, price).token_size(size);
+        if self.public {
+            bid.public()
+        } else {
+            bid
+        }
+    }
+
+    /// Creator accounts `execute_sale` takes after its named accounts.
+    pub fn creator_accounts(&self) -> Vec<AccountMeta> {
+        self.payee_accounts(&self.creators)
+    }
+
+    /// Fee schedule account `execute_sale` takes after the creator accounts for tokens in a verified collection.
+    pub fn fee_schedule_accounts(&self) -> Vec<AccountMeta> {
+        self.collection
+            .iter()
+            .map(|collection| {
+                let (fee_schedule, _) =
+                    find_fee_schedule_address(&self.auction_house.address, collection);
+                AccountMeta::new_readonly(fee_schedule, false)
+            })
+            .collect()
+    }
+
+    /// Collection stats account `execute_sale` takes after the fee schedule for tokens in a verified collection.
+    pub fn collection_stats_accounts(&self) -> Vec<AccountMeta> {
+        self.collection
+            .iter()
+            .map(|collection| {
+                let (collection_stats, _) =
+                    find_collection_stats_address(&self.auction_house.address, collection);
+                AccountMeta::new(collection_stats, false)
+            })
+            .collect()
+    }
+
+    /// Trader stats accounts `execute_sale` takes after the collection accounts: the buyer's, then the seller's.
+    pub fn trader_stats_accounts(&self) -> Vec<AccountMeta> {
+        [self.buyer, self.tokens.owner]
+            .iter()
+            .map(|wallet| {
+                let (trader_stats, _) =
+                    find_trader_stats_address(&self.auction_house.address, wallet);
+                AccountMeta::new(trader_stats, false)
+            })
+            .collect()
+    }
+
+    /// Referrer accounts `execute_sale` takes after the trader stats accounts. Referrals naming the buyer or the
+    /// seller are dropped at settlement and take no accounts.
+    pub fn referrer_accounts(&self) -> Vec<AccountMeta> {
+        let referrers: Vec<Pubkey> = self
+            .referrers
+            .iter()
+            .filter(|referrer| **referrer != self.buyer && **referrer != self.tokens.owner)
+            .copied()
+            .collect();
+        self.payee_accounts(&referrers)
+    }
+
+    /// Each wallet, followed by its treasury mint token account unless the treasury is native.
+    fn payee_accounts(&self, wallets: &[Pubkey]) -> Vec<AccountMeta> {
+        let mut accounts = vec![];
+        for wallet in wallets {
+            accounts.push(AccountMeta::new(*wallet, false));
+            if !self.auction_house.is_native() {
+                accounts.push(AccountMeta::new(
+                    self.auction_house.payment_account(wallet),
+                    false,
+                ));
+            }
+        }
+        accounts
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let ah = &self.auction_house;
+        let tokens = &self.tokens;
+        let seller = tokens.owner;
+        let (free_trade_state, free_trade_state_bump) = ah.trade_state(
+            &seller,
+            &tokens.token_account,
+            &tokens.mint,
+            0,
+            self.token_size,
+        );
+        let (escrow_payment_account, escrow_payment_bump) =
+            find_escrow_payment_address(&ah.address, &self.buyer);
+        let (escrow_nft_account, escrow_nft_bump) =
+            find_escrow_nft_address(&ah.address, &seller, &tokens.mint, &self.buyer);
+        let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+
+        let mut accounts = accounts::ExecuteSale {
+            buyer: self.buyer,
+            seller,
+            token_account: tokens.token_account,
+            token_mint: tokens.mint,
+            escrow_nft_account,
+            escrow_nft_record: find_escrow_nft_record_address(&escrow_nft_account).0,
+            metadata: tokens.metadata,
+            treasury_mint: ah.treasury_mint,
+            escrow_payment_account,
+            seller_payment_receipt_account: ah.payment_account(&seller),
+            buyer_receipt_token_account: tokens.token_account_of(&self.buyer),
+            authority: ah.authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account,
+            auction_house_treasury: ah.treasury,
+            buyer_trade_state: self.buyer_trade_state(),
+            seller_trade_state: self.seller_trade_state(),
+            free_trade_state,
+            token_program: tokens.token_program,
+            system_program: system_program::id(),
+            ata_program: spl_associated_token_account::id(),
+            program_as_signer,
+            rent: sysvar::rent::id(),
+        }
+        .to_account_metas(None);
+        set_signer(&mut accounts, &self.signer, false);
+        accounts.extend(self.creator_accounts());
+        accounts.extend(self.fee_schedule_accounts());
+        accounts.extend(self.collection_stats_accounts());
+        accounts.extend(self.trader_stats_accounts());
+        accounts.extend(self.referrer_accounts());
+        match self.partial_order {
+            Some((partial_order_size, partial_order_price)) => program_instruction(
+                accounts,
+                instruction::ExecutePartialSale {
+                    escrow_payment_bump,
+                    _free_trade_state_bump: free_trade_state_bump,
+                    program_as_signer_bump,
+                    escrow_nft_bump,
+                    buyer_price: self.price,
+                    token_size: self.token_size,
+                    partial_order_size,
+                    partial_order_price,
+                },
+            ),
+            None => program_instruction(
+                accounts,
+                instruction::ExecuteSale {
+                    escrow_payment_bump,
+                    _free_trade_state_bump: free_trade_state_bump,
+                    program_as_signer_bump,
+                    escrow_nft_bump,
+                    buyer_price: self.price,
+                    token_size: self.token_size,
+                },
+            ),
+        }
+    }
+}
+
+/// Builds `cancel` for a listing or a private bid, signed by the wallet that placed it.
+pub struct CancelBuilder {
+    auction_house: AuctionHouseKeys,
+    wallet: Pubkey,
+    tokens: TokenKeys,
+    price: u64,
+    token_size: u64,
+}
+
+impl CancelBuilder {
+    /// Cancel `wallet`'s order on the tokens in `tokens.owner`'s token account, which is a
+    /// listing when `wallet` is the owner, for one token unless set otherwise.
+    pub fn new(
+        auction_house: &AuctionHouseKeys,
+        wallet: &Pubkey,
+        tokens: &TokenKeys,
+        price: u64,
+    ) -> Self {
+        Self {
+            auction_house: *auction_house,
+            wallet: *wallet,
+            tokens: *tokens,
+            price,
+            token_size: 1,
+        }
+    }
+
+    pub fn token_size(mut self, token_size: u64) -> Self {
+        self.token_size = token_size;
+        self
+    }
+
+    pub fn trade_state(&self) -> Pubkey {
+        self.auction_house
+            .trade_state(
+                &self.wallet,
+                &self.tokens.token_account,
+                &self.tokens.mint,
+                self.price,
+                self.token_size,
+            )
+            .0
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let ah = &self.auction_house;
+        let mut accounts = accounts::Cancel {
+            wallet: self.wallet,
+            token_account: self.tokens.token_account,
+            token_mint: self.tokens.mint,
+            authority: ah.authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account,
+            trade_state: self.trade_state(),
+            token_program: self.tokens.token_program,
+        }
+        .to_account_metas(None);
+        set_signer(&mut accounts, &self.wallet, true);
+        program_instruction(
+            accounts,
+            instruction::Cancel {
+                buyer_price: self.price,
+                token_size: self.token_size,
+            },
+        )
+    }
+}
+
+/// Builds `deposit`, signed by the wallet funding its escrow.
+pub struct DepositBuilder {
+    auction_house: AuctionHouseKeys,
+    wallet: Pubkey,
+    amount: u64,
+}
+
+impl DepositBuilder {
+    pub fn new(auction_house: &AuctionHouseKeys, wallet: &Pubkey, amount: u64) -> Self {
+        Self {
+            auction_house: *auction_house,
+            wallet: *wallet,
+            amount,
+        }
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let ah = &self.auction_house;
+        let (escrow_payment_account, escrow_payment_bump) =
+            find_escrow_payment_address(&ah.address, &self.wallet);
+        let mut accounts = accounts::Deposit {
+            wallet: self.wallet,
+            payment_account: ah.payment_account(&self.wallet),
+            transfer_authority: self.wallet,
+            escrow_payment_account,
+            treasury_mint: ah.treasury_mint,
+            authority: ah.authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account,
+            token_program: spl_token::id(),
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+        }
+        .to_account_metas(None);
+        set_signer(&mut accounts, &self.wallet, true);
+        program_instruction(
+            accounts,
+            instruction::Deposit {
+                escrow_payment_bump,
+                amount: self.amount,
+            },
+        )
+    }
+}
+
+/// Builds `withdraw`, signed by the wallet emptying its escrow.
+pub struct WithdrawBuilder {
+    auction_house: AuctionHouseKeys,
+    wallet: Pubkey,
+    amount: u64,
+}
+
+impl WithdrawBuilder {
+    pub fn new(auction_house: &AuctionHouseKeys, wallet: &Pubkey, amount: u64) -> Self {
+        Self {
+            auction_house: *auction_house,
+            wallet: *wallet,
+            amount,
+        }
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let ah = &self.auction_house;
+        let (escrow_payment_account, escrow_payment_bump) =
+            find_escrow_payment_address(&ah.address, &self.wallet);
+        let mut accounts = accounts::Withdraw {
+            wallet: self.wallet,
+            receipt_account: ah.payment_account(&self.wallet),
+            escrow_payment_account,
+            treasury_mint: ah.treasury_mint,
+            authority: ah.authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account,
+            token_program: spl_token::id(),
+            system_program: system_program::id(),
+            ata_program: spl_associated_token_account::id(),
+            rent: sysvar::rent::id(),
+        }
+        .to_account_metas(None);
+        set_signer(&mut accounts, &self.wallet, true);
+        program_instruction(
+            accounts,
+            instruction::Withdraw {
+                escrow_payment_bump,
+                amount: self.amount,
+            },
+        )
+    }
+}
+
+/// Builds `print_listing_receipt`, which must directly follow the listing in its transaction.
+pub struct PrintListingReceiptBuilder {
+    bookkeeper: Pubkey,
+    trade_state: Pubkey,
+}
+
+impl PrintListingReceiptBuilder {
+    pub fn new(bookkeeper: &Pubkey, seller_trade_state: &Pubkey) -> Self {
+        Self {
+            bookkeeper: *bookkeeper,
+            trade_state: *seller_trade_state,
+        }
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let (receipt, receipt_bump) = find_listing_receipt_address(&self.trade_state);
+        let accounts = accounts::PrintListingReceipt {
+            receipt,
+            bookkeeper: self.bookkeeper,
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+            instruction: sysvar::instructions::id(),
+        }
+        .to_account_metas(None);
+        program_instruction(accounts, instruction::PrintListingReceipt { receipt_bump })
+    }
+}
+
+/// Builds `cancel_listing_receipt`, which must directly follow the cancel in its transaction.
+pub struct CancelListingReceiptBuilder {
+    trade_state: Pubkey,
+}
+
+impl CancelListingReceiptBuilder {
+    pub fn new(seller_trade_state: &Pubkey) -> Self {
+        Self {
+            trade_state: *seller_trade_state,
+        }
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let accounts = accounts::CancelListingReceipt {
+            receipt: find_listing_receipt_address(&self.trade_state).0,
+            system_program: system_program::id(),
+            instruction: sysvar::instructions::id(),
+        }
+        .to_account_metas(None);
+        program_instruction(accounts, instruction::CancelListingReceipt {})
+    }
+}
+
+/// Builds `print_bid_receipt`, which must directly follow the bid in its transaction.
+pub struct PrintBidReceiptBuilder {
+    bookkeeper: Pubkey,
+    trade_state: Pubkey,
+}
+
+impl PrintBidReceiptBuilder {
+    pub fn new(bookkeeper: &Pubkey, buyer_trade_state: &Pubkey) -> Self {
+        Self {
+            bookkeeper: *bookkeeper,
+            trade_state: *buyer_trade_state,
+        }
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let (receipt, receipt_bump) = find_bid_receipt_address(&self.trade_state);
+        let accounts = accounts::PrintBidReceipt {
+            receipt,
+            bookkeeper: self.bookkeeper,
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+            instruction: sysvar::instructions::id(),
+        }
+        .to_account_metas(None);
+        program_instruction(accounts, instruction::PrintBidReceipt { receipt_bump })
+    }
+}
+
+/// Builds `cancel_bid_receipt`, which must directly follow the cancel in its transaction.
+pub struct CancelBidReceiptBuilder {
+    trade_state: Pubkey,
+}
+
+impl CancelBidReceiptBuilder {
+    pub fn new(buyer_trade_state: &Pubkey) -> Self {
+        Self {
+            trade_state: *buyer_trade_state,
+        }
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let accounts = accounts::CancelBidReceipt {
+            receipt: find_bid_receipt_address(&self.trade_state).0,
+            system_program: system_program::id(),
+            instruction: sysvar::instructions::id(),
+        }
+        .to_account_metas(None);
+        program_instruction(accounts, instruction::CancelBidReceipt {})
+    }
+}
+
+/// Builds `print_purchase_receipt`, which must directly follow the sale in its transaction.
+pub struct PrintPurchaseReceiptBuilder {
+    bookkeeper: Pubkey,
+    seller_trade_state: Pubkey,
+    buyer_trade_state: Pubkey,
+}
+
+impl PrintPurchaseReceiptBuilder {
+    pub fn new(
+        bookkeeper: &Pubkey,
+        seller_trade_state: &Pubkey,
+        buyer_trade_state: &Pubkey,
+    ) -> Self {
+        Self {
+            bookkeeper: *bookkeeper,
+            seller_trade_state: *seller_trade_state,
+            buyer_trade_state: *buyer_trade_state,
+        }
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let (purchase_receipt, purchase_receipt_bump) =
+            find_purchase_receipt_address(&self.seller_trade_state, &self.buyer_trade_state);
+        let accounts = accounts::PrintPurchaseReceipt {
+            purchase_receipt,
+            listing_receipt: find_listing_receipt_address(&self.seller_trade_state).0,
+            bid_receipt: find_bid_receipt_address(&self.buyer_trade_state).0,
+            bookkeeper: self.bookkeeper,
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+            instruction: sysvar::instructions::id(),
+        }
+        .to_account_metas(None);
+        program_instruction(
+            accounts,
+            instruction::PrintPurchaseReceipt {
+                purchase_receipt_bump,
+            },
+        )
+    }
+}
+
+/// Decode an `AuctionHouse` account, checking its discriminator.
+pub fn decode_auction_house(data: &[u8]) -> Result<AuctionHouse> {
+    AuctionHouse::try_deserialize(&mut &data[..])
+}
+
+/// Decode a `ListingReceipt` account, checking its discriminator.
+pub fn decode_listing_receipt(data: &[u8]) -> Result<ListingReceipt> {
+    ListingReceipt::try_deserialize(&mut &data[..])
+}
+
+/// Decode a `BidReceipt` account, checking its discriminator.
+pub fn decode_bid_receipt(data: &[u8]) -> Result<BidReceipt> {
+    BidReceipt::try_deserialize(&mut &data[..])
+}
+
+/// Decode a `PurchaseReceipt` account, checking its discriminator.
+pub fn decode_purchase_receipt(data: &[u8]) -> Result<PurchaseReceipt> {
+    PurchaseReceipt::try_deserialize(&mut &data[..])
+}
@@ -0,0 +1,607 @@
+//! Collection-wide bids.
+//! `collection_buy` records a `CollectionBid` PDA offering a price per token for up to `quantity` tokens of any mint
+//! in a verified Metaplex collection, and funds the bidder's escrow payment account for all of them.
+//! `execute_collection_sale` matches the bid against a listing of any token in that collection by creating a
+//! buyer trade state keyed by `COLLECTION_BID` for `execute_sale_logic`, so each fill draws down the same escrow
+//! without touching the bidder's private bids. `cancel_collection_bid` closes the bid; the escrowed funds stay
+//! available to withdraw.
+
+use anchor_lang::{prelude::*, AnchorDeserialize};
+
+use crate::{
+    bid::*, constants::*, errors::*, execute_sale::*, utils::*, AuctionHouse, CollectionBid, *,
+};
+
+/// Accounts for the [`collection_buy` handler](auction_house/fn.collection_buy.html).
+#[derive(Accounts)]
+#[instruction(escrow_payment_bump: u8, price: u64, quantity: u64)]
+pub struct CollectionBuy<'info> {
+    /// User wallet account, paying for the collection bid PDA.
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// CHECK: Validated in fund_escrow_payment_account.
+    /// User SOL or SPL account to transfer funds from.
+    #[account(mut)]
+    pub payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in fund_escrow_payment_account.
+    /// SPL token account transfer authority.
+    pub transfer_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House instance treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Unpacked as a mint of either token program in constraint.
+    /// Collection mint account that listed tokens must be verified members of.
+    #[account(
+        constraint = unpack_mint(&collection_mint).is_ok() @ AuctionHouseError::IncorrectOwner
+    )]
+    pub collection_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            wallet.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in get_fee_payer.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// Collection bid PDA account.
+    #[account(
+        init,
+        payer=wallet,
+        space=COLLECTION_BID_SIZE,
+        seeds = [
+            PREFIX.as_bytes(),
+            COLLECTION_BID.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            collection_mint.key().as_ref(),
+            &price.to_le_bytes()
+        ],
+        bump
+    )]
+    pub collection_bid: Box<Account<'info, CollectionBid>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for the [`execute_collection_sale` handler](auction_house/fn.execute_collection_sale.html).
+#[derive(Accounts, Clone)]
+#[instruction(
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+    buyer_price: u64,
+    token_size: u64
+)]
+pub struct ExecuteCollectionSale<'info> {
+    /// CHECK: Checked against the collection bid.
+    /// Buyer user wallet account, receiving the collection bid rent once it is filled.
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Seller user wallet account.
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Token account where the SPL token is stored.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Token mint account for the SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow token account receiving the sold token.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            seller.key().as_ref(),
+            token_mint.key().as_ref(),
+            buyer.key().as_ref(),
+            NFT.as_bytes()
+        ],
+        bump
+    )]
+    pub escrow_nft_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow NFT record, created with the escrow NFT account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            ESCROW_NFT_RECORD.as_bytes(),
+            escrow_nft_account.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_nft_record: UncheckedAccount<'info>,
+
+    /// CHECK: Checked for the collection in execute_collection_sale.
+    /// Metaplex metadata account decorating SPL mint account.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Auction House treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account funding the collection bid.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            buyer.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Seller SOL or SPL account to receive payment at.
+    #[account(mut)]
+    pub seller_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer SPL token account to receive purchased item at.
+    #[account(mut)]
+    pub buyer_receipt_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Auction House instance authority.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_treasury,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance treasury account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            TREASURY.as_bytes()
+        ],
+        bump=auction_house.treasury_bump
+    )]
+    pub auction_house_treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in create_buyer_trade_state.
+    /// Buyer trade state PDA account, created for this fill and closed by the sale.
+    #[account(mut)]
+    pub buyer_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account encoding the sell order.
+    /// A closed trade state has no bump byte and fails the seeds check.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            &buyer_price.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump = seller_trade_state.to_account_info().data.borrow().first().copied().unwrap_or_default()
+    )]
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Free seller trade state PDA account encoding a free sell order.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            &0u64.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub free_trade_state: UncheckedAccount<'info>,
+
+    /// Collection bid PDA account being filled.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            COLLECTION_BID.as_bytes(),
+            buyer.key().as_ref(),
+            auction_house.key().as_ref(),
+            collection_bid.collection.as_ref(),
+            &collection_bid.price.to_le_bytes()
+        ],
+        bump=collection_bid.bump,
+        has_one=auction_house
+    )]
+    pub collection_bid: Box<Account<'info, CollectionBid>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> From<ExecuteCollectionSale<'info>> for ExecuteSale<'info> {
+    fn from(a: ExecuteCollectionSale<'info>) -> ExecuteSale<'info> {
+        ExecuteSale {
+            buyer: a.buyer,
+            seller: a.seller,
+            token_account: a.token_account,
+            token_mint: a.token_mint,
+            escrow_nft_account: a.escrow_nft_account,
+            escrow_nft_record: a.escrow_nft_record,
+            metadata: a.metadata,
+            treasury_mint: a.treasury_mint,
+            escrow_payment_account: a.escrow_payment_account,
+            seller_payment_receipt_account: a.seller_payment_receipt_account,
+            buyer_receipt_token_account: a.buyer_receipt_token_account,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            auction_house_treasury: a.auction_house_treasury,
+            buyer_trade_state: a.buyer_trade_state,
+            seller_trade_state: a.seller_trade_state,
+            free_trade_state: a.free_trade_state,
+            token_program: a.token_program,
+            system_program: a.system_program,
+            ata_program: a.ata_program,
+            program_as_signer: a.program_as_signer,
+            rent: a.rent,
+        }
+    }
+}
+
+/// Accounts for the [`cancel_collection_bid` handler](auction_house/fn.cancel_collection_bid.html).
+#[derive(Accounts)]
+pub struct CancelCollectionBid<'info> {
+    /// CHECK: Checked against the collection bid.
+    /// User wallet account, receiving the collection bid rent.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in cancel_collection_bid.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Collection bid PDA account.
+    #[account(
+        mut,
+        close=wallet,
+        seeds = [
+            PREFIX.as_bytes(),
+            COLLECTION_BID.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            collection_bid.collection.as_ref(),
+            &collection_bid.price.to_le_bytes()
+        ],
+        bump=collection_bid.bump,
+        has_one=auction_house,
+        has_one=wallet
+    )]
+    pub collection_bid: Box<Account<'info, CollectionBid>>,
+}
+
+/// Bid `price` per token for up to `quantity` tokens of any mint in the verified collection of `collection_mint`,
+/// funding the escrow payment account with `price * quantity`.
+pub fn collection_buy<'info>(
+    ctx: Context<'_, '_, '_, 'info, CollectionBuy<'info>>,
+    escrow_payment_bump: u8,
+    price: u64,
+    quantity: u64,
+) -> Result<()> {
+    let wallet = &ctx.accounts.wallet;
+    let payment_account = &ctx.accounts.payment_account;
+    let transfer_authority = &ctx.accounts.transfer_authority;
+    let treasury_mint = &ctx.accounts.treasury_mint;
+    let escrow_payment_account = &ctx.accounts.escrow_payment_account;
+    let authority = &ctx.accounts.authority;
+    let auction_house = &ctx.accounts.auction_house;
+    let auction_house_fee_account = &ctx.accounts.auction_house_fee_account;
+    let token_program = &ctx.accounts.token_program;
+    let system_program = &ctx.accounts.system_program;
+    let rent = &ctx.accounts.rent;
+
+    // Collection bids are open to any holder, so they follow a delegated PublicBuy scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::PublicBuy as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let escrow_canonical_bump = *ctx
+        .bumps
+        .get("escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    if escrow_canonical_bump != escrow_payment_bump {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    if quantity == 0 {
+        return Err(AuctionHouseError::InvalidTokenAmount.into());
+    }
+    let total = price
+        .checked_mul(quantity)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+
+    let auction_house_key = auction_house.key();
+    let seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        FEE_PAYER.as_bytes(),
+        &[auction_house.fee_payer_bump],
+    ];
+    let (fee_payer, fee_seeds) = get_fee_payer(
+        authority,
+        auction_house,
+        wallet.to_account_info(),
+        auction_house_fee_account.to_account_info(),
+        &seeds,
+    )?;
+
+    let is_native = treasury_mint.key() == spl_token::native_mint::id();
+    let wallet_key = wallet.key();
+    let escrow_signer_seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        wallet_key.as_ref(),
+        &[escrow_payment_bump],
+    ];
+    create_program_token_account_if_not_present(
+        escrow_payment_account,
+        system_program,
+        &fee_payer,
+        token_program,
+        treasury_mint,
+        &auction_house.to_account_info(),
+        rent,
+        &escrow_signer_seeds,
+        fee_seeds,
+        is_native,
+    )?;
+    fund_escrow_payment_account(
+        wallet,
+        payment_account,
+        transfer_authority,
+        escrow_payment_account,
+        treasury_mint,
+        token_program,
+        system_program,
+        rent,
+        is_native,
+        total,
+    )?;
+
+    let collection_bid = &mut ctx.accounts.collection_bid;
+    collection_bid.auction_house = auction_house_key;
+    collection_bid.wallet = wallet_key;
+    collection_bid.collection = ctx.accounts.collection_mint.key();
+    collection_bid.price = price;
+    collection_bid.quantity = quantity;
+    collection_bid.bump = *ctx
+        .bumps
+        .get("collection_bid")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    Ok(())
+}
+
+/// Sell `token_size` of a listed token in the bid's collection to the collection bid, at `buyer_price`,
+/// which must be the bid's price per token times `token_size`. The bid is closed once fully filled.
+pub fn execute_collection_sale<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteCollectionSale<'info>>,
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // Collection sales settle like any other sale, so they follow a delegated ExecuteSale scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::ExecuteSale as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let escrow_canonical_bump = *ctx
+        .bumps
+        .get("escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let free_trade_state_canonical_bump = *ctx
+        .bumps
+        .get("free_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let escrow_nft_canonical_bump = *ctx
+        .bumps
+        .get("escrow_nft_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if (escrow_canonical_bump != escrow_payment_bump)
+        || (free_trade_state_canonical_bump != free_trade_state_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+        || (escrow_nft_canonical_bump != escrow_nft_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    let collection_bid = &ctx.accounts.collection_bid;
+    if token_size == 0 || token_size > collection_bid.quantity {
+        return Err(AuctionHouseError::CollectionBidQuantityExceeded.into());
+    }
+    let price = collection_bid
+        .price
+        .checked_mul(token_size)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    if price != buyer_price {
+        return Err(AuctionHouseError::CollectionBidPriceMismatch.into());
+    }
+
+    assert_verified_collection_member(
+        &ctx.accounts.metadata,
+        &ctx.accounts.token_mint.key(),
+        &collection_bid.collection,
+    )?;
+
+    let mut accounts: ExecuteSale<'info> = (*ctx.accounts).clone().into();
+    // The buyer trade state is paid for the same way execute_sale_logic picks who it refunds.
+    let wallet = if accounts.buyer.is_signer {
+        accounts.buyer.to_account_info()
+    } else {
+        accounts.seller.to_account_info()
+    };
+    create_buyer_trade_state(&accounts, wallet, COLLECTION_BID, token_size)?;
+    execute_sale_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price,
+        token_size,
+        None,
+        None,
+        None,
+        None,
+        Some(COLLECTION_BID),
+    )?;
+
+    let collection_bid = &mut ctx.accounts.collection_bid;
+    collection_bid.quantity -= token_size;
+    if collection_bid.quantity == 0 {
+        // This moves lamports directly, so it has to come after the last CPI.
+        collection_bid.close(ctx.accounts.buyer.to_account_info())?;
+    }
+
+    Ok(())
+}
+
+/// Close a collection bid, returning its rent to the bidder. The escrowed funds stay available to withdraw.
+pub fn cancel_collection_bid<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelCollectionBid<'info>>,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // If the Cancel scope is delegated, bids can only be canceled through the Auctioneer.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Cancel as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    if !ctx.accounts.wallet.is_signer && !ctx.accounts.authority.is_signer {
+        return Err(AuctionHouseError::NoValidSignerPresent.into());
+    }
+
+    Ok(())
+}
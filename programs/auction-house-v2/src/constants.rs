@@ -6,8 +6,40 @@ pub const PURCHASE_RECEIPT_PREFIX: &str = "purchase_receipt";
 pub const BID_RECEIPT_PREFIX: &str = "bid_receipt";
 pub const LISTING_RECEIPT_PREFIX: &str = "listing_receipt";
 pub const NFT: &str = "nft";
-pub const TRADE_STATE_SIZE: usize = 1;
+pub const ESCROW_NFT_RECORD: &str = "escrow_nft_record";
+pub const AUCTIONEER: &str = "auctioneer";
+pub const AUCTION: &str = "auction";
+pub const AUCTION_BID: &str = "auction_bid";
+pub const DUTCH: &str = "dutch";
+pub const LISTING_CONFIG: &str = "listing_config";
+pub const COLLECTION_BID: &str = "collection_bid";
+pub const SWAP_OFFER: &str = "swap_offer";
+pub const BUNDLE: &str = "bundle";
+pub const FEE_SCHEDULE: &str = "fee_schedule";
+pub const TRADER_STATS: &str = "trader_stats";
+pub const COLLECTION_STATS: &str = "collection_stats";
+pub const TRADE_STATE_SIZE: usize = 1 + // bump
+8 + // expiry, 0 if the order never expires
+32 + // rent payer
+32 + // referrer, the default pubkey if the order has no referral
+2 + // referral basis points
+1 + 2; // royalty choice, a bid's share of the metadata royalty if its buyer chose one
+/// Trade states created before this layout hold only their bump.
+pub const LEGACY_TRADE_STATE_SIZE: usize = 1;
+pub const TRADE_STATE_EXPIRY_OFFSET: usize = 1;
+pub const TRADE_STATE_RENT_PAYER_OFFSET: usize = 9;
+pub const TRADE_STATE_REFERRER_OFFSET: usize = 41;
+pub const TRADE_STATE_REFERRAL_BASIS_POINTS_OFFSET: usize = 73;
+pub const TRADE_STATE_ROYALTY_CHOICE_OFFSET: usize = 75;
+/// Referrals on both sides of a sale together never exceed the house fee.
+pub const MAX_REFERRAL_BASIS_POINTS: u16 = 5000;
 pub const MAX_NUM_SCOPES: usize = 7;
+pub const COLLECTION_STATS_DAYS: usize = 7;
+pub const COLLECTION_STATS_SALES: usize = 10;
+pub const SECONDS_PER_DAY: i64 = 86_400;
+pub const MAX_BUNDLE_ITEMS: usize = 8;
+pub const MAX_ROYALTY_WAIVED_COLLECTIONS: usize = 4;
+pub const ROYALTY_POLICY_SIZE: usize = 1 + 32 * MAX_ROYALTY_WAIVED_COLLECTIONS;
 
 pub const AUCTION_HOUSE_SIZE: usize = 8 +                   // key
 32 +                                                        // fee Payer
@@ -27,5 +59,112 @@ pub const AUCTION_HOUSE_SIZE: usize = 8 +                   // key
 1 +                                                         // has external auctioneer program as an authority
 32 +                                                         // auctioneer address
 MAX_NUM_SCOPES +                                            // Array of AuthorityScope bools
-172                                                         // padding
+2 +                                                         // max referral basis points
+ROYALTY_POLICY_SIZE +                                       // royalty policy
+1 +                                                         // unverified creator policy
+40                                                          // padding
 ;
+
+pub const AUCTIONEER_SIZE: usize = 8 + // key
+32 + // auctioneer_authority
+32 + // auction_house
+1; // bump
+
+pub const AUCTION_STATE_SIZE: usize = 8 + // key
+32 + // auction_house
+32 + // seller
+32 + // seller_trade_state
+32 + // token_account
+32 + // token_mint
+8 + // token_size
+8 + // start_time
+8 + // end_time
+8 + // reserve_price
+8 + // min_increment
+8 + // extension_window
+32 + // highest_bidder
+8 + // highest_bid
+1 + // bump
+1; // escrow_payment_bump
+
+pub const LISTING_CONFIG_SIZE: usize = 8 + // key
+32 + // auction_house
+32 + // seller
+32 + // seller_trade_state
+32 + // token_account
+32 + // token_mint
+8 + // token_size
+8 + // start_price
+8 + // end_price
+8 + // start_time
+8 + // end_time
+1 + 8 + // curve
+1 + 8 + // sale_price
+1; // bump
+
+pub const COLLECTION_BID_SIZE: usize = 8 + // key
+32 + // auction_house
+32 + // wallet
+32 + // collection
+8 + // price
+8 + // quantity
+1; // bump
+
+pub const SWAP_OFFER_SIZE: usize = 8 + // key
+32 + // auction_house
+32 + // maker
+32 + // token_account
+32 + // token_mint
+8 + // token_size
+1 + 32 + // wanted
+8 + // wanted_size
+8 + // maker_extra
+8 + // taker_extra
+1; // bump
+
+pub const BUNDLE_LISTING_SIZE: usize = 8 + // key
+32 + // auction_house
+32 + // seller
+8 + // price
+4 + MAX_BUNDLE_ITEMS * (32 + 32 + 8) + // items
+1; // bump
+
+pub const FEE_SCHEDULE_SIZE: usize = 8 + // key
+32 + // auction_house
+32 + // collection
+2 + // seller_fee_basis_points
+1; // bump
+
+pub const TRADER_STATS_SIZE: usize = 8 + // key
+32 + // auction_house
+32 + // wallet
+8 + // buy_volume
+8 + // sell_volume
+8 + // trade_count
+8 + // first_trade_at
+8 + // last_trade_at
+8 + // fees_paid
+1; // bump
+
+pub const COLLECTION_STATS_SIZE: usize = 8 + // key
+32 + // auction_house
+32 + // collection
+8 + // last_sale_price
+8 + // last_sale_at
+8 + // volume
+8 + // sale_count
+8 * COLLECTION_STATS_DAYS + // daily_volume
+8 + // current_day
+8 * COLLECTION_STATS_SALES + // recent_prices
+8 * COLLECTION_STATS_SALES + // recent_sale_times
+8 + // twap
+8 + // floor
+1; // bump
+
+pub const ESCROW_NFT_RECORD_SIZE: usize = 8 + // key
+32 + // rent_payer
+8 + // price
+1; // bump
+
+/// Token-2022 extension type of a mint's transfer hook, which the pinned `spl-token-2022` predates and can't name.
+pub const TRANSFER_HOOK_EXTENSION_TYPE: u16 = 14;
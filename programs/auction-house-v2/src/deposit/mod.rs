@@ -1,6 +1,6 @@
 use anchor_lang::{prelude::*, solana_program::program::invoke, AnchorDeserialize};
 
-use crate::{constants::*, errors::*, utils::*, AuctionHouse, *};
+use crate::{constants::*, errors::*, events::*, utils::*, AuctionHouse, *};
 
 /// Accounts for the [`deposit` handler](auction_house/fn.deposit.html).
 #[derive(Accounts)]
@@ -31,8 +31,9 @@ pub struct Deposit<'info> {
     )]
     pub escrow_payment_account: UncheckedAccount<'info>,
 
+    /// CHECK: Checked against the Auction House treasury mint.
     /// Auction House instance treasury mint account.
-    pub treasury_mint: Box<Account<'info, Mint>>,
+    pub treasury_mint: UncheckedAccount<'info>,
 
     /// CHECK: Validated in deposit_logic.
     /// Auction House instance authority account.
@@ -65,7 +66,11 @@ pub struct Deposit<'info> {
     )]
     pub auction_house_fee_account: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
@@ -148,21 +153,14 @@ fn deposit_logic<'info>(
 
     if !is_native {
         assert_is_ata(payment_account, &wallet.key(), &treasury_mint.key())?;
-        invoke(
-            &spl_token::instruction::transfer(
-                token_program.key,
-                &payment_account.key(),
-                &escrow_payment_account.key(),
-                &transfer_authority.key(),
-                &[],
-                amount,
-            )?,
-            &[
-                escrow_payment_account.to_account_info(),
-                payment_account.to_account_info(),
-                token_program.to_account_info(),
-                transfer_authority.to_account_info(),
-            ],
+        transfer_tokens(
+            token_program,
+            payment_account,
+            treasury_mint,
+            escrow_payment_account,
+            transfer_authority,
+            amount,
+            &[],
         )?;
     } else {
         assert_keys_equal(payment_account.key(), wallet.key())?;
@@ -187,5 +185,12 @@ fn deposit_logic<'info>(
         )?;
     }
 
+    emit!(Deposited {
+        auction_house: auction_house_key,
+        wallet: wallet_key,
+        amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
     Ok(())
 }
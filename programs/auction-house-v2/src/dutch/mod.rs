@@ -0,0 +1,750 @@
+//! Dutch (descending price) listings.
+//! `create_dutch_listing` lists the token through `sell_logic` at a seller trade state keyed by `DUTCH` and
+//! records a `ListingConfig` PDA keyed by that trade state. `execute_dutch_sale` prices the listing from the
+//! `Clock` and settles it through `execute_sale_logic` against any bid at or above that price, which lets the
+//! seller lower the price over time without relisting. `cancel_dutch_listing` cancels the listing and closes its
+//! config.
+
+use anchor_lang::{prelude::*, AnchorDeserialize};
+
+use crate::{
+    cancel::*, constants::*, errors::*, execute_sale::*, sell::*, utils::*, AuctionHouse, *,
+};
+
+/// Accounts for the [`create_dutch_listing` handler](auction_house/fn.create_dutch_listing.html).
+#[derive(Accounts, Clone)]
+#[instruction(
+    trade_state_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    token_size: u64
+)]
+pub struct CreateDutchListing<'info> {
+    /// CHECK: Signs as the payer for the listing config.
+    /// Seller wallet account.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in the trade state seeds, owned by either token program.
+    /// SPL token account containing token for sale.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Metaplex metadata account decorating SPL mint account.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Auction House authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Account<'info, AuctionHouse>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account encoding the dutch listing, which has no fixed price.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            DUTCH.as_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Free seller trade state PDA account encoding a free sell order.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            &0u64.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub free_seller_trade_state: UncheckedAccount<'info>,
+
+    /// Dutch listing config PDA account.
+    #[account(
+        init,
+        payer=wallet,
+        space=LISTING_CONFIG_SIZE,
+        seeds = [
+            PREFIX.as_bytes(),
+            LISTING_CONFIG.as_bytes(),
+            seller_trade_state.key().as_ref()
+        ],
+        bump
+    )]
+    pub listing_config: Box<Account<'info, ListingConfig>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account of an auction listing of the same token, which must not be open.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            AUCTION.as_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub auction_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> From<CreateDutchListing<'info>> for Sell<'info> {
+    fn from(a: CreateDutchListing<'info>) -> Sell<'info> {
+        Sell {
+            wallet: a.wallet,
+            token_account: a.token_account,
+            metadata: a.metadata,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            seller_trade_state: a.seller_trade_state,
+            free_seller_trade_state: a.free_seller_trade_state,
+            token_program: a.token_program,
+            system_program: a.system_program,
+            program_as_signer: a.program_as_signer,
+            rent: a.rent,
+        }
+    }
+}
+
+/// Accounts for the [`execute_dutch_sale` handler](auction_house/fn.execute_dutch_sale.html).
+#[derive(Accounts, Clone)]
+#[instruction(
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+    buyer_price: u64,
+    token_size: u64
+)]
+pub struct ExecuteDutchSale<'info> {
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer user wallet account.
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the listing config.
+    /// Seller user wallet account.
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the listing config.
+    /// Token account where the SPL token is stored.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the listing config.
+    /// Token mint account for the SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow token account receiving the sold token.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            seller.key().as_ref(),
+            token_mint.key().as_ref(),
+            buyer.key().as_ref(),
+            NFT.as_bytes()
+        ],
+        bump
+    )]
+    pub escrow_nft_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow NFT record, created with the escrow NFT account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            ESCROW_NFT_RECORD.as_bytes(),
+            escrow_nft_account.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_nft_record: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Metaplex metadata account decorating SPL mint account.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Auction House treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            buyer.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Seller SOL or SPL account to receive payment at.
+    #[account(mut)]
+    pub seller_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer SPL token account to receive purchased item at.
+    #[account(mut)]
+    pub buyer_receipt_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Auction House instance authority.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_treasury,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance treasury account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            TREASURY.as_bytes()
+        ],
+        bump=auction_house.treasury_bump
+    )]
+    pub auction_house_treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer trade state PDA account encoding the buy order, at the most the buyer will pay.
+    #[account(mut)]
+    pub buyer_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account encoding the dutch listing, which has no fixed price.
+    /// A closed trade state has no bump byte and fails the seeds check.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            DUTCH.as_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump = seller_trade_state.to_account_info().data.borrow().first().copied().unwrap_or_default()
+    )]
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Free seller trade state PDA account encoding a free sell order.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            &0u64.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub free_trade_state: UncheckedAccount<'info>,
+
+    /// Dutch listing config PDA account pricing the seller trade state.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            LISTING_CONFIG.as_bytes(),
+            seller_trade_state.key().as_ref()
+        ],
+        bump=listing_config.bump,
+        has_one=auction_house,
+        has_one=seller,
+        has_one=token_account,
+        has_one=token_mint
+    )]
+    pub listing_config: Box<Account<'info, ListingConfig>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> From<ExecuteDutchSale<'info>> for ExecuteSale<'info> {
+    fn from(a: ExecuteDutchSale<'info>) -> ExecuteSale<'info> {
+        ExecuteSale {
+            buyer: a.buyer,
+            seller: a.seller,
+            token_account: a.token_account,
+            token_mint: a.token_mint,
+            escrow_nft_account: a.escrow_nft_account,
+            escrow_nft_record: a.escrow_nft_record,
+            metadata: a.metadata,
+            treasury_mint: a.treasury_mint,
+            escrow_payment_account: a.escrow_payment_account,
+            seller_payment_receipt_account: a.seller_payment_receipt_account,
+            buyer_receipt_token_account: a.buyer_receipt_token_account,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            auction_house_treasury: a.auction_house_treasury,
+            buyer_trade_state: a.buyer_trade_state,
+            seller_trade_state: a.seller_trade_state,
+            free_trade_state: a.free_trade_state,
+            token_program: a.token_program,
+            system_program: a.system_program,
+            ata_program: a.ata_program,
+            program_as_signer: a.program_as_signer,
+            rent: a.rent,
+        }
+    }
+}
+
+/// Accounts for the [`cancel_dutch_listing` handler](auction_house/fn.cancel_dutch_listing.html).
+#[derive(Accounts, Clone)]
+pub struct CancelDutchListing<'info> {
+    /// CHECK: Verified in cancel_logic.
+    /// Seller wallet account, receiving the listing config rent.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in cancel_logic, owned by either token program.
+    /// SPL token account containing the listed token.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the token account mint in cancel_logic.
+    /// Token mint account of SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in cancel_logic.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the listing config, validated in cancel_logic.
+    /// Seller trade state PDA account encoding the dutch listing.
+    #[account(mut)]
+    pub trade_state: UncheckedAccount<'info>,
+
+    /// Dutch listing config PDA account.
+    #[account(
+        mut,
+        close=wallet,
+        seeds = [
+            PREFIX.as_bytes(),
+            LISTING_CONFIG.as_bytes(),
+            trade_state.key().as_ref()
+        ],
+        bump=listing_config.bump,
+        has_one=auction_house
+    )]
+    pub listing_config: Box<Account<'info, ListingConfig>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+}
+
+impl<'info> From<CancelDutchListing<'info>> for Cancel<'info> {
+    fn from(a: CancelDutchListing<'info>) -> Cancel<'info> {
+        Cancel {
+            wallet: a.wallet,
+            token_account: a.token_account,
+            token_mint: a.token_mint,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            trade_state: a.trade_state,
+            token_program: a.token_program,
+        }
+    }
+}
+
+/// List a token at a price falling from `start_price` at `start_time` to `end_price` at `end_time` (unix seconds),
+/// following `curve`. The price stays at `end_price` after `end_time` until the listing sells or is canceled.
+#[allow(clippy::too_many_arguments)]
+pub fn create_dutch_listing<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateDutchListing<'info>>,
+    trade_state_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    token_size: u64,
+    start_price: u64,
+    end_price: u64,
+    start_time: i64,
+    end_time: i64,
+    curve: DecayCurve,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // Dutch listings are sell orders, so they follow a delegated Sell scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Sell as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let clock = Clock::get()?;
+    if end_time <= start_time || end_time <= clock.unix_timestamp {
+        return Err(AuctionHouseError::InvalidAuctionTimes.into());
+    }
+    if let DecayCurve::Stepwise { step_duration } = curve {
+        if step_duration <= 0 {
+            return Err(AuctionHouseError::InvalidAuctionTimes.into());
+        }
+    }
+    if end_price == 0 || start_price < end_price {
+        return Err(AuctionHouseError::InvalidDutchPrices.into());
+    }
+
+    let trade_state_canonical_bump = *ctx
+        .bumps
+        .get("seller_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let free_trade_state_canonical_bump = *ctx
+        .bumps
+        .get("free_seller_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let listing_config_bump = *ctx
+        .bumps
+        .get("listing_config")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if (trade_state_canonical_bump != trade_state_bump)
+        || (free_trade_state_canonical_bump != free_trade_state_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    // Whichever listing sold first would leave the other one's buyers without the token.
+    if trade_state_is_open(&ctx.accounts.auction_trade_state)? {
+        return Err(AuctionHouseError::ListingConflict.into());
+    }
+
+    let mut accounts: Sell<'info> = (*ctx.accounts).clone().into();
+    sell_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        ctx.program_id,
+        trade_state_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        u64::MAX,
+        token_size,
+        None,
+        None,
+        Some(DUTCH),
+    )?;
+
+    let listing_config = &mut ctx.accounts.listing_config;
+    listing_config.auction_house = ctx.accounts.auction_house.key();
+    listing_config.seller = ctx.accounts.wallet.key();
+    listing_config.seller_trade_state = ctx.accounts.seller_trade_state.key();
+    listing_config.token_account = ctx.accounts.token_account.key();
+    listing_config.token_mint = get_mint_from_token_account(&ctx.accounts.token_account)?;
+    listing_config.token_size = token_size;
+    listing_config.start_price = start_price;
+    listing_config.end_price = end_price;
+    listing_config.start_time = start_time;
+    listing_config.end_time = end_time;
+    listing_config.curve = curve;
+    listing_config.sale_price = None;
+    listing_config.bump = listing_config_bump;
+
+    Ok(())
+}
+
+/// Sell a dutch listing at its current price to a bid of at least that price.
+/// `buyer_price` is the price of the buyer trade state; the escrow only pays the current price.
+pub fn execute_dutch_sale<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteDutchSale<'info>>,
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // Dutch sales settle like any other sale, so they follow a delegated ExecuteSale scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::ExecuteSale as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let escrow_canonical_bump = *ctx
+        .bumps
+        .get("escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let free_trade_state_canonical_bump = *ctx
+        .bumps
+        .get("free_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let escrow_nft_canonical_bump = *ctx
+        .bumps
+        .get("escrow_nft_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if (escrow_canonical_bump != escrow_payment_bump)
+        || (free_trade_state_canonical_bump != free_trade_state_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+        || (escrow_nft_canonical_bump != escrow_nft_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    let listing_config = &ctx.accounts.listing_config;
+    if listing_config.sale_price.is_some() {
+        return Err(AuctionHouseError::ListingAlreadySold.into());
+    }
+
+    let clock = Clock::get()?;
+    if clock.unix_timestamp < listing_config.start_time {
+        return Err(AuctionHouseError::AuctionNotActive.into());
+    }
+
+    let price = dutch_price(listing_config, clock.unix_timestamp)?;
+    if price > buyer_price {
+        return Err(AuctionHouseError::BidBelowListingPrice.into());
+    }
+
+    // Native escrows are checked against the price in execute_sale_logic.
+    if ctx.accounts.treasury_mint.key() != spl_token::native_mint::id() {
+        let escrow = unpack_token_account(&ctx.accounts.escrow_payment_account)?;
+        if escrow.amount < price {
+            return Err(AuctionHouseError::InsufficientFunds.into());
+        }
+    }
+
+    let mut accounts: ExecuteSale<'info> = (*ctx.accounts).clone().into();
+    execute_sale_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price,
+        token_size,
+        None,
+        None,
+        Some(price),
+        None,
+        None,
+    )?;
+
+    ctx.accounts.listing_config.sale_price = Some(price);
+
+    Ok(())
+}
+
+/// Cancel a dutch listing if it is still open and close its config, returning the rent to the seller.
+/// Also reclaims the config of a sold listing.
+pub fn cancel_dutch_listing<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelDutchListing<'info>>,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let listing_config = &ctx.accounts.listing_config;
+
+    // If the Cancel scope is delegated, cancels must go through auctioneer_cancel.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Cancel as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    assert_keys_equal(ctx.accounts.wallet.key(), listing_config.seller)?;
+    assert_keys_equal(
+        ctx.accounts.trade_state.key(),
+        listing_config.seller_trade_state,
+    )?;
+    if !ctx.accounts.wallet.is_signer && !ctx.accounts.authority.is_signer {
+        return Err(AuctionHouseError::NoValidSignerPresent.into());
+    }
+
+    let trade_state = &ctx.accounts.trade_state;
+    let token_size = listing_config.token_size;
+    if !trade_state_is_open(trade_state)? {
+        return Ok(());
+    }
+
+    let mut accounts: Cancel<'info> = (*ctx.accounts).clone().into();
+    cancel_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        u64::MAX,
+        token_size,
+        Some(DUTCH),
+    )
+}
+
+/// Price of a dutch listing at `now`: `start_price` until `start_time`, falling along the curve to `end_price`
+/// at `end_time`. Partial drops round down, in the seller's favor.
+pub fn dutch_price(listing_config: &ListingConfig, now: i64) -> Result<u64> {
+    if now <= listing_config.start_time {
+        return Ok(listing_config.start_price);
+    }
+    if now >= listing_config.end_time {
+        return Ok(listing_config.end_price);
+    }
+
+    let duration = listing_config
+        .end_time
+        .checked_sub(listing_config.start_time)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    let mut elapsed = now
+        .checked_sub(listing_config.start_time)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    if let DecayCurve::Stepwise { step_duration } = listing_config.curve {
+        elapsed -= elapsed % step_duration;
+    }
+
+    let price_range = listing_config
+        .start_price
+        .checked_sub(listing_config.end_price)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    let drop = (price_range as u128)
+        .checked_mul(elapsed as u128)
+        .and_then(|drop| drop.checked_div(duration as u128))
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+
+    listing_config
+        .start_price
+        .checked_sub(drop as u64)
+        .ok_or_else(|| AuctionHouseError::NumericalOverflow.into())
+}
@@ -181,4 +181,128 @@ pub enum AuctionHouseError {
     // 6044
     #[msg("This sale requires exactly one signer: either the seller or the authority.")]
     SaleRequiresExactlyOneSigner,
+
+    // 6045
+    #[msg("The NFT escrow account already holds tokens for another buyer.")]
+    EscrowNftAccountNotEmpty,
+
+    // 6046
+    #[msg("The NFT escrow account holds no tokens.")]
+    EscrowNftAccountEmpty,
+
+    // 6047
+    #[msg("The auction must end after it starts and after the current time.")]
+    InvalidAuctionTimes,
+
+    // 6048
+    #[msg("The auction is not accepting bids.")]
+    AuctionNotActive,
+
+    // 6049
+    #[msg("The auction has not ended yet.")]
+    AuctionNotEnded,
+
+    // 6050
+    #[msg("The bid must meet the reserve price and beat the highest bid by the minimum increment.")]
+    BidTooLow,
+
+    // 6051
+    #[msg("A dutch listing must not rise in price and must end above zero.")]
+    InvalidDutchPrices,
+
+    // 6052
+    #[msg("The bid does not cover the current listing price.")]
+    BidBelowListingPrice,
+
+    // 6053
+    #[msg("The dutch listing has already sold.")]
+    ListingAlreadySold,
+
+    // 6054
+    #[msg("The expiry must be in the future.")]
+    InvalidExpiry,
+
+    // 6055
+    #[msg("The trade state has expired.")]
+    TradeStateExpired,
+
+    // 6056
+    #[msg("The trade state has not expired.")]
+    TradeStateNotExpired,
+
+    // 6057
+    #[msg("The token is not in the verified collection of the bid.")]
+    NotInCollection,
+
+    // 6058
+    #[msg("The sale price does not match the collection bid.")]
+    CollectionBidPriceMismatch,
+
+    // 6059
+    #[msg("The collection bid does not want that many more tokens.")]
+    CollectionBidQuantityExceeded,
+
+    // 6060
+    #[msg("The token program must be SPL Token or Token-2022.")]
+    InvalidTokenProgram,
+
+    // 6061
+    #[msg("The mint has a Token-2022 extension the Auction House does not support.")]
+    UnsupportedMintExtension,
+
+    // 6062
+    #[msg("The metadata does not belong to a programmable NFT.")]
+    NotProgrammableNft,
+
+    // 6063
+    #[msg("The offered token does not match what the swap offer wants.")]
+    SwapTargetMismatch,
+
+    // 6064
+    #[msg("A bundle needs one to eight distinct tokens, each with a nonzero weight.")]
+    InvalidBundle,
+
+    // 6065
+    #[msg("The accounts passed do not match the bundle's items.")]
+    BundleItemMismatch,
+
+    // 6066
+    #[msg("The price does not match the bundle listing.")]
+    BundlePriceMismatch,
+
+    // 6067
+    #[msg("The referral exceeds the Auction House maximum, refers a party to the sale, or cannot be recorded on this trade state.")]
+    InvalidReferral,
+
+    // 6068
+    #[msg("The Auction House royalty policy does not let bids choose this royalty share.")]
+    InvalidRoyaltyChoice,
+
+    // 6069
+    #[msg("Remaining accounts must list every metadata creator, in order, before any other accounts.")]
+    CreatorAccountMismatch,
+
+    // 6070
+    #[msg("Tokens in a verified collection must pass the collection's fee schedule address after the creator accounts.")]
+    FeeScheduleMismatch,
+
+    // 6071
+    #[msg("Sales must pass the buyer's and then the seller's trader stats address after the collection accounts.")]
+    TraderStatsMismatch,
+
+    // 6072
+    #[msg("Tokens in a verified collection must pass the collection's stats address after the fee schedule.")]
+    CollectionStatsMismatch,
+
+    // 6073
+    #[msg("The auction listing has been canceled or its token can no longer be sold.")]
+    AuctionListingClosed,
+
+    // 6074
+    #[msg("The token is already up for auction or in a dutch listing.")]
+    ListingConflict,
+
+    // 6075
+    #[msg("The seller cannot bid on their own auction.")]
+    SellerCannotBid,
 }
@@ -0,0 +1,460 @@
+//! Release the NFT held in the `escrow_nft_account` PDA after `execute_sale`.
+//! Claiming moves it on to the buyer. Refunding returns it to the seller when the Auction House authority undoes the
+//! sale, and the seller pays the sale price back to the buyer. Both close the escrow account and its record, returning
+//! their rent to whoever paid it.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program::invoke, system_instruction},
+    AnchorDeserialize,
+};
+use spl_token_2022::{instruction::close_account, state::Account as SplAccount};
+
+use crate::{constants::*, errors::*, utils::*, AuctionHouse, EscrowNftRecord, *};
+
+/// Accounts for the [`claim_nft` handler](auction_house/fn.claim_nft.html).
+#[derive(Accounts)]
+#[instruction(escrow_nft_bump: u8)]
+pub struct ClaimNft<'info> {
+    /// CHECK: Used as a seed for the escrow NFT account, validated as a signer in claim_nft_logic.
+    /// Buyer user wallet account.
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// CHECK: Used as a seed for the escrow NFT account.
+    /// Seller user wallet account.
+    pub seller: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the escrow NFT account mint.
+    /// Token mint account of the escrowed SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow token account holding the purchased NFT.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            seller.key().as_ref(),
+            token_mint.key().as_ref(),
+            buyer.key().as_ref(),
+            NFT.as_bytes()
+        ],
+        bump = escrow_nft_bump
+    )]
+    pub escrow_nft_account: UncheckedAccount<'info>,
+
+    /// Escrow NFT record, closed to its rent payer.
+    #[account(
+        mut,
+        close=rent_payer,
+        seeds = [
+            PREFIX.as_bytes(),
+            ESCROW_NFT_RECORD.as_bytes(),
+            escrow_nft_account.key().as_ref()
+        ],
+        bump=escrow_nft_record.bump,
+        has_one=rent_payer
+    )]
+    pub escrow_nft_record: Box<Account<'info, EscrowNftRecord>>,
+
+    /// CHECK: Checked against the escrow NFT record.
+    /// Account that paid the escrow NFT account and record rent, receiving it back.
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in claim_nft_logic.
+    /// Buyer SPL token account to receive the purchased item at.
+    #[account(mut)]
+    pub buyer_receipt_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in claim_nft_logic.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for the [`refund_nft` handler](auction_house/fn.refund_nft.html).
+#[derive(Accounts)]
+#[instruction(escrow_nft_bump: u8)]
+pub struct RefundNft<'info> {
+    /// CHECK: Used as a seed for the escrow NFT account.
+    /// Buyer user wallet account.
+    pub buyer: UncheckedAccount<'info>,
+
+    /// Seller user wallet account, which pays the sale price back to the buyer.
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// CHECK: Checked against the escrow NFT account mint.
+    /// Token mint account of the escrowed SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow token account holding the sold NFT.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            seller.key().as_ref(),
+            token_mint.key().as_ref(),
+            buyer.key().as_ref(),
+            NFT.as_bytes()
+        ],
+        bump = escrow_nft_bump
+    )]
+    pub escrow_nft_account: UncheckedAccount<'info>,
+
+    /// Escrow NFT record, closed to its rent payer.
+    #[account(
+        mut,
+        close=rent_payer,
+        seeds = [
+            PREFIX.as_bytes(),
+            ESCROW_NFT_RECORD.as_bytes(),
+            escrow_nft_account.key().as_ref()
+        ],
+        bump=escrow_nft_record.bump,
+        has_one=rent_payer
+    )]
+    pub escrow_nft_record: Box<Account<'info, EscrowNftRecord>>,
+
+    /// CHECK: Checked against the escrow NFT record.
+    /// Account that paid the escrow NFT account and record rent, receiving it back.
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in refund_nft_logic.
+    /// Seller SPL token account to return the item to.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in refund_nft_logic.
+    /// Seller SOL or SPL account to pay the sale price back from.
+    #[account(mut)]
+    pub seller_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account, receiving the sale price back.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            buyer.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// Auction House instance authority account, which must approve undoing a sale.
+    pub authority: Signer<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+pub fn claim_nft<'info>(ctx: Context<'_, '_, '_, 'info, ClaimNft<'info>>) -> Result<()> {
+    claim_nft_logic(ctx.accounts)
+}
+
+/// Move the escrowed NFT to the buyer. Either the buyer or the Auction House authority must sign.
+fn claim_nft_logic(accounts: &mut ClaimNft) -> Result<()> {
+    let buyer = &accounts.buyer;
+    let token_mint = &accounts.token_mint;
+    let escrow_nft_account = &accounts.escrow_nft_account;
+    let buyer_receipt_token_account = &accounts.buyer_receipt_token_account;
+    let authority = &accounts.authority;
+    let auction_house = &accounts.auction_house;
+    let auction_house_fee_account = &accounts.auction_house_fee_account;
+    let token_program = &accounts.token_program;
+    let system_program = &accounts.system_program;
+    let ata_program = &accounts.ata_program;
+    let rent = &accounts.rent;
+
+    if !buyer.to_account_info().is_signer && !authority.to_account_info().is_signer {
+        return Err(AuctionHouseError::NoValidSignerPresent.into());
+    }
+
+    let escrow_nft_data = assert_escrow_nft_holds(escrow_nft_account, &token_mint.key())?;
+
+    let auction_house_key = auction_house.key();
+    let seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        FEE_PAYER.as_bytes(),
+        &[auction_house.fee_payer_bump],
+    ];
+
+    let (fee_payer, fee_payer_seeds) = get_fee_payer(
+        authority,
+        auction_house,
+        buyer.to_account_info(),
+        auction_house_fee_account.to_account_info(),
+        &seeds,
+    )?;
+
+    if buyer_receipt_token_account.data_is_empty() {
+        make_ata(
+            buyer_receipt_token_account.to_account_info(),
+            buyer.to_account_info(),
+            token_mint.to_account_info(),
+            fee_payer.to_account_info(),
+            ata_program.to_account_info(),
+            token_program.to_account_info(),
+            system_program.to_account_info(),
+            rent.to_account_info(),
+            fee_payer_seeds,
+        )?;
+    }
+
+    let buyer_rec_acct = assert_is_ata(
+        &buyer_receipt_token_account.to_account_info(),
+        &buyer.key(),
+        &token_mint.key(),
+    )?;
+
+    // make sure you cant get rugged
+    if buyer_rec_acct.delegate.is_some() {
+        return Err(AuctionHouseError::BuyerATACannotHaveDelegate.into());
+    }
+
+    release_escrow_nft(
+        auction_house,
+        escrow_nft_account,
+        token_mint,
+        &buyer_receipt_token_account.to_account_info(),
+        &accounts.rent_payer,
+        token_program,
+        escrow_nft_data.amount,
+    )
+}
+
+pub fn refund_nft<'info>(ctx: Context<'_, '_, '_, 'info, RefundNft<'info>>) -> Result<()> {
+    refund_nft_logic(ctx.accounts)
+}
+
+/// Return the escrowed NFT to the seller, reversing the sale. The seller pays the price recorded for the escrowed
+/// tokens back into the buyer's escrow payment account, so that neither side keeps both the payment and the NFT.
+/// The Auction House authority must approve undoing the sale, as the buyer gives up a purchase they paid for.
+fn refund_nft_logic(accounts: &mut RefundNft) -> Result<()> {
+    let seller = &accounts.seller;
+    let token_mint = &accounts.token_mint;
+    let escrow_nft_account = &accounts.escrow_nft_account;
+    let token_account = &accounts.token_account;
+    let treasury_mint = &accounts.treasury_mint;
+    let seller_payment_account = &accounts.seller_payment_account;
+    let escrow_payment_account = &accounts.escrow_payment_account;
+    let auction_house = &accounts.auction_house;
+    let auction_house_fee_account = &accounts.auction_house_fee_account;
+    let token_program = &accounts.token_program;
+    let system_program = &accounts.system_program;
+    let ata_program = &accounts.ata_program;
+    let rent = &accounts.rent;
+
+    let escrow_nft_data = assert_escrow_nft_holds(escrow_nft_account, &token_mint.key())?;
+
+    let auction_house_key = auction_house.key();
+    let fee_payer_seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        FEE_PAYER.as_bytes(),
+        &[auction_house.fee_payer_bump],
+    ];
+
+    if token_account.data_is_empty() {
+        make_ata(
+            token_account.to_account_info(),
+            seller.to_account_info(),
+            token_mint.to_account_info(),
+            auction_house_fee_account.to_account_info(),
+            ata_program.to_account_info(),
+            token_program.to_account_info(),
+            system_program.to_account_info(),
+            rent.to_account_info(),
+            &fee_payer_seeds,
+        )?;
+    }
+
+    assert_is_ata(
+        &token_account.to_account_info(),
+        &seller.key(),
+        &token_mint.key(),
+    )?;
+
+    let price = accounts.escrow_nft_record.price;
+    if treasury_mint.key() == spl_token::native_mint::id() {
+        assert_keys_equal(seller_payment_account.key(), seller.key())?;
+        invoke(
+            &system_instruction::transfer(seller.key, escrow_payment_account.key, price),
+            &[
+                seller.to_account_info(),
+                escrow_payment_account.to_account_info(),
+                system_program.to_account_info(),
+            ],
+        )?;
+    } else {
+        assert_is_ata(
+            &seller_payment_account.to_account_info(),
+            &seller.key(),
+            &treasury_mint.key(),
+        )?;
+        transfer_tokens(
+            token_program,
+            seller_payment_account,
+            treasury_mint,
+            escrow_payment_account,
+            &seller.to_account_info(),
+            price,
+            &[],
+        )?;
+    }
+
+    release_escrow_nft(
+        auction_house,
+        escrow_nft_account,
+        token_mint,
+        &token_account.to_account_info(),
+        &accounts.rent_payer,
+        token_program,
+        escrow_nft_data.amount,
+    )
+}
+
+/// Check that the escrow NFT account is initialized for `mint` and holds at least one token.
+fn assert_escrow_nft_holds(
+    escrow_nft_account: &UncheckedAccount,
+    mint: &Pubkey,
+) -> Result<SplAccount> {
+    if escrow_nft_account.data_is_empty() {
+        return Err(AuctionHouseError::EscrowNftAccountEmpty.into());
+    }
+    let escrow_nft_data = unpack_token_account(escrow_nft_account)?;
+    assert_keys_equal(escrow_nft_data.mint, *mint)?;
+    if escrow_nft_data.amount == 0 {
+        return Err(AuctionHouseError::EscrowNftAccountEmpty.into());
+    }
+
+    Ok(escrow_nft_data)
+}
+
+/// Transfer `amount` out of the escrow NFT account to `destination` and close the escrow,
+/// returning its rent to `rent_payer`.
+fn release_escrow_nft<'info>(
+    auction_house: &Account<'info, AuctionHouse>,
+    escrow_nft_account: &UncheckedAccount<'info>,
+    token_mint: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    rent_payer: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let ah_seeds = [
+        PREFIX.as_bytes(),
+        auction_house.creator.as_ref(),
+        auction_house.treasury_mint.as_ref(),
+        &[auction_house.bump],
+    ];
+
+    transfer_tokens(
+        token_program,
+        escrow_nft_account,
+        token_mint,
+        destination,
+        &auction_house.to_account_info(),
+        amount,
+        &[&ah_seeds],
+    )?;
+
+    invoke_signed(
+        &close_account(
+            token_program.key,
+            escrow_nft_account.key,
+            rent_payer.key,
+            &auction_house.key(),
+            &[],
+        )?,
+        &[
+            escrow_nft_account.to_account_info(),
+            rent_payer.clone(),
+            auction_house.to_account_info(),
+            token_program.to_account_info(),
+        ],
+        &[&ah_seeds],
+    )?;
+
+    Ok(())
+}
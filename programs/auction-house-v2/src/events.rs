@@ -0,0 +1,183 @@
+use anchor_lang::prelude::*;
+
+use crate::{Referral, RoyaltyPolicy, SwapTarget, UnverifiedCreatorPolicy};
+
+#[event]
+pub struct AuctionHouseCreated {
+    pub auction_house: Pubkey,
+    pub authority: Pubkey,
+    pub treasury_mint: Pubkey,
+    pub seller_fee_basis_points: u16,
+    pub requires_sign_off: bool,
+    pub can_change_sale_price: bool,
+    pub timestamp: i64,
+}
+
+/// Carries the auction house settings as they stand after the update.
+#[event]
+pub struct AuctionHouseUpdated {
+    pub auction_house: Pubkey,
+    pub authority: Pubkey,
+    pub treasury_mint: Pubkey,
+    pub seller_fee_basis_points: u16,
+    pub requires_sign_off: bool,
+    pub can_change_sale_price: bool,
+    pub max_referral_basis_points: u16,
+    pub royalty_policy: RoyaltyPolicy,
+    pub unverified_creator_policy: UnverifiedCreatorPolicy,
+    pub timestamp: i64,
+}
+
+/// Emitted whenever a seller trade state is written, including relisting to update its expiry.
+#[event]
+pub struct ListingCreated {
+    pub auction_house: Pubkey,
+    pub trade_state: Pubkey,
+    pub seller: Pubkey,
+    pub token_account: Pubkey,
+    pub token_mint: Pubkey,
+    pub price: u64,
+    pub token_size: u64,
+    pub expiry: Option<i64>,
+    pub referral: Option<Referral>,
+    pub timestamp: i64,
+}
+
+/// Emitted whenever a buyer trade state is written, including rebidding to update its expiry.
+#[event]
+pub struct BidPlaced {
+    pub auction_house: Pubkey,
+    pub trade_state: Pubkey,
+    pub buyer: Pubkey,
+    pub token_account: Pubkey,
+    pub token_mint: Pubkey,
+    pub price: u64,
+    pub token_size: u64,
+    pub public: bool,
+    pub expiry: Option<i64>,
+    pub referral: Option<Referral>,
+    pub royalty_choice: Option<u16>,
+    pub timestamp: i64,
+}
+
+/// `price` is split between `royalties`, `auction_house_fee` and `seller_proceeds`. `royalty_basis_points` is the
+/// rate of the price the house's royalty policy paid creators. `referral_fees` is the part of `auction_house_fee`
+/// paid to the referrers of the listing and bid rather than the treasury.
+#[event]
+pub struct SaleExecuted {
+    pub auction_house: Pubkey,
+    pub buyer_trade_state: Pubkey,
+    pub seller_trade_state: Pubkey,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub token_mint: Pubkey,
+    pub price: u64,
+    pub token_size: u64,
+    pub royalties: u64,
+    pub royalty_basis_points: u16,
+    pub auction_house_fee: u64,
+    pub referral_fees: u64,
+    pub seller_proceeds: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderCanceled {
+    pub auction_house: Pubkey,
+    pub trade_state: Pubkey,
+    pub wallet: Pubkey,
+    pub token_mint: Pubkey,
+    pub price: u64,
+    pub token_size: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Deposited {
+    pub auction_house: Pubkey,
+    pub wallet: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+/// `amount` is what left the escrow, net of any rent kept back in a native escrow.
+#[event]
+pub struct Withdrew {
+    pub auction_house: Pubkey,
+    pub wallet: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SwapOfferCreated {
+    pub auction_house: Pubkey,
+    pub swap_offer: Pubkey,
+    pub maker: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_size: u64,
+    pub wanted: SwapTarget,
+    pub wanted_size: u64,
+    pub maker_extra: u64,
+    pub taker_extra: u64,
+    pub timestamp: i64,
+}
+
+/// Each extra amount is split between the royalties of the token it pays for and the counterparty.
+#[event]
+pub struct SwapExecuted {
+    pub auction_house: Pubkey,
+    pub swap_offer: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub maker_mint: Pubkey,
+    pub taker_mint: Pubkey,
+    pub token_size: u64,
+    pub wanted_size: u64,
+    pub maker_extra: u64,
+    pub maker_extra_royalties: u64,
+    pub taker_extra: u64,
+    pub taker_extra_royalties: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SwapOfferCanceled {
+    pub auction_house: Pubkey,
+    pub swap_offer: Pubkey,
+    pub maker: Pubkey,
+    pub token_mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BundleListed {
+    pub auction_house: Pubkey,
+    pub bundle_listing: Pubkey,
+    pub seller: Pubkey,
+    pub token_mints: Vec<Pubkey>,
+    pub price: u64,
+    pub timestamp: i64,
+}
+
+/// `price` is split between `royalties`, `auction_house_fee` and `seller_proceeds`.
+#[event]
+pub struct BundleSold {
+    pub auction_house: Pubkey,
+    pub bundle_listing: Pubkey,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub price: u64,
+    pub royalties: u64,
+    pub auction_house_fee: u64,
+    pub seller_proceeds: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BundleCanceled {
+    pub auction_house: Pubkey,
+    pub bundle_listing: Pubkey,
+    pub seller: Pubkey,
+    pub timestamp: i64,
+}
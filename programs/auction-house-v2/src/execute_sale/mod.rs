@@ -1,9 +1,13 @@
-use crate::{constants::*, errors::*, utils::*, AuctionHouse, *};
-use anchor_lang::{prelude::*, solana_program::program_pack::Pack, AnchorDeserialize};
-use spl_token::state::Account as SplAccount;
+use crate::{constants::*, errors::*, events::*, utils::*, AuctionHouse, *};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program::invoke, program_pack::Pack},
+    AnchorDeserialize,
+};
+use spl_token_2022::{instruction::initialize_account2, state::Account as SplAccount};
 
 /// Accounts for the [`execute_sale` handler](auction_house/fn.execute_sale.html).
-#[derive(Accounts)]
+#[derive(Accounts, Clone)]
 #[instruction(
     escrow_payment_bump: u8,
     free_trade_state_bump: u8,
@@ -40,12 +44,27 @@ pub struct ExecuteSale<'info> {
             PREFIX.as_bytes(),
             auction_house.key().as_ref(),
             seller.key().as_ref(), // user/seller wallet pk as seed
+            token_mint.key().as_ref(),
+            buyer.key().as_ref(),
             NFT.as_bytes()
         ],
         bump
     )]
     pub escrow_nft_account: UncheckedAccount<'info>,
 
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow NFT record, created with the escrow NFT account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            ESCROW_NFT_RECORD.as_bytes(),
+            escrow_nft_account.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_nft_record: UncheckedAccount<'info>,
+
     /// CHECK: Validated in execute_sale_logic.
     /// Metaplex metadata account decorating SPL mint account.
     pub metadata: UncheckedAccount<'info>,
@@ -130,6 +149,7 @@ pub struct ExecuteSale<'info> {
 
     /// CHECK: Not dangerous. Account seeds checked in constraint.
     /// Seller trade state PDA account encoding the sell order.
+    /// A closed trade state has no bump byte and fails the seeds check.
     #[account(
         mut,
         seeds = [
@@ -142,7 +162,7 @@ pub struct ExecuteSale<'info> {
             &buyer_price.to_le_bytes(),
             &token_size.to_le_bytes()
         ],
-        bump = seller_trade_state.to_account_info().data.borrow()[0]
+        bump = seller_trade_state.to_account_info().data.borrow().first().copied().unwrap_or_default()
     )]
     pub seller_trade_state: UncheckedAccount<'info>,
 
@@ -164,7 +184,11 @@ pub struct ExecuteSale<'info> {
     )]
     pub free_trade_state: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
     pub ata_program: Program<'info, AssociatedToken>,
 
@@ -175,6 +199,243 @@ pub struct ExecuteSale<'info> {
     pub rent: Sysvar<'info, Rent>,
 }
 
+/// Accounts for the [`auctioneer_execute_sale` handler](auction_house/fn.auctioneer_execute_sale.html).
+#[derive(Accounts, Clone)]
+#[instruction(
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+    buyer_price: u64,
+    token_size: u64
+)]
+pub struct AuctioneerExecuteSale<'info> {
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer user wallet account.
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Seller user wallet account.
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    // cannot mark these as real Accounts or else we blow stack size limit
+    ///Token account where the SPL token is stored.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Token mint account for the SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+    
+    /// CHECK: Validated in execute sale logic
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            seller.key().as_ref(), // user/seller wallet pk as seed
+            token_mint.key().as_ref(),
+            buyer.key().as_ref(),
+            NFT.as_bytes()
+        ],
+        bump
+    )]
+    pub escrow_nft_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Escrow NFT record, created with the escrow NFT account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            ESCROW_NFT_RECORD.as_bytes(),
+            escrow_nft_account.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_nft_record: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Metaplex metadata account decorating SPL mint account.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    // cannot mark these as real Accounts or else we blow stack size limit
+    /// Auction House treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            buyer.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Seller SOL or SPL account to receive payment at.
+    #[account(mut)]
+    pub seller_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer SPL token account to receive purchased item at.
+    #[account(mut)]
+    pub buyer_receipt_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Auction House instance authority.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_treasury,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance treasury account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            TREASURY.as_bytes()
+        ],
+        bump=auction_house.treasury_bump
+    )]
+    pub auction_house_treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer trade state PDA account encoding the buy order.
+    #[account(mut)]
+    pub buyer_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account encoding the sell order, listed by the Auctioneer at no fixed price.
+    /// A closed trade state has no bump byte and fails the seeds check.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            &u64::MAX.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump = seller_trade_state.to_account_info().data.borrow().first().copied().unwrap_or_default()
+    )]
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Free seller trade state PDA account encoding a free sell order.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            &0u64.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub free_trade_state: UncheckedAccount<'info>,
+
+    /// The Auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    pub auctioneer_authority: Signer<'info>,
+
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        bump=ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> From<AuctioneerExecuteSale<'info>> for ExecuteSale<'info> {
+    fn from(a: AuctioneerExecuteSale<'info>) -> ExecuteSale<'info> {
+        ExecuteSale {
+            buyer: a.buyer,
+            seller: a.seller,
+            token_account: a.token_account,
+            token_mint: a.token_mint,
+            escrow_nft_account: a.escrow_nft_account,
+            escrow_nft_record: a.escrow_nft_record,
+            metadata: a.metadata,
+            treasury_mint: a.treasury_mint,
+            escrow_payment_account: a.escrow_payment_account,
+            seller_payment_receipt_account: a.seller_payment_receipt_account,
+            buyer_receipt_token_account: a.buyer_receipt_token_account,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            auction_house_treasury: a.auction_house_treasury,
+            buyer_trade_state: a.buyer_trade_state,
+            seller_trade_state: a.seller_trade_state,
+            free_trade_state: a.free_trade_state,
+            token_program: a.token_program,
+            system_program: a.system_program,
+            ata_program: a.ata_program,
+            program_as_signer: a.program_as_signer,
+            rent: a.rent,
+        }
+    }
+}
+
 pub fn execute_sale<'info>(
     ctx: Context<'_, '_, '_, 'info, ExecuteSale<'info>>,
     escrow_payment_bump: u8,
@@ -183,9 +444,66 @@ pub fn execute_sale<'info>(
     escrow_nft_bump: u8,
     buyer_price: u64,
     token_size: u64,
+) -> Result<()> {
+    execute_sale_with_partial_order(
+        ctx,
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price,
+        token_size,
+        None,
+        None,
+    )
+}
+
+/// Fill part of a listing. The buyer trade state is the bid for `partial_order_size` tokens at
+/// `partial_order_price`, which must be exactly that share of the listing at `buyer_price`.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_partial_sale<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteSale<'info>>,
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+    partial_order_size: u64,
+    partial_order_price: u64,
+) -> Result<()> {
+    execute_sale_with_partial_order(
+        ctx,
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price,
+        token_size,
+        Some(partial_order_size),
+        Some(partial_order_price),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_sale_with_partial_order<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteSale<'info>>,
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+    partial_order_size: Option<u64>,
+    partial_order_price: Option<u64>,
 ) -> Result<()> {
     let auction_house = &ctx.accounts.auction_house;
 
+    // If the ExecuteSale scope is delegated, sales must go through auctioneer_execute_sale.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::ExecuteSale as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
     let escrow_canonical_bump = *ctx
         .bumps
         .get("escrow_payment_account")
@@ -220,6 +538,79 @@ pub fn execute_sale<'info>(
         escrow_nft_bump,
         buyer_price,
         token_size,
+        partial_order_size,
+        partial_order_price,
+        None,
+        None,
+        None,
+    )
+}
+
+/// Execute a sale through the Auctioneer delegated the ExecuteSale scope.
+/// The seller trade state is the Auctioneer listing at `u64::MAX`; the buyer trade state fixes the price.
+pub fn auctioneer_execute_sale<'info>(
+    ctx: Context<'_, '_, '_, 'info, AuctioneerExecuteSale<'info>>,
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    escrow_nft_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auctioneer_authority = &ctx.accounts.auctioneer_authority;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::NoAuctioneerProgramSet.into());
+    }
+
+    assert_valid_auctioneer_and_scope(
+        auction_house,
+        &auctioneer_authority.key(),
+        ah_auctioneer_pda,
+        AuthorityScope::ExecuteSale,
+    )?;
+
+    let escrow_canonical_bump = *ctx
+        .bumps
+        .get("escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let free_trade_state_canonical_bump = *ctx
+        .bumps
+        .get("free_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let escrow_nft_canonical_bump = *ctx
+        .bumps
+        .get("escrow_nft_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if (escrow_canonical_bump != escrow_payment_bump)
+        || (free_trade_state_canonical_bump != free_trade_state_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+        || (escrow_nft_canonical_bump != escrow_nft_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    let mut accounts: ExecuteSale<'info> = (*ctx.accounts).clone().into();
+
+    execute_sale_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price,
+        token_size,
+        None,
+        None,
+        None,
         None,
         None,
     )
@@ -227,10 +618,14 @@ pub fn execute_sale<'info>(
 
 //edit
 /// Execute sale between provided buyer and seller trade state accounts transferring funds to seller wallet and token to buyer wallet.
+/// `listing_price` settles a full order below `buyer_price` when the listing sets the price, as in `execute_dutch_sale`.
+/// `payer` covers settlement costs in place of the buyer or seller wallet when neither signs, as in `settle_auction`.
+/// `bid_seed` keys a buyer trade state made by [`create_buyer_trade_state`] in place of `buyer_price`.
 #[inline(never)]
-fn execute_sale_logic<'c, 'info>(
+#[allow(clippy::too_many_arguments)]
+pub fn execute_sale_logic<'info>(
     accounts: &mut ExecuteSale<'info>,
-    remaining_accounts: &'c [AccountInfo<'info>],
+    remaining_accounts: &[AccountInfo<'info>],
     escrow_payment_bump: u8,
     _free_trade_state_bump: u8,
     program_as_signer_bump: u8,
@@ -239,6 +634,9 @@ fn execute_sale_logic<'c, 'info>(
     token_size: u64,
     partial_order_size: Option<u64>,
     partial_order_price: Option<u64>,
+    listing_price: Option<u64>,
+    payer: Option<AccountInfo<'info>>,
+    bid_seed: Option<&str>,
 ) -> Result<()> {
     let buyer = &accounts.buyer;
     let seller = &accounts.seller;
@@ -246,33 +644,34 @@ fn execute_sale_logic<'c, 'info>(
     let token_mint = &accounts.token_mint;
     let metadata = &accounts.metadata;
     let treasury_mint = &accounts.treasury_mint;
-    // let seller_payment_receipt_account = &accounts.seller_payment_receipt_account;
+    let seller_payment_receipt_account = &accounts.seller_payment_receipt_account;
     let buyer_receipt_token_account = &accounts.buyer_receipt_token_account;
     let escrow_payment_account = &accounts.escrow_payment_account;
     let escrow_nft_account = &accounts.escrow_nft_account;
+    let escrow_nft_record = &accounts.escrow_nft_record;
     let authority = &accounts.authority;
     let auction_house = &accounts.auction_house;
     let auction_house_fee_account = &accounts.auction_house_fee_account;
     let auction_house_treasury = &accounts.auction_house_treasury;
     let buyer_trade_state = &accounts.buyer_trade_state;
     let seller_trade_state = &accounts.seller_trade_state;
-    // let free_trade_state = &accounts.free_trade_state;
+    let free_trade_state = &accounts.free_trade_state;
     let token_program = &accounts.token_program;
     let system_program = &accounts.system_program;
     let ata_program = &accounts.ata_program;
     let program_as_signer = &accounts.program_as_signer;
     let rent = &accounts.rent;
 
-    // let metadata_clone = metadata.to_account_info();
-    // let escrow_clone = escrow_payment_account.to_account_info();
+    let metadata_clone = metadata.to_account_info();
+    let escrow_clone = escrow_payment_account.to_account_info();
     let escrow_nft_clone = escrow_nft_account.to_account_info();
 
-    // let auction_house_clone = auction_house.to_account_info();
-    // let ata_clone = ata_program.to_account_info();
+    let auction_house_clone = auction_house.to_account_info();
+    let ata_clone = ata_program.to_account_info();
     let token_clone = token_program.to_account_info();
-    // let sys_clone = system_program.to_account_info();
-    // let rent_clone = rent.to_account_info();
-    // let treasury_clone = auction_house_treasury.to_account_info();
+    let sys_clone = system_program.to_account_info();
+    let rent_clone = rent.to_account_info();
+    let treasury_clone = auction_house_treasury.to_account_info();
     let authority_clone = authority.to_account_info();
     let buyer_receipt_clone = buyer_receipt_token_account.to_account_info();
     let token_account_clone = token_account.to_account_info();
@@ -288,6 +687,7 @@ fn execute_sale_logic<'c, 'info>(
     let token_account_mint = get_mint_from_token_account(&token_account_clone)?;
 
     assert_keys_equal(token_mint.key(), token_account_mint)?;
+    assert_supported_mint(token_mint)?;
     let delegate = get_delegate_from_token_account(&token_account_clone)?;
     if let Some(d) = delegate {
         assert_keys_equal(program_as_signer.key(), d)?;
@@ -309,7 +709,11 @@ fn execute_sale_logic<'c, 'info>(
         return Err(AuctionHouseError::BothPartiesNeedToAgreeToSale.into());
     }
 
-    let token_account_data = SplAccount::unpack(&token_account.data.borrow())?;
+    let now = Clock::get()?.unix_timestamp;
+    assert_trade_state_not_expired(buyer_trade_state, now)?;
+    assert_trade_state_not_expired(seller_trade_state, now)?;
+
+    let token_account_data = unpack_token_account(token_account)?;
 
     let (size, price): (u64, u64) = match (partial_order_size, partial_order_price) {
         (Some(size), Some(price)) => {
@@ -324,7 +728,16 @@ fn execute_sale_logic<'c, 'info>(
                 ts_bump,
             )?;
 
-            if ((buyer_price / token_size) * size) != price {
+            if size == 0 {
+                return Err(AuctionHouseError::InvalidTokenAmount.into());
+            }
+            if size > token_size {
+                return Err(AuctionHouseError::NotEnoughTokensAvailableForPurchase.into());
+            }
+
+            // `price / size` must equal `buyer_price / token_size` exactly. Cross-multiplying
+            // in u128 cannot overflow, and unlike dividing first leaves no rounding to exploit.
+            if price as u128 * token_size as u128 != buyer_price as u128 * size as u128 {
                 return Err(AuctionHouseError::PartialPriceMismatch.into());
             }
 
@@ -339,22 +752,40 @@ fn execute_sale_logic<'c, 'info>(
             (size, price)
         }
         (None, None) => {
-            assert_valid_trade_state(
-                &buyer.key(),
-                auction_house,
-                buyer_price,
-                token_size,
-                buyer_trade_state,
-                &token_mint.key(),
-                &token_account.key(),
-                ts_bump,
-            )?;
+            match bid_seed {
+                Some(bid_seed) => assert_valid_seeded_trade_state(
+                    &buyer.key(),
+                    auction_house,
+                    bid_seed,
+                    token_size,
+                    buyer_trade_state,
+                    &token_mint.key(),
+                    &token_account.key(),
+                    ts_bump,
+                )?,
+                None => assert_valid_trade_state(
+                    &buyer.key(),
+                    auction_house,
+                    buyer_price,
+                    token_size,
+                    buyer_trade_state,
+                    &token_mint.key(),
+                    &token_account.key(),
+                    ts_bump,
+                )?,
+            };
 
             if token_account_data.amount < token_size {
                 return Err(AuctionHouseError::NotEnoughTokensAvailableForPurchase.into());
             };
 
-            (token_size, buyer_price)
+            match listing_price {
+                Some(price) if price > buyer_price => {
+                    return Err(AuctionHouseError::BidBelowListingPrice.into());
+                }
+                Some(price) => (token_size, price),
+                None => (token_size, buyer_price),
+            }
         }
         _ => {
             return Err(AuctionHouseError::MissingElementForPartialOrder.into());
@@ -369,16 +800,20 @@ fn execute_sale_logic<'c, 'info>(
         &[auction_house.fee_payer_bump],
     ];
 
-    let wallet_to_use = if buyer.is_signer { buyer } else { seller };
+    let wallet_to_use = match payer {
+        Some(payer) => payer,
+        None if buyer.is_signer => buyer.to_account_info(),
+        None => seller.to_account_info(),
+    };
 
     let (fee_payer, fee_payer_seeds) = get_fee_payer(
         authority,
         auction_house,
-        wallet_to_use.to_account_info(),
+        wallet_to_use.clone(),
         auction_house_fee_account.to_account_info(),
         &seeds,
     )?;
-    // let fee_payer_clone = fee_payer.to_account_info();
+    let fee_payer_clone = fee_payer.to_account_info();
 
     assert_is_ata(
         &token_account.to_account_info(),
@@ -421,141 +856,191 @@ fn execute_sale_logic<'c, 'info>(
     if metadata.data_is_empty() {
         return Err(AuctionHouseError::MetadataDoesntExist.into());
     }
+    let programmable = is_programmable(&metadata_clone)?;
 
-    // let auction_house_key = auction_house.key();
-    // let wallet_key = buyer.key();
-    // let escrow_signer_seeds = [
-    //     PREFIX.as_bytes(),
-    //     auction_house_key.as_ref(),
-    //     wallet_key.as_ref(),
-    //     &[escrow_payment_bump],
-    // ];
-
-    // let ah_seeds = [
-    //     PREFIX.as_bytes(),
-    //     auction_house.creator.as_ref(),
-    //     auction_house.treasury_mint.as_ref(),
-    //     &[auction_house.bump],
-    // ];
+    let wallet_key = buyer.key();
+    let escrow_signer_seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        wallet_key.as_ref(),
+        &[escrow_payment_bump],
+    ];
+
+    let ah_seeds = [
+        PREFIX.as_bytes(),
+        auction_house.creator.as_ref(),
+        auction_house.treasury_mint.as_ref(),
+        &[auction_house.bump],
+    ];
 
     // with the native account, the escrow is its own owner,
     // whereas with token, it is the auction house that is owner.
-    // let signer_seeds_for_royalties = if is_native {
-    //     escrow_signer_seeds
-    // } else {
-    //     ah_seeds
-    // };
-
-    // let buyer_leftover_after_royalties = pay_creator_fees(
-    //     &mut remaining_accounts.iter(),
-    //     &metadata_clone,
-    //     &escrow_clone,
-    //     &auction_house_clone,
-    //     &fee_payer_clone,
-    //     treasury_mint,
-    //     &ata_clone,
-    //     &token_clone,
-    //     &sys_clone,
-    //     &rent_clone,
-    //     &signer_seeds_for_royalties,
-    //     fee_payer_seeds,
-    //     price,
-    //     is_native,
-    // )?;
-
-    // let auction_house_fee_paid = pay_auction_house_fees(
-    //     auction_house,
-    //     &treasury_clone,
-    //     &escrow_clone,
-    //     &token_clone,
-    //     &sys_clone,
-    //     &signer_seeds_for_royalties,
-    //     price,
-    //     is_native,
-    // )?;
-
-    // let buyer_leftover_after_royalties_and_house_fee = buyer_leftover_after_royalties
-    //     .checked_sub(auction_house_fee_paid)
-    //     .ok_or(AuctionHouseError::NumericalOverflow)?;
-
-    // if !is_native {
-    //     if seller_payment_receipt_account.data_is_empty() {
-    //         make_ata(
-    //             seller_payment_receipt_account.to_account_info(),
-    //             seller.to_account_info(),
-    //             treasury_mint.to_account_info(),
-    //             fee_payer.to_account_info(),
-    //             ata_program.to_account_info(),
-    //             token_program.to_account_info(),
-    //             system_program.to_account_info(),
-    //             rent.to_account_info(),
-    //             fee_payer_seeds,
-    //         )?;
-    //     }
-
-    //     let seller_rec_acct = assert_is_ata(
-    //         &seller_payment_receipt_account.to_account_info(),
-    //         &seller.key(),
-    //         &treasury_mint.key(),
-    //     )?;
-
-    //     // make sure you cant get rugged
-    //     if seller_rec_acct.delegate.is_some() {
-    //         return Err(AuctionHouseError::SellerATACannotHaveDelegate.into());
-    //     }
-
-    //     invoke_signed(
-    //         &spl_token::instruction::transfer(
-    //             token_program.key,
-    //             &escrow_payment_account.key(),
-    //             &seller_payment_receipt_account.key(),
-    //             &auction_house.key(),
-    //             &[],
-    //             buyer_leftover_after_royalties_and_house_fee,
-    //         )?,
-    //         &[
-    //             escrow_payment_account.to_account_info(),
-    //             seller_payment_receipt_account.to_account_info(),
-    //             token_program.to_account_info(),
-    //             auction_house.to_account_info(),
-    //         ],
-    //         &[&ah_seeds],
-    //     )?;
-    // } else {
-    //     assert_keys_equal(seller_payment_receipt_account.key(), seller.key())?;
-    //     invoke_signed(
-    //         &system_instruction::transfer(
-    //             escrow_payment_account.key,
-    //             seller_payment_receipt_account.key,
-    //             buyer_leftover_after_royalties_and_house_fee,
-    //         ),
-    //         &[
-    //             escrow_payment_account.to_account_info(),
-    //             seller_payment_receipt_account.to_account_info(),
-    //             system_program.to_account_info(),
-    //         ],
-    //         &[&escrow_signer_seeds],
-    //     )?;
-    // }
-
-    // if buyer_receipt_token_account.data_is_empty() {
-    //     make_ata(
-    //         buyer_receipt_token_account.to_account_info(),
-    //         buyer.to_account_info(),
-    //         token_mint.to_account_info(),
-    //         fee_payer.to_account_info(),
-    //         ata_program.to_account_info(),
-    //         token_program.to_account_info(),
-    //         system_program.to_account_info(),
-    //         rent.to_account_info(),
-    //         fee_payer_seeds,
-    //     )?;
-    // }
-
-    // TODO:KIV
-    if escrow_nft_account.data_is_empty() {
+    let signer_seeds_for_royalties = if is_native {
+        escrow_signer_seeds
+    } else {
+        ah_seeds
+    };
+
+    // The royalty policy may let the bid pay creators less than the metadata royalty.
+    let royalty_basis_points = royalty_basis_points(
+        auction_house,
+        &metadata_clone,
+        get_trade_state_royalty_choice(buyer_trade_state)?,
+    )?;
+
+    // Creator accounts come first, then the fee schedule and stats of a verified collection, the
+    // trader stats of the buyer and the seller, and the referrer accounts of the listing and the bid, in that order,
+    // followed by the programmable NFT accounts if any.
+    let remaining_accounts = &mut remaining_accounts.iter();
+    let buyer_leftover_after_royalties = pay_creator_fees(
+        remaining_accounts,
+        &metadata_clone,
+        &escrow_clone,
+        &auction_house_clone,
+        &fee_payer_clone,
+        treasury_mint,
+        &ata_clone,
+        &token_clone,
+        &sys_clone,
+        &rent_clone,
+        &signer_seeds_for_royalties,
+        fee_payer_seeds,
+        royalty_basis_points,
+        auction_house.unverified_creator_policy,
+        price,
+        is_native,
+    )?;
+
+    // Referrers are paid their share of the house fee, and the treasury the rest.
+    let seller_fee_basis_points =
+        seller_fee_basis_points(auction_house, &metadata_clone, remaining_accounts)?;
+    let house_fee = auction_house_fee(seller_fee_basis_points, price)?;
+    let collection = verified_collection(&metadata_clone)?;
+    let collection_stats_info = match collection {
+        Some(collection) => Some(next_collection_stats_info(
+            remaining_accounts,
+            &auction_house_key,
+            &collection,
+        )?),
+        None => None,
+    };
+    let buyer_stats_info =
+        next_trader_stats_info(remaining_accounts, &auction_house_key, &buyer.key())?;
+    let seller_stats_info =
+        next_trader_stats_info(remaining_accounts, &auction_house_key, &seller.key())?;
+    let mut referral_fees: u64 = 0;
+    let referrals = [
+        get_trade_state_referral(seller_trade_state)?,
+        get_trade_state_referral(buyer_trade_state)?,
+    ];
+    for referral in referrals.iter().flatten() {
+        // Neither party to the sale can take a referral fee. A listing can't know its buyer, nor a bid its seller, so
+        // such a referral is dropped, with no referrer accounts passed for it, and its share stays with the treasury.
+        // Rates are held to the house maximum at settlement too, in case the authority lowered it after the order was
+        // placed.
+        if referral.referrer == buyer.key() || referral.referrer == seller.key() {
+            continue;
+        }
+        let referral = Referral {
+            basis_points: referral
+                .basis_points
+                .min(auction_house.max_referral_basis_points),
+            ..*referral
+        };
+        let referral_fee = pay_referral_fee(
+            remaining_accounts,
+            &referral,
+            house_fee,
+            &escrow_clone,
+            &auction_house_clone,
+            &fee_payer_clone,
+            treasury_mint,
+            &ata_clone,
+            &token_clone,
+            &sys_clone,
+            &rent_clone,
+            &signer_seeds_for_royalties,
+            fee_payer_seeds,
+            is_native,
+        )?;
+        referral_fees = referral_fees
+            .checked_add(referral_fee)
+            .ok_or(AuctionHouseError::NumericalOverflow)?;
+    }
+
+    let auction_house_fee_paid = pay_auction_house_fees(
+        auction_house,
+        &treasury_clone,
+        &escrow_clone,
+        treasury_mint,
+        &token_clone,
+        &sys_clone,
+        &signer_seeds_for_royalties,
+        seller_fee_basis_points,
+        price,
+        referral_fees,
+        is_native,
+    )?;
+
+    let buyer_leftover_after_royalties_and_house_fee = buyer_leftover_after_royalties
+        .checked_sub(auction_house_fee_paid)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+
+    if !is_native {
+        if seller_payment_receipt_account.data_is_empty() {
+            make_ata(
+                seller_payment_receipt_account.to_account_info(),
+                seller.to_account_info(),
+                treasury_mint.to_account_info(),
+                fee_payer.to_account_info(),
+                ata_program.to_account_info(),
+                token_program.to_account_info(),
+                system_program.to_account_info(),
+                rent.to_account_info(),
+                fee_payer_seeds,
+            )?;
+        }
+
+        let seller_rec_acct = assert_is_ata(
+            &seller_payment_receipt_account.to_account_info(),
+            &seller.key(),
+            &treasury_mint.key(),
+        )?;
+
+        // make sure you cant get rugged
+        if seller_rec_acct.delegate.is_some() {
+            return Err(AuctionHouseError::SellerATACannotHaveDelegate.into());
+        }
+
+        transfer_tokens(
+            token_program,
+            escrow_payment_account,
+            treasury_mint,
+            seller_payment_receipt_account,
+            &auction_house_clone,
+            buyer_leftover_after_royalties_and_house_fee,
+            &[&ah_seeds],
+        )?;
+    } else {
+        assert_keys_equal(seller_payment_receipt_account.key(), seller.key())?;
+        invoke_signed(
+            &system_instruction::transfer(
+                escrow_payment_account.key,
+                seller_payment_receipt_account.key,
+                buyer_leftover_after_royalties_and_house_fee,
+            ),
+            &[
+                escrow_payment_account.to_account_info(),
+                seller_payment_receipt_account.to_account_info(),
+                system_program.to_account_info(),
+            ],
+            &[&escrow_signer_seeds],
+        )?;
+    }
+
+    if buyer_receipt_token_account.data_is_empty() {
         make_ata(
-            escrow_nft_account.to_account_info(),
+            buyer_receipt_token_account.to_account_info(),
             buyer.to_account_info(),
             token_mint.to_account_info(),
             fee_payer.to_account_info(),
@@ -567,6 +1052,50 @@ fn execute_sale_logic<'c, 'info>(
         )?;
     }
 
+    // Programmable NFTs bypass the escrow NFT account, see the transfer below. A sale of more of
+    // the same tokens between the same parties adds to the escrow account already holding them.
+    let escrow_nft_created = !programmable && escrow_nft_account.data_is_empty();
+    if escrow_nft_created {
+        // The escrow NFT account is a token account at the `nft` PDA owned by the auction house,
+        // rather than an ATA, so it is created and initialized directly.
+        let seller_key = seller.key();
+        let token_mint_key = token_mint.key();
+        let buyer_key = buyer.key();
+        let escrow_nft_seeds = [
+            PREFIX.as_bytes(),
+            auction_house_key.as_ref(),
+            seller_key.as_ref(),
+            token_mint_key.as_ref(),
+            buyer_key.as_ref(),
+            NFT.as_bytes(),
+            &[escrow_nft_bump],
+        ];
+        create_or_allocate_account_raw(
+            *token_program.key,
+            &escrow_nft_clone,
+            &rent_clone,
+            system_program,
+            &fee_payer,
+            SplAccount::LEN,
+            fee_payer_seeds,
+            &escrow_nft_seeds,
+        )?;
+        invoke(
+            &initialize_account2(
+                token_program.key,
+                escrow_nft_account.key,
+                &token_mint.key(),
+                &auction_house.key(),
+            )?,
+            &[
+                token_clone.clone(),
+                token_mint.to_account_info(),
+                escrow_nft_clone.clone(),
+                rent_clone.clone(),
+            ],
+        )?;
+    }
+
     let buyer_rec_acct = assert_is_ata(&buyer_receipt_clone, &buyer.key(), &token_mint.key())?;
 
     // make sure you cant get rugged
@@ -580,47 +1109,220 @@ fn execute_sale_logic<'c, 'info>(
         &[program_as_signer_bump],
     ];
 
-    //transfer NFT from seller to escrow_nft_account
-    invoke_signed(
-        &spl_token::instruction::transfer(
-            token_program.key,
-            &token_account.key(),
-            // &buyer_receipt_token_account.key(),
-            &escrow_nft_account.key(),
-            &program_as_signer.key(),
-            &[],
+    if programmable {
+        // Token Metadata keeps programmable NFT accounts frozen, so the escrow could not record
+        // the buyer as its delegate. The NFT goes straight to the buyer instead, moved by the
+        // program as signer as the seller's Sale delegate.
+        let programmable_accounts = ProgrammableAccounts::next(remaining_accounts)?;
+        let buyer_token_record = next_account_info(remaining_accounts)?;
+        transfer_programmable(
+            &programmable_accounts,
+            buyer_token_record,
+            token_account,
+            seller,
+            &buyer_receipt_clone,
+            buyer,
+            token_mint,
+            &metadata_clone,
+            program_as_signer,
+            &fee_payer,
+            system_program,
+            &token_clone,
+            &ata_clone,
             size,
-        )?,
-        &[
-            token_account.to_account_info(),
-            // buyer_receipt_clone,
-            escrow_nft_clone,
-            program_as_signer.to_account_info(),
-            token_clone,
-        ],
-        &[&program_as_signer_seeds],
+            &program_as_signer_seeds,
+            fee_payer_seeds,
+        )?;
+    } else {
+        //transfer NFT from seller to escrow_nft_account
+        transfer_tokens(
+            token_program,
+            token_account,
+            token_mint,
+            &escrow_nft_clone,
+            program_as_signer,
+            size,
+            &[&program_as_signer_seeds],
+        )?;
+
+        // The escrow address already pins the buyer entitled to claim the NFT. Its record keeps
+        // who paid the escrow rent, to return it on close, and the price a refund pays back.
+        record_escrow_nft(
+            &escrow_nft_record.to_account_info(),
+            escrow_nft_account.key,
+            &fee_payer,
+            &sys_clone,
+            &rent_clone,
+            fee_payer_seeds,
+            price,
+        )?;
+    }
+
+    if let (Some(collection_stats_info), Some(collection)) = (collection_stats_info, collection) {
+        record_collection_sale(
+            collection_stats_info,
+            &auction_house_key,
+            &collection,
+            &fee_payer_clone,
+            &sys_clone,
+            &rent_clone,
+            fee_payer_seeds,
+            price,
+            now,
+        )?;
+    }
+
+    // Royalties follow the bid's royalty choice, so they count toward the buyer's fees. The house fee comes out of
+    // the seller's proceeds, so it counts toward the seller's fees.
+    let royalties_paid = price
+        .checked_sub(buyer_leftover_after_royalties)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    record_trade(
+        buyer_stats_info,
+        &auction_house_key,
+        &buyer.key(),
+        &fee_payer_clone,
+        &sys_clone,
+        &rent_clone,
+        fee_payer_seeds,
+        price,
+        0,
+        royalties_paid,
+        now,
+    )?;
+    record_trade(
+        seller_stats_info,
+        &auction_house_key,
+        &seller.key(),
+        &fee_payer_clone,
+        &sys_clone,
+        &rent_clone,
+        fee_payer_seeds,
+        0,
+        price,
+        auction_house_fee_paid,
+        now,
     )?;
 
+    emit!(SaleExecuted {
+        auction_house: auction_house_key,
+        buyer_trade_state: buyer_trade_state.key(),
+        seller_trade_state: seller_trade_state.key(),
+        buyer: buyer.key(),
+        seller: seller.key(),
+        token_mint: token_mint.key(),
+        price,
+        token_size: size,
+        royalties: royalties_paid,
+        royalty_basis_points,
+        auction_house_fee: auction_house_fee_paid,
+        referral_fees,
+        seller_proceeds: buyer_leftover_after_royalties_and_house_fee,
+        timestamp: now,
+    });
+
+    // Trade states were paid for by their wallet, the fee account, or for a bid made at settlement by `payer`, and
+    // each refunds whichever did rather than whoever settles the sale.
+    let rent_payers = [
+        buyer.to_account_info(),
+        seller.to_account_info(),
+        auction_house_fee_account.to_account_info(),
+        wallet_to_use,
+    ];
+
     // Close the buyer trade state account if the rest of execute sale was successful.
-    // close_account(
-    //     &buyer_trade_state.to_account_info(),
-    //     &fee_payer.to_account_info(),
-    // )?;
-
-    // let token_account_data = SplAccount::unpack(&token_account.data.borrow())?;
-    // if token_account_data.delegated_amount == 0 {
-    //     close_account(
-    //         &seller_trade_state.to_account_info(),
-    //         &fee_payer.to_account_info(),
-    //     )?;
-
-    //     if free_trade_state.lamports() > 0 {
-    //         close_account(
-    //             &free_trade_state.to_account_info(),
-    //             &fee_payer.to_account_info(),
-    //         )?;
-    //     }
-    // }
+    let buyer_trade_state_info = buyer_trade_state.to_account_info();
+    let rent_payer =
+        trade_state_rent_payer_info(&buyer_trade_state_info, &rent_payers, &fee_payer)?;
+    close_account(&buyer_trade_state_info, &rent_payer)?;
+
+    // A partially filled listing keeps its seller trade state open until the remaining
+    // delegated tokens are sold.
+    let token_account_data = unpack_token_account(token_account)?;
+    if token_account_data.delegated_amount == 0 {
+        let seller_trade_state_info = seller_trade_state.to_account_info();
+        let rent_payer =
+            trade_state_rent_payer_info(&seller_trade_state_info, &rent_payers, &fee_payer)?;
+        close_account(&seller_trade_state_info, &rent_payer)?;
+
+        if free_trade_state.lamports() > 0 {
+            let free_trade_state_info = free_trade_state.to_account_info();
+            let rent_payer =
+                trade_state_rent_payer_info(&free_trade_state_info, &rent_payers, &fee_payer)?;
+            close_account(&free_trade_state_info, &rent_payer)?;
+        }
+    }
 
     Ok(())
 }
+
+/// Create a buyer trade state for a bid held elsewhere, such as an auction's winning bid or a collection bid, so
+/// `execute_sale_logic` can match it. It is keyed by `bid_seed` in place of a price, so it is never one of the buyer's
+/// private bids, and the sale only consumes the bid it was made for. `wallet` pays the rent when the authority does not
+/// sign, and must be the wallet later passed to `execute_sale_logic` so the rent is refunded to whoever paid it.
+pub fn create_buyer_trade_state<'info>(
+    accounts: &ExecuteSale<'info>,
+    wallet: AccountInfo<'info>,
+    bid_seed: &str,
+    token_size: u64,
+) -> Result<()> {
+    let buyer_trade_state = &accounts.buyer_trade_state;
+    let auction_house = &accounts.auction_house;
+
+    let buyer_key = accounts.buyer.key();
+    let auction_house_key = auction_house.key();
+    let token_account_key = accounts.token_account.key();
+    let token_mint_key = accounts.token_mint.key();
+    let size = token_size.to_le_bytes();
+    let trade_state_bump = assert_derivation(
+        &crate::id(),
+        &buyer_trade_state.to_account_info(),
+        &[
+            PREFIX.as_bytes(),
+            buyer_key.as_ref(),
+            auction_house_key.as_ref(),
+            token_account_key.as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint_key.as_ref(),
+            bid_seed.as_bytes(),
+            &size,
+        ],
+    )?;
+
+    let seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        FEE_PAYER.as_bytes(),
+        &[auction_house.fee_payer_bump],
+    ];
+    let (fee_payer, fee_seeds) = get_fee_payer(
+        &accounts.authority,
+        auction_house,
+        wallet,
+        accounts.auction_house_fee_account.to_account_info(),
+        &seeds,
+    )?;
+
+    let ts_info = buyer_trade_state.to_account_info();
+    create_or_allocate_account_raw(
+        crate::id(),
+        &ts_info,
+        &accounts.rent.to_account_info(),
+        &accounts.system_program,
+        &fee_payer,
+        TRADE_STATE_SIZE,
+        fee_seeds,
+        &[
+            PREFIX.as_bytes(),
+            buyer_key.as_ref(),
+            auction_house_key.as_ref(),
+            token_account_key.as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint_key.as_ref(),
+            bid_seed.as_bytes(),
+            &size,
+            &[trade_state_bump],
+        ],
+    )?;
+    init_trade_state(&ts_info, trade_state_bump, fee_payer.key)
+}
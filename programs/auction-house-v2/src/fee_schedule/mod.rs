@@ -0,0 +1,153 @@
+//! Per-collection house fees.
+//! The Auction House authority can give a verified collection its own `FeeSchedule` PDA, whose rate replaces the
+//! house `seller_fee_basis_points` when a token in the collection settles through `execute_sale`.
+
+use anchor_lang::prelude::*;
+
+use crate::{constants::*, errors::*, AuctionHouse, FeeSchedule};
+
+/// Accounts for the [`create_fee_schedule` handler](auction_house/fn.create_fee_schedule.html).
+#[derive(Accounts)]
+pub struct CreateFeeSchedule<'info> {
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Auction House instance authority account, paying for the fee schedule PDA.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Used as a seed for the fee schedule PDA.
+    /// Collection mint account the fee schedule applies to.
+    pub collection: UncheckedAccount<'info>,
+
+    /// Fee schedule PDA account.
+    #[account(
+        init,
+        payer=authority,
+        space=FEE_SCHEDULE_SIZE,
+        seeds = [
+            PREFIX.as_bytes(),
+            FEE_SCHEDULE.as_bytes(),
+            auction_house.key().as_ref(),
+            collection.key().as_ref()
+        ],
+        bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for the [`update_fee_schedule` handler](auction_house/fn.update_fee_schedule.html).
+#[derive(Accounts)]
+pub struct UpdateFeeSchedule<'info> {
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Auction House instance authority account.
+    pub authority: Signer<'info>,
+
+    /// Fee schedule PDA account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            FEE_SCHEDULE.as_bytes(),
+            auction_house.key().as_ref(),
+            fee_schedule.collection.as_ref()
+        ],
+        bump=fee_schedule.bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+}
+
+/// Accounts for the [`close_fee_schedule` handler](auction_house/fn.close_fee_schedule.html).
+#[derive(Accounts)]
+pub struct CloseFeeSchedule<'info> {
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Auction House instance authority account, receiving the fee schedule PDA rent.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Fee schedule PDA account.
+    #[account(
+        mut,
+        close=authority,
+        seeds = [
+            PREFIX.as_bytes(),
+            FEE_SCHEDULE.as_bytes(),
+            auction_house.key().as_ref(),
+            fee_schedule.collection.as_ref()
+        ],
+        bump=fee_schedule.bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+}
+
+pub fn create_fee_schedule<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateFeeSchedule<'info>>,
+    seller_fee_basis_points: u16,
+) -> Result<()> {
+    if seller_fee_basis_points > 10000 {
+        return Err(AuctionHouseError::InvalidBasisPoints.into());
+    }
+
+    let fee_schedule = &mut ctx.accounts.fee_schedule;
+    fee_schedule.auction_house = ctx.accounts.auction_house.key();
+    fee_schedule.collection = ctx.accounts.collection.key();
+    fee_schedule.seller_fee_basis_points = seller_fee_basis_points;
+    fee_schedule.bump = *ctx
+        .bumps
+        .get("fee_schedule")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    Ok(())
+}
+
+pub fn update_fee_schedule<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpdateFeeSchedule<'info>>,
+    seller_fee_basis_points: u16,
+) -> Result<()> {
+    if seller_fee_basis_points > 10000 {
+        return Err(AuctionHouseError::InvalidBasisPoints.into());
+    }
+
+    ctx.accounts.fee_schedule.seller_fee_basis_points = seller_fee_basis_points;
+
+    Ok(())
+}
+
+pub fn close_fee_schedule<'info>(
+    _ctx: Context<'_, '_, '_, 'info, CloseFeeSchedule<'info>>,
+) -> Result<()> {
+    Ok(())
+}
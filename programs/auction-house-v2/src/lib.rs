@@ -1,21 +1,34 @@
+pub mod auction;
+pub mod auctioneer;
 pub mod bid;
+pub mod bundle;
 pub mod cancel;
+/// Off-chain instruction builders, left out of the on-chain program.
+#[cfg(not(target_os = "solana"))]
+pub mod client;
+pub mod collection_bid;
 pub mod constants;
 pub mod deposit;
+pub mod dutch;
 pub mod errors;
+pub mod escrow_nft;
+pub mod events;
 pub mod execute_sale;
+pub mod fee_schedule;
 pub mod pda;
 pub mod receipt;
 pub mod sell;
 pub mod state;
+pub mod swap;
 pub mod utils;
 pub mod withdraw;
 
 pub use state::*;
 
 use crate::{
-    bid::*, cancel::*, constants::*, deposit::*, errors::AuctionHouseError,
-    execute_sale::*, receipt::*, sell::*, utils::*, withdraw::*,
+    auction::*, auctioneer::*, bid::*, bundle::*, cancel::*, collection_bid::*, constants::*, deposit::*,
+    dutch::*, errors::AuctionHouseError, escrow_nft::*, events::*, execute_sale::*, fee_schedule::*, receipt::*,
+    sell::*, swap::*, utils::*, withdraw::*,
 };
 
 use anchor_lang::{
@@ -23,11 +36,8 @@ use anchor_lang::{
     solana_program::{program::invoke_signed, system_instruction},
     AnchorDeserialize, AnchorSerialize,
 };
-use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
-};
-use spl_token::instruction::revoke;
+use anchor_spl::associated_token::AssociatedToken;
+use spl_token_2022::instruction::revoke;
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
 #[program]
@@ -85,6 +95,10 @@ pub mod auction_house_v2 {
         }
         auction_house.treasury_bump = treasury_bump;
 
+        // The treasury and every escrow are created by the program owning the mint.
+        assert_owned_by(treasury_mint, token_program.key)?;
+        assert_supported_mint(treasury_mint)?;
+
         if seller_fee_basis_points > 10000 {
             return Err(AuctionHouseError::InvalidBasisPoints.into());
         }
@@ -150,16 +164,310 @@ pub mod auction_house_v2 {
             )?;
         }
 
+        emit!(AuctionHouseCreated {
+            auction_house: auction_house.key(),
+            authority: auction_house.authority,
+            treasury_mint: auction_house.treasury_mint,
+            seller_fee_basis_points: auction_house.seller_fee_basis_points,
+            requires_sign_off: auction_house.requires_sign_off,
+            can_change_sale_price: auction_house.can_change_sale_price,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Update Auction House values such as seller fee basis points, update authority, treasury account, etc.
+    pub fn update_auction_house<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdateAuctionHouse<'info>>,
+        seller_fee_basis_points: Option<u16>,
+        requires_sign_off: Option<bool>,
+        can_change_sale_price: Option<bool>,
+        max_referral_basis_points: Option<u16>,
+        royalty_policy: Option<RoyaltyPolicy>,
+        unverified_creator_policy: Option<UnverifiedCreatorPolicy>,
+    ) -> Result<()> {
+        let treasury_mint = &ctx.accounts.treasury_mint;
+        let payer = &ctx.accounts.payer;
+        let new_authority = &ctx.accounts.new_authority;
+        let auction_house = &mut ctx.accounts.auction_house;
+        let fee_withdrawal_destination = &ctx.accounts.fee_withdrawal_destination;
+        let treasury_withdrawal_destination_owner =
+            &ctx.accounts.treasury_withdrawal_destination_owner;
+        let treasury_withdrawal_destination = &ctx.accounts.treasury_withdrawal_destination;
+        let token_program = &ctx.accounts.token_program;
+        let system_program = &ctx.accounts.system_program;
+        let ata_program = &ctx.accounts.ata_program;
+        let rent = &ctx.accounts.rent;
+        let is_native = treasury_mint.key() == spl_token::native_mint::id();
+
+        if let Some(sfbp) = seller_fee_basis_points {
+            if sfbp > 10000 {
+                return Err(AuctionHouseError::InvalidBasisPoints.into());
+            }
+
+            auction_house.seller_fee_basis_points = sfbp;
+        }
+
+        if let Some(rqf) = requires_sign_off {
+            auction_house.requires_sign_off = rqf;
+        }
+        if let Some(chsp) = can_change_sale_price {
+            auction_house.can_change_sale_price = chsp;
+        }
+        if let Some(mrbp) = max_referral_basis_points {
+            if mrbp > MAX_REFERRAL_BASIS_POINTS {
+                return Err(AuctionHouseError::InvalidBasisPoints.into());
+            }
+
+            auction_house.max_referral_basis_points = mrbp;
+        }
+        if let Some(rp) = royalty_policy {
+            if let RoyaltyPolicy::Optional { min_basis_points } = rp {
+                if min_basis_points > 10000 {
+                    return Err(AuctionHouseError::InvalidBasisPoints.into());
+                }
+            }
+
+            auction_house.royalty_policy = rp;
+        }
+        if let Some(ucp) = unverified_creator_policy {
+            auction_house.unverified_creator_policy = ucp;
+        }
+
+        // The PDA seeds use `creator`, so rotating the authority keeps the house address.
+        auction_house.authority = new_authority.key();
+        auction_house.treasury_withdrawal_destination = treasury_withdrawal_destination.key();
+        auction_house.fee_withdrawal_destination = fee_withdrawal_destination.key();
+
+        if !is_native {
+            if treasury_withdrawal_destination.data_is_empty() {
+                make_ata(
+                    treasury_withdrawal_destination.to_account_info(),
+                    treasury_withdrawal_destination_owner.to_account_info(),
+                    treasury_mint.to_account_info(),
+                    payer.to_account_info(),
+                    ata_program.to_account_info(),
+                    token_program.to_account_info(),
+                    system_program.to_account_info(),
+                    rent.to_account_info(),
+                    &[],
+                )?;
+            }
+
+            assert_is_ata(
+                &treasury_withdrawal_destination.to_account_info(),
+                &treasury_withdrawal_destination_owner.key(),
+                &treasury_mint.key(),
+            )?;
+        } else {
+            assert_keys_equal(
+                treasury_withdrawal_destination.key(),
+                treasury_withdrawal_destination_owner.key(),
+            )?;
+        }
+
+        emit!(AuctionHouseUpdated {
+            auction_house: auction_house.key(),
+            authority: auction_house.authority,
+            treasury_mint: auction_house.treasury_mint,
+            seller_fee_basis_points: auction_house.seller_fee_basis_points,
+            requires_sign_off: auction_house.requires_sign_off,
+            can_change_sale_price: auction_house.can_change_sale_price,
+            max_referral_basis_points: auction_house.max_referral_basis_points,
+            royalty_policy: auction_house.royalty_policy,
+            unverified_creator_policy: auction_house.unverified_creator_policy,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw `amount` from the Auction House Fee Account to a provided destination account.
+    pub fn withdraw_from_fee<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawFromFee<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let auction_house_fee_account = &ctx.accounts.auction_house_fee_account;
+        let fee_withdrawal_destination = &ctx.accounts.fee_withdrawal_destination;
+        let auction_house = &ctx.accounts.auction_house;
+        let system_program = &ctx.accounts.system_program;
+
+        let auction_house_key = auction_house.key();
+        let seeds = [
+            PREFIX.as_bytes(),
+            auction_house_key.as_ref(),
+            FEE_PAYER.as_bytes(),
+            &[auction_house.fee_payer_bump],
+        ];
+
+        let rent_shortfall =
+            verify_withdrawal(auction_house_fee_account.to_account_info(), amount)?;
+        let checked_amount = amount
+            .checked_sub(rent_shortfall)
+            .ok_or(AuctionHouseError::InsufficientFunds)?;
+
+        invoke_signed(
+            &system_instruction::transfer(
+                &auction_house_fee_account.key(),
+                &fee_withdrawal_destination.key(),
+                checked_amount,
+            ),
+            &[
+                auction_house_fee_account.to_account_info(),
+                fee_withdrawal_destination.to_account_info(),
+                system_program.to_account_info(),
+            ],
+            &[&seeds],
+        )?;
+
+        Ok(())
+    }
+
+    /// Withdraw `amount` from the Auction House Treasury Account to a provided destination account.
+    pub fn withdraw_from_treasury<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawFromTreasury<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let treasury_mint = &ctx.accounts.treasury_mint;
+        let treasury_withdrawal_destination = &ctx.accounts.treasury_withdrawal_destination;
+        let auction_house_treasury = &ctx.accounts.auction_house_treasury;
+        let auction_house = &ctx.accounts.auction_house;
+        let token_program = &ctx.accounts.token_program;
+        let system_program = &ctx.accounts.system_program;
+
+        let is_native = treasury_mint.key() == spl_token::native_mint::id();
+        let auction_house_seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            &[auction_house.bump],
+        ];
+
+        let auction_house_key = auction_house.key();
+        let auction_house_treasury_seeds = [
+            PREFIX.as_bytes(),
+            auction_house_key.as_ref(),
+            TREASURY.as_bytes(),
+            &[auction_house.treasury_bump],
+        ];
+
+        if !is_native {
+            transfer_tokens(
+                token_program,
+                auction_house_treasury,
+                treasury_mint,
+                treasury_withdrawal_destination,
+                &auction_house.to_account_info(),
+                amount,
+                &[&auction_house_seeds],
+            )?;
+        } else {
+            let rent_shortfall =
+                verify_withdrawal(auction_house_treasury.to_account_info(), amount)?;
+            let checked_amount = amount
+                .checked_sub(rent_shortfall)
+                .ok_or(AuctionHouseError::InsufficientFunds)?;
+
+            invoke_signed(
+                &system_instruction::transfer(
+                    &auction_house_treasury.key(),
+                    &treasury_withdrawal_destination.key(),
+                    checked_amount,
+                ),
+                &[
+                    auction_house_treasury.to_account_info(),
+                    treasury_withdrawal_destination.to_account_info(),
+                    system_program.to_account_info(),
+                ],
+                &[&auction_house_treasury_seeds],
+            )?;
+        }
+
         Ok(())
     }
 
+    /// Delegate the given scopes of this Auction House to an Auctioneer authority.
+    pub fn delegate_auctioneer<'info>(
+        ctx: Context<'_, '_, '_, 'info, DelegateAuctioneer<'info>>,
+        scopes: Vec<AuthorityScope>,
+    ) -> Result<()> {
+        auctioneer::delegate_auctioneer(ctx, scopes)
+    }
+
+    /// Replace the scopes delegated to the Auctioneer authority.
+    pub fn update_auctioneer<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdateAuctioneer<'info>>,
+        scopes: Vec<AuthorityScope>,
+    ) -> Result<()> {
+        auctioneer::update_auctioneer(ctx, scopes)
+    }
+
+    /// Revoke the Auctioneer authority, clearing all delegated scopes and closing the Auctioneer PDA.
+    pub fn revoke_auctioneer<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevokeAuctioneer<'info>>,
+    ) -> Result<()> {
+        auctioneer::revoke_auctioneer(ctx)
+    }
+
+    /// Charge sales of tokens in the `collection` `seller_fee_basis_points` instead of the house rate.
+    pub fn create_fee_schedule<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateFeeSchedule<'info>>,
+        seller_fee_basis_points: u16,
+    ) -> Result<()> {
+        fee_schedule::create_fee_schedule(ctx, seller_fee_basis_points)
+    }
+
+    /// Change the rate of a collection fee schedule.
+    pub fn update_fee_schedule<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdateFeeSchedule<'info>>,
+        seller_fee_basis_points: u16,
+    ) -> Result<()> {
+        fee_schedule::update_fee_schedule(ctx, seller_fee_basis_points)
+    }
+
+    /// Close a collection fee schedule, returning the collection to the house rate.
+    pub fn close_fee_schedule<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseFeeSchedule<'info>>,
+    ) -> Result<()> {
+        fee_schedule::close_fee_schedule(ctx)
+    }
+
+    /// Create a public buy bid by creating a `public_buyer_trade_state` account and an `escrow_payment` account and funding the escrow with the necessary SOL or SPL token amount.
+    #[allow(clippy::too_many_arguments)]
+    pub fn public_buy<'info>(
+        ctx: Context<'_, '_, '_, 'info, PublicBuy<'info>>,
+        trade_state_bump: u8,
+        escrow_payment_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+        expiry: Option<i64>,
+        referral: Option<Referral>,
+        royalty_choice: Option<u16>,
+    ) -> Result<()> {
+        public_bid(
+            ctx,
+            trade_state_bump,
+            escrow_payment_bump,
+            buyer_price,
+            token_size,
+            expiry,
+            referral,
+            royalty_choice,
+        )
+    }
+
     /// Create a private buy bid by creating a `buyer_trade_state` account and an `escrow_payment` account and funding the escrow with the necessary SOL or SPL token amount.
+    #[allow(clippy::too_many_arguments)]
     pub fn buy<'info>(
         ctx: Context<'_, '_, '_, 'info, Buy<'info>>,
         trade_state_bump: u8,
         escrow_payment_bump: u8,
         buyer_price: u64,
         token_size: u64,
+        expiry: Option<i64>,
+        referral: Option<Referral>,
+        royalty_choice: Option<u16>,
     ) -> Result<()> {
         private_bid(
             ctx,
@@ -167,6 +475,43 @@ pub mod auction_house_v2 {
             escrow_payment_bump,
             buyer_price,
             token_size,
+            expiry,
+            referral,
+            royalty_choice,
+        )
+    }
+
+    /// Create a public buy bid through the delegated Auctioneer.
+    pub fn auctioneer_public_buy<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerPublicBuy<'info>>,
+        trade_state_bump: u8,
+        escrow_payment_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+    ) -> Result<()> {
+        auctioneer_public_bid(
+            ctx,
+            trade_state_bump,
+            escrow_payment_bump,
+            buyer_price,
+            token_size,
+        )
+    }
+
+    /// Create a private buy bid through the delegated Auctioneer.
+    pub fn auctioneer_buy<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerBuy<'info>>,
+        trade_state_bump: u8,
+        escrow_payment_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+    ) -> Result<()> {
+        auctioneer_private_bid(
+            ctx,
+            trade_state_bump,
+            escrow_payment_bump,
+            buyer_price,
+            token_size,
         )
     }
 
@@ -179,6 +524,7 @@ pub mod auction_house_v2 {
         deposit::deposit(ctx, escrow_payment_bump, amount)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn sell<'info>(
         ctx: Context<'_, '_, '_, 'info, Sell<'info>>,
         trade_state_bump: u8,
@@ -186,6 +532,8 @@ pub mod auction_house_v2 {
         program_as_signer_bump: u8,
         buyer_price: u64,
         token_size: u64,
+        expiry: Option<i64>,
+        referral: Option<Referral>,
     ) -> Result<()> {
         sell::sell(
             ctx,
@@ -194,6 +542,25 @@ pub mod auction_house_v2 {
             program_as_signer_bump,
             buyer_price,
             token_size,
+            expiry,
+            referral,
+        )
+    }
+
+    /// List an NFT through the delegated Auctioneer. The listing has no fixed price; the Auctioneer settles it against a bid.
+    pub fn auctioneer_sell<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerSell<'info>>,
+        trade_state_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        token_size: u64,
+    ) -> Result<()> {
+        sell::auctioneer_sell(
+            ctx,
+            trade_state_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            token_size,
         )
     }
 
@@ -217,6 +584,297 @@ pub mod auction_house_v2 {
         )
     }
 
+    /// Sell `partial_order_size` of the `token_size` tokens listed at `buyer_price` for
+    /// `partial_order_price`, keeping the listing open for the tokens still delegated.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_partial_sale<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteSale<'info>>,
+        escrow_payment_bump: u8,
+        _free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        escrow_nft_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+        partial_order_size: u64,
+        partial_order_price: u64,
+    ) -> Result<()> {
+        execute_sale::execute_partial_sale(
+            ctx,
+            escrow_payment_bump,
+            _free_trade_state_bump,
+            program_as_signer_bump,
+            escrow_nft_bump,
+            buyer_price,
+            token_size,
+            partial_order_size,
+            partial_order_price,
+        )
+    }
+
+    /// Execute a sale of an Auctioneer listing through the delegated Auctioneer.
+    pub fn auctioneer_execute_sale<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerExecuteSale<'info>>,
+        escrow_payment_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        escrow_nft_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+    ) -> Result<()> {
+        execute_sale::auctioneer_execute_sale(
+            ctx,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            escrow_nft_bump,
+            buyer_price,
+            token_size,
+        )
+    }
+
+    /// List an NFT for a timed English auction by creating a `seller_trade_state` with no fixed price and an `auction_state` account.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_auction<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateAuction<'info>>,
+        trade_state_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        token_size: u64,
+        start_time: i64,
+        end_time: i64,
+        reserve_price: u64,
+        min_increment: u64,
+        extension_window: i64,
+    ) -> Result<()> {
+        auction::create_auction(
+            ctx,
+            trade_state_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            token_size,
+            start_time,
+            end_time,
+            reserve_price,
+            min_increment,
+            extension_window,
+        )
+    }
+
+    /// Bid on an English auction from the escrow payment account, locking the bid and releasing the bid it outbids.
+    pub fn place_auction_bid<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceAuctionBid<'info>>,
+        escrow_payment_bump: u8,
+        bid_price: u64,
+    ) -> Result<()> {
+        auction::place_auction_bid(ctx, escrow_payment_bump, bid_price)
+    }
+
+    /// Settle an ended English auction, selling to the highest bidder through the `execute_sale` path. Anyone can settle.
+    pub fn settle_auction<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleAuction<'info>>,
+        escrow_payment_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        escrow_nft_bump: u8,
+    ) -> Result<()> {
+        auction::settle_auction(
+            ctx,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            escrow_nft_bump,
+        )
+    }
+
+    /// Pull an English auction listing. Settling the auction once it ends returns the highest bid.
+    pub fn cancel_auction<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelAuction<'info>>,
+    ) -> Result<()> {
+        auction::cancel_auction(ctx)
+    }
+
+    /// List an NFT at a price falling over time by creating a `seller_trade_state` with no fixed price and a `listing_config` account.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_dutch_listing<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateDutchListing<'info>>,
+        trade_state_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        token_size: u64,
+        start_price: u64,
+        end_price: u64,
+        start_time: i64,
+        end_time: i64,
+        curve: DecayCurve,
+    ) -> Result<()> {
+        dutch::create_dutch_listing(
+            ctx,
+            trade_state_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            token_size,
+            start_price,
+            end_price,
+            start_time,
+            end_time,
+            curve,
+        )
+    }
+
+    /// Execute a sale of a dutch listing at its current price, priced from the `Clock`, against a bid covering that price.
+    pub fn execute_dutch_sale<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteDutchSale<'info>>,
+        escrow_payment_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        escrow_nft_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+    ) -> Result<()> {
+        dutch::execute_dutch_sale(
+            ctx,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            escrow_nft_bump,
+            buyer_price,
+            token_size,
+        )
+    }
+
+    /// Cancel a dutch listing and close its `listing_config` account.
+    pub fn cancel_dutch_listing<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelDutchListing<'info>>,
+    ) -> Result<()> {
+        dutch::cancel_dutch_listing(ctx)
+    }
+
+    /// Bid `price` per token for up to `quantity` tokens of any mint in a verified collection, funding the escrow for all of them.
+    pub fn collection_buy<'info>(
+        ctx: Context<'_, '_, '_, 'info, CollectionBuy<'info>>,
+        escrow_payment_bump: u8,
+        price: u64,
+        quantity: u64,
+    ) -> Result<()> {
+        collection_bid::collection_buy(ctx, escrow_payment_bump, price, quantity)
+    }
+
+    /// Sell a listed token in the bid's collection to a collection bid, drawing from the bidder's escrow.
+    pub fn execute_collection_sale<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteCollectionSale<'info>>,
+        escrow_payment_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        escrow_nft_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+    ) -> Result<()> {
+        collection_bid::execute_collection_sale(
+            ctx,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            escrow_nft_bump,
+            buyer_price,
+            token_size,
+        )
+    }
+
+    /// Close a collection bid, returning its rent to the bidder.
+    pub fn cancel_collection_bid<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelCollectionBid<'info>>,
+    ) -> Result<()> {
+        collection_bid::cancel_collection_bid(ctx)
+    }
+
+    /// Offer `token_size` of a token for `wanted_size` of a token matching `wanted`, with optional extra amounts
+    /// paid by either side.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_swap_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateSwapOffer<'info>>,
+        escrow_payment_bump: u8,
+        token_size: u64,
+        wanted: SwapTarget,
+        wanted_size: u64,
+        maker_extra: u64,
+        taker_extra: u64,
+    ) -> Result<()> {
+        swap::create_swap_offer(
+            ctx,
+            escrow_payment_bump,
+            token_size,
+            wanted,
+            wanted_size,
+            maker_extra,
+            taker_extra,
+        )
+    }
+
+    /// Accept a swap offer, exchanging both tokens and paying royalties on each extra amount.
+    pub fn execute_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteSwap<'info>>,
+        maker_escrow_payment_bump: u8,
+        taker_escrow_payment_bump: u8,
+        program_as_signer_bump: u8,
+    ) -> Result<()> {
+        swap::execute_swap(
+            ctx,
+            maker_escrow_payment_bump,
+            taker_escrow_payment_bump,
+            program_as_signer_bump,
+        )
+    }
+
+    /// Close a swap offer, revoking the delegate over the offered token if the maker signs.
+    pub fn cancel_swap_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelSwapOffer<'info>>,
+    ) -> Result<()> {
+        swap::cancel_swap_offer(ctx)
+    }
+
+    /// List one of each of the tokens in the remaining accounts together at `price`, splitting royalties by `weights`.
+    pub fn create_bundle_listing<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateBundleListing<'info>>,
+        price: u64,
+        weights: Vec<u64>,
+    ) -> Result<()> {
+        bundle::create_bundle_listing(ctx, price, weights)
+    }
+
+    /// Buy every token in a bundle listing at its price in one instruction.
+    pub fn execute_bundle_sale<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteBundleSale<'info>>,
+        escrow_payment_bump: u8,
+        program_as_signer_bump: u8,
+        price: u64,
+    ) -> Result<()> {
+        bundle::execute_bundle_sale(ctx, escrow_payment_bump, program_as_signer_bump, price)
+    }
+
+    /// Close a bundle listing, revoking the delegate over each item if the seller signs.
+    pub fn cancel_bundle_listing<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelBundleListing<'info>>,
+    ) -> Result<()> {
+        bundle::cancel_bundle_listing(ctx)
+    }
+
+    /// Move the NFT held in escrow after `execute_sale` to the buyer and close the escrow account.
+    pub fn claim_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimNft<'info>>,
+        _escrow_nft_bump: u8,
+    ) -> Result<()> {
+        escrow_nft::claim_nft(ctx)
+    }
+
+    /// Return the NFT held in escrow after `execute_sale` to the seller, who pays the sale price back to the buyer, and
+    /// close the escrow account.
+    pub fn refund_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundNft<'info>>,
+        _escrow_nft_bump: u8,
+    ) -> Result<()> {
+        escrow_nft::refund_nft(ctx)
+    }
+
     /// Create a listing receipt by creating a `listing_receipt` account.
     pub fn print_listing_receipt<'info>(
         ctx: Context<'_, '_, '_, 'info, PrintListingReceipt<'info>>,
@@ -263,6 +921,24 @@ pub mod auction_house_v2 {
         cancel::cancel(ctx, buyer_price, token_size)
     }
 
+    /// Cancel a bid or listing through the delegated Auctioneer.
+    pub fn auctioneer_cancel<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerCancel<'info>>,
+        buyer_price: u64,
+        token_size: u64,
+    ) -> Result<()> {
+        cancel::auctioneer_cancel(ctx, buyer_price, token_size)
+    }
+
+    /// Close a bid or listing whose expiry has passed and refund its rent. Anyone can call this.
+    pub fn close_expired_trade_state<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseExpiredTradeState<'info>>,
+        buyer_price: u64,
+        token_size: u64,
+    ) -> Result<()> {
+        cancel::close_expired_trade_state(ctx, buyer_price, token_size)
+    }
+
     /// Withdraw `amount` from the escrow payment account for your specific wallet.
     pub fn withdraw<'info>(
         ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
@@ -307,8 +983,9 @@ pub mod auction_house_v2 {
 #[derive(Accounts)]
 #[instruction(bump: u8, fee_payer_bump: u8, treasury_bump: u8)]
 pub struct CreateAuctionHouse<'info> {
-    /// Treasury mint account, either native SOL mint or a SPL token mint.
-    pub treasury_mint: Account<'info, Mint>,
+    /// CHECK: Validated in create_auction_house.
+    /// Treasury mint account, either native SOL mint or a SPL Token or Token-2022 mint.
+    pub treasury_mint: UncheckedAccount<'info>,
 
     /// Key paying SOL fees for setting up the Auction House.
     #[account(mut)]
@@ -346,13 +1023,59 @@ pub struct CreateAuctionHouse<'info> {
     #[account(mut, seeds=[PREFIX.as_bytes(), auction_house.key().as_ref(), TREASURY.as_bytes()], bump)]
     pub auction_house_treasury: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram)]
+    pub token_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
     pub ata_program: Program<'info, AssociatedToken>,
     pub rent: Sysvar<'info, Rent>,
 }
 
 
+/// Accounts for the [`update_auction_house` handler](auction_house/fn.update_auction_house.html).
+#[derive(Accounts)]
+pub struct UpdateAuctionHouse<'info> {
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Treasury mint account, either native SOL mint or a SPL Token or Token-2022 mint.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// Key paying SOL fees for setting up the Auction House.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Authority key for the Auction House.
+    pub authority: Signer<'info>,
+
+    /// CHECK: User can use whatever they want for updating this.
+    /// New authority key for the Auction House.
+    pub new_authority: UncheckedAccount<'info>,
+
+    /// CHECK: User can use whatever they want for updating this.
+    /// Account that pays for fees if the marketplace executes sales.
+    #[account(mut)]
+    pub fee_withdrawal_destination: UncheckedAccount<'info>,
+
+    /// CHECK: User can use whatever they want for updating this.
+    /// SOL or SPL token account to receive Auction House fees. If treasury mint is native this will be the same as the `treasury_withdrawl_destination_owner`.
+    #[account(mut)]
+    pub treasury_withdrawal_destination: UncheckedAccount<'info>,
+
+    /// CHECK: User can use whatever they want for updating this.
+    /// Owner of the `treasury_withdrawal_destination` account or the same address if the `treasury_mint` is native.
+    pub treasury_withdrawal_destination_owner: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(mut, seeds=[PREFIX.as_bytes(), auction_house.creator.as_ref(), treasury_mint.key().as_ref()], bump=auction_house.bump, has_one=authority, has_one=treasury_mint)]
+    pub auction_house: Account<'info, AuctionHouse>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram)]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
 #[derive(Accounts)]
 #[instruction(escrow_payment_bump: u8)]
 pub struct CloseEscrowAccount<'info> {
@@ -396,8 +1119,9 @@ pub struct WithdrawFromFee<'info> {
 /// Accounts for the [`withdraw_from_treasury` handler](auction_house/fn.withdraw_from_treasury.html).
 #[derive(Accounts)]
 pub struct WithdrawFromTreasury<'info> {
-    /// Treasury mint account, either native SOL mint or a SPL token mint.
-    pub treasury_mint: Account<'info, Mint>,
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Treasury mint account, either native SOL mint or a SPL Token or Token-2022 mint.
+    pub treasury_mint: UncheckedAccount<'info>,
 
     /// Authority key for the Auction House.
     pub authority: Signer<'info>,
@@ -416,6 +1140,8 @@ pub struct WithdrawFromTreasury<'info> {
     #[account(mut, seeds=[PREFIX.as_bytes(), auction_house.creator.as_ref(), treasury_mint.key().as_ref()], bump=auction_house.bump, has_one=authority, has_one=treasury_mint, has_one=treasury_withdrawal_destination, has_one=auction_house_treasury)]
     pub auction_house: Account<'info, AuctionHouse>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram)]
+    pub token_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 }
\ No newline at end of file
@@ -48,6 +48,50 @@ pub fn find_escrow_payment_address(auction_house: &Pubkey, wallet: &Pubkey) -> (
     )
 }
 
+/// Return escrow NFT account `Pubkey` address and bump seed for a sale of `mint` from `seller` to `buyer`.
+pub fn find_escrow_nft_address(
+    auction_house: &Pubkey,
+    seller: &Pubkey,
+    mint: &Pubkey,
+    buyer: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            auction_house.as_ref(),
+            seller.as_ref(),
+            mint.as_ref(),
+            buyer.as_ref(),
+            NFT.as_bytes(),
+        ],
+        &id(),
+    )
+}
+
+/// Return `EscrowNftRecord` PDA `Pubkey` address and bump seed for `escrow_nft_account`.
+pub fn find_escrow_nft_record_address(escrow_nft_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            ESCROW_NFT_RECORD.as_bytes(),
+            escrow_nft_account.as_ref(),
+        ],
+        &id(),
+    )
+}
+
+/// Return `Auctioneer` PDA `Pubkey` address and bump seed.
+pub fn find_auctioneer_pda(auction_house: &Pubkey, auctioneer_authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            AUCTIONEER.as_bytes(),
+            auction_house.as_ref(),
+            auctioneer_authority.as_ref(),
+        ],
+        &id(),
+    )
+}
+
 /// Return trade state `Pubkey` address and bump seed.
 pub fn find_trade_state_address(
     wallet: &Pubkey,
@@ -73,6 +117,32 @@ pub fn find_trade_state_address(
     )
 }
 
+/// Return trade state `Pubkey` address and bump seed for an order keyed by `seed` in place of a price, such as a
+/// listing priced at settlement or a buyer trade state created for a bid held elsewhere.
+pub fn find_seeded_trade_state_address(
+    wallet: &Pubkey,
+    auction_house: &Pubkey,
+    token_account: &Pubkey,
+    treasury_mint: &Pubkey,
+    token_mint: &Pubkey,
+    seed: &str,
+    token_size: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            wallet.as_ref(),
+            auction_house.as_ref(),
+            token_account.as_ref(),
+            treasury_mint.as_ref(),
+            token_mint.as_ref(),
+            seed.as_bytes(),
+            &token_size.to_le_bytes(),
+        ],
+        &id(),
+    )
+}
+
 /// Return trade state `Pubkey` address and bump seed.
 pub fn find_public_bid_trade_state_address(
     wallet: &Pubkey,
@@ -125,4 +195,139 @@ pub fn find_purchase_receipt_address(
         ],
         &id(),
     )
-}
\ No newline at end of file
+}
+
+/// Return auction state `Pubkey` address and bump seed.
+pub fn find_auction_state_address(seller_trade_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            seller_trade_state.as_ref(),
+        ],
+        &id(),
+    )
+}
+
+/// Return `Pubkey` address and bump seed of the escrow payment account locking the highest bid of an auction.
+pub fn find_auction_escrow_payment_address(
+    auction_house: &Pubkey,
+    auction_state: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            AUCTION.as_bytes(),
+            auction_house.as_ref(),
+            auction_state.as_ref(),
+        ],
+        &id(),
+    )
+}
+
+/// Return dutch listing config `Pubkey` address and bump seed.
+pub fn find_listing_config_address(seller_trade_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            LISTING_CONFIG.as_bytes(),
+            seller_trade_state.as_ref(),
+        ],
+        &id(),
+    )
+}
+
+/// Return collection bid `Pubkey` address and bump seed.
+pub fn find_collection_bid_address(
+    wallet: &Pubkey,
+    auction_house: &Pubkey,
+    collection: &Pubkey,
+    price: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            COLLECTION_BID.as_bytes(),
+            wallet.as_ref(),
+            auction_house.as_ref(),
+            collection.as_ref(),
+            &price.to_le_bytes(),
+        ],
+        &id(),
+    )
+}
+
+/// Return swap offer `Pubkey` address and bump seed.
+pub fn find_swap_offer_address(
+    maker: &Pubkey,
+    auction_house: &Pubkey,
+    token_account: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            SWAP_OFFER.as_bytes(),
+            maker.as_ref(),
+            auction_house.as_ref(),
+            token_account.as_ref(),
+        ],
+        &id(),
+    )
+}
+
+/// Return bundle listing `Pubkey` address and bump seed, keyed by the token account of its first item.
+pub fn find_bundle_listing_address(
+    seller: &Pubkey,
+    auction_house: &Pubkey,
+    first_token_account: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            BUNDLE.as_bytes(),
+            seller.as_ref(),
+            auction_house.as_ref(),
+            first_token_account.as_ref(),
+        ],
+        &id(),
+    )
+}
+
+/// Return fee schedule `Pubkey` address and bump seed for a collection mint.
+pub fn find_fee_schedule_address(auction_house: &Pubkey, collection: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            FEE_SCHEDULE.as_bytes(),
+            auction_house.as_ref(),
+            collection.as_ref(),
+        ],
+        &id(),
+    )
+}
+
+/// Return trader stats `Pubkey` address and bump seed for a wallet.
+pub fn find_trader_stats_address(auction_house: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            TRADER_STATS.as_bytes(),
+            auction_house.as_ref(),
+            wallet.as_ref(),
+        ],
+        &id(),
+    )
+}
+
+/// Return collection stats `Pubkey` address and bump seed for a collection mint.
+pub fn find_collection_stats_address(auction_house: &Pubkey, collection: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            COLLECTION_STATS.as_bytes(),
+            auction_house.as_ref(),
+            collection.as_ref(),
+        ],
+        &id(),
+    )
+}
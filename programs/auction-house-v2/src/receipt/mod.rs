@@ -3,12 +3,38 @@ use crate::{
     constants::*,
     errors::AuctionHouseError,
     id,
-    instruction::{Buy, ExecuteSale, Sell},
+    instruction::{AuctioneerSell, Buy, CreateDutchListing, ExecutePartialSale, ExecuteSale, Sell},
     utils::*,
+    ListingConfig, Referral,
 };
 use anchor_lang::{prelude::*, AnchorDeserialize, AnchorSerialize};
 use solana_program::{sysvar, sysvar::instructions::get_instruction_relative};
 
+// Positions of the accounts the receipts read from the instruction they follow. Every variant of
+// a listing, bid, purchase or cancel instruction keeps these accounts where its base instruction
+// has them; the `client` builders are tested against them.
+pub const LISTING_WALLET_INDEX: usize = 0;
+pub const LISTING_METADATA_INDEX: usize = 2;
+pub const LISTING_AUCTION_HOUSE_INDEX: usize = 4;
+pub const LISTING_TRADE_STATE_INDEX: usize = 6;
+
+pub const BID_WALLET_INDEX: usize = 0;
+pub const BID_TOKEN_ACCOUNT_INDEX: usize = 4;
+pub const BID_METADATA_INDEX: usize = 5;
+pub const BID_AUCTION_HOUSE_INDEX: usize = 8;
+pub const BID_TRADE_STATE_INDEX: usize = 10;
+
+pub const PURCHASE_BUYER_INDEX: usize = 0;
+pub const PURCHASE_SELLER_INDEX: usize = 1;
+pub const PURCHASE_METADATA_INDEX: usize = 6;
+pub const PURCHASE_AUCTION_HOUSE_INDEX: usize = 12;
+pub const PURCHASE_BUYER_TRADE_STATE_INDEX: usize = 15;
+pub const PURCHASE_SELLER_TRADE_STATE_INDEX: usize = 16;
+/// Only in `execute_dutch_sale`.
+pub const PURCHASE_LISTING_CONFIG_INDEX: usize = 18;
+
+pub const CANCEL_TRADE_STATE_INDEX: usize = 6;
+
 pub const BID_RECEIPT_SIZE: usize = 8 + //key
 32 + // trade_state
 32 + // bookkeeper
@@ -22,7 +48,9 @@ pub const BID_RECEIPT_SIZE: usize = 8 + //key
 1 + // bump
 1 + // trade_state_bump
 8 + // created_at
-1 + 8; // canceled_at
+1 + 8 + // canceled_at
+1 + 32 + 2 + // referral
+1 + 2; // royalty_choice
 
 /// Receipt for a bid transaction.
 #[account]
@@ -40,6 +68,9 @@ pub struct BidReceipt {
     pub trade_state_bump: u8,
     pub created_at: i64,
     pub canceled_at: Option<i64>,
+    pub referral: Option<Referral>,
+    /// Share of the metadata royalty, in basis points, the bid chose to pay creators.
+    pub royalty_choice: Option<u16>,
 }
 
 pub const LISTING_RECEIPT_SIZE: usize = 8 + //key
@@ -54,7 +85,8 @@ pub const LISTING_RECEIPT_SIZE: usize = 8 + //key
 1 + // bump
 1 + // trade_state_bump
 8 + // created_at
-1 + 8; // canceled_at;
+1 + 8 + // canceled_at
+1 + 32 + 2; // referral
 
 /// Receipt for a listing transaction.
 #[account]
@@ -71,6 +103,7 @@ pub struct ListingReceipt {
     pub trade_state_bump: u8,
     pub created_at: i64,
     pub canceled_at: Option<i64>,
+    pub referral: Option<Referral>,
 }
 
 pub const PURCHASE_RECEIPT_SIZE: usize = 8 + //key
@@ -82,7 +115,10 @@ pub const PURCHASE_RECEIPT_SIZE: usize = 8 + //key
 8 + // token_size
 8 + // price
 1 + // bump
-8; // created_at
+8 + // created_at
+1 + 32 + 2 + // seller_referral
+1 + 32 + 2 + // buyer_referral
+1 + 2; // royalty_choice
 
 /// Receipt for a purchase transaction.
 #[account]
@@ -96,6 +132,12 @@ pub struct PurchaseReceipt {
     pub price: u64,
     pub bump: u8,
     pub created_at: i64,
+    /// Referral of the listing, paid its share of the house fee at settlement.
+    pub seller_referral: Option<Referral>,
+    /// Referral of the bid, paid its share of the house fee at settlement.
+    pub buyer_referral: Option<Referral>,
+    /// Share of the metadata royalty, in basis points, the bid chose to pay creators under an optional royalty policy.
+    pub royalty_choice: Option<u16>,
 }
 
 /// Accounts for the [`print_listing_receipt` hanlder](fn.print_listing_receipt.html).
@@ -138,15 +180,43 @@ pub fn print_listing_receipt<'info>(
     let prev_instruction = get_instruction_relative(-1, instruction_account)?;
     let prev_instruction_accounts = prev_instruction.accounts;
 
-    let wallet = &prev_instruction_accounts[0];
-    let auction_house = &prev_instruction_accounts[4];
-    let seller_trade_state = &prev_instruction_accounts[6];
-    let metadata = &prev_instruction_accounts[2];
+    let wallet = &prev_instruction_accounts[LISTING_WALLET_INDEX];
+    let auction_house = &prev_instruction_accounts[LISTING_AUCTION_HOUSE_INDEX];
+    let seller_trade_state = &prev_instruction_accounts[LISTING_TRADE_STATE_INDEX];
+    let metadata = &prev_instruction_accounts[LISTING_METADATA_INDEX];
 
-    assert_program_listing_instruction(&prev_instruction.data[..8])?;
+    let listing_type = assert_program_listing_instruction(&prev_instruction.data[..8])?;
 
     let mut buffer = &prev_instruction.data[8..];
-    let sell_data = Sell::deserialize(&mut buffer)?;
+    let sell_data = match listing_type {
+        ListingType::Sell => Sell::deserialize(&mut buffer)?,
+        // Auctioneer listings are keyed at `u64::MAX`; the Auctioneer sets the final price.
+        ListingType::AuctioneerSell => {
+            let auctioneer_sell_data = AuctioneerSell::deserialize(&mut buffer)?;
+            Sell {
+                trade_state_bump: auctioneer_sell_data.trade_state_bump,
+                free_trade_state_bump: auctioneer_sell_data.free_trade_state_bump,
+                program_as_signer_bump: auctioneer_sell_data.program_as_signer_bump,
+                buyer_price: u64::MAX,
+                token_size: auctioneer_sell_data.token_size,
+                expiry: None,
+                referral: None,
+            }
+        }
+        // Dutch listings have no fixed price either; the purchase receipt records the price they sell at.
+        ListingType::CreateDutchListing => {
+            let dutch_listing_data = CreateDutchListing::deserialize(&mut buffer)?;
+            Sell {
+                trade_state_bump: dutch_listing_data.trade_state_bump,
+                free_trade_state_bump: dutch_listing_data.free_trade_state_bump,
+                program_as_signer_bump: dutch_listing_data.program_as_signer_bump,
+                buyer_price: u64::MAX,
+                token_size: dutch_listing_data.token_size,
+                expiry: None,
+                referral: None,
+            }
+        }
+    };
 
     assert_keys_equal(prev_instruction.program_id, id())?;
 
@@ -193,9 +263,11 @@ pub fn print_listing_receipt<'info>(
         trade_state_bump: sell_data.trade_state_bump,
         created_at: clock.unix_timestamp,
         canceled_at: None,
+        referral: sell_data.referral,
     };
 
-    receipt.try_serialize(&mut *receipt_account.try_borrow_mut_data()?)?;
+    // Serialize through a temporary slice; writing advances the slice it is given.
+    receipt.try_serialize(&mut &mut receipt_account.try_borrow_mut_data()?[..])?;
 
     Ok(())
 }
@@ -227,7 +299,7 @@ pub fn cancel_listing_receipt<'info>(
     let prev_instruction = get_instruction_relative(-1, instruction_account)?;
     let prev_instruction_accounts = prev_instruction.accounts;
 
-    let trade_state = &prev_instruction_accounts[6];
+    let trade_state = &prev_instruction_accounts[CANCEL_TRADE_STATE_INDEX];
 
     assert_program_cancel_instruction(&prev_instruction.data[..8])?;
 
@@ -248,7 +320,7 @@ pub fn cancel_listing_receipt<'info>(
 
     receipt.canceled_at = Some(clock.unix_timestamp);
 
-    receipt.try_serialize(&mut *receipt_data)?;
+    receipt.try_serialize(&mut &mut receipt_data[..])?;
 
     Ok(())
 }
@@ -295,11 +367,11 @@ pub fn print_bid_receipt<'info>(
     let prev_instruction = get_instruction_relative(-1, instruction_account)?;
     let prev_instruction_accounts = prev_instruction.accounts;
 
-    let wallet = &prev_instruction_accounts[0];
-    let token_account = &prev_instruction_accounts[4];
-    let auction_house = &prev_instruction_accounts[8];
-    let buyer_trade_state = &prev_instruction_accounts[10];
-    let metadata = &prev_instruction_accounts[5];
+    let wallet = &prev_instruction_accounts[BID_WALLET_INDEX];
+    let token_account = &prev_instruction_accounts[BID_TOKEN_ACCOUNT_INDEX];
+    let auction_house = &prev_instruction_accounts[BID_AUCTION_HOUSE_INDEX];
+    let buyer_trade_state = &prev_instruction_accounts[BID_TRADE_STATE_INDEX];
+    let metadata = &prev_instruction_accounts[BID_METADATA_INDEX];
 
     let mut buffer = &prev_instruction.data[8..];
     let buy_data = Buy::deserialize(&mut buffer)?;
@@ -308,9 +380,9 @@ pub fn print_bid_receipt<'info>(
 
     let token_account = match bid_type {
         BidType::PrivateSale => Some(token_account.pubkey),
-        // BidType::AuctioneerPrivateSale => Some(token_account.pubkey),
-        // BidType::PublicSale => None,
-        // BidType::AuctioneerPublicSale => None,
+        BidType::AuctioneerPrivateSale => Some(token_account.pubkey),
+        BidType::PublicSale => None,
+        BidType::AuctioneerPublicSale => None,
     };
 
     assert_derivation(
@@ -359,9 +431,11 @@ pub fn print_bid_receipt<'info>(
         trade_state_bump: buy_data.trade_state_bump,
         created_at: clock.unix_timestamp,
         canceled_at: None,
+        referral: buy_data.referral,
+        royalty_choice: buy_data.royalty_choice,
     };
 
-    receipt.try_serialize(&mut *receipt_account.try_borrow_mut_data()?)?;
+    receipt.try_serialize(&mut &mut receipt_account.try_borrow_mut_data()?[..])?;
 
     Ok(())
 }
@@ -393,7 +467,7 @@ pub fn cancel_bid_receipt<'info>(
     let prev_instruction = get_instruction_relative(-1, instruction_account)?;
     let prev_instruction_accounts = prev_instruction.accounts;
 
-    let trade_state = &prev_instruction_accounts[6];
+    let trade_state = &prev_instruction_accounts[CANCEL_TRADE_STATE_INDEX];
 
     assert_program_cancel_instruction(&prev_instruction.data[..8])?;
 
@@ -414,7 +488,7 @@ pub fn cancel_bid_receipt<'info>(
 
     receipt.canceled_at = Some(clock.unix_timestamp);
 
-    receipt.try_serialize(&mut *receipt_data)?;
+    receipt.try_serialize(&mut &mut receipt_data[..])?;
 
     Ok(())
 }
@@ -471,16 +545,45 @@ pub fn print_purchase_receipt<'info>(
     let mut buffer = &prev_instruction.data[8..];
     let execute_sale_data = ExecuteSale::deserialize(&mut buffer)?;
 
-    assert_program_purchase_instruction(&prev_instruction.data[..8])?;
+    let purchase_type = assert_program_purchase_instruction(&prev_instruction.data[..8])?;
 
     assert_keys_equal(prev_instruction.program_id, id())?;
 
-    let buyer = &prev_instruction_accounts[0];
-    let seller = &prev_instruction_accounts[1];
-    let metadata = &prev_instruction_accounts[4];
-    let auction_house = &prev_instruction_accounts[10];
-    let buyer_trade_state = &prev_instruction_accounts[13];
-    let seller_trade_state = &prev_instruction_accounts[14];
+    let buyer = &prev_instruction_accounts[PURCHASE_BUYER_INDEX];
+    let seller = &prev_instruction_accounts[PURCHASE_SELLER_INDEX];
+    let metadata = &prev_instruction_accounts[PURCHASE_METADATA_INDEX];
+    let auction_house = &prev_instruction_accounts[PURCHASE_AUCTION_HOUSE_INDEX];
+    let buyer_trade_state = &prev_instruction_accounts[PURCHASE_BUYER_TRADE_STATE_INDEX];
+    let seller_trade_state = &prev_instruction_accounts[PURCHASE_SELLER_TRADE_STATE_INDEX];
+
+    // A dutch sale settles below the bid at the price it records in the listing config,
+    // which is passed after the named accounts. A partial sale settles the share it filled.
+    let (price, token_size) = match purchase_type {
+        PurchaseType::ExecuteDutchSale => {
+            let listing_config_info = ctx
+                .remaining_accounts
+                .first()
+                .ok_or(ErrorCode::AccountNotEnoughKeys)?;
+            assert_keys_equal(
+                listing_config_info.key(),
+                prev_instruction_accounts[PURCHASE_LISTING_CONFIG_INDEX].pubkey,
+            )?;
+            let listing_config: Account<ListingConfig> = Account::try_from(listing_config_info)?;
+            let sale_price = listing_config
+                .sale_price
+                .ok_or(AuctionHouseError::InstructionMismatch)?;
+            (sale_price, execute_sale_data.token_size)
+        }
+        PurchaseType::ExecutePartialSale => {
+            let mut buffer = &prev_instruction.data[8..];
+            let partial_sale_data = ExecutePartialSale::deserialize(&mut buffer)?;
+            (
+                partial_sale_data.partial_order_price,
+                partial_sale_data.partial_order_size,
+            )
+        }
+        _ => (execute_sale_data.buyer_price, execute_sale_data.token_size),
+    };
 
     let timestamp = clock.unix_timestamp;
 
@@ -538,6 +641,18 @@ pub fn print_purchase_receipt<'info>(
         )?;
     }
 
+    let mut listing_receipt_data = listing_receipt_info.try_borrow_mut_data()?;
+    let mut listing_receipt_data_slice: &[u8] = &listing_receipt_data;
+
+    let mut listing_receipt = ListingReceipt::try_deserialize(&mut listing_receipt_data_slice)?;
+
+    let mut bid_receipt_data = bid_receipt_account.try_borrow_mut_data()?;
+    let mut bid_receipt_slice: &[u8] = &bid_receipt_data;
+
+    let mut bid_receipt = BidReceipt::try_deserialize(&mut bid_receipt_slice)?;
+
+    // Referrals and the royalty choice carry over from the listing and bid receipts, which read them from the same
+    // instruction data.
     let purchase = PurchaseReceipt {
         buyer: buyer.pubkey,
         seller: seller.pubkey,
@@ -545,30 +660,23 @@ pub fn print_purchase_receipt<'info>(
         metadata: metadata.pubkey,
         bookkeeper: bookkeeper.key(),
         bump: purchase_receipt_bump,
-        price: execute_sale_data.buyer_price,
-        token_size: execute_sale_data.token_size,
+        price,
+        token_size,
         created_at: timestamp,
+        seller_referral: listing_receipt.referral,
+        buyer_referral: bid_receipt.referral,
+        royalty_choice: bid_receipt.royalty_choice,
     };
 
-    purchase.try_serialize(&mut *purchase_receipt_account.try_borrow_mut_data()?)?;
-
-    let mut listing_receipt_data = listing_receipt_info.try_borrow_mut_data()?;
-    let mut listing_receipt_data_slice: &[u8] = &listing_receipt_data;
-
-    let mut listing_receipt = ListingReceipt::try_deserialize(&mut listing_receipt_data_slice)?;
+    purchase.try_serialize(&mut &mut purchase_receipt_account.try_borrow_mut_data()?[..])?;
 
     listing_receipt.purchase_receipt = Some(purchase_receipt_account.key());
 
-    listing_receipt.try_serialize(&mut *listing_receipt_data)?;
-
-    let mut bid_receipt_data = bid_receipt_account.try_borrow_mut_data()?;
-    let mut bid_receipt_slice: &[u8] = &bid_receipt_data;
-
-    let mut bid_receipt = BidReceipt::try_deserialize(&mut bid_receipt_slice)?;
+    listing_receipt.try_serialize(&mut &mut listing_receipt_data[..])?;
 
     bid_receipt.purchase_receipt = Some(purchase_receipt_account.key());
 
-    bid_receipt.try_serialize(&mut *bid_receipt_data)?;
+    bid_receipt.try_serialize(&mut &mut bid_receipt_data[..])?;
 
     Ok(())
 }
@@ -1,10 +1,14 @@
-use anchor_lang::{prelude::*, solana_program::program::invoke, AnchorDeserialize};
-use spl_token::instruction::approve;
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program::invoke, program_option::COption},
+    AnchorDeserialize,
+};
+use spl_token_2022::instruction::approve;
 
-use crate::{constants::*, errors::*, utils::*, AuctionHouse, *};
+use crate::{constants::*, errors::*, events::*, utils::*, AuctionHouse, *};
 
 /// Accounts for the [`sell` handler](auction_house/fn.sell.html).
-#[derive(Accounts)]
+#[derive(Accounts, Clone)]
 #[instruction(
     trade_state_bump: u8,
     free_trade_state_bump: u8,
@@ -17,9 +21,10 @@ pub struct Sell<'info> {
     /// User wallet account.
     pub wallet: UncheckedAccount<'info>,
 
+    /// CHECK: Unpacked in the trade state seeds, owned by either token program.
     /// SPL token account containing token for sale.
     #[account(mut)]
-    pub token_account: Box<Account<'info, TokenAccount>>,
+    pub token_account: UncheckedAccount<'info>,
 
     /// CHECK: Verified through CPI
     /// Metaplex metadata account decorating SPL mint account.
@@ -65,7 +70,7 @@ pub struct Sell<'info> {
             auction_house.key().as_ref(),
             token_account.key().as_ref(),
             auction_house.treasury_mint.as_ref(),
-            token_account.mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
             &buyer_price.to_le_bytes(),
             &token_size.to_le_bytes()
         ],
@@ -83,7 +88,7 @@ pub struct Sell<'info> {
             auction_house.key().as_ref(),
             token_account.key().as_ref(),
             auction_house.treasury_mint.as_ref(),
-            token_account.mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
             &0u64.to_le_bytes(),
             &token_size.to_le_bytes()
         ],
@@ -91,7 +96,127 @@ pub struct Sell<'info> {
     )]
     pub free_seller_trade_state: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for the [`auctioneer_sell` handler](auction_house/fn.auctioneer_sell.html).
+#[derive(Accounts, Clone)]
+#[instruction(
+    trade_state_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    token_size: u64
+)]
+pub struct AuctioneerSell<'info> {
+    /// CHECK: Verified through CPI
+    /// User wallet account.
+    pub wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Unpacked in the trade state seeds, owned by either token program.
+    /// SPL token account containing token for sale.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Metaplex metadata account decorating SPL mint account.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Auction House authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Account<'info, AuctionHouse>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account encoding the sell order. The Auctioneer sets the sale price, so the listing is keyed at `u64::MAX`.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            &u64::MAX.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Free seller trade state PDA account encoding a free sell order.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            get_mint_from_token_account(&token_account)?.as_ref(),
+            &0u64.to_le_bytes(),
+            &token_size.to_le_bytes()
+        ],
+        bump
+    )]
+    pub free_seller_trade_state: UncheckedAccount<'info>,
+
+    /// The Auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    pub auctioneer_authority: Signer<'info>,
+
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        bump=ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 
     /// CHECK: Not dangerous. Account seeds checked in constraint.
@@ -101,6 +226,26 @@ pub struct Sell<'info> {
     pub rent: Sysvar<'info, Rent>,
 }
 
+impl<'info> From<AuctioneerSell<'info>> for Sell<'info> {
+    fn from(a: AuctioneerSell<'info>) -> Sell<'info> {
+        Sell {
+            wallet: a.wallet,
+            token_account: a.token_account,
+            metadata: a.metadata,
+            authority: a.authority,
+            auction_house: a.auction_house,
+            auction_house_fee_account: a.auction_house_fee_account,
+            seller_trade_state: a.seller_trade_state,
+            free_seller_trade_state: a.free_seller_trade_state,
+            token_program: a.token_program,
+            system_program: a.system_program,
+            program_as_signer: a.program_as_signer,
+            rent: a.rent,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn sell<'info>(
     ctx: Context<'_, '_, '_, 'info, Sell<'info>>,
     trade_state_bump: u8,
@@ -108,7 +253,15 @@ pub fn sell<'info>(
     program_as_signer_bump: u8,
     buyer_price: u64,
     token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
 ) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+
+    // If the Sell scope is delegated, listings must go through auctioneer_sell.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Sell as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
 
     let trade_state_canonical_bump = *ctx
         .bumps
@@ -132,24 +285,96 @@ pub fn sell<'info>(
 
     sell_logic(
         ctx.accounts,
+        ctx.remaining_accounts,
         ctx.program_id,
         trade_state_bump,
         free_trade_state_bump,
         program_as_signer_bump,
         buyer_price,
         token_size,
+        expiry,
+        referral,
+        None,
+    )
+}
+
+/// List an NFT for sale through the Auctioneer delegated the Sell scope, which sets the sale price.
+pub fn auctioneer_sell<'info>(
+    ctx: Context<'_, '_, '_, 'info, AuctioneerSell<'info>>,
+    trade_state_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auctioneer_authority = &ctx.accounts.auctioneer_authority;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::NoAuctioneerProgramSet.into());
+    }
+
+    assert_valid_auctioneer_and_scope(
+        auction_house,
+        &auctioneer_authority.key(),
+        ah_auctioneer_pda,
+        AuthorityScope::Sell,
+    )?;
+
+    let trade_state_canonical_bump = *ctx
+        .bumps
+        .get("seller_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let free_trade_state_canonical_bump = *ctx
+        .bumps
+        .get("free_seller_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if (trade_state_canonical_bump != trade_state_bump)
+        || (free_trade_state_canonical_bump != free_trade_state_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    let mut accounts: Sell<'info> = (*ctx.accounts).clone().into();
+
+    sell_logic(
+        &mut accounts,
+        ctx.remaining_accounts,
+        ctx.program_id,
+        trade_state_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        u64::MAX,
+        token_size,
+        None,
+        None,
+        None,
     )
 }
 
 /// Create a sell bid by creating a `seller_trade_state` account and approving the program as the token delegate.
-fn sell_logic<'info>(
+/// Programmable NFTs are delegated through Token Metadata, and take the [`ProgrammableAccounts`] followed by
+/// their mint as remaining accounts.
+/// Listings priced at settlement pass `listing_seed`, which keys the trade state in place of `buyer_price`.
+#[allow(clippy::too_many_arguments)]
+pub fn sell_logic<'info>(
     accounts: &mut Sell<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
     program_id: &Pubkey,
     trade_state_bump: u8,
     _free_trade_state_bump: u8,
     _program_as_signer_bump: u8,
     buyer_price: u64,
     token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
+    listing_seed: Option<&str>,
 ) -> Result<()> {
     let wallet = &accounts.wallet;
     let token_account = &accounts.token_account;
@@ -197,19 +422,39 @@ fn sell_logic<'info>(
         auction_house_fee_account.to_account_info(),
         &seeds,
     )?;
+    let token_account_data = unpack_token_account(token_account)?;
     assert_is_ata(
         &token_account.to_account_info(),
         &wallet.key(),
-        &token_account.mint,
+        &token_account_data.mint,
     )?;
 
-    assert_metadata_valid(metadata, token_account)?;
+    assert_metadata_valid(metadata, &token_account_data.mint)?;
 
-    if token_size > token_account.amount {
+    if token_size > token_account_data.amount {
         return Err(AuctionHouseError::InvalidTokenAmount.into());
     }
 
-    if wallet.is_signer {
+    if wallet.is_signer && is_programmable(metadata)? {
+        // Token Metadata refuses to replace a Sale delegate, so relisting leaves it in place.
+        if token_account_data.delegate != COption::Some(program_as_signer.key()) {
+            let remaining_accounts = &mut remaining_accounts.iter();
+            let programmable = ProgrammableAccounts::next(remaining_accounts)?;
+            let mint = next_account_info(remaining_accounts)?;
+            assert_keys_equal(mint.key(), token_account_data.mint)?;
+            delegate_programmable_sale(
+                &programmable,
+                program_as_signer,
+                metadata,
+                mint,
+                token_account,
+                wallet,
+                system_program,
+                token_program,
+                token_size,
+            )?;
+        }
+    } else if wallet.is_signer {
         invoke(
             &approve(
                 &token_program.key(),
@@ -233,14 +478,16 @@ fn sell_logic<'info>(
     if ts_info.data_is_empty() {
         let token_account_key = token_account.key();
         let wallet_key = wallet.key();
+        let buyer_price_bytes = buyer_price.to_le_bytes();
+        let price_seed = listing_seed.map_or(&buyer_price_bytes[..], str::as_bytes);
         let ts_seeds = [
             PREFIX.as_bytes(),
             wallet_key.as_ref(),
             auction_house_key.as_ref(),
             token_account_key.as_ref(),
             auction_house.treasury_mint.as_ref(),
-            token_account.mint.as_ref(),
-            &buyer_price.to_le_bytes(),
+            token_account_data.mint.as_ref(),
+            price_seed,
             &token_size.to_le_bytes(),
             &[trade_state_bump],
         ];
@@ -254,10 +501,29 @@ fn sell_logic<'info>(
             fee_seeds,
             &ts_seeds,
         )?;
+        init_trade_state(&ts_info, trade_state_bump, fee_payer.key)?;
     }
+    // Re-sending the same listing updates its expiry and referral.
+    set_trade_state_expiry(&ts_info, expiry)?;
+    set_trade_state_referral(
+        &ts_info,
+        &wallet.key(),
+        referral,
+        auction_house.max_referral_basis_points,
+    )?;
 
-    let data = &mut ts_info.data.borrow_mut();
-    data[0] = trade_state_bump;
+    emit!(ListingCreated {
+        auction_house: auction_house_key,
+        trade_state: seller_trade_state.key(),
+        seller: wallet.key(),
+        token_account: token_account.key(),
+        token_mint: token_account_data.mint,
+        price: buyer_price,
+        token_size,
+        expiry,
+        referral,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
 
     Ok(())
 }
@@ -18,17 +18,231 @@ pub struct AuctionHouse {
     pub requires_sign_off: bool,
     pub can_change_sale_price: bool,
     pub escrow_payment_bump: u8,
-    // pub has_auctioneer: bool,
-    // pub auctioneer_address: Pubkey,
+    pub has_auctioneer: bool,
+    pub auctioneer_address: Pubkey,
     pub scopes: [bool; MAX_NUM_SCOPES],
+    /// Largest share of the house fee, in basis points, a listing or bid may promise its referrer. 0 disables referrals.
+    pub max_referral_basis_points: u16,
+    pub royalty_policy: RoyaltyPolicy,
+    pub unverified_creator_policy: UnverifiedCreatorPolicy,
 }
 
-// #[account]
-// pub struct Auctioneer {
-//     pub auctioneer_authority: Pubkey,
-//     pub auction_house: Pubkey,
-//     pub bump: u8,
-// }
+#[account]
+pub struct Auctioneer {
+    pub auctioneer_authority: Pubkey,
+    pub auction_house: Pubkey,
+    pub bump: u8,
+}
+
+/// English auction over a listing, keyed by the seller trade state.
+/// The highest bid is locked in the escrow payment account derived from this PDA.
+#[account]
+pub struct AuctionState {
+    pub auction_house: Pubkey,
+    pub seller: Pubkey,
+    pub seller_trade_state: Pubkey,
+    pub token_account: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_size: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub reserve_price: u64,
+    pub min_increment: u64,
+    pub extension_window: i64,
+    pub highest_bidder: Pubkey,
+    pub highest_bid: u64,
+    pub bump: u8,
+    pub escrow_payment_bump: u8,
+}
+
+/// How a dutch listing's price falls from its start price to its end price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecayCurve {
+    /// The price falls continuously over the listing period.
+    Linear,
+    /// The price falls in equal drops every `step_duration` seconds.
+    Stepwise { step_duration: i64 },
+}
+
+/// Dutch (descending price) listing over a listing, keyed by the seller trade state.
+/// `sale_price` is set once the listing sells, for the purchase receipt.
+#[account]
+pub struct ListingConfig {
+    pub auction_house: Pubkey,
+    pub seller: Pubkey,
+    pub seller_trade_state: Pubkey,
+    pub token_account: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_size: u64,
+    pub start_price: u64,
+    pub end_price: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub curve: DecayCurve,
+    pub sale_price: Option<u64>,
+    pub bump: u8,
+}
+
+/// Bid on any token in a verified Metaplex collection, keyed by the bidder, collection mint and price per token.
+/// Each sale fills `token_size` of the remaining `quantity` from the bidder's escrow payment account.
+#[account]
+pub struct CollectionBid {
+    pub auction_house: Pubkey,
+    pub wallet: Pubkey,
+    pub collection: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+    pub bump: u8,
+}
+
+/// What a swap offer accepts in exchange for the maker's token.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SwapTarget {
+    /// Any token account holding this mint.
+    Mint(Pubkey),
+    /// Any mint in the verified Metaplex collection of this collection mint.
+    Collection(Pubkey),
+    /// The tokens held in this token account.
+    TokenAccount(Pubkey),
+}
+
+/// Offer to swap `token_size` of the maker's token, delegated to the program as signer, for `wanted_size` of a
+/// token matching `wanted`. `maker_extra` is paid from the maker's escrow on top of the maker's token and
+/// `taker_extra` from the taker's escrow on top of theirs. Keyed by the maker and the offered token account.
+#[account]
+pub struct SwapOffer {
+    pub auction_house: Pubkey,
+    pub maker: Pubkey,
+    pub token_account: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_size: u64,
+    pub wanted: SwapTarget,
+    pub wanted_size: u64,
+    pub maker_extra: u64,
+    pub taker_extra: u64,
+    pub bump: u8,
+}
+
+/// House fee override for tokens in a verified collection, created by the Auction House authority.
+/// Keyed by the Auction House and the collection mint.
+#[account]
+pub struct FeeSchedule {
+    pub auction_house: Pubkey,
+    pub collection: Pubkey,
+    pub seller_fee_basis_points: u16,
+    pub bump: u8,
+}
+
+/// Running totals of a wallet's trades on an Auction House, in treasury mint units. Every sale updates the stats of
+/// both parties, creating them on a wallet's first trade. Keyed by the Auction House and the wallet.
+#[account]
+pub struct TraderStats {
+    pub auction_house: Pubkey,
+    pub wallet: Pubkey,
+    pub buy_volume: u64,
+    pub sell_volume: u64,
+    pub trade_count: u64,
+    pub first_trade_at: i64,
+    pub last_trade_at: i64,
+    /// Royalties paid by the wallet's bids and house fees taken out of its sales.
+    pub fees_paid: u64,
+    pub bump: u8,
+}
+
+/// Market stats of a verified collection on an Auction House, in treasury mint units, for other programs to read.
+/// Every sale of a token in the collection updates them, creating the account on the collection's first sale. Keyed
+/// by the Auction House and the collection mint.
+#[account]
+pub struct CollectionStats {
+    pub auction_house: Pubkey,
+    pub collection: Pubkey,
+    pub last_sale_price: u64,
+    pub last_sale_at: i64,
+    /// All-time volume.
+    pub volume: u64,
+    pub sale_count: u64,
+    /// Volume of each of the last `COLLECTION_STATS_DAYS` days, at index day % `COLLECTION_STATS_DAYS` where day is
+    /// unix time / `SECONDS_PER_DAY`. Days before `current_day` - `COLLECTION_STATS_DAYS` are stale.
+    pub daily_volume: [u64; COLLECTION_STATS_DAYS],
+    /// Day of the most recent sale, whose bucket is the latest in `daily_volume`.
+    pub current_day: i64,
+    /// Prices of the last `COLLECTION_STATS_SALES` sales, at index sale number % `COLLECTION_STATS_SALES`.
+    pub recent_prices: [u64; COLLECTION_STATS_SALES],
+    /// Times of the sales in `recent_prices`, at the same indices.
+    pub recent_sale_times: [i64; COLLECTION_STATS_SALES],
+    /// Average of `recent_prices`, each weighted by how long it stood as the last sale price. The plain average while
+    /// no time has passed between them.
+    pub twap: u64,
+    /// Lowest of `recent_prices`. A floor over recent sales, as open listings are not tracked on chain.
+    pub floor: u64,
+    pub bump: u8,
+}
+
+/// Kept by `execute_sale` alongside each escrow NFT account: who paid the escrow rent, and what the buyer paid for the
+/// tokens held in it. Keyed by the escrow NFT account and closed with it.
+#[account]
+pub struct EscrowNftRecord {
+    pub rent_payer: Pubkey,
+    /// Total price of the escrowed tokens, which a refund returns to the buyer.
+    pub price: u64,
+    pub bump: u8,
+}
+
+/// A token in a bundle listing. Its share of the bundle price, which its royalties are paid on, is its weight over
+/// the total weight of the bundle.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BundleItem {
+    pub token_mint: Pubkey,
+    pub token_account: Pubkey,
+    pub weight: u64,
+}
+
+/// Listing of up to `MAX_BUNDLE_ITEMS` tokens, one of each, sold together at `price`. Each token is delegated to the
+/// program as signer. Keyed by the seller and the token account of the first item.
+#[account]
+pub struct BundleListing {
+    pub auction_house: Pubkey,
+    pub seller: Pubkey,
+    pub price: u64,
+    pub items: Vec<BundleItem>,
+    pub bump: u8,
+}
+
+/// How much of the metadata's `seller_fee_basis_points` settlement pays creators. Houses created before royalty
+/// policies existed read as `Full`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoyaltyPolicy {
+    /// Creators are always paid the full royalty.
+    Full,
+    /// Bids may choose the share of the royalty, in basis points, they pay creators, down to `min_basis_points`.
+    /// Bids that do not choose pay the full royalty.
+    Optional { min_basis_points: u16 },
+    /// Royalties are waived on tokens in these verified collections, and paid in full on any other. Unused entries
+    /// are the default pubkey.
+    WaivedForCollections {
+        collections: [Pubkey; MAX_ROYALTY_WAIVED_COLLECTIONS],
+    },
+}
+
+/// What settlement does with the royalty share of creators the metadata lists as unverified. Houses created before
+/// this setting existed read as `PayAll`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnverifiedCreatorPolicy {
+    /// Every listed creator is paid its share, verified or not.
+    PayAll,
+    /// Unverified shares are split among the verified creators in proportion to their shares. With no verified
+    /// creator the royalty goes to the seller.
+    Redistribute,
+    /// Unverified shares go to the seller.
+    RefundSeller,
+}
+
+/// Frontend that originated a listing or bid. It is paid `basis_points` of the house fee when the order settles.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Referral {
+    pub referrer: Pubkey,
+    pub basis_points: u16,
+}
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
 #[repr(u32)]
@@ -0,0 +1,872 @@
+//! Token-for-token swap offers.
+//! `create_swap_offer` delegates the maker's token to the program as signer, like a listing, and records a
+//! `SwapOffer` PDA naming what the maker wants for it: a mint, any mint in a verified collection or a specific token
+//! account. Either side can add an extra amount of the treasury mint, paid from their escrow payment account.
+//! `execute_swap` exchanges both tokens and pays each extra amount to the other side, less the royalties of the
+//! token it pays for. `cancel_swap_offer` revokes the delegate and closes the offer.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program::invoke, program_option::COption, system_instruction},
+    AnchorDeserialize,
+};
+use spl_token_2022::instruction::{approve, revoke};
+use std::slice::Iter;
+
+use crate::{
+    bid::*, constants::*, errors::*, events::*, utils::*, AuctionHouse, SwapOffer, SwapTarget, *,
+};
+
+/// Accounts for the [`create_swap_offer` handler](auction_house/fn.create_swap_offer.html).
+#[derive(Accounts)]
+#[instruction(escrow_payment_bump: u8)]
+pub struct CreateSwapOffer<'info> {
+    /// Maker wallet account, paying for the swap offer PDA.
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// CHECK: Validated in create_swap_offer.
+    /// SPL token account containing the offered token.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in create_swap_offer.
+    /// Metaplex metadata account decorating the offered mint.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in fund_escrow_payment_account.
+    /// Maker SOL or SPL account funding the maker's extra amount.
+    #[account(mut)]
+    pub payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in fund_escrow_payment_account.
+    /// SPL token account transfer authority.
+    pub transfer_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House instance treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Maker escrow payment account PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            wallet.key().as_ref()
+        ],
+        bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in get_fee_payer.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// Swap offer PDA account.
+    #[account(
+        init,
+        payer=wallet,
+        space=SWAP_OFFER_SIZE,
+        seeds = [
+            PREFIX.as_bytes(),
+            SWAP_OFFER.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref()
+        ],
+        bump
+    )]
+    pub swap_offer: Box<Account<'info, SwapOffer>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for the [`execute_swap` handler](auction_house/fn.execute_swap.html).
+#[derive(Accounts)]
+#[instruction(maker_escrow_payment_bump: u8, taker_escrow_payment_bump: u8, program_as_signer_bump: u8)]
+pub struct ExecuteSwap<'info> {
+    /// CHECK: Checked against the swap offer.
+    /// Maker wallet account, receiving the swap offer rent.
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Taker wallet account, accepting the swap offer.
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Checked against the swap offer.
+    /// Token account holding the maker's offered token.
+    #[account(mut)]
+    pub maker_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the swap offer.
+    /// Mint of the maker's offered token.
+    pub maker_token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_swap.
+    /// Metaplex metadata account decorating the maker's mint.
+    pub maker_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_swap.
+    /// Token account holding the taker's token.
+    #[account(mut)]
+    pub taker_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_swap.
+    /// Mint of the taker's token.
+    pub taker_token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_swap.
+    /// Metaplex metadata account decorating the taker's mint.
+    pub taker_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_swap.
+    /// Maker associated token account to receive the taker's token at.
+    #[account(mut)]
+    pub maker_receipt_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_swap.
+    /// Taker associated token account to receive the maker's token at.
+    #[account(mut)]
+    pub taker_receipt_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Maker escrow payment account PDA, paying the maker's extra amount.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            maker.key().as_ref()
+        ],
+        bump
+    )]
+    pub maker_escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Taker escrow payment account PDA, paying the taker's extra amount.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            taker.key().as_ref()
+        ],
+        bump
+    )]
+    pub taker_escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in pay_swap_leg.
+    /// Maker SOL or SPL account to receive the taker's extra amount at.
+    #[account(mut)]
+    pub maker_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in pay_swap_leg.
+    /// Taker SOL or SPL account to receive the maker's extra amount at.
+    #[account(mut)]
+    pub taker_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the Auction House treasury mint.
+    /// Auction House instance treasury mint account.
+    pub treasury_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in get_fee_payer.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// Swap offer PDA account being accepted.
+    #[account(
+        mut,
+        close=maker,
+        seeds = [
+            PREFIX.as_bytes(),
+            SWAP_OFFER.as_bytes(),
+            maker.key().as_ref(),
+            auction_house.key().as_ref(),
+            maker_token_account.key().as_ref()
+        ],
+        bump=swap_offer.bump,
+        has_one=auction_house,
+        has_one=maker
+    )]
+    pub swap_offer: Box<Account<'info, SwapOffer>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], bump)]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for the [`cancel_swap_offer` handler](auction_house/fn.cancel_swap_offer.html).
+#[derive(Accounts)]
+pub struct CancelSwapOffer<'info> {
+    /// CHECK: Checked against the swap offer.
+    /// Maker wallet account, receiving the swap offer rent.
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the swap offer.
+    /// Token account holding the maker's offered token.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated as a signer in cancel_swap_offer.
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        bump=auction_house.bump,
+        has_one=authority
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// Swap offer PDA account.
+    #[account(
+        mut,
+        close=maker,
+        seeds = [
+            PREFIX.as_bytes(),
+            SWAP_OFFER.as_bytes(),
+            maker.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref()
+        ],
+        bump=swap_offer.bump,
+        has_one=auction_house,
+        has_one=maker
+    )]
+    pub swap_offer: Box<Account<'info, SwapOffer>>,
+
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
+}
+
+/// Offer `token_size` of the maker's token for `wanted_size` of a token matching `wanted`, delegating the offered
+/// token to the program as signer and funding the escrow payment account with at least `maker_extra`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_swap_offer<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateSwapOffer<'info>>,
+    escrow_payment_bump: u8,
+    token_size: u64,
+    wanted: SwapTarget,
+    wanted_size: u64,
+    maker_extra: u64,
+    taker_extra: u64,
+) -> Result<()> {
+    let wallet = &ctx.accounts.wallet;
+    let token_account = &ctx.accounts.token_account;
+    let treasury_mint = &ctx.accounts.treasury_mint;
+    let escrow_payment_account = &ctx.accounts.escrow_payment_account;
+    let authority = &ctx.accounts.authority;
+    let auction_house = &ctx.accounts.auction_house;
+    let auction_house_fee_account = &ctx.accounts.auction_house_fee_account;
+    let token_program = &ctx.accounts.token_program;
+    let system_program = &ctx.accounts.system_program;
+    let program_as_signer = &ctx.accounts.program_as_signer;
+    let rent = &ctx.accounts.rent;
+
+    // Swap offers put the maker's token up like a listing, so they follow a delegated Sell scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Sell as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let escrow_canonical_bump = *ctx
+        .bumps
+        .get("escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    if escrow_canonical_bump != escrow_payment_bump {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    if token_size == 0 || wanted_size == 0 {
+        return Err(AuctionHouseError::InvalidTokenAmount.into());
+    }
+
+    let auction_house_key = auction_house.key();
+    let seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        FEE_PAYER.as_bytes(),
+        &[auction_house.fee_payer_bump],
+    ];
+    let (fee_payer, fee_seeds) = get_fee_payer(
+        authority,
+        auction_house,
+        wallet.to_account_info(),
+        auction_house_fee_account.to_account_info(),
+        &seeds,
+    )?;
+
+    let token_account_data = unpack_token_account(token_account)?;
+    assert_is_ata(token_account, &wallet.key(), &token_account_data.mint)?;
+    assert_metadata_valid(&ctx.accounts.metadata, &token_account_data.mint)?;
+    if token_size > token_account_data.amount {
+        return Err(AuctionHouseError::InvalidTokenAmount.into());
+    }
+
+    invoke(
+        &approve(
+            &token_program.key(),
+            &token_account.key(),
+            &program_as_signer.key(),
+            &wallet.key(),
+            &[],
+            token_size,
+        )?,
+        &[
+            token_program.to_account_info(),
+            token_account.to_account_info(),
+            program_as_signer.to_account_info(),
+            wallet.to_account_info(),
+        ],
+    )?;
+
+    if maker_extra > 0 {
+        let is_native = treasury_mint.key() == spl_token::native_mint::id();
+        let wallet_key = wallet.key();
+        let escrow_signer_seeds = [
+            PREFIX.as_bytes(),
+            auction_house_key.as_ref(),
+            wallet_key.as_ref(),
+            &[escrow_payment_bump],
+        ];
+        create_program_token_account_if_not_present(
+            escrow_payment_account,
+            system_program,
+            &fee_payer,
+            token_program,
+            treasury_mint,
+            &auction_house.to_account_info(),
+            rent,
+            &escrow_signer_seeds,
+            fee_seeds,
+            is_native,
+        )?;
+        fund_escrow_payment_account(
+            wallet,
+            &ctx.accounts.payment_account,
+            &ctx.accounts.transfer_authority,
+            escrow_payment_account,
+            treasury_mint,
+            token_program,
+            system_program,
+            rent,
+            is_native,
+            maker_extra,
+        )?;
+    }
+
+    let swap_offer = &mut ctx.accounts.swap_offer;
+    swap_offer.auction_house = auction_house_key;
+    swap_offer.maker = wallet.key();
+    swap_offer.token_account = token_account.key();
+    swap_offer.token_mint = token_account_data.mint;
+    swap_offer.token_size = token_size;
+    swap_offer.wanted = wanted;
+    swap_offer.wanted_size = wanted_size;
+    swap_offer.maker_extra = maker_extra;
+    swap_offer.taker_extra = taker_extra;
+    swap_offer.bump = *ctx
+        .bumps
+        .get("swap_offer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    emit!(SwapOfferCreated {
+        auction_house: auction_house_key,
+        swap_offer: swap_offer.key(),
+        maker: wallet.key(),
+        token_mint: token_account_data.mint,
+        token_size,
+        wanted,
+        wanted_size,
+        maker_extra,
+        taker_extra,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Accept a swap offer with the taker's token, exchanging both tokens and paying each side's extra amount to the
+/// other, less the royalties of the token it pays for. The remaining accounts are the creators of the taker's
+/// token when the maker pays an extra amount, followed by the creators of the maker's token when the taker does.
+pub fn execute_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteSwap<'info>>,
+    maker_escrow_payment_bump: u8,
+    taker_escrow_payment_bump: u8,
+    program_as_signer_bump: u8,
+) -> Result<()> {
+    let maker = &ctx.accounts.maker;
+    let taker = &ctx.accounts.taker;
+    let maker_token_account = &ctx.accounts.maker_token_account;
+    let maker_token_mint = &ctx.accounts.maker_token_mint;
+    let maker_metadata = &ctx.accounts.maker_metadata;
+    let taker_token_account = &ctx.accounts.taker_token_account;
+    let taker_token_mint = &ctx.accounts.taker_token_mint;
+    let taker_metadata = &ctx.accounts.taker_metadata;
+    let maker_receipt_token_account = &ctx.accounts.maker_receipt_token_account;
+    let taker_receipt_token_account = &ctx.accounts.taker_receipt_token_account;
+    let treasury_mint = &ctx.accounts.treasury_mint;
+    let authority = &ctx.accounts.authority;
+    let auction_house = &ctx.accounts.auction_house;
+    let auction_house_fee_account = &ctx.accounts.auction_house_fee_account;
+    let swap_offer = &ctx.accounts.swap_offer;
+    let token_program = &ctx.accounts.token_program;
+    let system_program = &ctx.accounts.system_program;
+    let ata_program = &ctx.accounts.ata_program;
+    let program_as_signer = &ctx.accounts.program_as_signer;
+    let rent = &ctx.accounts.rent;
+
+    // Swaps settle like any other sale, so they follow a delegated ExecuteSale scope.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::ExecuteSale as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    let maker_escrow_canonical_bump = *ctx
+        .bumps
+        .get("maker_escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let taker_escrow_canonical_bump = *ctx
+        .bumps
+        .get("taker_escrow_payment_account")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let program_as_signer_canonical_bump = *ctx
+        .bumps
+        .get("program_as_signer")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+
+    if (maker_escrow_canonical_bump != maker_escrow_payment_bump)
+        || (taker_escrow_canonical_bump != taker_escrow_payment_bump)
+        || (program_as_signer_canonical_bump != program_as_signer_bump)
+    {
+        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
+    }
+
+    assert_keys_equal(maker_token_mint.key(), swap_offer.token_mint)?;
+    let maker_token_data =
+        assert_is_ata(maker_token_account, &maker.key(), &swap_offer.token_mint)?;
+    if maker_token_data.delegate != COption::Some(program_as_signer.key())
+        || maker_token_data.delegated_amount < swap_offer.token_size
+    {
+        return Err(AuctionHouseError::BothPartiesNeedToAgreeToSale.into());
+    }
+    if maker_token_data.amount < swap_offer.token_size {
+        return Err(AuctionHouseError::NotEnoughTokensAvailableForPurchase.into());
+    }
+
+    let taker_token_data =
+        assert_is_ata(taker_token_account, &taker.key(), &taker_token_mint.key())?;
+    if taker_token_data.amount < swap_offer.wanted_size {
+        return Err(AuctionHouseError::NotEnoughTokensAvailableForPurchase.into());
+    }
+
+    assert_supported_mint(maker_token_mint)?;
+    assert_supported_mint(taker_token_mint)?;
+    assert_metadata_valid(maker_metadata, &maker_token_mint.key())?;
+    assert_metadata_valid(taker_metadata, &taker_token_mint.key())?;
+
+    match swap_offer.wanted {
+        SwapTarget::Mint(mint) if mint != taker_token_mint.key() => {
+            return Err(AuctionHouseError::SwapTargetMismatch.into());
+        }
+        SwapTarget::TokenAccount(account) if account != taker_token_account.key() => {
+            return Err(AuctionHouseError::SwapTargetMismatch.into());
+        }
+        SwapTarget::Collection(collection) => {
+            assert_verified_collection_member(
+                taker_metadata,
+                &taker_token_mint.key(),
+                &collection,
+            )?;
+        }
+        _ => {}
+    }
+
+    let auction_house_key = auction_house.key();
+    let seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        FEE_PAYER.as_bytes(),
+        &[auction_house.fee_payer_bump],
+    ];
+    let (fee_payer, fee_payer_seeds) = get_fee_payer(
+        authority,
+        auction_house,
+        taker.to_account_info(),
+        auction_house_fee_account.to_account_info(),
+        &seeds,
+    )?;
+
+    for (receipt_token_account, wallet, mint) in [
+        (
+            maker_receipt_token_account,
+            maker.to_account_info(),
+            taker_token_mint,
+        ),
+        (
+            taker_receipt_token_account,
+            taker.to_account_info(),
+            maker_token_mint,
+        ),
+    ] {
+        if receipt_token_account.data_is_empty() {
+            make_ata(
+                receipt_token_account.to_account_info(),
+                wallet.clone(),
+                mint.to_account_info(),
+                fee_payer.clone(),
+                ata_program.to_account_info(),
+                token_program.to_account_info(),
+                system_program.to_account_info(),
+                rent.to_account_info(),
+                fee_payer_seeds,
+            )?;
+        }
+        let receipt_data = assert_is_ata(receipt_token_account, wallet.key, &mint.key())?;
+        // make sure you cant get rugged
+        if receipt_data.delegate.is_some() {
+            return Err(AuctionHouseError::BuyerATACannotHaveDelegate.into());
+        }
+    }
+
+    let program_as_signer_seeds = [
+        PREFIX.as_bytes(),
+        SIGNER.as_bytes(),
+        &[program_as_signer_bump],
+    ];
+    transfer_tokens(
+        token_program,
+        maker_token_account,
+        maker_token_mint,
+        taker_receipt_token_account,
+        program_as_signer,
+        swap_offer.token_size,
+        &[&program_as_signer_seeds],
+    )?;
+    transfer_tokens(
+        token_program,
+        taker_token_account,
+        taker_token_mint,
+        maker_receipt_token_account,
+        &taker.to_account_info(),
+        swap_offer.wanted_size,
+        &[],
+    )?;
+
+    let is_native = treasury_mint.key() == spl_token::native_mint::id();
+    let remaining_accounts = &mut ctx.remaining_accounts.iter();
+    let maker_extra_royalties = pay_swap_leg(
+        remaining_accounts,
+        taker_metadata,
+        &ctx.accounts.maker_escrow_payment_account,
+        maker,
+        maker_escrow_payment_bump,
+        &taker.to_account_info(),
+        &ctx.accounts.taker_payment_receipt_account,
+        auction_house,
+        treasury_mint,
+        &fee_payer,
+        fee_payer_seeds,
+        ata_program,
+        token_program,
+        system_program,
+        rent,
+        swap_offer.maker_extra,
+        is_native,
+    )?;
+    let taker_extra_royalties = pay_swap_leg(
+        remaining_accounts,
+        maker_metadata,
+        &ctx.accounts.taker_escrow_payment_account,
+        &taker.to_account_info(),
+        taker_escrow_payment_bump,
+        maker,
+        &ctx.accounts.maker_payment_receipt_account,
+        auction_house,
+        treasury_mint,
+        &fee_payer,
+        fee_payer_seeds,
+        ata_program,
+        token_program,
+        system_program,
+        rent,
+        swap_offer.taker_extra,
+        is_native,
+    )?;
+
+    emit!(SwapExecuted {
+        auction_house: auction_house_key,
+        swap_offer: swap_offer.key(),
+        maker: maker.key(),
+        taker: taker.key(),
+        maker_mint: maker_token_mint.key(),
+        taker_mint: taker_token_mint.key(),
+        token_size: swap_offer.token_size,
+        wanted_size: swap_offer.wanted_size,
+        maker_extra: swap_offer.maker_extra,
+        maker_extra_royalties,
+        taker_extra: swap_offer.taker_extra,
+        taker_extra_royalties,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Close a swap offer, returning its rent to the maker. If the maker signs, the delegate over the offered token is
+/// revoked as well. Any extra amount stays in the maker's escrow, available to withdraw.
+pub fn cancel_swap_offer<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelSwapOffer<'info>>,
+) -> Result<()> {
+    let maker = &ctx.accounts.maker;
+    let token_account = &ctx.accounts.token_account;
+    let auction_house = &ctx.accounts.auction_house;
+    let token_program = &ctx.accounts.token_program;
+
+    // If the Cancel scope is delegated, swap offers can only be canceled through the Auctioneer.
+    if auction_house.has_auctioneer && auction_house.scopes[AuthorityScope::Cancel as usize] {
+        return Err(AuctionHouseError::MustUseAuctioneerHandler.into());
+    }
+
+    if !maker.is_signer && !ctx.accounts.authority.is_signer {
+        return Err(AuctionHouseError::NoValidSignerPresent.into());
+    }
+
+    let token_account_data = unpack_token_account(token_account)?;
+    if token_account_data.owner == maker.key() && maker.is_signer {
+        invoke(
+            &revoke(
+                &token_program.key(),
+                &token_account.key(),
+                &maker.key(),
+                &[],
+            )?,
+            &[
+                token_program.to_account_info(),
+                token_account.to_account_info(),
+                maker.to_account_info(),
+            ],
+        )?;
+    }
+
+    emit!(SwapOfferCanceled {
+        auction_house: auction_house.key(),
+        swap_offer: ctx.accounts.swap_offer.key(),
+        maker: maker.key(),
+        token_mint: ctx.accounts.swap_offer.token_mint,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Pay `amount` from `payer`'s escrow payment account to `recipient`, less the royalties of the token described by
+/// `metadata`, which `recipient` gave up for it. Returns the royalties paid.
+#[allow(clippy::too_many_arguments)]
+fn pay_swap_leg<'info>(
+    remaining_accounts: &mut Iter<AccountInfo<'info>>,
+    metadata: &UncheckedAccount<'info>,
+    escrow_payment_account: &UncheckedAccount<'info>,
+    payer: &AccountInfo<'info>,
+    escrow_payment_bump: u8,
+    recipient: &AccountInfo<'info>,
+    payment_receipt_account: &UncheckedAccount<'info>,
+    auction_house: &Account<'info, AuctionHouse>,
+    treasury_mint: &UncheckedAccount<'info>,
+    fee_payer: &AccountInfo<'info>,
+    fee_payer_seeds: &[&[u8]],
+    ata_program: &Program<'info, AssociatedToken>,
+    token_program: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    rent: &Sysvar<'info, Rent>,
+    amount: u64,
+    is_native: bool,
+) -> Result<u64> {
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    let auction_house_key = auction_house.key();
+    let payer_key = payer.key();
+    let escrow_signer_seeds = [
+        PREFIX.as_bytes(),
+        auction_house_key.as_ref(),
+        payer_key.as_ref(),
+        &[escrow_payment_bump],
+    ];
+    let ah_seeds = [
+        PREFIX.as_bytes(),
+        auction_house.creator.as_ref(),
+        auction_house.treasury_mint.as_ref(),
+        &[auction_house.bump],
+    ];
+
+    // with the native account, the escrow is its own owner,
+    // whereas with token, it is the auction house that is owner.
+    let (escrow_owner, signer_seeds) = if is_native {
+        if verify_withdrawal(escrow_payment_account.to_account_info(), amount)? > 0 {
+            return Err(AuctionHouseError::InsufficientFunds.into());
+        }
+        (
+            escrow_payment_account.to_account_info(),
+            escrow_signer_seeds,
+        )
+    } else {
+        (auction_house.to_account_info(), ah_seeds)
+    };
+
+    let leftover = pay_creator_fees(
+        remaining_accounts,
+        metadata,
+        escrow_payment_account,
+        &escrow_owner,
+        fee_payer,
+        treasury_mint,
+        ata_program,
+        token_program,
+        system_program,
+        &rent.to_account_info(),
+        &signer_seeds,
+        fee_payer_seeds,
+        royalty_basis_points(auction_house, metadata, None)?,
+        auction_house.unverified_creator_policy,
+        amount,
+        is_native,
+    )?;
+
+    if !is_native {
+        if payment_receipt_account.data_is_empty() {
+            make_ata(
+                payment_receipt_account.to_account_info(),
+                recipient.clone(),
+                treasury_mint.to_account_info(),
+                fee_payer.clone(),
+                ata_program.to_account_info(),
+                token_program.to_account_info(),
+                system_program.to_account_info(),
+                rent.to_account_info(),
+                fee_payer_seeds,
+            )?;
+        }
+        let receipt_data =
+            assert_is_ata(payment_receipt_account, recipient.key, &treasury_mint.key())?;
+        // make sure you cant get rugged
+        if receipt_data.delegate.is_some() {
+            return Err(AuctionHouseError::SellerATACannotHaveDelegate.into());
+        }
+
+        transfer_tokens(
+            token_program,
+            escrow_payment_account,
+            treasury_mint,
+            payment_receipt_account,
+            &escrow_owner,
+            leftover,
+            &[&signer_seeds],
+        )?;
+    } else {
+        assert_keys_equal(payment_receipt_account.key(), recipient.key())?;
+        invoke_signed(
+            &system_instruction::transfer(
+                escrow_payment_account.key,
+                payment_receipt_account.key,
+                leftover,
+            ),
+            &[
+                escrow_payment_account.to_account_info(),
+                payment_receipt_account.to_account_info(),
+                system_program.to_account_info(),
+            ],
+            &[&signer_seeds],
+        )?;
+    }
+
+    Ok(amount
+        .checked_sub(leftover)
+        .ok_or(AuctionHouseError::NumericalOverflow)?)
+}
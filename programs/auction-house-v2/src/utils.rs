@@ -1,5 +1,12 @@
 use crate::{
-    constants::*, errors::AuctionHouseError, AuctionHouse, AuthorityScope, PREFIX,
+    constants::*,
+    errors::AuctionHouseError,
+    pda::{
+        find_collection_stats_address, find_escrow_nft_record_address, find_fee_schedule_address,
+        find_trader_stats_address,
+    },
+    AuctionHouse, Auctioneer, AuthorityScope, CollectionStats, EscrowNftRecord, FeeSchedule,
+    Referral, RoyaltyPolicy, TraderStats, UnverifiedCreatorPolicy, PREFIX,
 };
 
 use anchor_lang::{
@@ -9,19 +16,133 @@ use anchor_lang::{
         program_memory::{sol_memcmp, sol_memset},
         program_option::COption,
         program_pack::{IsInitialized, Pack},
+        instruction::{AccountMeta, Instruction},
         pubkey::PUBKEY_BYTES,
-        system_instruction,
+        system_instruction, sysvar,
     },
 };
-use anchor_spl::token::{Mint, Token, TokenAccount};
 use arrayref::array_ref;
-use mpl_token_metadata::state::{Metadata, TokenMetadataAccount};
-use spl_token::{instruction::initialize_account2, state::Account as SplAccount};
+use mpl_token_metadata::{
+    instruction::{DelegateArgs, MetadataInstruction, RevokeArgs, TransferArgs},
+    state::{Metadata, TokenMetadataAccount, TokenStandard},
+};
+use spl_token_2022::{
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    instruction::initialize_account2,
+    state::{Account as SplAccount, Mint as SplMint},
+};
 use std::{convert::TryInto, slice::Iter};
 
+/// Both SPL Token and Token-2022 can own the NFT and treasury mints.
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == spl_token::id() || *program_id == spl_token_2022::id()
+}
+
+/// Unpack a token account owned by either token program, skipping any Token-2022 extensions.
+pub fn unpack_token_account(account: &AccountInfo) -> Result<SplAccount> {
+    if !is_token_program(account.owner) {
+        return err!(AuctionHouseError::IncorrectOwner);
+    }
+    let data = account.try_borrow_data()?;
+
+    Ok(StateWithExtensions::<SplAccount>::unpack(&data)?.base)
+}
+
+/// Unpack a mint owned by either token program, skipping any Token-2022 extensions.
+pub fn unpack_mint(mint: &AccountInfo) -> Result<SplMint> {
+    if !is_token_program(mint.owner) {
+        return err!(AuctionHouseError::IncorrectOwner);
+    }
+    let data = mint.try_borrow_data()?;
+
+    Ok(StateWithExtensions::<SplMint>::unpack(&data)?.base)
+}
+
+/// Whether the raw Token-2022 extension data of `mint_data` holds an extension of type `extension_type`, read
+/// without parsing so extensions newer than `spl-token-2022` can be recognised.
+fn has_raw_mint_extension(mint_data: &[u8], extension_type: u16) -> bool {
+    // Mint extensions follow the base state, padded to a token account's length, and the account type byte.
+    let mut tlv_data = mint_data.get(SplAccount::LEN + 1..).unwrap_or_default();
+    while let [type_low, type_high, length_low, length_high, rest @ ..] = tlv_data {
+        let tlv_type = u16::from_le_bytes([*type_low, *type_high]);
+        if tlv_type == extension_type {
+            return true;
+        }
+        if tlv_type == u16::from(ExtensionType::Uninitialized) {
+            return false;
+        }
+        let length = u16::from_le_bytes([*length_low, *length_high]) as usize;
+        tlv_data = rest.get(length..).unwrap_or_default();
+    }
+
+    false
+}
+
+/// Reject mints whose Token-2022 extensions would change what a transfer delivers. Transfer
+/// fees would shortchange the receiving party, non-transferable tokens can never settle and
+/// transfer hooks need accounts this program never passes. Other extensions newer than this
+/// program fail to parse and are rejected as well.
+pub fn assert_supported_mint(mint: &AccountInfo) -> Result<SplMint> {
+    if !is_token_program(mint.owner) {
+        return err!(AuctionHouseError::IncorrectOwner);
+    }
+    let data = mint.try_borrow_data()?;
+    if has_raw_mint_extension(&data, TRANSFER_HOOK_EXTENSION_TYPE) {
+        return err!(AuctionHouseError::UnsupportedMintExtension);
+    }
+    let state = StateWithExtensions::<SplMint>::unpack(&data)?;
+    let extensions = state
+        .get_extension_types()
+        .map_err(|_| AuctionHouseError::UnsupportedMintExtension)?;
+    if extensions.iter().any(|extension| {
+        matches!(
+            extension,
+            ExtensionType::TransferFeeConfig | ExtensionType::NonTransferable
+        )
+    }) {
+        return err!(AuctionHouseError::UnsupportedMintExtension);
+    }
+
+    Ok(state.base)
+}
+
+/// Move `amount` of `mint` with `transfer_checked`, which both token programs accept.
+pub fn transfer_tokens<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let decimals = unpack_mint(mint)?.decimals;
+    invoke_signed(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            source.key,
+            mint.key,
+            destination.key,
+            authority.key,
+            &[],
+            amount,
+            decimals,
+        )?,
+        &[
+            source.clone(),
+            mint.clone(),
+            destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+
+    Ok(())
+}
+
 pub fn assert_is_ata(ata: &AccountInfo, wallet: &Pubkey, mint: &Pubkey) -> Result<SplAccount> {
-    assert_owned_by(ata, &spl_token::id())?;
-    let ata_account: SplAccount = assert_initialized(ata)?;
+    let ata_account = unpack_token_account(ata)?;
     assert_keys_equal(ata_account.owner, *wallet)?;
     assert_keys_equal(ata_account.mint, *mint)?;
 
@@ -52,7 +173,7 @@ pub fn make_ata<'a>(
             fee_payer.key,
             wallet.key,
             mint.key,
-            &spl_token::ID,
+            token_program.key,
         ),
         &[
             ata,
@@ -70,17 +191,14 @@ pub fn make_ata<'a>(
     Ok(())
 }
 
-pub fn assert_metadata_valid<'a>(
-    metadata: &UncheckedAccount,
-    token_account: &anchor_lang::prelude::Account<'a, TokenAccount>,
-) -> Result<()> {
+pub fn assert_metadata_valid(metadata: &AccountInfo, mint: &Pubkey) -> Result<()> {
     assert_derivation(
         &mpl_token_metadata::id(),
-        &metadata.to_account_info(),
+        metadata,
         &[
             mpl_token_metadata::state::PREFIX.as_bytes(),
             mpl_token_metadata::id().as_ref(),
-            token_account.mint.as_ref(),
+            mint.as_ref(),
         ],
     )?;
 
@@ -90,6 +208,270 @@ pub fn assert_metadata_valid<'a>(
     Ok(())
 }
 
+/// Assert that `metadata` decorates `mint` and places it in the verified collection of `collection`.
+pub fn assert_verified_collection_member(
+    metadata: &AccountInfo,
+    mint: &Pubkey,
+    collection: &Pubkey,
+) -> Result<()> {
+    assert_derivation(
+        &mpl_token_metadata::id(),
+        metadata,
+        &[
+            mpl_token_metadata::state::PREFIX.as_bytes(),
+            mpl_token_metadata::id().as_ref(),
+            mint.as_ref(),
+        ],
+    )?;
+    if metadata.data_is_empty() {
+        return Err(AuctionHouseError::MetadataDoesntExist.into());
+    }
+
+    match Metadata::from_account_info(metadata)?.collection {
+        Some(c) if c.verified && c.key == *collection => Ok(()),
+        _ => Err(AuctionHouseError::NotInCollection.into()),
+    }
+}
+
+/// Whether `metadata` decorates a programmable NFT, whose token account stays frozen and only
+/// moves through Token Metadata.
+pub fn is_programmable(metadata: &AccountInfo) -> Result<bool> {
+    Ok(matches!(
+        Metadata::from_account_info(metadata)?.token_standard,
+        Some(TokenStandard::ProgrammableNonFungible)
+    ))
+}
+
+/// Token Metadata accounts a programmable NFT needs on top of an instruction's named accounts,
+/// read in this order from its remaining accounts. Pass the Token Metadata program id in place
+/// of absent authorization rules.
+pub struct ProgrammableAccounts<'info> {
+    pub token_metadata_program: AccountInfo<'info>,
+    pub edition: AccountInfo<'info>,
+    pub token_record: AccountInfo<'info>,
+    pub sysvar_instructions: AccountInfo<'info>,
+    pub authorization_rules_program: AccountInfo<'info>,
+    pub authorization_rules: AccountInfo<'info>,
+}
+
+impl<'info> ProgrammableAccounts<'info> {
+    pub fn next(remaining_accounts: &mut Iter<AccountInfo<'info>>) -> Result<Self> {
+        let accounts = Self {
+            token_metadata_program: next_account_info(remaining_accounts)?.clone(),
+            edition: next_account_info(remaining_accounts)?.clone(),
+            token_record: next_account_info(remaining_accounts)?.clone(),
+            sysvar_instructions: next_account_info(remaining_accounts)?.clone(),
+            authorization_rules_program: next_account_info(remaining_accounts)?.clone(),
+            authorization_rules: next_account_info(remaining_accounts)?.clone(),
+        };
+        assert_keys_equal(accounts.token_metadata_program.key(), mpl_token_metadata::id())?;
+        assert_keys_equal(accounts.sysvar_instructions.key(), sysvar::instructions::id())?;
+
+        Ok(accounts)
+    }
+
+    fn authorization_rules_metas(&self) -> [AccountMeta; 2] {
+        [
+            AccountMeta::new_readonly(self.authorization_rules_program.key(), false),
+            AccountMeta::new_readonly(self.authorization_rules.key(), false),
+        ]
+    }
+}
+
+/// Make `delegate` the Sale delegate of a programmable NFT. This puts its token record in the
+/// `Listed` state, which locks the token against its holder until the delegate is revoked.
+#[allow(clippy::too_many_arguments)]
+pub fn delegate_programmable_sale<'info>(
+    programmable: &ProgrammableAccounts<'info>,
+    delegate: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_account: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    invoke_token_delegate_instruction(
+        MetadataInstruction::Delegate(DelegateArgs::SaleV1 {
+            amount,
+            authorization_data: None,
+        }),
+        programmable,
+        delegate,
+        metadata,
+        mint,
+        token_account,
+        owner,
+        system_program,
+        token_program,
+    )
+}
+
+/// Revoke the Sale delegate of a programmable NFT, unlocking it for its holder.
+#[allow(clippy::too_many_arguments)]
+pub fn revoke_programmable_sale<'info>(
+    programmable: &ProgrammableAccounts<'info>,
+    delegate: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_account: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    invoke_token_delegate_instruction(
+        MetadataInstruction::Revoke(RevokeArgs::SaleV1),
+        programmable,
+        delegate,
+        metadata,
+        mint,
+        token_account,
+        owner,
+        system_program,
+        token_program,
+    )
+}
+
+/// Token Metadata `Delegate` and `Revoke` share their accounts. The token owner signs and pays.
+#[allow(clippy::too_many_arguments)]
+fn invoke_token_delegate_instruction<'info>(
+    instruction: MetadataInstruction,
+    programmable: &ProgrammableAccounts<'info>,
+    delegate: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_account: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let mut accounts = vec![
+        // Token delegates keep no delegate record.
+        AccountMeta::new_readonly(mpl_token_metadata::id(), false),
+        AccountMeta::new_readonly(delegate.key(), false),
+        AccountMeta::new_readonly(metadata.key(), false),
+        AccountMeta::new_readonly(programmable.edition.key(), false),
+        AccountMeta::new(programmable.token_record.key(), false),
+        AccountMeta::new_readonly(mint.key(), false),
+        AccountMeta::new(token_account.key(), false),
+        AccountMeta::new_readonly(owner.key(), true),
+        AccountMeta::new(owner.key(), true),
+        AccountMeta::new_readonly(system_program.key(), false),
+        AccountMeta::new_readonly(programmable.sysvar_instructions.key(), false),
+        AccountMeta::new_readonly(token_program.key(), false),
+    ];
+    accounts.extend(programmable.authorization_rules_metas());
+
+    invoke_signed(
+        &Instruction {
+            program_id: mpl_token_metadata::id(),
+            accounts,
+            data: instruction.try_to_vec()?,
+        },
+        &[
+            programmable.token_metadata_program.clone(),
+            delegate.clone(),
+            metadata.clone(),
+            programmable.edition.clone(),
+            programmable.token_record.clone(),
+            mint.clone(),
+            token_account.clone(),
+            owner.clone(),
+            system_program.clone(),
+            programmable.sysvar_instructions.clone(),
+            token_program.clone(),
+            programmable.authorization_rules_program.clone(),
+            programmable.authorization_rules.clone(),
+        ],
+        &[],
+    )?;
+
+    Ok(())
+}
+
+/// Move `amount` of a programmable NFT from `source` to `destination` through Token Metadata,
+/// signed by its Sale delegate. Token Metadata creates the destination token record if needed.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_programmable<'info>(
+    programmable: &ProgrammableAccounts<'info>,
+    destination_token_record: &AccountInfo<'info>,
+    source: &AccountInfo<'info>,
+    source_owner: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    destination_owner: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    delegate: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    ata_program: &AccountInfo<'info>,
+    amount: u64,
+    delegate_seeds: &[&[u8]],
+    payer_seeds: &[&[u8]],
+) -> Result<()> {
+    let mut accounts = vec![
+        AccountMeta::new(source.key(), false),
+        AccountMeta::new_readonly(source_owner.key(), false),
+        AccountMeta::new(destination.key(), false),
+        AccountMeta::new_readonly(destination_owner.key(), false),
+        AccountMeta::new_readonly(mint.key(), false),
+        AccountMeta::new_readonly(metadata.key(), false),
+        AccountMeta::new_readonly(programmable.edition.key(), false),
+        AccountMeta::new(programmable.token_record.key(), false),
+        AccountMeta::new(destination_token_record.key(), false),
+        AccountMeta::new_readonly(delegate.key(), true),
+        AccountMeta::new(payer.key(), true),
+        AccountMeta::new_readonly(system_program.key(), false),
+        AccountMeta::new_readonly(programmable.sysvar_instructions.key(), false),
+        AccountMeta::new_readonly(token_program.key(), false),
+        AccountMeta::new_readonly(ata_program.key(), false),
+    ];
+    accounts.extend(programmable.authorization_rules_metas());
+
+    let signer_seeds: &[&[&[u8]]] = if payer_seeds.is_empty() {
+        &[delegate_seeds]
+    } else {
+        &[delegate_seeds, payer_seeds]
+    };
+
+    invoke_signed(
+        &Instruction {
+            program_id: mpl_token_metadata::id(),
+            accounts,
+            data: MetadataInstruction::Transfer(TransferArgs::V1 {
+                amount,
+                authorization_data: None,
+            })
+            .try_to_vec()?,
+        },
+        &[
+            programmable.token_metadata_program.clone(),
+            source.clone(),
+            source_owner.clone(),
+            destination.clone(),
+            destination_owner.clone(),
+            mint.clone(),
+            metadata.clone(),
+            programmable.edition.clone(),
+            programmable.token_record.clone(),
+            destination_token_record.clone(),
+            delegate.clone(),
+            payer.clone(),
+            system_program.clone(),
+            programmable.sysvar_instructions.clone(),
+            token_program.clone(),
+            ata_program.clone(),
+            programmable.authorization_rules_program.clone(),
+            programmable.authorization_rules.clone(),
+        ],
+        signer_seeds,
+    )?;
+
+    Ok(())
+}
+
 pub fn get_fee_payer<'a, 'b>(
     authority: &UncheckedAccount,
     auction_house: &anchor_lang::prelude::Account<AuctionHouse>,
@@ -120,10 +502,10 @@ pub fn assert_valid_delegation(
     src_wallet: &AccountInfo,
     dst_wallet: &AccountInfo,
     transfer_authority: &AccountInfo,
-    mint: &anchor_lang::prelude::Account<Mint>,
+    mint: &AccountInfo,
     paysize: u64,
 ) -> Result<()> {
-    match SplAccount::unpack(&src_account.data.borrow()) {
+    match unpack_token_account(src_account) {
         Ok(token_account) => {
             // Ensure that the delegated amount is exactly equal to the maker_size
             msg!(
@@ -141,12 +523,12 @@ pub fn assert_valid_delegation(
             }
 
             msg!("Delegate matches");
-            assert_is_ata(src_account, src_wallet.key, &mint.key())?;
-            assert_is_ata(dst_account, dst_wallet.key, &mint.key())?;
+            assert_is_ata(src_account, src_wallet.key, mint.key)?;
+            assert_is_ata(dst_account, dst_wallet.key, mint.key)?;
             msg!("ATAs match")
         }
         Err(_) => {
-            if mint.key() != spl_token::native_mint::id() {
+            if *mint.key != spl_token::native_mint::id() {
                 return err!(AuctionHouseError::ExpectedSolAccount);
             }
 
@@ -180,33 +562,40 @@ pub fn assert_keys_equal_err(key1: Pubkey, key2: Pubkey) -> Result<()> {
 
 // #[derive(Debug, Clone)]
 pub enum BidType {
-    // PublicSale,
+    PublicSale,
     PrivateSale,
-    // AuctioneerPublicSale,
-    // AuctioneerPrivateSale,
+    AuctioneerPublicSale,
+    AuctioneerPrivateSale,
 }
 
 // #[derive(Debug, Clone)]
 pub enum ListingType {
     Sell,
+    AuctioneerSell,
+    CreateDutchListing,
 }
 
 #[derive(Debug, Clone)]
 pub enum PurchaseType {
     ExecuteSale,
+    AuctioneerExecuteSale,
+    ExecuteDutchSale,
+    ExecutePartialSale,
 }
 
 // #[derive(Debug, Clone)]
 pub enum CancelType {
     Cancel,
+    AuctioneerCancel,
+    CancelDutchListing,
 }
 
 pub fn assert_program_bid_instruction(sighash: &[u8]) -> Result<BidType> {
     match sighash {
-        // [169, 84, 218, 35, 42, 206, 16, 171] => Ok(BidType::PublicSale),
+        [169, 84, 218, 35, 42, 206, 16, 171] => Ok(BidType::PublicSale),
         [102, 6, 61, 18, 1, 218, 235, 234] => Ok(BidType::PrivateSale),
-        // [221, 239, 99, 240, 86, 46, 213, 126] => Ok(BidType::AuctioneerPublicSale),
-        // [17, 106, 133, 46, 229, 48, 45, 208] => Ok(BidType::AuctioneerPrivateSale),
+        [221, 239, 99, 240, 86, 46, 213, 126] => Ok(BidType::AuctioneerPublicSale),
+        [17, 106, 133, 46, 229, 48, 45, 208] => Ok(BidType::AuctioneerPrivateSale),
         _ => Err(AuctionHouseError::InstructionMismatch.into()),
     }
 }
@@ -214,7 +603,8 @@ pub fn assert_program_bid_instruction(sighash: &[u8]) -> Result<BidType> {
 pub fn assert_program_listing_instruction(sighash: &[u8]) -> Result<ListingType> {
     match sighash {
         [51, 230, 133, 164, 1, 127, 131, 173] => Ok(ListingType::Sell),
-        // [251, 60, 142, 195, 121, 203, 26, 183] => Ok(ListingType::AuctioneerSell),
+        [251, 60, 142, 195, 121, 203, 26, 183] => Ok(ListingType::AuctioneerSell),
+        [160, 234, 69, 145, 228, 182, 19, 36] => Ok(ListingType::CreateDutchListing),
         _ => Err(AuctionHouseError::InstructionMismatch.into()),
     }
 }
@@ -222,7 +612,9 @@ pub fn assert_program_listing_instruction(sighash: &[u8]) -> Result<ListingType>
 pub fn assert_program_purchase_instruction(sighash: &[u8]) -> Result<PurchaseType> {
     match sighash {
         [37, 74, 217, 157, 79, 49, 35, 6] => Ok(PurchaseType::ExecuteSale),
-        // [68, 125, 32, 65, 251, 43, 35, 53] => Ok(PurchaseType::AuctioneerExecuteSale),
+        [68, 125, 32, 65, 251, 43, 35, 53] => Ok(PurchaseType::AuctioneerExecuteSale),
+        [2, 139, 27, 40, 242, 101, 137, 183] => Ok(PurchaseType::ExecuteDutchSale),
+        [163, 18, 35, 157, 49, 164, 203, 133] => Ok(PurchaseType::ExecutePartialSale),
         _ => Err(AuctionHouseError::InstructionMismatch.into()),
     }
 }
@@ -230,7 +622,8 @@ pub fn assert_program_purchase_instruction(sighash: &[u8]) -> Result<PurchaseTyp
 pub fn assert_program_cancel_instruction(sighash: &[u8]) -> Result<CancelType> {
     match sighash {
         [232, 219, 223, 41, 219, 236, 220, 190] => Ok(CancelType::Cancel),
-        // [197, 97, 152, 196, 115, 204, 64, 215] => Ok(CancelType::AuctioneerCancel),
+        [197, 97, 152, 196, 115, 204, 64, 215] => Ok(CancelType::AuctioneerCancel),
+        [152, 72, 239, 246, 191, 10, 136, 133] => Ok(CancelType::CancelDutchListing),
         _ => Err(AuctionHouseError::InstructionMismatch.into()),
     }
 }
@@ -260,39 +653,368 @@ pub fn assert_owned_by(account: &AccountInfo, owner: &Pubkey) -> Result<()> {
     }
 }
 
+/// Verified collection mint of the token described by `metadata_info`, if any.
+pub fn verified_collection(metadata_info: &AccountInfo) -> Result<Option<Pubkey>> {
+    match Metadata::from_account_info(metadata_info)?.collection {
+        Some(c) if c.verified => Ok(Some(c.key)),
+        _ => Ok(None),
+    }
+}
+
+/// House fee rate, in basis points, on a sale of the token described by `metadata_info`. A token in a verified
+/// collection takes the next remaining account as the collection's fee schedule address, whether or not the authority
+/// created one, and pays the schedule's rate if it exists. Any other token pays the house rate.
+pub fn seller_fee_basis_points<'a>(
+    auction_house: &Account<'a, AuctionHouse>,
+    metadata_info: &AccountInfo<'a>,
+    remaining_accounts: &mut Iter<AccountInfo<'a>>,
+) -> Result<u16> {
+    let collection = match verified_collection(metadata_info)? {
+        Some(collection) => collection,
+        None => return Ok(auction_house.seller_fee_basis_points),
+    };
+    let fee_schedule_info = next_account_info(remaining_accounts)
+        .map_err(|_| AuctionHouseError::FeeScheduleMismatch)?;
+    let (fee_schedule_key, _) = find_fee_schedule_address(&auction_house.key(), &collection);
+    if *fee_schedule_info.key != fee_schedule_key {
+        return err!(AuctionHouseError::FeeScheduleMismatch);
+    }
+    // Only this program can create an account at the PDA, so anything there is a fee schedule.
+    if fee_schedule_info.data_is_empty() {
+        return Ok(auction_house.seller_fee_basis_points);
+    }
+    let fee_schedule = FeeSchedule::try_deserialize(&mut &fee_schedule_info.data.borrow()[..])?;
+    Ok(fee_schedule.seller_fee_basis_points)
+}
+
+/// Next remaining account, checked to be the trader stats address of `wallet`.
+pub fn next_trader_stats_info<'a, 'b>(
+    remaining_accounts: &mut Iter<'b, AccountInfo<'a>>,
+    auction_house: &Pubkey,
+    wallet: &Pubkey,
+) -> Result<&'b AccountInfo<'a>> {
+    let trader_stats_info = next_account_info(remaining_accounts)
+        .map_err(|_| AuctionHouseError::TraderStatsMismatch)?;
+    let (trader_stats_key, _) = find_trader_stats_address(auction_house, wallet);
+    if *trader_stats_info.key != trader_stats_key {
+        return err!(AuctionHouseError::TraderStatsMismatch);
+    }
+    Ok(trader_stats_info)
+}
+
+/// Add a trade to the stats of `wallet`, creating the account at `trader_stats_info` with `fee_payer` on the wallet's
+/// first trade.
+#[allow(clippy::too_many_arguments)]
+pub fn record_trade<'a>(
+    trader_stats_info: &AccountInfo<'a>,
+    auction_house: &Pubkey,
+    wallet: &Pubkey,
+    fee_payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rent: &AccountInfo<'a>,
+    fee_payer_seeds: &[&[u8]],
+    bought: u64,
+    sold: u64,
+    fees: u64,
+    now: i64,
+) -> Result<()> {
+    let mut trader_stats = if trader_stats_info.data_is_empty() {
+        let (_, bump) = find_trader_stats_address(auction_house, wallet);
+        create_or_allocate_account_raw(
+            crate::id(),
+            trader_stats_info,
+            rent,
+            system_program,
+            fee_payer,
+            TRADER_STATS_SIZE,
+            fee_payer_seeds,
+            &[
+                PREFIX.as_bytes(),
+                TRADER_STATS.as_bytes(),
+                auction_house.as_ref(),
+                wallet.as_ref(),
+                &[bump],
+            ],
+        )?;
+        TraderStats {
+            auction_house: *auction_house,
+            wallet: *wallet,
+            buy_volume: 0,
+            sell_volume: 0,
+            trade_count: 0,
+            first_trade_at: now,
+            last_trade_at: now,
+            fees_paid: 0,
+            bump,
+        }
+    } else {
+        TraderStats::try_deserialize(&mut &trader_stats_info.data.borrow()[..])?
+    };
+
+    trader_stats.buy_volume = trader_stats
+        .buy_volume
+        .checked_add(bought)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    trader_stats.sell_volume = trader_stats
+        .sell_volume
+        .checked_add(sold)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    trader_stats.trade_count = trader_stats
+        .trade_count
+        .checked_add(1)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    trader_stats.fees_paid = trader_stats
+        .fees_paid
+        .checked_add(fees)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    trader_stats.last_trade_at = now;
+
+    trader_stats.try_serialize(&mut &mut trader_stats_info.try_borrow_mut_data()?[..])
+}
+
+/// Next remaining account, checked to be the stats address of `collection`.
+pub fn next_collection_stats_info<'a, 'b>(
+    remaining_accounts: &mut Iter<'b, AccountInfo<'a>>,
+    auction_house: &Pubkey,
+    collection: &Pubkey,
+) -> Result<&'b AccountInfo<'a>> {
+    let collection_stats_info = next_account_info(remaining_accounts)
+        .map_err(|_| AuctionHouseError::CollectionStatsMismatch)?;
+    let (collection_stats_key, _) = find_collection_stats_address(auction_house, collection);
+    if *collection_stats_info.key != collection_stats_key {
+        return err!(AuctionHouseError::CollectionStatsMismatch);
+    }
+    Ok(collection_stats_info)
+}
+
+/// Add a sale at `price` to the stats of `collection`, creating the account at `collection_stats_info` with
+/// `fee_payer` on the collection's first sale.
+#[allow(clippy::too_many_arguments)]
+pub fn record_collection_sale<'a>(
+    collection_stats_info: &AccountInfo<'a>,
+    auction_house: &Pubkey,
+    collection: &Pubkey,
+    fee_payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rent: &AccountInfo<'a>,
+    fee_payer_seeds: &[&[u8]],
+    price: u64,
+    now: i64,
+) -> Result<()> {
+    let day = now.div_euclid(SECONDS_PER_DAY);
+    let mut collection_stats = if collection_stats_info.data_is_empty() {
+        let (_, bump) = find_collection_stats_address(auction_house, collection);
+        create_or_allocate_account_raw(
+            crate::id(),
+            collection_stats_info,
+            rent,
+            system_program,
+            fee_payer,
+            COLLECTION_STATS_SIZE,
+            fee_payer_seeds,
+            &[
+                PREFIX.as_bytes(),
+                COLLECTION_STATS.as_bytes(),
+                auction_house.as_ref(),
+                collection.as_ref(),
+                &[bump],
+            ],
+        )?;
+        CollectionStats {
+            auction_house: *auction_house,
+            collection: *collection,
+            last_sale_price: 0,
+            last_sale_at: 0,
+            volume: 0,
+            sale_count: 0,
+            daily_volume: [0; COLLECTION_STATS_DAYS],
+            current_day: day,
+            recent_prices: [0; COLLECTION_STATS_SALES],
+            recent_sale_times: [0; COLLECTION_STATS_SALES],
+            twap: 0,
+            floor: 0,
+            bump,
+        }
+    } else {
+        CollectionStats::try_deserialize(&mut &collection_stats_info.data.borrow()[..])?
+    };
+
+    // Clear the buckets of the days without sales since the last one. A clock running backwards keeps adding to the
+    // latest bucket.
+    if day > collection_stats.current_day {
+        let elapsed = (day - collection_stats.current_day).min(COLLECTION_STATS_DAYS as i64);
+        for skipped in day - elapsed + 1..=day {
+            let bucket = skipped.rem_euclid(COLLECTION_STATS_DAYS as i64) as usize;
+            collection_stats.daily_volume[bucket] = 0;
+        }
+        collection_stats.current_day = day;
+    }
+    let bucket = collection_stats
+        .current_day
+        .rem_euclid(COLLECTION_STATS_DAYS as i64) as usize;
+    collection_stats.daily_volume[bucket] = collection_stats.daily_volume[bucket]
+        .checked_add(price)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+
+    let slot = (collection_stats.sale_count % COLLECTION_STATS_SALES as u64) as usize;
+    collection_stats.recent_prices[slot] = price;
+    collection_stats.recent_sale_times[slot] = now;
+    collection_stats.sale_count = collection_stats
+        .sale_count
+        .checked_add(1)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    collection_stats.volume = collection_stats
+        .volume
+        .checked_add(price)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+    collection_stats.last_sale_price = price;
+    collection_stats.last_sale_at = now;
+    collection_stats.twap = recent_sales_twap(&collection_stats)?;
+    collection_stats.floor = recent_sales_floor(&collection_stats);
+
+    collection_stats.try_serialize(&mut &mut collection_stats_info.try_borrow_mut_data()?[..])
+}
+
+/// Time-weighted average of the recent sale prices of `collection_stats`, oldest first, each weighted by the time until
+/// the next sale. The latest sale has not stood yet, so it only counts in the plain average used when no time passed.
+fn recent_sales_twap(collection_stats: &CollectionStats) -> Result<u64> {
+    let count = collection_stats
+        .sale_count
+        .min(COLLECTION_STATS_SALES as u64) as usize;
+    let oldest = if count < COLLECTION_STATS_SALES {
+        0
+    } else {
+        (collection_stats.sale_count % COLLECTION_STATS_SALES as u64) as usize
+    };
+    let sales: Vec<(u64, i64)> = (0..count)
+        .map(|i| {
+            let slot = (oldest + i) % COLLECTION_STATS_SALES;
+            (
+                collection_stats.recent_prices[slot],
+                collection_stats.recent_sale_times[slot],
+            )
+        })
+        .collect();
+
+    let mut weighted_sum: u128 = 0;
+    let mut total_time: u128 = 0;
+    for pair in sales.windows(2) {
+        let (price, sold_at) = pair[0];
+        let (_, next_sold_at) = pair[1];
+        let held = next_sold_at.saturating_sub(sold_at).max(0) as u128;
+        weighted_sum = weighted_sum
+            .checked_add(
+                (price as u128)
+                    .checked_mul(held)
+                    .ok_or(AuctionHouseError::NumericalOverflow)?,
+            )
+            .ok_or(AuctionHouseError::NumericalOverflow)?;
+        total_time += held;
+    }
+    if total_time == 0 {
+        weighted_sum = sales.iter().map(|(price, _)| *price as u128).sum();
+        total_time = count as u128;
+    }
+
+    u64::try_from(weighted_sum / total_time)
+        .map_err(|_| AuctionHouseError::NumericalOverflow.into())
+}
+
+/// Lowest of the recent sale prices of `collection_stats`.
+fn recent_sales_floor(collection_stats: &CollectionStats) -> u64 {
+    let count = collection_stats
+        .sale_count
+        .min(COLLECTION_STATS_SALES as u64) as usize;
+    collection_stats.recent_prices[..count]
+        .iter()
+        .copied()
+        .min()
+        .unwrap_or_default()
+}
+
+/// Add a sale at `price` to the record of the escrow NFT account `escrow_nft_account`, creating the record at
+/// `escrow_nft_record_info` with `fee_payer` as the rent payer of both on the escrow's first sale.
+#[allow(clippy::too_many_arguments)]
+pub fn record_escrow_nft<'a>(
+    escrow_nft_record_info: &AccountInfo<'a>,
+    escrow_nft_account: &Pubkey,
+    fee_payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rent: &AccountInfo<'a>,
+    fee_payer_seeds: &[&[u8]],
+    price: u64,
+) -> Result<()> {
+    let mut escrow_nft_record = if escrow_nft_record_info.data_is_empty() {
+        let (_, bump) = find_escrow_nft_record_address(escrow_nft_account);
+        create_or_allocate_account_raw(
+            crate::id(),
+            escrow_nft_record_info,
+            rent,
+            system_program,
+            fee_payer,
+            ESCROW_NFT_RECORD_SIZE,
+            fee_payer_seeds,
+            &[
+                PREFIX.as_bytes(),
+                ESCROW_NFT_RECORD.as_bytes(),
+                escrow_nft_account.as_ref(),
+                &[bump],
+            ],
+        )?;
+        EscrowNftRecord {
+            rent_payer: fee_payer.key(),
+            price: 0,
+            bump,
+        }
+    } else {
+        EscrowNftRecord::try_deserialize(&mut &escrow_nft_record_info.data.borrow()[..])?
+    };
+
+    escrow_nft_record.price = escrow_nft_record
+        .price
+        .checked_add(price)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
+
+    escrow_nft_record.try_serialize(&mut &mut escrow_nft_record_info.try_borrow_mut_data()?[..])
+}
+
+/// The house fee on a sale of `size`, before any of it is paid to referrers.
+pub fn auction_house_fee(seller_fee_basis_points: u16, size: u64) -> Result<u64> {
+    Ok((seller_fee_basis_points as u128)
+        .checked_mul(size as u128)
+        .ok_or(AuctionHouseError::NumericalOverflow)?
+        .checked_div(10000)
+        .ok_or(AuctionHouseError::NumericalOverflow)? as u64)
+}
+
+/// Pay the house fee on `size` to the treasury, less `referral_fees` already paid out of it to referrers.
+/// Returns the whole house fee.
 #[allow(clippy::too_many_arguments)]
 pub fn pay_auction_house_fees<'a>(
     auction_house: &anchor_lang::prelude::Account<'a, AuctionHouse>,
     auction_house_treasury: &AccountInfo<'a>,
     escrow_payment_account: &AccountInfo<'a>,
+    treasury_mint: &AccountInfo<'a>,
     token_program: &AccountInfo<'a>,
     system_program: &AccountInfo<'a>,
     signer_seeds: &[&[u8]],
+    seller_fee_basis_points: u16,
     size: u64,
+    referral_fees: u64,
     is_native: bool,
 ) -> Result<u64> {
-    let fees = auction_house.seller_fee_basis_points;
-    let total_fee = (fees as u128)
-        .checked_mul(size as u128)
-        .ok_or(AuctionHouseError::NumericalOverflow)?
-        .checked_div(10000)
-        .ok_or(AuctionHouseError::NumericalOverflow)? as u64;
+    let total_fee = auction_house_fee(seller_fee_basis_points, size)?;
+    let treasury_fee = total_fee
+        .checked_sub(referral_fees)
+        .ok_or(AuctionHouseError::NumericalOverflow)?;
     if !is_native {
-        invoke_signed(
-            &spl_token::instruction::transfer(
-                token_program.key,
-                escrow_payment_account.key,
-                auction_house_treasury.key,
-                &auction_house.key(),
-                &[],
-                total_fee,
-            )?,
-            &[
-                escrow_payment_account.clone(),
-                auction_house_treasury.clone(),
-                token_program.clone(),
-                auction_house.to_account_info(),
-            ],
+        transfer_tokens(
+            token_program,
+            escrow_payment_account,
+            treasury_mint,
+            auction_house_treasury,
+            &auction_house.to_account_info(),
+            treasury_fee,
             &[signer_seeds],
         )?;
     } else {
@@ -300,7 +1022,7 @@ pub fn pay_auction_house_fees<'a>(
             &system_instruction::transfer(
                 escrow_payment_account.key,
                 auction_house_treasury.key,
-                total_fee,
+                treasury_fee,
             ),
             &[
                 escrow_payment_account.clone(),
@@ -317,8 +1039,8 @@ pub fn create_program_token_account_if_not_present<'a>(
     payment_account: &UncheckedAccount<'a>,
     system_program: &Program<'a, System>,
     fee_payer: &AccountInfo<'a>,
-    token_program: &Program<'a, Token>,
-    treasury_mint: &anchor_lang::prelude::Account<'a, Mint>,
+    token_program: &UncheckedAccount<'a>,
+    treasury_mint: &UncheckedAccount<'a>,
     owner: &AccountInfo<'a>,
     rent: &Sysvar<'a, Rent>,
     signer_seeds: &[&[u8]],
@@ -332,11 +1054,10 @@ pub fn create_program_token_account_if_not_present<'a>(
             &rent.to_account_info(),
             system_program,
             fee_payer,
-            spl_token::state::Account::LEN,
+            SplAccount::LEN,
             fee_seeds,
             signer_seeds,
         )?;
-        msg!("This.");
         invoke_signed(
             &initialize_account2(
                 token_program.key,
@@ -354,11 +1075,119 @@ pub fn create_program_token_account_if_not_present<'a>(
             ],
             &[signer_seeds],
         )?;
-        msg!("Passes");
     }
     Ok(())
 }
 
+/// Pay `referral` its share of `house_fee` from the escrow. The referrer's wallet is the next remaining account,
+/// followed by its treasury mint token account when the treasury is not native, which is created if missing.
+/// Returns the amount paid.
+#[allow(clippy::too_many_arguments)]
+pub fn pay_referral_fee<'a>(
+    remaining_accounts: &mut Iter<AccountInfo<'a>>,
+    referral: &Referral,
+    house_fee: u64,
+    escrow_payment_account: &AccountInfo<'a>,
+    payment_account_owner: &AccountInfo<'a>,
+    fee_payer: &AccountInfo<'a>,
+    treasury_mint: &AccountInfo<'a>,
+    ata_program: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rent: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+    fee_payer_seeds: &[&[u8]],
+    is_native: bool,
+) -> Result<u64> {
+    let referral_fee = (referral.basis_points as u128)
+        .checked_mul(house_fee as u128)
+        .ok_or(AuctionHouseError::NumericalOverflow)?
+        .checked_div(10000)
+        .ok_or(AuctionHouseError::NumericalOverflow)? as u64;
+    let referrer_info = next_account_info(remaining_accounts)?;
+    assert_keys_equal(referral.referrer, *referrer_info.key)?;
+    if !is_native {
+        let referrer_token_account_info = next_account_info(remaining_accounts)?;
+        if referrer_token_account_info.data_is_empty() {
+            make_ata(
+                referrer_token_account_info.to_account_info(),
+                referrer_info.to_account_info(),
+                treasury_mint.to_account_info(),
+                fee_payer.to_account_info(),
+                ata_program.to_account_info(),
+                token_program.to_account_info(),
+                system_program.to_account_info(),
+                rent.to_account_info(),
+                fee_payer_seeds,
+            )?;
+        }
+        assert_is_ata(
+            referrer_token_account_info,
+            referrer_info.key,
+            &treasury_mint.key(),
+        )?;
+        if referral_fee > 0 {
+            transfer_tokens(
+                token_program,
+                escrow_payment_account,
+                treasury_mint,
+                referrer_token_account_info,
+                payment_account_owner,
+                referral_fee,
+                &[signer_seeds],
+            )?;
+        }
+    } else if referral_fee > 0 {
+        invoke_signed(
+            &system_instruction::transfer(
+                escrow_payment_account.key,
+                referrer_info.key,
+                referral_fee,
+            ),
+            &[
+                escrow_payment_account.clone(),
+                referrer_info.clone(),
+                system_program.clone(),
+            ],
+            &[signer_seeds],
+        )?;
+    }
+
+    Ok(referral_fee)
+}
+
+/// Rate of the sale price, in basis points, the house's royalty policy pays the creators of the token `metadata_info`
+/// decorates. `royalty_choice` is the bid's chosen share of the metadata royalty, which only an optional policy honors.
+pub fn royalty_basis_points(
+    auction_house: &AuctionHouse,
+    metadata_info: &AccountInfo,
+    royalty_choice: Option<u16>,
+) -> Result<u16> {
+    let metadata = Metadata::from_account_info(metadata_info)?;
+    let royalty = metadata.data.seller_fee_basis_points;
+    match auction_house.royalty_policy {
+        RoyaltyPolicy::Full => Ok(royalty),
+        RoyaltyPolicy::Optional { min_basis_points } => {
+            // The minimum may have been raised since the bid chose its share.
+            let share = royalty_choice.unwrap_or(10000).max(min_basis_points).min(10000);
+            Ok((royalty as u32 * share as u32 / 10000) as u16)
+        }
+        RoyaltyPolicy::WaivedForCollections { collections } => match metadata.collection {
+            Some(c)
+                if c.verified
+                    && c.key != Pubkey::default()
+                    && collections.contains(&c.key) =>
+            {
+                Ok(0)
+            }
+            _ => Ok(royalty),
+        },
+    }
+}
+
+/// Pay the creators listed in `metadata_info` `royalty_basis_points` of `size` from the escrow, split by their shares.
+/// `unverified_creator_policy` decides what happens to the share of unverified creators. Every listed creator still
+/// needs its accounts in `remaining_accounts`. Returns what is left of `size`.
 #[allow(clippy::too_many_arguments)]
 pub fn pay_creator_fees<'a>(
     remaining_accounts: &mut Iter<AccountInfo<'a>>,
@@ -373,11 +1202,13 @@ pub fn pay_creator_fees<'a>(
     rent: &AccountInfo<'a>,
     signer_seeds: &[&[u8]],
     fee_payer_seeds: &[&[u8]],
+    royalty_basis_points: u16,
+    unverified_creator_policy: UnverifiedCreatorPolicy,
     size: u64,
     is_native: bool,
 ) -> Result<u64> {
     let metadata = Metadata::from_account_info(metadata_info)?;
-    let fees = metadata.data.seller_fee_basis_points;
+    let fees = royalty_basis_points;
     let total_fee = (fees as u128)
         .checked_mul(size as u128)
         .ok_or(AuctionHouseError::NumericalOverflow)?
@@ -389,20 +1220,42 @@ pub fn pay_creator_fees<'a>(
         .ok_or(AuctionHouseError::NumericalOverflow)?;
     match metadata.data.creators {
         Some(creators) => {
+            // Redistributing splits the royalty over the verified shares only.
+            let share_total = match unverified_creator_policy {
+                UnverifiedCreatorPolicy::Redistribute => creators
+                    .iter()
+                    .filter(|c| c.verified)
+                    .map(|c| c.share as u128)
+                    .sum(),
+                _ => 100,
+            };
             for creator in creators {
-                let pct = creator.share as u128;
-                let creator_fee =
-                    pct.checked_mul(total_fee as u128)
+                let paid = creator.verified
+                    || unverified_creator_policy == UnverifiedCreatorPolicy::PayAll;
+                let creator_fee = if paid && share_total > 0 {
+                    (creator.share as u128)
+                        .checked_mul(total_fee as u128)
                         .ok_or(AuctionHouseError::NumericalOverflow)?
-                        .checked_div(100)
-                        .ok_or(AuctionHouseError::NumericalOverflow)? as u64;
+                        .checked_div(share_total)
+                        .ok_or(AuctionHouseError::NumericalOverflow)? as u64
+                } else {
+                    0
+                };
                 remaining_fee = remaining_fee
                     .checked_sub(creator_fee)
                     .ok_or(AuctionHouseError::NumericalOverflow)?;
-                let current_creator_info = next_account_info(remaining_accounts)?;
-                assert_keys_equal(creator.address, *current_creator_info.key)?;
+                let current_creator_info = next_account_info(remaining_accounts)
+                    .map_err(|_| AuctionHouseError::CreatorAccountMismatch)?;
+                if creator.address != *current_creator_info.key {
+                    return err!(AuctionHouseError::CreatorAccountMismatch);
+                }
                 if !is_native {
-                    let current_creator_token_account_info = next_account_info(remaining_accounts)?;
+                    let current_creator_token_account_info = next_account_info(remaining_accounts)
+                        .map_err(|_| AuctionHouseError::CreatorAccountMismatch)?;
+                    // Creators that are not paid do not get a token account created for them.
+                    if !paid {
+                        continue;
+                    }
                     if current_creator_token_account_info.data_is_empty() {
                         make_ata(
                             current_creator_token_account_info.to_account_info(),
@@ -422,21 +1275,13 @@ pub fn pay_creator_fees<'a>(
                         &treasury_mint.key(),
                     )?;
                     if creator_fee > 0 {
-                        invoke_signed(
-                            &spl_token::instruction::transfer(
-                                token_program.key,
-                                escrow_payment_account.key,
-                                current_creator_token_account_info.key,
-                                payment_account_owner.key,
-                                &[],
-                                creator_fee,
-                            )?,
-                            &[
-                                escrow_payment_account.clone(),
-                                current_creator_token_account_info.clone(),
-                                token_program.clone(),
-                                payment_account_owner.clone(),
-                            ],
+                        transfer_tokens(
+                            token_program,
+                            escrow_payment_account,
+                            treasury_mint,
+                            current_creator_token_account_info,
+                            payment_account_owner,
+                            creator_fee,
                             &[signer_seeds],
                         )?;
                     }
@@ -467,25 +1312,14 @@ pub fn pay_creator_fees<'a>(
         .ok_or(AuctionHouseError::NumericalOverflow)?)
 }
 
-/// Cheap method to just grab mint Pubkey from token account, instead of deserializing entire thing
+/// Mint of a token account owned by either token program.
 pub fn get_mint_from_token_account(token_account_info: &AccountInfo) -> Result<Pubkey> {
-    // TokeAccount layout:   mint(32), owner(32), ...
-    let data = token_account_info.try_borrow_data()?;
-    let mint_data = array_ref![data, 0, 32];
-    Ok(Pubkey::new_from_array(*mint_data))
+    Ok(unpack_token_account(token_account_info)?.mint)
 }
 
-/// Cheap method to just grab delegate Pubkey from token account, instead of deserializing entire thing
+/// Delegate of a token account owned by either token program.
 pub fn get_delegate_from_token_account(token_account_info: &AccountInfo) -> Result<Option<Pubkey>> {
-    // TokeAccount layout:   mint(32), owner(32), ...
-    let data = token_account_info.try_borrow_data()?;
-    let key_data = array_ref![data, 76, 32];
-    let coption_data = u32::from_le_bytes(*array_ref![data, 72, 4]);
-    if coption_data == 0 {
-        Ok(None)
-    } else {
-        Ok(Some(Pubkey::new_from_array(*key_data)))
-    }
+    Ok(unpack_token_account(token_account_info)?.delegate.into())
 }
 
 /// Create account almost from scratch, lifted from
@@ -614,6 +1448,224 @@ pub fn assert_valid_trade_state(
     }
 }
 
+/// Check that `trade_state` is an order keyed by `seed` in place of a price, as found by
+/// [`find_seeded_trade_state_address`](crate::pda::find_seeded_trade_state_address).
+#[allow(clippy::too_many_arguments)]
+pub fn assert_valid_seeded_trade_state(
+    wallet: &Pubkey,
+    auction_house: &Account<AuctionHouse>,
+    seed: &str,
+    token_size: u64,
+    trade_state: &AccountInfo,
+    mint: &Pubkey,
+    token_holder: &Pubkey,
+    ts_bump: u8,
+) -> Result<u8> {
+    let canonical_bump = assert_derivation(
+        &crate::id(),
+        trade_state,
+        &[
+            PREFIX.as_bytes(),
+            wallet.as_ref(),
+            auction_house.key().as_ref(),
+            token_holder.as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            mint.as_ref(),
+            seed.as_bytes(),
+            &token_size.to_le_bytes(),
+        ],
+    )?;
+    if canonical_bump != ts_bump {
+        return Err(AuctionHouseError::DerivedKeyInvalid.into());
+    }
+
+    Ok(canonical_bump)
+}
+
+/// Whether `trade_state` holds an order; closed trade states are empty or have a zeroed bump.
+pub fn trade_state_is_open(trade_state: &AccountInfo) -> Result<bool> {
+    Ok(matches!(trade_state.try_borrow_data()?.first(), Some(bump) if *bump != 0))
+}
+
+/// Fill in a newly created trade state with its bump and the account that paid its rent.
+pub fn init_trade_state(trade_state: &AccountInfo, ts_bump: u8, rent_payer: &Pubkey) -> Result<()> {
+    let data = &mut trade_state.try_borrow_mut_data()?;
+    data[0] = ts_bump;
+    if data.len() >= TRADE_STATE_SIZE {
+        data[TRADE_STATE_RENT_PAYER_OFFSET..TRADE_STATE_RENT_PAYER_OFFSET + PUBKEY_BYTES]
+            .copy_from_slice(rent_payer.as_ref());
+    }
+
+    Ok(())
+}
+
+/// Set the unix timestamp after which a trade state can no longer be executed; `None` never expires.
+pub fn set_trade_state_expiry(trade_state: &AccountInfo, expiry: Option<i64>) -> Result<()> {
+    if let Some(expiry) = expiry {
+        if expiry <= Clock::get()?.unix_timestamp {
+            return Err(AuctionHouseError::InvalidExpiry.into());
+        }
+    }
+
+    let data = &mut trade_state.try_borrow_mut_data()?;
+    // Legacy trade states hold only their bump and have no room for one.
+    if data.len() < TRADE_STATE_SIZE {
+        return match expiry {
+            Some(_) => Err(AuctionHouseError::InvalidExpiry.into()),
+            None => Ok(()),
+        };
+    }
+    data[TRADE_STATE_EXPIRY_OFFSET..TRADE_STATE_EXPIRY_OFFSET + 8]
+        .copy_from_slice(&expiry.unwrap_or(0).to_le_bytes());
+
+    Ok(())
+}
+
+pub fn get_trade_state_expiry(trade_state: &AccountInfo) -> Result<Option<i64>> {
+    let data = trade_state.try_borrow_data()?;
+    if data.len() < TRADE_STATE_SIZE {
+        return Ok(None);
+    }
+    let expiry = i64::from_le_bytes(*array_ref![data, TRADE_STATE_EXPIRY_OFFSET, 8]);
+
+    Ok((expiry != 0).then_some(expiry))
+}
+
+pub fn get_trade_state_rent_payer(trade_state: &AccountInfo) -> Result<Option<Pubkey>> {
+    let data = trade_state.try_borrow_data()?;
+    if data.len() < TRADE_STATE_SIZE {
+        return Ok(None);
+    }
+
+    Ok(Some(Pubkey::new_from_array(*array_ref![
+        data,
+        TRADE_STATE_RENT_PAYER_OFFSET,
+        PUBKEY_BYTES
+    ])))
+}
+
+/// The account among `accounts` that paid `trade_state`'s rent, to refund it when the trade state closes. Legacy
+/// trade states did not record their payer and refund `fallback`.
+pub fn trade_state_rent_payer_info<'info>(
+    trade_state: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    fallback: &AccountInfo<'info>,
+) -> Result<AccountInfo<'info>> {
+    match get_trade_state_rent_payer(trade_state)? {
+        Some(rent_payer) => accounts
+            .iter()
+            .find(|account| account.key() == rent_payer)
+            .cloned()
+            .ok_or_else(|| AuctionHouseError::PublicKeyMismatch.into()),
+        None => Ok(fallback.clone()),
+    }
+}
+
+/// Record the frontend to pay a share of the house fee when `wallet`'s order settles; `None` clears it. A wallet can't
+/// refer its own order.
+pub fn set_trade_state_referral(
+    trade_state: &AccountInfo,
+    wallet: &Pubkey,
+    referral: Option<Referral>,
+    max_referral_basis_points: u16,
+) -> Result<()> {
+    if let Some(referral) = referral {
+        if referral.referrer == Pubkey::default()
+            || referral.referrer == *wallet
+            || referral.basis_points > max_referral_basis_points
+        {
+            return Err(AuctionHouseError::InvalidReferral.into());
+        }
+    }
+
+    let data = &mut trade_state.try_borrow_mut_data()?;
+    // Legacy trade states hold only their bump and have no room for one.
+    if data.len() < TRADE_STATE_SIZE {
+        return match referral {
+            Some(_) => Err(AuctionHouseError::InvalidReferral.into()),
+            None => Ok(()),
+        };
+    }
+    let (referrer, basis_points) = referral
+        .map(|referral| (referral.referrer, referral.basis_points))
+        .unwrap_or_default();
+    data[TRADE_STATE_REFERRER_OFFSET..TRADE_STATE_REFERRER_OFFSET + PUBKEY_BYTES]
+        .copy_from_slice(referrer.as_ref());
+    data[TRADE_STATE_REFERRAL_BASIS_POINTS_OFFSET..TRADE_STATE_REFERRAL_BASIS_POINTS_OFFSET + 2]
+        .copy_from_slice(&basis_points.to_le_bytes());
+
+    Ok(())
+}
+
+pub fn get_trade_state_referral(trade_state: &AccountInfo) -> Result<Option<Referral>> {
+    let data = trade_state.try_borrow_data()?;
+    if data.len() < TRADE_STATE_SIZE {
+        return Ok(None);
+    }
+    let referrer =
+        Pubkey::new_from_array(*array_ref![data, TRADE_STATE_REFERRER_OFFSET, PUBKEY_BYTES]);
+    let basis_points = u16::from_le_bytes(*array_ref![
+        data,
+        TRADE_STATE_REFERRAL_BASIS_POINTS_OFFSET,
+        2
+    ]);
+
+    Ok((referrer != Pubkey::default()).then_some(Referral {
+        referrer,
+        basis_points,
+    }))
+}
+
+/// Record the share of the metadata royalty, in basis points, a bid pays creators; `None` pays the full royalty.
+/// Only an optional royalty policy lets bids choose, down to its minimum.
+pub fn set_trade_state_royalty_choice(
+    trade_state: &AccountInfo,
+    royalty_choice: Option<u16>,
+    royalty_policy: &RoyaltyPolicy,
+) -> Result<()> {
+    if let Some(share) = royalty_choice {
+        match royalty_policy {
+            RoyaltyPolicy::Optional { min_basis_points }
+                if share >= *min_basis_points && share <= 10000 => {}
+            _ => return Err(AuctionHouseError::InvalidRoyaltyChoice.into()),
+        }
+    }
+
+    let data = &mut trade_state.try_borrow_mut_data()?;
+    // Legacy trade states hold only their bump and have no room for one.
+    if data.len() < TRADE_STATE_SIZE {
+        return match royalty_choice {
+            Some(_) => Err(AuctionHouseError::InvalidRoyaltyChoice.into()),
+            None => Ok(()),
+        };
+    }
+    data[TRADE_STATE_ROYALTY_CHOICE_OFFSET] = royalty_choice.is_some() as u8;
+    data[TRADE_STATE_ROYALTY_CHOICE_OFFSET + 1..TRADE_STATE_ROYALTY_CHOICE_OFFSET + 3]
+        .copy_from_slice(&royalty_choice.unwrap_or(0).to_le_bytes());
+
+    Ok(())
+}
+
+pub fn get_trade_state_royalty_choice(trade_state: &AccountInfo) -> Result<Option<u16>> {
+    let data = trade_state.try_borrow_data()?;
+    if data.len() < TRADE_STATE_SIZE || data[TRADE_STATE_ROYALTY_CHOICE_OFFSET] == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(u16::from_le_bytes(*array_ref![
+        data,
+        TRADE_STATE_ROYALTY_CHOICE_OFFSET + 1,
+        2
+    ])))
+}
+
+pub fn assert_trade_state_not_expired(trade_state: &AccountInfo, now: i64) -> Result<()> {
+    match get_trade_state_expiry(trade_state)? {
+        Some(expiry) if now >= expiry => Err(AuctionHouseError::TradeStateExpired.into()),
+        _ => Ok(()),
+    }
+}
+
 // This function verifies that there are enough funds in `account` such that `amount` can be
 // withdrawn.  If there are not sufficent funds it returns an error.  If there are sufficient
 // funds, it returns any additional amount needed to keep the account above the rent exempt
@@ -641,34 +1693,34 @@ pub fn verify_deposit(account: AccountInfo, amount: u64) -> Result<u64> {
     Ok(rent_minimum.saturating_sub(total))
 }
 
-// pub fn assert_valid_auctioneer_and_scope(
-//     auction_house_instance: &Account<AuctionHouse>,
-//     auctioneer_authority: &Pubkey,
-//     auctioneer_pda: &Account<Auctioneer>,
-//     scope: AuthorityScope,
-// ) -> Result<()> {
-//     // Assert the Auctioneer is tagged on the auction house
-//     assert_keys_equal(
-//         auction_house_instance.auctioneer_address,
-//         auctioneer_pda.key(),
-//     )
-//     .map_err(|_e| AuctionHouseError::InvalidAuctioneer)?;
-//     // Assert the auctioneer_authority is tagged in the Auctioneer
-//     assert_keys_equal(
-//         auctioneer_pda.auctioneer_authority,
-//         auctioneer_authority.key(),
-//     )
-//     .map_err(|_e| AuctionHouseError::InvalidAuctioneer)?;
-//     // Assert authority, auction house instance and scopes are correct.
-//     assert_keys_equal(auctioneer_pda.auction_house, auction_house_instance.key())
-//         .map_err(|_e| AuctionHouseError::InvalidAuctioneer)?;
-
-//     if !(auction_house_instance.scopes[scope as usize]) {
-//         return Err(AuctionHouseError::MissingAuctioneerScope.into());
-//     }
-
-//     Ok(())
-// }
+pub fn assert_valid_auctioneer_and_scope(
+    auction_house_instance: &Account<AuctionHouse>,
+    auctioneer_authority: &Pubkey,
+    auctioneer_pda: &Account<Auctioneer>,
+    scope: AuthorityScope,
+) -> Result<()> {
+    // Assert the Auctioneer is tagged on the auction house
+    assert_keys_equal(
+        auction_house_instance.auctioneer_address,
+        auctioneer_pda.key(),
+    )
+    .map_err(|_e| AuctionHouseError::InvalidAuctioneer)?;
+    // Assert the auctioneer_authority is tagged in the Auctioneer
+    assert_keys_equal(
+        auctioneer_pda.auctioneer_authority,
+        auctioneer_authority.key(),
+    )
+    .map_err(|_e| AuctionHouseError::InvalidAuctioneer)?;
+    // Assert authority, auction house instance and scopes are correct.
+    assert_keys_equal(auctioneer_pda.auction_house, auction_house_instance.key())
+        .map_err(|_e| AuctionHouseError::InvalidAuctioneer)?;
+
+    if !(auction_house_instance.scopes[scope as usize]) {
+        return Err(AuctionHouseError::MissingAuctioneerScope.into());
+    }
+
+    Ok(())
+}
 
 pub fn assert_scopes_eq(
     scopes: Vec<AuthorityScope>,
@@ -1,6 +1,6 @@
 use anchor_lang::{prelude::*, AnchorDeserialize};
 
-use crate::{constants::*, errors::*, utils::*, AuctionHouse, *};
+use crate::{constants::*, errors::*, events::*, utils::*, AuctionHouse, *};
 
 /// Accounts for the [`withdraw` handler](auction_house/fn.withdraw.html).
 #[derive(Accounts)]
@@ -28,8 +28,9 @@ pub struct Withdraw<'info> {
     )]
     pub escrow_payment_account: UncheckedAccount<'info>,
 
+    /// CHECK: Checked against the Auction House treasury mint.
     /// Auction House instance treasury mint account.
-    pub treasury_mint: Box<Account<'info, Mint>>,
+    pub treasury_mint: UncheckedAccount<'info>,
 
     /// CHECK: Validated in withdraw_logic.
     /// Auction House instance authority account.
@@ -62,7 +63,11 @@ pub struct Withdraw<'info> {
     )]
     pub auction_house_fee_account: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: Not dangerous. Checked to be SPL Token or Token-2022 in constraint.
+    #[account(
+        constraint = is_token_program(token_program.key) @ AuctionHouseError::InvalidTokenProgram
+    )]
+    pub token_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
     pub ata_program: Program<'info, AssociatedToken>,
     pub rent: Sysvar<'info, Rent>,
@@ -143,7 +148,7 @@ fn withdraw_logic<'info>(
 
     let is_native = treasury_mint.key() == spl_token::native_mint::id();
 
-    if !is_native {
+    let withdrawn = if !is_native {
         if receipt_account.data_is_empty() {
             make_ata(
                 receipt_account.to_account_info(),
@@ -171,23 +176,16 @@ fn withdraw_logic<'info>(
         }
 
         assert_is_ata(receipt_account, &wallet.key(), &treasury_mint.key())?;
-        invoke_signed(
-            &spl_token::instruction::transfer(
-                token_program.key,
-                &escrow_payment_account.key(),
-                &receipt_account.key(),
-                &auction_house.key(),
-                &[],
-                amount,
-            )?,
-            &[
-                escrow_payment_account.to_account_info(),
-                receipt_account.to_account_info(),
-                token_program.to_account_info(),
-                auction_house.to_account_info(),
-            ],
+        transfer_tokens(
+            token_program,
+            escrow_payment_account,
+            treasury_mint,
+            receipt_account,
+            &auction_house.to_account_info(),
+            amount,
             &[&ah_seeds],
         )?;
+        amount
     } else {
         assert_keys_equal(receipt_account.key(), wallet.key())?;
         let rent_shortfall = verify_withdrawal(escrow_payment_account.to_account_info(), amount)?;
@@ -208,7 +206,15 @@ fn withdraw_logic<'info>(
             ],
             &[&escrow_signer_seeds],
         )?;
-    }
+        checked_amount
+    };
+
+    emit!(Withdrew {
+        auction_house: auction_house_key,
+        wallet: wallet_key,
+        amount: withdrawn,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
 
     Ok(())
 }
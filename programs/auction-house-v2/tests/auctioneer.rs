@@ -0,0 +1,213 @@
+mod utils;
+
+use auction_house_v2::{errors::AuctionHouseError, pda::find_auctioneer_pda, AuthorityScope};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+async fn native_house(context: &mut ProgramTestContext) -> TestAuctionHouse {
+    create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await
+}
+
+async fn delegate(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    scopes: Vec<AuthorityScope>,
+) -> Keypair {
+    let auctioneer = Keypair::new();
+    // The authority pays rent for the Auctioneer PDA.
+    airdrop(context, &ah.authority.pubkey(), ONE_SOL).await;
+    let delegate = delegate_auctioneer_ix(ah, &auctioneer.pubkey(), scopes);
+    process_tx(context, &[delegate], &[&ah.authority])
+        .await
+        .unwrap();
+    auctioneer
+}
+
+#[tokio::test]
+async fn delegate_update_and_revoke() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let auctioneer = delegate(
+        &mut context,
+        &ah,
+        vec![AuthorityScope::Sell, AuthorityScope::Buy],
+    )
+    .await;
+    let (ah_auctioneer_pda, bump) = find_auctioneer_pda(&ah.address, &auctioneer.pubkey());
+
+    let house = get_auction_house(&mut context, &ah.address).await;
+    assert!(house.has_auctioneer);
+    assert_eq!(house.auctioneer_address, ah_auctioneer_pda);
+    assert!(house.scopes[AuthorityScope::Sell as usize]);
+    assert!(house.scopes[AuthorityScope::Buy as usize]);
+    assert!(!house.scopes[AuthorityScope::ExecuteSale as usize]);
+    let pda: auction_house_v2::Auctioneer =
+        get_anchor_account(&mut context, &ah_auctioneer_pda).await;
+    assert_eq!(pda.auctioneer_authority, auctioneer.pubkey());
+    assert_eq!(pda.auction_house, ah.address);
+    assert_eq!(pda.bump, bump);
+
+    // Updating replaces the scopes rather than adding to them.
+    let update = update_auctioneer_ix(&ah, &auctioneer.pubkey(), vec![AuthorityScope::Cancel]);
+    process_tx(&mut context, &[update], &[&ah.authority])
+        .await
+        .unwrap();
+    let house = get_auction_house(&mut context, &ah.address).await;
+    assert!(house.scopes[AuthorityScope::Cancel as usize]);
+    assert!(!house.scopes[AuthorityScope::Sell as usize]);
+    assert!(!house.scopes[AuthorityScope::Buy as usize]);
+
+    let revoke = revoke_auctioneer_ix(&ah, &auctioneer.pubkey());
+    process_tx(&mut context, &[revoke], &[&ah.authority])
+        .await
+        .unwrap();
+    let house = get_auction_house(&mut context, &ah.address).await;
+    assert!(!house.has_auctioneer);
+    assert_eq!(house.auctioneer_address, Default::default());
+    assert!(house.scopes.iter().all(|scope| !scope));
+    assert!(get_account(&mut context, &ah_auctioneer_pda)
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn only_one_auctioneer_per_house() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    delegate(&mut context, &ah, vec![AuthorityScope::Sell]).await;
+
+    let other = Keypair::new();
+    let delegate = delegate_auctioneer_ix(&ah, &other.pubkey(), vec![AuthorityScope::Sell]);
+    let result = process_tx(&mut context, &[delegate], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::AuctionHouseAlreadyDelegated);
+}
+
+#[tokio::test]
+async fn delegated_scope_requires_auctioneer_handler() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    delegate(&mut context, &ah, vec![AuthorityScope::Sell]).await;
+    let seller = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let result = process_tx(&mut context, &[sell], &[&seller]).await;
+    assert_auction_house_error(result, AuctionHouseError::MustUseAuctioneerHandler);
+
+    // Scopes that were not delegated keep working through the plain handlers.
+    let buyer = Keypair::new();
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+}
+
+#[tokio::test]
+async fn auctioneer_handler_requires_scope() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let auctioneer = delegate(&mut context, &ah, vec![AuthorityScope::Buy]).await;
+    let seller = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let sell = auctioneer_sell_ix(&ah, &auctioneer.pubkey(), &seller.pubkey(), &nft, 1);
+    let result = process_tx(&mut context, &[sell], &[&seller, &auctioneer]).await;
+    assert_auction_house_error(result, AuctionHouseError::MissingAuctioneerScope);
+}
+
+#[tokio::test]
+async fn auctioneer_settles_listing_at_bid_price() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let auctioneer = delegate(
+        &mut context,
+        &ah,
+        vec![
+            AuthorityScope::Sell,
+            AuthorityScope::Buy,
+            AuthorityScope::ExecuteSale,
+        ],
+    )
+    .await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let sell = auctioneer_sell_ix(&ah, &auctioneer.pubkey(), &seller.pubkey(), &nft, 1);
+    process_tx(&mut context, &[sell], &[&seller, &auctioneer])
+        .await
+        .unwrap();
+    let listing = trade_state_address(&ah, &seller.pubkey(), &nft, u64::MAX, 1);
+    assert!(get_account(&mut context, &listing).await.is_some());
+
+    let price = ONE_SOL / 2;
+    let buy = auctioneer_buy_ix(&ah, &auctioneer.pubkey(), &buyer.pubkey(), &nft, price, 1);
+    process_tx(&mut context, &[buy], &[&buyer, &auctioneer])
+        .await
+        .unwrap();
+
+    let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+    let listing_rent = get_lamports(&mut context, &listing).await;
+    let execute_sale = auctioneer_execute_sale_ix(
+        &ah,
+        &auctioneer.pubkey(),
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        price,
+        1,
+    );
+    process_tx(&mut context, &[execute_sale], &[&ah.authority, &auctioneer])
+        .await
+        .unwrap();
+
+    let (escrow_nft, _) =
+        find_escrow_nft_address(&ah.address, &seller.pubkey(), &nft.mint, &buyer.pubkey());
+    assert_eq!(get_token_balance(&mut context, &escrow_nft).await, 1);
+    // The seller paid for the listing, so its rent comes back with the proceeds.
+    assert_eq!(
+        get_lamports(&mut context, &seller.pubkey()).await,
+        seller_lamports + price + listing_rent
+    );
+    assert!(get_account(&mut context, &listing).await.is_none());
+}
+
+#[tokio::test]
+async fn auctioneer_cancels_listing() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let auctioneer = delegate(
+        &mut context,
+        &ah,
+        vec![AuthorityScope::Sell, AuthorityScope::Cancel],
+    )
+    .await;
+    let seller = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let sell = auctioneer_sell_ix(&ah, &auctioneer.pubkey(), &seller.pubkey(), &nft, 1);
+    process_tx(&mut context, &[sell], &[&seller, &auctioneer])
+        .await
+        .unwrap();
+
+    let cancel = auctioneer_cancel_ix(
+        &ah,
+        &auctioneer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        u64::MAX,
+        1,
+    );
+    process_tx(&mut context, &[cancel], &[&seller, &auctioneer])
+        .await
+        .unwrap();
+
+    let listing = trade_state_address(&ah, &seller.pubkey(), &nft, u64::MAX, 1);
+    assert!(get_account(&mut context, &listing).await.is_none());
+}
@@ -0,0 +1,184 @@
+mod utils;
+
+use auction_house_v2::{errors::AuctionHouseError, BundleListing};
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const HOUSE_FEE_BPS: u16 = 250;
+const ROYALTY_BPS: [u16; 3] = [500, 1_000, 0];
+
+/// Native house, a funded buyer and a seller holding three NFTs, each with its own creator and royalty.
+async fn setup(
+    context: &mut ProgramTestContext,
+) -> (TestAuctionHouse, Keypair, Keypair, Vec<TestNft>) {
+    let ah = create_auction_house(
+        context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let mut nfts = vec![];
+    for royalty in ROYALTY_BPS {
+        let creator = Keypair::new().pubkey();
+        airdrop(context, &creator, ONE_SOL).await;
+        nfts.push(create_nft(context, &seller.pubkey(), &[(creator, 100)], royalty, 1).await);
+    }
+    (ah, seller, buyer, nfts)
+}
+
+async fn delegate_of(context: &mut ProgramTestContext, nft: &TestNft) -> COption<Pubkey> {
+    let account = get_account(context, &nft.token_account).await.unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .delegate
+}
+
+#[tokio::test]
+async fn bundle_sale_splits_royalties_by_weight() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nfts) = setup(&mut context).await;
+    let items: Vec<&TestNft> = nfts.iter().collect();
+    let price = ONE_SOL;
+    let listing = create_bundle_listing_ix(&ah, &seller.pubkey(), &items, vec![1, 1, 2], price);
+    process_tx(&mut context, &[listing], &[&seller])
+        .await
+        .unwrap();
+    let bundle_listing = bundle_listing_address(&ah, &seller.pubkey(), &nfts[0]);
+    let recorded: BundleListing = get_anchor_account(&mut context, &bundle_listing).await;
+    assert_eq!(recorded.items.len(), 3);
+    assert_eq!(recorded.items[2].weight, 2);
+
+    let mut creators_before = vec![];
+    for nft in &nfts {
+        creators_before.push(get_lamports(&mut context, &nft.creators[0]).await);
+    }
+    let seller_before = get_lamports(&mut context, &seller.pubkey()).await;
+    let treasury_before = get_lamports(&mut context, &ah.treasury).await;
+    let listing_rent = get_lamports(&mut context, &bundle_listing).await;
+    let sale = execute_bundle_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &items, price);
+    process_tx(&mut context, &[sale], &[&buyer]).await.unwrap();
+
+    let shares = [price / 4, price / 4, price / 2];
+    let mut royalties = 0;
+    for ((nft, share), (royalty, before)) in nfts
+        .iter()
+        .zip(shares)
+        .zip(ROYALTY_BPS.iter().zip(creators_before))
+    {
+        let paid = share * *royalty as u64 / 10_000;
+        assert_eq!(
+            get_lamports(&mut context, &nft.creators[0]).await - before,
+            paid
+        );
+        royalties += paid;
+        assert_eq!(
+            get_token_balance(&mut context, &nft.token_account_of(&buyer.pubkey())).await,
+            1
+        );
+        assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 0);
+    }
+    let house_fee = price * HOUSE_FEE_BPS as u64 / 10_000;
+    assert_eq!(
+        get_lamports(&mut context, &ah.treasury).await - treasury_before,
+        house_fee
+    );
+    assert_eq!(
+        get_lamports(&mut context, &seller.pubkey()).await - seller_before,
+        price - royalties - house_fee + listing_rent
+    );
+    assert_eq!(get_lamports(&mut context, &bundle_listing).await, 0);
+}
+
+#[tokio::test]
+async fn cancel_bundle_listing_revokes_every_delegate() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, nfts) = setup(&mut context).await;
+    let items: Vec<&TestNft> = nfts.iter().collect();
+    let listing = create_bundle_listing_ix(&ah, &seller.pubkey(), &items, vec![1, 1, 1], ONE_SOL);
+    process_tx(&mut context, &[listing], &[&seller])
+        .await
+        .unwrap();
+    for nft in &nfts {
+        assert!(delegate_of(&mut context, nft).await.is_some());
+    }
+
+    let cancel = cancel_bundle_listing_ix(&ah, &seller.pubkey(), &items);
+    process_tx(&mut context, &[cancel], &[&seller])
+        .await
+        .unwrap();
+
+    for nft in &nfts {
+        assert_eq!(delegate_of(&mut context, nft).await, COption::None);
+    }
+    let bundle_listing = bundle_listing_address(&ah, &seller.pubkey(), &nfts[0]);
+    assert_eq!(get_lamports(&mut context, &bundle_listing).await, 0);
+}
+
+#[tokio::test]
+async fn bundle_sale_rejects_items_out_of_order() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nfts) = setup(&mut context).await;
+    let listing = create_bundle_listing_ix(
+        &ah,
+        &seller.pubkey(),
+        &[&nfts[0], &nfts[1], &nfts[2]],
+        vec![1, 1, 1],
+        ONE_SOL,
+    );
+    process_tx(&mut context, &[listing], &[&seller])
+        .await
+        .unwrap();
+
+    let sale = execute_bundle_sale_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &[&nfts[0], &nfts[2], &nfts[1]],
+        ONE_SOL,
+    );
+    let result = process_tx(&mut context, &[sale], &[&buyer]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::BundleItemMismatch);
+}
+
+#[tokio::test]
+async fn bundle_sale_rejects_other_price() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nfts) = setup(&mut context).await;
+    let items: Vec<&TestNft> = nfts.iter().collect();
+    let listing = create_bundle_listing_ix(&ah, &seller.pubkey(), &items, vec![1, 1, 1], ONE_SOL);
+    process_tx(&mut context, &[listing], &[&seller])
+        .await
+        .unwrap();
+
+    let sale = execute_bundle_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &items, ONE_SOL / 2);
+    let result = process_tx(&mut context, &[sale], &[&buyer]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::BundlePriceMismatch);
+}
+
+#[tokio::test]
+async fn bundle_listing_requires_a_weight_per_item() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, nfts) = setup(&mut context).await;
+
+    let listing = create_bundle_listing_ix(
+        &ah,
+        &seller.pubkey(),
+        &[&nfts[0], &nfts[1]],
+        vec![1],
+        ONE_SOL,
+    );
+    let result = process_tx(&mut context, &[listing], &[&seller]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InvalidBundle);
+}
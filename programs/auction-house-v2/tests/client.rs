@@ -0,0 +1,246 @@
+mod utils;
+
+use auction_house_v2::{client::*, pda::*, receipt::*};
+use solana_program_test::*;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use utils::*;
+
+const ROYALTY_BPS: u16 = 500;
+
+struct Trade {
+    ah: AuctionHouseKeys,
+    authority: Keypair,
+    seller: Keypair,
+    buyer: Keypair,
+    creator: Pubkey,
+    tokens: TokenKeys,
+}
+
+async fn setup(context: &mut ProgramTestContext) -> Trade {
+    let authority = Keypair::new();
+    let create = CreateAuctionHouseBuilder::new(
+        &context.payer.pubkey(),
+        &authority.pubkey(),
+        &spl_token::native_mint::id(),
+    )
+    .seller_fee_basis_points(250);
+    process_tx(context, &[create.instruction()], &[])
+        .await
+        .unwrap();
+    let ah = create.keys();
+
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    let creator = Keypair::new().pubkey();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    airdrop(context, &creator, ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    airdrop(context, &authority.pubkey(), ONE_SOL).await;
+    let nft = create_nft(context, &seller.pubkey(), &[(creator, 100)], ROYALTY_BPS, 1).await;
+    let tokens = TokenKeys::new(&seller.pubkey(), &nft.mint);
+    assert_eq!(tokens.token_account, nft.token_account);
+    assert_eq!(tokens.metadata, nft.metadata);
+
+    Trade {
+        ah,
+        authority,
+        seller,
+        buyer,
+        creator,
+        tokens,
+    }
+}
+
+#[tokio::test]
+async fn builders_settle_a_sale_with_receipts() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let trade = setup(&mut context).await;
+    let account = get_account(&mut context, &trade.ah.address).await.unwrap();
+    let auction_house = decode_auction_house(&account.data).unwrap();
+    assert_eq!(
+        AuctionHouseKeys::from_account(trade.ah.address, &auction_house),
+        trade.ah
+    );
+    assert_eq!(auction_house.seller_fee_basis_points, 250);
+
+    let sell = SellBuilder::new(&trade.ah, &trade.tokens, ONE_SOL);
+    let seller_trade_state = sell.trade_state();
+    let listing_receipt =
+        PrintListingReceiptBuilder::new(&trade.seller.pubkey(), &seller_trade_state);
+    process_tx(
+        &mut context,
+        &[sell.instruction(), listing_receipt.instruction()],
+        &[&trade.seller],
+    )
+    .await
+    .unwrap();
+
+    let buy = BuyBuilder::new(&trade.ah, &trade.buyer.pubkey(), &trade.tokens, ONE_SOL);
+    let buyer_trade_state = buy.trade_state();
+    let bid_receipt = PrintBidReceiptBuilder::new(&trade.buyer.pubkey(), &buyer_trade_state);
+    process_tx(
+        &mut context,
+        &[buy.instruction(), bid_receipt.instruction()],
+        &[&trade.buyer],
+    )
+    .await
+    .unwrap();
+
+    let execute_sale =
+        ExecuteSaleBuilder::new(&trade.ah, &trade.buyer.pubkey(), &trade.tokens, ONE_SOL)
+            .creators(&[trade.creator]);
+    assert_eq!(execute_sale.seller_trade_state(), seller_trade_state);
+    assert_eq!(execute_sale.buyer_trade_state(), buyer_trade_state);
+    let purchase_receipt = PrintPurchaseReceiptBuilder::new(
+        &trade.authority.pubkey(),
+        &seller_trade_state,
+        &buyer_trade_state,
+    );
+    let creator_lamports = get_lamports(&mut context, &trade.creator).await;
+    process_tx(
+        &mut context,
+        &[execute_sale.instruction(), purchase_receipt.instruction()],
+        &[&trade.authority],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        get_lamports(&mut context, &trade.creator).await,
+        creator_lamports + ONE_SOL * ROYALTY_BPS as u64 / 10_000
+    );
+    let (purchase_receipt_address, _) =
+        find_purchase_receipt_address(&seller_trade_state, &buyer_trade_state);
+    let account = get_account(&mut context, &purchase_receipt_address)
+        .await
+        .unwrap();
+    let purchase = decode_purchase_receipt(&account.data).unwrap();
+    assert_eq!(purchase.buyer, trade.buyer.pubkey());
+    assert_eq!(purchase.seller, trade.seller.pubkey());
+    assert_eq!(purchase.price, ONE_SOL);
+
+    let (address, _) = find_listing_receipt_address(&seller_trade_state);
+    let account = get_account(&mut context, &address).await.unwrap();
+    let listing = decode_listing_receipt(&account.data).unwrap();
+    assert_eq!(listing.seller, trade.seller.pubkey());
+    assert_eq!(listing.purchase_receipt, Some(purchase_receipt_address));
+
+    let (address, _) = find_bid_receipt_address(&buyer_trade_state);
+    let account = get_account(&mut context, &address).await.unwrap();
+    let bid = decode_bid_receipt(&account.data).unwrap();
+    assert_eq!(bid.buyer, trade.buyer.pubkey());
+    assert_eq!(bid.token_account, Some(trade.tokens.token_account));
+    assert_eq!(bid.purchase_receipt, Some(purchase_receipt_address));
+    assert!(decode_listing_receipt(&account.data).is_err());
+}
+
+#[tokio::test]
+async fn builders_cancel_listing_and_move_escrow_funds() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let trade = setup(&mut context).await;
+    let sell = SellBuilder::new(&trade.ah, &trade.tokens, ONE_SOL);
+    let seller_trade_state = sell.trade_state();
+    let listing_receipt =
+        PrintListingReceiptBuilder::new(&trade.seller.pubkey(), &seller_trade_state);
+    process_tx(
+        &mut context,
+        &[sell.instruction(), listing_receipt.instruction()],
+        &[&trade.seller],
+    )
+    .await
+    .unwrap();
+
+    let cancel = CancelBuilder::new(&trade.ah, &trade.seller.pubkey(), &trade.tokens, ONE_SOL);
+    assert_eq!(cancel.trade_state(), seller_trade_state);
+    let cancel_receipt = CancelListingReceiptBuilder::new(&seller_trade_state);
+    process_tx(
+        &mut context,
+        &[cancel.instruction(), cancel_receipt.instruction()],
+        &[&trade.seller],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(get_lamports(&mut context, &seller_trade_state).await, 0);
+    let (address, _) = find_listing_receipt_address(&seller_trade_state);
+    let account = get_account(&mut context, &address).await.unwrap();
+    assert!(decode_listing_receipt(&account.data)
+        .unwrap()
+        .canceled_at
+        .is_some());
+
+    let buyer = trade.buyer.pubkey();
+    let (escrow, _) = find_escrow_payment_address(&trade.ah.address, &buyer);
+    let deposit = DepositBuilder::new(&trade.ah, &buyer, ONE_SOL);
+    process_tx(&mut context, &[deposit.instruction()], &[&trade.buyer])
+        .await
+        .unwrap();
+    let escrow_lamports = get_lamports(&mut context, &escrow).await;
+    let withdraw = WithdrawBuilder::new(&trade.ah, &buyer, ONE_SOL / 2);
+    process_tx(&mut context, &[withdraw.instruction()], &[&trade.buyer])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_lamports(&mut context, &escrow).await,
+        escrow_lamports - ONE_SOL / 2
+    );
+}
+
+#[test]
+fn builders_place_receipt_accounts_where_receipts_read_them() {
+    let ah = AuctionHouseKeys::new(&Pubkey::new_unique(), &spl_token::native_mint::id());
+    let seller = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let tokens = TokenKeys::new(&seller, &Pubkey::new_unique());
+
+    let sell = SellBuilder::new(&ah, &tokens, ONE_SOL);
+    let accounts = sell.instruction().accounts;
+    assert_eq!(accounts[LISTING_WALLET_INDEX].pubkey, seller);
+    assert_eq!(accounts[LISTING_METADATA_INDEX].pubkey, tokens.metadata);
+    assert_eq!(accounts[LISTING_AUCTION_HOUSE_INDEX].pubkey, ah.address);
+    assert_eq!(
+        accounts[LISTING_TRADE_STATE_INDEX].pubkey,
+        sell.trade_state()
+    );
+
+    for buy in [
+        BuyBuilder::new(&ah, &buyer, &tokens, ONE_SOL),
+        BuyBuilder::new(&ah, &buyer, &tokens, ONE_SOL).public(),
+    ] {
+        let accounts = buy.instruction().accounts;
+        assert_eq!(accounts[BID_WALLET_INDEX].pubkey, buyer);
+        assert_eq!(
+            accounts[BID_TOKEN_ACCOUNT_INDEX].pubkey,
+            tokens.token_account
+        );
+        assert_eq!(accounts[BID_METADATA_INDEX].pubkey, tokens.metadata);
+        assert_eq!(accounts[BID_AUCTION_HOUSE_INDEX].pubkey, ah.address);
+        assert_eq!(accounts[BID_TRADE_STATE_INDEX].pubkey, buy.trade_state());
+    }
+
+    let execute_sale = ExecuteSaleBuilder::new(&ah, &buyer, &tokens, ONE_SOL);
+    let accounts = execute_sale.instruction().accounts;
+    assert_eq!(accounts[PURCHASE_BUYER_INDEX].pubkey, buyer);
+    assert_eq!(accounts[PURCHASE_SELLER_INDEX].pubkey, seller);
+    assert_eq!(accounts[PURCHASE_METADATA_INDEX].pubkey, tokens.metadata);
+    assert_eq!(accounts[PURCHASE_AUCTION_HOUSE_INDEX].pubkey, ah.address);
+    assert_eq!(
+        accounts[PURCHASE_BUYER_TRADE_STATE_INDEX].pubkey,
+        execute_sale.buyer_trade_state()
+    );
+    assert_eq!(
+        accounts[PURCHASE_SELLER_TRADE_STATE_INDEX].pubkey,
+        execute_sale.seller_trade_state()
+    );
+
+    let cancel = CancelBuilder::new(&ah, &seller, &tokens, ONE_SOL);
+    let accounts = cancel.instruction().accounts;
+    assert_eq!(
+        accounts[CANCEL_TRADE_STATE_INDEX].pubkey,
+        cancel.trade_state()
+    );
+}
@@ -0,0 +1,172 @@
+mod utils;
+
+use anchor_lang::error::ErrorCode;
+use auction_house_v2::{
+    constants::COLLECTION_BID, errors::AuctionHouseError, pda::find_escrow_payment_address,
+    CollectionBid,
+};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const PRICE: u64 = ONE_SOL;
+
+/// Native house with a collection and a buyer bidding `PRICE` for `quantity` of its items.
+async fn place_collection_bid(
+    context: &mut ProgramTestContext,
+    quantity: u64,
+) -> (TestAuctionHouse, TestCollection, Keypair) {
+    let ah = create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let collection = create_collection(context).await;
+    let buyer = Keypair::new();
+    airdrop(context, &buyer.pubkey(), ONE_SOL + PRICE * quantity).await;
+
+    let bid = collection_buy_ix(&ah, &buyer.pubkey(), &collection.mint, PRICE, quantity);
+    process_tx(context, &[bid], &[&buyer]).await.unwrap();
+
+    (ah, collection, buyer)
+}
+
+/// A seller listing an item of `collection` at `PRICE`.
+async fn list_item(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    collection: &TestCollection,
+    verified: bool,
+) -> (Keypair, TestNft) {
+    let seller = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_collection_nft(context, &seller.pubkey(), collection, verified).await;
+    let sell = sell_ix(ah, &seller.pubkey(), &nft, PRICE, 1);
+    process_tx(context, &[sell], &[&seller]).await.unwrap();
+    (seller, nft)
+}
+
+#[tokio::test]
+async fn collection_bid_fills_across_sales() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, collection, buyer) = place_collection_bid(&mut context, 2).await;
+    let (escrow, _) = find_escrow_payment_address(&ah.address, &buyer.pubkey());
+    let collection_bid = collection_bid_address(&ah, &buyer.pubkey(), &collection.mint, PRICE);
+    let escrow_lamports = get_lamports(&mut context, &escrow).await;
+
+    for filled in 1..=2 {
+        let (seller, nft) = list_item(&mut context, &ah, &collection, true).await;
+        let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+        let listing = trade_state_address(&ah, &seller.pubkey(), &nft, PRICE, 1);
+        let listing_rent = get_lamports(&mut context, &listing).await;
+        let execute_sale = execute_collection_sale_ix(
+            &ah,
+            &buyer.pubkey(),
+            &seller.pubkey(),
+            &nft,
+            &collection.mint,
+            PRICE,
+        );
+        process_tx(&mut context, &[execute_sale], &[&ah.authority])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            get_lamports(&mut context, &seller.pubkey()).await,
+            seller_lamports + PRICE + listing_rent
+        );
+        assert_eq!(
+            get_lamports(&mut context, &escrow).await,
+            escrow_lamports - PRICE * filled
+        );
+        let (escrow_nft, _) =
+            find_escrow_nft_address(&ah.address, &seller.pubkey(), &nft.mint, &buyer.pubkey());
+        assert_eq!(get_token_balance(&mut context, &escrow_nft).await, 1);
+        let buyer_trade_state =
+            seeded_trade_state_address(&ah, &buyer.pubkey(), &nft, COLLECTION_BID);
+        assert!(get_account(&mut context, &buyer_trade_state)
+            .await
+            .is_none());
+
+        if filled == 1 {
+            let bid: CollectionBid = get_anchor_account(&mut context, &collection_bid).await;
+            assert_eq!(bid.quantity, 1);
+        }
+    }
+
+    // The filled bid is closed.
+    assert!(get_account(&mut context, &collection_bid).await.is_none());
+}
+
+#[tokio::test]
+async fn collection_fill_leaves_private_bid_open() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, collection, buyer) = place_collection_bid(&mut context, 1).await;
+    let (seller, nft) = list_item(&mut context, &ah, &collection, true).await;
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+
+    let execute_sale = execute_collection_sale_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &collection.mint,
+        PRICE,
+    );
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    // The fill consumed the collection bid only.
+    let collection_bid = collection_bid_address(&ah, &buyer.pubkey(), &collection.mint, PRICE);
+    assert!(get_account(&mut context, &collection_bid).await.is_none());
+    let private_bid = trade_state_address(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    assert!(get_account(&mut context, &private_bid).await.is_some());
+}
+
+#[tokio::test]
+async fn collection_sale_requires_verified_member() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, collection, buyer) = place_collection_bid(&mut context, 1).await;
+    let other_collection = create_collection(&mut context).await;
+
+    for (item_collection, verified) in [(&collection, false), (&other_collection, true)] {
+        let (seller, nft) = list_item(&mut context, &ah, item_collection, verified).await;
+        let execute_sale = execute_collection_sale_ix(
+            &ah,
+            &buyer.pubkey(),
+            &seller.pubkey(),
+            &nft,
+            &collection.mint,
+            PRICE,
+        );
+        let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+        assert_auction_house_error(result, AuctionHouseError::NotInCollection);
+    }
+}
+
+#[tokio::test]
+async fn cancel_collection_bid_keeps_escrow() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, collection, buyer) = place_collection_bid(&mut context, 1).await;
+    let (escrow, _) = find_escrow_payment_address(&ah.address, &buyer.pubkey());
+    let escrow_lamports = get_lamports(&mut context, &escrow).await;
+
+    let cancel = cancel_collection_bid_ix(&ah, &buyer.pubkey(), &collection.mint, PRICE);
+    process_tx(&mut context, &[cancel], &[&buyer])
+        .await
+        .unwrap();
+    let collection_bid = collection_bid_address(&ah, &buyer.pubkey(), &collection.mint, PRICE);
+    assert!(get_account(&mut context, &collection_bid).await.is_none());
+    assert_eq!(get_lamports(&mut context, &escrow).await, escrow_lamports);
+
+    let (seller, nft) = list_item(&mut context, &ah, &collection, true).await;
+    let execute_sale = execute_collection_sale_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &collection.mint,
+        PRICE,
+    );
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+    assert_anchor_error(result, ErrorCode::AccountNotInitialized);
+}
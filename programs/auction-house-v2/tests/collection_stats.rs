@@ -0,0 +1,124 @@
+mod utils;
+
+use auction_house_v2::{
+    constants::{COLLECTION_STATS_DAYS, SECONDS_PER_DAY},
+    errors::AuctionHouseError,
+    CollectionStats,
+};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const PRICE: u64 = ONE_SOL;
+
+/// Sell a fresh verified member of `collection` from `seller` to `buyer` at `price` at `unix_timestamp`, returning
+/// the collection's stats.
+async fn sell_member_at(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    collection: &TestCollection,
+    seller: &Keypair,
+    buyer: &Keypair,
+    price: u64,
+    unix_timestamp: i64,
+) -> CollectionStats {
+    set_clock(context, unix_timestamp).await;
+    let nft = create_collection_nft(context, &seller.pubkey(), collection, true).await;
+    list_and_bid(context, ah, seller, buyer, &nft, price).await;
+    let execute_sale = execute_sale_ix(ah, &buyer.pubkey(), &seller.pubkey(), &nft, price, 1);
+    process_tx(context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    get_anchor_account(context, &collection_stats_address(ah, &collection.mint)).await
+}
+
+fn bucket(unix_timestamp: i64) -> usize {
+    (unix_timestamp / SECONDS_PER_DAY) as usize % COLLECTION_STATS_DAYS
+}
+
+#[tokio::test]
+async fn sales_update_collection_stats() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let collection = create_collection(&mut context).await;
+    let start = get_clock(&mut context).await.unix_timestamp;
+
+    let stats = sell_member_at(
+        &mut context,
+        &ah,
+        &collection,
+        &seller,
+        &buyer,
+        PRICE,
+        start,
+    )
+    .await;
+    assert_eq!(stats.auction_house, ah.address);
+    assert_eq!(stats.collection, collection.mint);
+    assert_eq!(stats.last_sale_price, PRICE);
+    assert_eq!(stats.last_sale_at, start);
+    assert_eq!(stats.volume, PRICE);
+    assert_eq!(stats.sale_count, 1);
+    assert_eq!(stats.daily_volume[bucket(start)], PRICE);
+    assert_eq!(stats.current_day, start / SECONDS_PER_DAY);
+    assert_eq!(stats.twap, PRICE);
+    assert_eq!(stats.floor, PRICE);
+
+    // The first price stood for a day when the second sale lands, and the second sale isn't weighted yet.
+    let next_day = start + SECONDS_PER_DAY;
+    let stats = sell_member_at(
+        &mut context,
+        &ah,
+        &collection,
+        &seller,
+        &buyer,
+        2 * PRICE,
+        next_day,
+    )
+    .await;
+    assert_eq!(stats.last_sale_price, 2 * PRICE);
+    assert_eq!(stats.volume, 3 * PRICE);
+    assert_eq!(stats.sale_count, 2);
+    assert_eq!(stats.daily_volume[bucket(start)], PRICE);
+    assert_eq!(stats.daily_volume[bucket(next_day)], 2 * PRICE);
+    assert_eq!(stats.twap, PRICE);
+    assert_eq!(stats.floor, PRICE);
+
+    // A sale more than a week later clears every older day, and a cheaper sale lowers the floor.
+    let later = next_day + 8 * SECONDS_PER_DAY;
+    let stats = sell_member_at(
+        &mut context,
+        &ah,
+        &collection,
+        &seller,
+        &buyer,
+        PRICE / 2,
+        later,
+    )
+    .await;
+    assert_eq!(stats.volume, 3 * PRICE + PRICE / 2);
+    assert_eq!(stats.sale_count, 3);
+    assert_eq!(stats.current_day, later / SECONDS_PER_DAY);
+    assert_eq!(stats.daily_volume.iter().sum::<u64>(), PRICE / 2);
+    assert_eq!(stats.daily_volume[bucket(later)], PRICE / 2);
+    assert_eq!(stats.twap, (PRICE + 2 * PRICE * 8) / 9);
+    assert_eq!(stats.floor, PRICE / 2);
+}
+
+#[tokio::test]
+async fn sale_requires_collection_stats_address_for_verified_collection() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let collection = create_collection(&mut context).await;
+    let nft = create_collection_nft(&mut context, &seller.pubkey(), &collection, true).await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, PRICE).await;
+
+    let collection_stats = collection_stats_address(&ah, &collection.mint);
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    execute_sale
+        .accounts
+        .retain(|account| account.pubkey != collection_stats);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::CollectionStatsMismatch);
+}
@@ -0,0 +1,388 @@
+mod utils;
+
+use anchor_lang::error::ErrorCode;
+use auction_house_v2::{
+    dutch::dutch_price,
+    errors::AuctionHouseError,
+    pda::{
+        find_escrow_payment_address, find_listing_receipt_address, find_purchase_receipt_address,
+    },
+    receipt::{ListingReceipt, PurchaseReceipt},
+    AuthorityScope, DecayCurve, ListingConfig,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use utils::*;
+
+const START_PRICE: u64 = 2 * ONE_SOL;
+const END_PRICE: u64 = ONE_SOL;
+const DURATION: i64 = 1_000;
+
+struct TestListing {
+    ah: TestAuctionHouse,
+    seller: Keypair,
+    nft: TestNft,
+    start_time: i64,
+}
+
+fn linear_terms(start_time: i64) -> DutchTerms {
+    DutchTerms {
+        start_price: START_PRICE,
+        end_price: END_PRICE,
+        start_time,
+        end_time: start_time + DURATION,
+        curve: DecayCurve::Linear,
+    }
+}
+
+/// Dutch-list a fresh royalty-free NFT on a native house, starting now.
+async fn start_listing(context: &mut ProgramTestContext) -> TestListing {
+    let ah = create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let seller = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(context, &seller.pubkey(), &[], 0, 1).await;
+
+    let start_time = get_clock(context).await.unix_timestamp;
+    let create = create_dutch_listing_ix(&ah, &seller.pubkey(), &nft, &linear_terms(start_time));
+    process_tx(context, &[create], &[&seller]).await.unwrap();
+
+    TestListing {
+        ah,
+        seller,
+        nft,
+        start_time,
+    }
+}
+
+/// A buyer with a bid at `buyer_price` on the listed NFT.
+async fn place_bid(
+    context: &mut ProgramTestContext,
+    listing: &TestListing,
+    buyer_price: u64,
+) -> Keypair {
+    let buyer = Keypair::new();
+    airdrop(context, &buyer.pubkey(), ONE_SOL + buyer_price).await;
+    let buy = buy_ix(&listing.ah, &buyer.pubkey(), &listing.nft, buyer_price, 1);
+    process_tx(context, &[buy], &[&buyer]).await.unwrap();
+    buyer
+}
+
+#[tokio::test]
+async fn dutch_sale_settles_at_current_price() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let listing = start_listing(&mut context).await;
+    let buyer = place_bid(&mut context, &listing, START_PRICE).await;
+
+    set_clock(&mut context, listing.start_time + DURATION / 4).await;
+    let price = START_PRICE - (START_PRICE - END_PRICE) / 4;
+    let (escrow, _) = find_escrow_payment_address(&listing.ah.address, &buyer.pubkey());
+    let escrow_lamports = get_lamports(&mut context, &escrow).await;
+    let seller_lamports = get_lamports(&mut context, &listing.seller.pubkey()).await;
+    let seller_trade_state =
+        dutch_trade_state_address(&listing.ah, &listing.seller.pubkey(), &listing.nft);
+    let listing_rent = get_lamports(&mut context, &seller_trade_state).await;
+
+    let execute_sale = execute_dutch_sale_ix(
+        &listing.ah,
+        &buyer.pubkey(),
+        &listing.seller.pubkey(),
+        &listing.nft,
+        START_PRICE,
+    );
+    process_tx(&mut context, &[execute_sale], &[&listing.ah.authority])
+        .await
+        .unwrap();
+
+    // Only the current price leaves the escrow; the rest of the bid stays available to withdraw.
+    assert_eq!(
+        get_lamports(&mut context, &escrow).await,
+        escrow_lamports - price
+    );
+    assert_eq!(
+        get_lamports(&mut context, &listing.seller.pubkey()).await,
+        seller_lamports + price + listing_rent
+    );
+    let (escrow_nft, _) = find_escrow_nft_address(
+        &listing.ah.address,
+        &listing.seller.pubkey(),
+        &listing.nft.mint,
+        &buyer.pubkey(),
+    );
+    assert_eq!(get_token_balance(&mut context, &escrow_nft).await, 1);
+
+    let listing_config =
+        listing_config_address(&listing.ah, &listing.seller.pubkey(), &listing.nft);
+    let config: ListingConfig = get_anchor_account(&mut context, &listing_config).await;
+    assert_eq!(config.sale_price, Some(price));
+}
+
+#[tokio::test]
+async fn bid_must_cover_current_price() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let listing = start_listing(&mut context).await;
+    let buyer_price = START_PRICE - (START_PRICE - END_PRICE) / 2;
+    let buyer = place_bid(&mut context, &listing, buyer_price).await;
+
+    set_clock(&mut context, listing.start_time + DURATION / 4).await;
+    let execute_sale = execute_dutch_sale_ix(
+        &listing.ah,
+        &buyer.pubkey(),
+        &listing.seller.pubkey(),
+        &listing.nft,
+        buyer_price,
+    );
+    let result = process_tx(
+        &mut context,
+        std::slice::from_ref(&execute_sale),
+        &[&listing.ah.authority],
+    )
+    .await;
+    assert_auction_house_error(result, AuctionHouseError::BidBelowListingPrice);
+
+    // Once the price has fallen to the bid, the same sale goes through.
+    set_clock(&mut context, listing.start_time + DURATION / 2).await;
+    process_tx(&mut context, &[execute_sale], &[&listing.ah.authority])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn sale_waits_for_listing_start() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let seller = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+    let now = get_clock(&mut context).await.unix_timestamp;
+
+    let rising = DutchTerms {
+        start_price: END_PRICE,
+        end_price: START_PRICE,
+        ..linear_terms(now)
+    };
+    let create = create_dutch_listing_ix(&ah, &seller.pubkey(), &nft, &rising);
+    let result = process_tx(&mut context, &[create], &[&seller]).await;
+    assert_auction_house_error(result, AuctionHouseError::InvalidDutchPrices);
+
+    let create = create_dutch_listing_ix(&ah, &seller.pubkey(), &nft, &linear_terms(now + 100));
+    process_tx(&mut context, &[create], &[&seller])
+        .await
+        .unwrap();
+    let listing = TestListing {
+        ah,
+        seller,
+        nft,
+        start_time: now + 100,
+    };
+    let buyer = place_bid(&mut context, &listing, START_PRICE).await;
+
+    let execute_sale = execute_dutch_sale_ix(
+        &listing.ah,
+        &buyer.pubkey(),
+        &listing.seller.pubkey(),
+        &listing.nft,
+        START_PRICE,
+    );
+    let result = process_tx(&mut context, &[execute_sale], &[&listing.ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::AuctionNotActive);
+}
+
+#[tokio::test]
+async fn purchase_receipt_records_dutch_price() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), ONE_SOL + START_PRICE).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+    let start_time = get_clock(&mut context).await.unix_timestamp;
+    let seller_trade_state = dutch_trade_state_address(&ah, &seller.pubkey(), &nft);
+    let buyer_trade_state = trade_state_address(&ah, &buyer.pubkey(), &nft, START_PRICE, 1);
+
+    let create = create_dutch_listing_ix(&ah, &seller.pubkey(), &nft, &linear_terms(start_time));
+    let listing_receipt = print_listing_receipt_ix(&seller.pubkey(), &seller_trade_state);
+    process_tx(&mut context, &[create, listing_receipt], &[&seller])
+        .await
+        .unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, START_PRICE, 1);
+    let bid_receipt = print_bid_receipt_ix(&buyer.pubkey(), &buyer_trade_state);
+    process_tx(&mut context, &[buy, bid_receipt], &[&buyer])
+        .await
+        .unwrap();
+
+    set_clock(&mut context, start_time + DURATION / 2).await;
+    let price = START_PRICE - (START_PRICE - END_PRICE) / 2;
+    let listing_config = listing_config_address(&ah, &seller.pubkey(), &nft);
+    let execute_sale =
+        execute_dutch_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, START_PRICE);
+    let purchase_receipt = print_purchase_receipt_ix(
+        &buyer.pubkey(),
+        &seller_trade_state,
+        &buyer_trade_state,
+        Some(&listing_config),
+    );
+    process_tx(
+        &mut context,
+        &[execute_sale, purchase_receipt],
+        &[&ah.authority, &buyer],
+    )
+    .await
+    .unwrap();
+
+    let (purchase_receipt, _) =
+        find_purchase_receipt_address(&seller_trade_state, &buyer_trade_state);
+    let receipt: PurchaseReceipt = get_anchor_account(&mut context, &purchase_receipt).await;
+    assert_eq!(receipt.price, price);
+    assert_eq!(receipt.buyer, buyer.pubkey());
+    assert_eq!(receipt.seller, seller.pubkey());
+    let (listing_receipt, _) = find_listing_receipt_address(&seller_trade_state);
+    let listing_receipt: ListingReceipt = get_anchor_account(&mut context, &listing_receipt).await;
+    assert_eq!(listing_receipt.purchase_receipt, Some(purchase_receipt));
+}
+
+#[tokio::test]
+async fn cancel_dutch_listing_closes_config() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let listing = start_listing(&mut context).await;
+    let seller_trade_state =
+        dutch_trade_state_address(&listing.ah, &listing.seller.pubkey(), &listing.nft);
+    let listing_config =
+        listing_config_address(&listing.ah, &listing.seller.pubkey(), &listing.nft);
+
+    let cancel = cancel_dutch_listing_ix(&listing.ah, &listing.seller.pubkey(), &listing.nft);
+    process_tx(&mut context, &[cancel], &[&listing.seller])
+        .await
+        .unwrap();
+    assert!(get_account(&mut context, &seller_trade_state)
+        .await
+        .is_none());
+    assert!(get_account(&mut context, &listing_config).await.is_none());
+
+    // The seller can list the token again.
+    let now = get_clock(&mut context).await.unix_timestamp;
+    let create = create_dutch_listing_ix(
+        &listing.ah,
+        &listing.seller.pubkey(),
+        &listing.nft,
+        &linear_terms(now),
+    );
+    process_tx(&mut context, &[create], &[&listing.seller])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn auctioneer_cannot_sell_dutch_listing() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let listing = start_listing(&mut context).await;
+    let auctioneer = Keypair::new();
+    airdrop(&mut context, &listing.ah.authority.pubkey(), ONE_SOL).await;
+    let delegate = delegate_auctioneer_ix(
+        &listing.ah,
+        &auctioneer.pubkey(),
+        vec![AuthorityScope::ExecuteSale],
+    );
+    process_tx(&mut context, &[delegate], &[&listing.ah.authority])
+        .await
+        .unwrap();
+    let buyer = place_bid(&mut context, &listing, END_PRICE / 10).await;
+
+    // The dutch listing is not an Auctioneer listing, so the Auctioneer cannot sell it off its curve.
+    let execute_sale = auctioneer_execute_sale_ix(
+        &listing.ah,
+        &auctioneer.pubkey(),
+        &buyer.pubkey(),
+        &listing.seller.pubkey(),
+        &listing.nft,
+        END_PRICE / 10,
+        1,
+    );
+    let result = process_tx(
+        &mut context,
+        &[execute_sale],
+        &[&listing.ah.authority, &auctioneer],
+    )
+    .await;
+
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+    assert_eq!(
+        get_token_balance(&mut context, &listing.nft.token_account).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn token_cannot_be_auctioned_and_dutch_listed_at_once() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let listing = start_listing(&mut context).await;
+    let now = get_clock(&mut context).await.unix_timestamp;
+    let terms = AuctionTerms {
+        start_time: now,
+        end_time: now + DURATION,
+        reserve_price: END_PRICE,
+        min_increment: 1,
+        extension_window: 0,
+    };
+
+    let create_auction =
+        create_auction_ix(&listing.ah, &listing.seller.pubkey(), &listing.nft, &terms);
+    let result = process_tx(&mut context, &[create_auction], &[&listing.seller]).await;
+    assert_auction_house_error(result, AuctionHouseError::ListingConflict);
+
+    // Once the dutch listing is canceled the token can go up for auction, and then not back into a dutch listing.
+    let cancel = cancel_dutch_listing_ix(&listing.ah, &listing.seller.pubkey(), &listing.nft);
+    process_tx(&mut context, &[cancel], &[&listing.seller])
+        .await
+        .unwrap();
+    let create_auction =
+        create_auction_ix(&listing.ah, &listing.seller.pubkey(), &listing.nft, &terms);
+    process_tx(&mut context, &[create_auction], &[&listing.seller])
+        .await
+        .unwrap();
+    let create = create_dutch_listing_ix(
+        &listing.ah,
+        &listing.seller.pubkey(),
+        &listing.nft,
+        &linear_terms(now),
+    );
+    let result = process_tx(&mut context, &[create], &[&listing.seller]).await;
+    assert_auction_house_error(result, AuctionHouseError::ListingConflict);
+}
+
+#[test]
+fn dutch_price_follows_curve() {
+    let mut config = ListingConfig {
+        auction_house: Pubkey::default(),
+        seller: Pubkey::default(),
+        seller_trade_state: Pubkey::default(),
+        token_account: Pubkey::default(),
+        token_mint: Pubkey::default(),
+        token_size: 1,
+        start_price: 1_000,
+        end_price: 100,
+        start_time: 0,
+        end_time: 90,
+        curve: DecayCurve::Linear,
+        sale_price: None,
+        bump: 0,
+    };
+    assert_eq!(dutch_price(&config, -10).unwrap(), 1_000);
+    assert_eq!(dutch_price(&config, 1).unwrap(), 990);
+    assert_eq!(dutch_price(&config, 45).unwrap(), 550);
+    assert_eq!(dutch_price(&config, 90).unwrap(), 100);
+    assert_eq!(dutch_price(&config, 1_000).unwrap(), 100);
+
+    config.curve = DecayCurve::Stepwise { step_duration: 30 };
+    assert_eq!(dutch_price(&config, 29).unwrap(), 1_000);
+    assert_eq!(dutch_price(&config, 30).unwrap(), 700);
+    assert_eq!(dutch_price(&config, 89).unwrap(), 400);
+    assert_eq!(dutch_price(&config, 90).unwrap(), 100);
+}
@@ -0,0 +1,508 @@
+mod utils;
+
+use anchor_lang::error::ErrorCode;
+use auction_house_v2::{
+    errors::AuctionHouseError,
+    pda::{find_auction_escrow_payment_address, find_escrow_payment_address},
+    AuctionState, AuthorityScope,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use utils::*;
+
+const RESERVE: u64 = ONE_SOL / 2;
+const INCREMENT: u64 = ONE_SOL / 10;
+const DURATION: i64 = 3_600;
+const EXTENSION: i64 = 300;
+
+struct TestAuction {
+    ah: TestAuctionHouse,
+    seller: Keypair,
+    nft: TestNft,
+    auction_state: Pubkey,
+    end_time: i64,
+}
+
+/// Put a fresh royalty-free NFT up for auction on a native house, starting now.
+async fn start_auction(context: &mut ProgramTestContext) -> TestAuction {
+    let ah = create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await;
+    let seller = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(context, &seller.pubkey(), &[], 0, 1).await;
+
+    let now = get_clock(context).await.unix_timestamp;
+    let terms = AuctionTerms {
+        start_time: now,
+        end_time: now + DURATION,
+        reserve_price: RESERVE,
+        min_increment: INCREMENT,
+        extension_window: EXTENSION,
+    };
+    let create = create_auction_ix(&ah, &seller.pubkey(), &nft, &terms);
+    process_tx(context, &[create], &[&seller]).await.unwrap();
+
+    let auction_state = auction_state_address(&ah, &seller.pubkey(), &nft);
+    TestAuction {
+        ah,
+        seller,
+        nft,
+        auction_state,
+        end_time: terms.end_time,
+    }
+}
+
+/// A bidder with `amount` already deposited in their escrow payment account.
+async fn funded_bidder(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    amount: u64,
+) -> Keypair {
+    let bidder = Keypair::new();
+    airdrop(context, &bidder.pubkey(), ONE_SOL + amount).await;
+    let deposit = deposit_ix(ah, &bidder.pubkey(), amount);
+    process_tx(context, &[deposit], &[&bidder]).await.unwrap();
+    bidder
+}
+
+async fn bid(
+    context: &mut ProgramTestContext,
+    auction: &TestAuction,
+    bidder: &Keypair,
+    previous_bidder: Option<&Pubkey>,
+    price: u64,
+) -> Result<(), solana_sdk::transport::TransportError> {
+    let bid = place_auction_bid_ix(
+        &auction.ah,
+        &bidder.pubkey(),
+        &auction.seller.pubkey(),
+        &auction.nft,
+        previous_bidder,
+        price,
+    );
+    process_tx(context, &[bid], &[bidder]).await
+}
+
+async fn escrow_balance(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+) -> u64 {
+    let (escrow, _) = find_escrow_payment_address(&ah.address, wallet);
+    get_lamports(context, &escrow).await
+}
+
+#[tokio::test]
+async fn auction_settles_to_highest_bidder() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let first = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    let second = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    let first_escrow = escrow_balance(&mut context, &auction.ah, &first.pubkey()).await;
+
+    bid(&mut context, &auction, &first, None, RESERVE)
+        .await
+        .unwrap();
+    assert_eq!(
+        escrow_balance(&mut context, &auction.ah, &first.pubkey()).await,
+        first_escrow - RESERVE
+    );
+
+    // Outbidding releases the first bid back to its escrow.
+    let winning_bid = RESERVE + INCREMENT;
+    bid(
+        &mut context,
+        &auction,
+        &second,
+        Some(&first.pubkey()),
+        winning_bid,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        escrow_balance(&mut context, &auction.ah, &first.pubkey()).await,
+        first_escrow
+    );
+    let state: AuctionState = get_anchor_account(&mut context, &auction.auction_state).await;
+    assert_eq!(state.highest_bidder, second.pubkey());
+    assert_eq!(state.highest_bid, winning_bid);
+
+    set_clock(&mut context, auction.end_time).await;
+    let seller_lamports = get_lamports(&mut context, &auction.seller.pubkey()).await;
+    let second_escrow = escrow_balance(&mut context, &auction.ah, &second.pubkey()).await;
+    let cranker = Keypair::new();
+    airdrop(&mut context, &cranker.pubkey(), ONE_SOL).await;
+    let settle = settle_auction_ix(
+        &auction.ah,
+        &cranker.pubkey(),
+        &second.pubkey(),
+        &auction.seller.pubkey(),
+        &auction.nft,
+    );
+    process_tx(&mut context, &[settle], &[&cranker])
+        .await
+        .unwrap();
+
+    let (escrow_nft, _) = find_escrow_nft_address(
+        &auction.ah.address,
+        &auction.seller.pubkey(),
+        &auction.nft.mint,
+        &second.pubkey(),
+    );
+    assert_eq!(get_token_balance(&mut context, &escrow_nft).await, 1);
+    // The winning bid left the locking escrow and was paid out of the winner's escrow.
+    assert_eq!(
+        escrow_balance(&mut context, &auction.ah, &second.pubkey()).await,
+        second_escrow
+    );
+    // The seller is paid and recovers the auction state and locking escrow rent.
+    assert!(
+        get_lamports(&mut context, &auction.seller.pubkey()).await > seller_lamports + winning_bid
+    );
+    assert!(get_account(&mut context, &auction.auction_state)
+        .await
+        .is_none());
+    let (auction_escrow, _) =
+        find_auction_escrow_payment_address(&auction.ah.address, &auction.auction_state);
+    assert!(get_account(&mut context, &auction_escrow).await.is_none());
+
+    let claim = claim_nft_ix(
+        &auction.ah,
+        &second.pubkey(),
+        &auction.seller.pubkey(),
+        &auction.nft,
+        &cranker.pubkey(),
+        &second.pubkey(),
+    );
+    process_tx(&mut context, &[claim], &[&second])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn bids_must_meet_reserve_and_increment() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let first = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    let second = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+
+    let result = bid(&mut context, &auction, &first, None, RESERVE - 1).await;
+    assert_auction_house_error(result, AuctionHouseError::BidTooLow);
+    bid(&mut context, &auction, &first, None, RESERVE)
+        .await
+        .unwrap();
+
+    let result = bid(
+        &mut context,
+        &auction,
+        &second,
+        Some(&first.pubkey()),
+        RESERVE + INCREMENT - 1,
+    )
+    .await;
+    assert_auction_house_error(result, AuctionHouseError::BidTooLow);
+
+    // The refund must go to the escrow of the bidder being outbid.
+    let result = bid(
+        &mut context,
+        &auction,
+        &second,
+        Some(&second.pubkey()),
+        RESERVE + INCREMENT,
+    )
+    .await;
+    assert_auction_house_error(result, AuctionHouseError::DerivedKeyInvalid);
+}
+
+#[tokio::test]
+async fn seller_cannot_bid_on_own_auction() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let deposit = deposit_ix(&auction.ah, &auction.seller.pubkey(), RESERVE);
+    process_tx(&mut context, &[deposit], &[&auction.seller])
+        .await
+        .unwrap();
+
+    let result = bid(&mut context, &auction, &auction.seller, None, RESERVE).await;
+    assert_auction_house_error(result, AuctionHouseError::SellerCannotBid);
+}
+
+#[tokio::test]
+async fn settlement_returns_listing_rent_to_seller() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let bidder = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    bid(&mut context, &auction, &bidder, None, RESERVE)
+        .await
+        .unwrap();
+
+    set_clock(&mut context, auction.end_time).await;
+    let listing = auction_trade_state_address(&auction.ah, &auction.seller.pubkey(), &auction.nft);
+    let (auction_escrow, _) =
+        find_auction_escrow_payment_address(&auction.ah.address, &auction.auction_state);
+    // The locking escrow holds the bid on top of its rent.
+    let seller_due = get_lamports(&mut context, &listing).await
+        + get_lamports(&mut context, &auction.auction_state).await
+        + get_lamports(&mut context, &auction_escrow).await;
+    let seller_lamports = get_lamports(&mut context, &auction.seller.pubkey()).await;
+    let cranker = Keypair::new();
+    airdrop(&mut context, &cranker.pubkey(), ONE_SOL).await;
+    let settle = settle_auction_ix(
+        &auction.ah,
+        &cranker.pubkey(),
+        &bidder.pubkey(),
+        &auction.seller.pubkey(),
+        &auction.nft,
+    );
+    process_tx(&mut context, &[settle], &[&cranker])
+        .await
+        .unwrap();
+
+    // Anyone can settle, but the caller only pays for the accounts the sale creates and keeps none of the seller's
+    // listing rent.
+    assert!(get_account(&mut context, &listing).await.is_none());
+    assert_eq!(
+        get_lamports(&mut context, &auction.seller.pubkey()).await,
+        seller_lamports + seller_due
+    );
+    assert!(get_lamports(&mut context, &cranker.pubkey()).await < ONE_SOL);
+}
+
+#[tokio::test]
+async fn bids_only_while_auction_runs() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let bidder = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+
+    set_clock(&mut context, auction.end_time).await;
+    let result = bid(&mut context, &auction, &bidder, None, RESERVE).await;
+    assert_auction_house_error(result, AuctionHouseError::AuctionNotActive);
+}
+
+#[tokio::test]
+async fn late_bid_extends_auction() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let bidder = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+
+    let bid_time = auction.end_time - 10;
+    set_clock(&mut context, bid_time).await;
+    bid(&mut context, &auction, &bidder, None, RESERVE)
+        .await
+        .unwrap();
+    let state: AuctionState = get_anchor_account(&mut context, &auction.auction_state).await;
+    assert_eq!(state.end_time, bid_time + EXTENSION);
+
+    set_clock(&mut context, auction.end_time).await;
+    let settle = settle_auction_ix(
+        &auction.ah,
+        &bidder.pubkey(),
+        &bidder.pubkey(),
+        &auction.seller.pubkey(),
+        &auction.nft,
+    );
+    let result = process_tx(&mut context, &[settle], &[&bidder]).await;
+    assert_auction_house_error(result, AuctionHouseError::AuctionNotEnded);
+}
+
+#[tokio::test]
+async fn auction_without_bids_closes_listing() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+
+    set_clock(&mut context, auction.end_time).await;
+    let seller = auction.seller.pubkey();
+    let settle = settle_auction_ix(&auction.ah, &seller, &seller, &seller, &auction.nft);
+    process_tx(&mut context, &[settle], &[&auction.seller])
+        .await
+        .unwrap();
+
+    let listing = auction_trade_state_address(&auction.ah, &seller, &auction.nft);
+    assert!(get_account(&mut context, &listing).await.is_none());
+    assert!(get_account(&mut context, &auction.auction_state)
+        .await
+        .is_none());
+    assert_eq!(
+        get_token_balance(&mut context, &auction.nft.token_account).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn canceled_listing_refunds_highest_bid() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let bidder = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    let escrow = escrow_balance(&mut context, &auction.ah, &bidder.pubkey()).await;
+    bid(&mut context, &auction, &bidder, None, RESERVE)
+        .await
+        .unwrap();
+
+    let cancel = cancel_auction_ix(&auction.ah, &auction.seller.pubkey(), &auction.nft);
+    process_tx(&mut context, &[cancel], &[&auction.seller])
+        .await
+        .unwrap();
+
+    // A pulled listing takes no new bids.
+    let outbidder = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    let result = bid(
+        &mut context,
+        &auction,
+        &outbidder,
+        Some(&bidder.pubkey()),
+        RESERVE + INCREMENT,
+    )
+    .await;
+    assert_auction_house_error(result, AuctionHouseError::AuctionListingClosed);
+
+    set_clock(&mut context, auction.end_time).await;
+    let settle = settle_auction_ix(
+        &auction.ah,
+        &bidder.pubkey(),
+        &bidder.pubkey(),
+        &auction.seller.pubkey(),
+        &auction.nft,
+    );
+    process_tx(&mut context, &[settle], &[&bidder])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        escrow_balance(&mut context, &auction.ah, &bidder.pubkey()).await,
+        escrow
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &auction.nft.token_account).await,
+        1
+    );
+    assert!(get_account(&mut context, &auction.auction_state)
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn winner_keeps_private_bid_at_winning_price() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let bidder = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    let buy = buy_ix(&auction.ah, &bidder.pubkey(), &auction.nft, RESERVE, 1);
+    process_tx(&mut context, &[buy], &[&bidder]).await.unwrap();
+    bid(&mut context, &auction, &bidder, None, RESERVE)
+        .await
+        .unwrap();
+
+    set_clock(&mut context, auction.end_time).await;
+    let settle = settle_auction_ix(
+        &auction.ah,
+        &bidder.pubkey(),
+        &bidder.pubkey(),
+        &auction.seller.pubkey(),
+        &auction.nft,
+    );
+    process_tx(&mut context, &[settle], &[&bidder])
+        .await
+        .unwrap();
+
+    // Settlement consumed the auction bid only.
+    assert_eq!(
+        get_token_balance(&mut context, &auction.nft.token_account).await,
+        0
+    );
+    let private_bid = trade_state_address(&auction.ah, &bidder.pubkey(), &auction.nft, RESERVE, 1);
+    assert!(get_account(&mut context, &private_bid).await.is_some());
+}
+
+#[tokio::test]
+async fn auctioneer_cannot_sell_auction_listing() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let auctioneer = Keypair::new();
+    airdrop(&mut context, &auction.ah.authority.pubkey(), ONE_SOL).await;
+    let delegate = delegate_auctioneer_ix(
+        &auction.ah,
+        &auctioneer.pubkey(),
+        vec![AuthorityScope::ExecuteSale],
+    );
+    process_tx(&mut context, &[delegate], &[&auction.ah.authority])
+        .await
+        .unwrap();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &buyer.pubkey(), ONE_SOL).await;
+    let low_price = RESERVE / 10;
+    let buy = buy_ix(&auction.ah, &buyer.pubkey(), &auction.nft, low_price, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+
+    // The auction listing is not an Auctioneer listing, so it cannot be sold below its reserve there.
+    let execute_sale = auctioneer_execute_sale_ix(
+        &auction.ah,
+        &auctioneer.pubkey(),
+        &buyer.pubkey(),
+        &auction.seller.pubkey(),
+        &auction.nft,
+        low_price,
+        1,
+    );
+    let result = process_tx(
+        &mut context,
+        &[execute_sale],
+        &[&auction.ah.authority, &auctioneer],
+    )
+    .await;
+
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+    assert_eq!(
+        get_token_balance(&mut context, &auction.nft.token_account).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn delegated_scopes_require_auctioneer() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let bidder = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    airdrop(&mut context, &auction.ah.authority.pubkey(), ONE_SOL).await;
+    let delegate = delegate_auctioneer_ix(
+        &auction.ah,
+        &Keypair::new().pubkey(),
+        vec![AuthorityScope::Buy, AuthorityScope::ExecuteSale],
+    );
+    process_tx(&mut context, &[delegate], &[&auction.ah.authority])
+        .await
+        .unwrap();
+
+    let result = bid(&mut context, &auction, &bidder, None, RESERVE).await;
+    assert_auction_house_error(result, AuctionHouseError::MustUseAuctioneerHandler);
+
+    set_clock(&mut context, auction.end_time).await;
+    let seller = auction.seller.pubkey();
+    let settle = settle_auction_ix(&auction.ah, &seller, &seller, &seller, &auction.nft);
+    let result = process_tx(&mut context, &[settle], &[&auction.seller]).await;
+    assert_auction_house_error(result, AuctionHouseError::MustUseAuctioneerHandler);
+}
+
+#[tokio::test]
+async fn locked_bid_cannot_be_withdrawn() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let auction = start_auction(&mut context).await;
+    let bidder = funded_bidder(&mut context, &auction.ah, ONE_SOL).await;
+    bid(&mut context, &auction, &bidder, None, RESERVE)
+        .await
+        .unwrap();
+    let (auction_escrow, _) =
+        find_auction_escrow_payment_address(&auction.ah.address, &auction.auction_state);
+    let locked = get_lamports(&mut context, &auction_escrow).await;
+
+    // The authority can withdraw on behalf of any wallet, including the auction state.
+    airdrop(&mut context, &auction.ah.fee_account, ONE_SOL).await;
+    let mut withdraw = withdraw_ix(&auction.ah, &auction.auction_state, RESERVE);
+    for meta in withdraw.accounts.iter_mut() {
+        meta.is_signer = meta.pubkey == auction.ah.authority.pubkey();
+    }
+    let result = process_tx(&mut context, &[withdraw], &[&auction.ah.authority]).await;
+
+    assert!(result.is_err());
+    assert_eq!(get_lamports(&mut context, &auction_escrow).await, locked);
+}
@@ -0,0 +1,204 @@
+mod utils;
+
+use auction_house_v2::errors::AuctionHouseError;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+/// Native house with a funded seller and buyer and a royalty-free NFT owned by the seller.
+async fn setup(
+    context: &mut ProgramTestContext,
+    requires_sign_off: bool,
+) -> (TestAuctionHouse, Keypair, Keypair, TestNft) {
+    let ah = create_auction_house(
+        context,
+        &spl_token::native_mint::id(),
+        0,
+        requires_sign_off,
+        false,
+    )
+    .await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let nft = create_nft(context, &seller.pubkey(), &[], 0, 1).await;
+    (ah, seller, buyer, nft)
+}
+
+#[tokio::test]
+async fn sell_rejects_more_tokens_than_held() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, nft) = setup(&mut context, false).await;
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 2);
+    let result = process_tx(&mut context, &[sell], &[&seller]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InvalidTokenAmount);
+}
+
+#[tokio::test]
+async fn sell_rejects_wallet_and_authority_both_signing() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, nft) = setup(&mut context, false).await;
+
+    let mut sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    mark_signer(&mut sell.accounts, &ah.authority.pubkey());
+    let result = process_tx(&mut context, &[sell], &[&seller, &ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::SaleRequiresExactlyOneSigner);
+}
+
+#[tokio::test]
+async fn sell_rejects_listing_signed_only_by_authority() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, nft) = setup(&mut context, false).await;
+
+    let mut sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    sell.accounts[0].is_signer = false;
+    mark_signer(&mut sell.accounts, &ah.authority.pubkey());
+    let result = process_tx(&mut context, &[sell], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::SaleRequiresSigner);
+}
+
+#[tokio::test]
+async fn sell_requires_sign_off_when_house_demands_it() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, nft) = setup(&mut context, true).await;
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let result = process_tx(&mut context, &[sell], &[&seller]).await;
+
+    assert_auction_house_error(
+        result,
+        AuctionHouseError::CannotTakeThisActionWithoutAuctionHouseSignOff,
+    );
+}
+
+#[tokio::test]
+async fn sell_rejects_mint_without_metadata() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, _) = setup(&mut context, false).await;
+    let mint_authority = Keypair::new();
+    let mint = create_mint(&mut context, &mint_authority.pubkey(), 0).await;
+    let token_account = create_ata(&mut context, &seller.pubkey(), &mint.pubkey()).await;
+    mint_to(
+        &mut context,
+        &mint.pubkey(),
+        &mint_authority,
+        &token_account,
+        1,
+    )
+    .await;
+    let nft = TestNft {
+        mint: mint.pubkey(),
+        token_account,
+        metadata: find_metadata_address(&mint.pubkey()),
+        creators: vec![],
+        token_program: spl_token::id(),
+        verified_collection: None,
+    };
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let result = process_tx(&mut context, &[sell], &[&seller]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::MetadataDoesntExist);
+}
+
+#[tokio::test]
+async fn execute_sale_requires_a_bid() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nft) = setup(&mut context, false).await;
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::BuyerTradeStateNotValid);
+}
+
+#[tokio::test]
+async fn execute_sale_requires_seller_delegate() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nft) = setup(&mut context, false).await;
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+    // Revoking outside the Auction House leaves the listing with nothing to transfer through.
+    let revoke =
+        spl_token::instruction::revoke(&spl_token::id(), &nft.token_account, &seller.pubkey(), &[])
+            .unwrap();
+    process_tx(&mut context, &[revoke], &[&seller])
+        .await
+        .unwrap();
+
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::BothPartiesNeedToAgreeToSale);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_more_than_escrow_holds() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, _, buyer, _) = setup(&mut context, false).await;
+    let deposit = deposit_ix(&ah, &buyer.pubkey(), ONE_SOL);
+    process_tx(&mut context, &[deposit], &[&buyer])
+        .await
+        .unwrap();
+
+    let withdraw = withdraw_ix(&ah, &buyer.pubkey(), 2 * ONE_SOL);
+    let result = process_tx(&mut context, &[withdraw], &[&buyer]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn cancel_listing_receipt_requires_printed_receipt() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, nft) = setup(&mut context, false).await;
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+
+    let trade_state = trade_state_address(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let cancel = cancel_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let cancel_receipt = cancel_listing_receipt_ix(&trade_state);
+    let result = process_tx(&mut context, &[cancel, cancel_receipt], &[&seller]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::ReceiptIsEmpty);
+}
+
+#[tokio::test]
+async fn listing_receipt_must_follow_a_listing() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, _, buyer, nft) = setup(&mut context, false).await;
+
+    let trade_state = trade_state_address(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    let listing_receipt = print_listing_receipt_ix(&buyer.pubkey(), &trade_state);
+    let result = process_tx(&mut context, &[buy, listing_receipt], &[&buyer]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InstructionMismatch);
+}
+
+#[tokio::test]
+async fn cancel_rejects_unknown_token_program() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _, nft) = setup(&mut context, false).await;
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+
+    let mut cancel = cancel_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    for meta in cancel.accounts.iter_mut() {
+        if meta.pubkey == nft.token_program {
+            meta.pubkey = solana_sdk::system_program::id();
+        }
+    }
+    let result = process_tx(&mut context, &[cancel], &[&seller]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InvalidTokenProgram);
+}
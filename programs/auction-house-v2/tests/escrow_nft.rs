@@ -0,0 +1,342 @@
+mod utils;
+
+use anchor_lang::error::ErrorCode;
+use auction_house_v2::{errors::AuctionHouseError, pda::find_escrow_payment_address};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use spl_associated_token_account::get_associated_token_address;
+use utils::*;
+
+async fn native_house(context: &mut ProgramTestContext) -> TestAuctionHouse {
+    create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await
+}
+
+fn escrow_nft_address(ah: &TestAuctionHouse, sale: &TestSale) -> solana_program::pubkey::Pubkey {
+    find_escrow_nft_address(
+        &ah.address,
+        &sale.seller.pubkey(),
+        &sale.nft.mint,
+        &sale.buyer.pubkey(),
+    )
+    .0
+}
+
+/// Rent of the escrow NFT account of `sale` and its record together.
+async fn escrow_rent(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    sale: &TestSale,
+) -> u64 {
+    let escrow_nft = escrow_nft_address(ah, sale);
+    let escrow_nft_record = find_escrow_nft_record_address(&escrow_nft).0;
+    get_lamports(context, &escrow_nft).await + get_lamports(context, &escrow_nft_record).await
+}
+
+#[tokio::test]
+async fn buyer_claims_escrowed_nft() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let sale = execute_native_sale(&mut context, &ah, ONE_SOL).await;
+    let escrow_nft = escrow_nft_address(&ah, &sale);
+    let escrow_rent = escrow_rent(&mut context, &ah, &sale).await;
+    let fee_account_lamports = get_lamports(&mut context, &ah.fee_account).await;
+
+    let claim = claim_nft_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &ah.fee_account,
+        &sale.buyer.pubkey(),
+    );
+    process_tx(&mut context, &[claim], &[&sale.buyer])
+        .await
+        .unwrap();
+
+    let buyer_token_account = get_associated_token_address(&sale.buyer.pubkey(), &sale.nft.mint);
+    assert_eq!(
+        get_token_balance(&mut context, &buyer_token_account).await,
+        1
+    );
+    assert!(get_account(&mut context, &escrow_nft).await.is_none());
+    assert!(
+        get_account(&mut context, &find_escrow_nft_record_address(&escrow_nft).0)
+            .await
+            .is_none()
+    );
+    // The authority settled the sale, so the fee account funded the escrow and gets its rent back.
+    assert_eq!(
+        get_lamports(&mut context, &ah.fee_account).await,
+        fee_account_lamports + escrow_rent
+    );
+}
+
+#[tokio::test]
+async fn authority_claims_for_buyer() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let sale = execute_native_sale(&mut context, &ah, ONE_SOL).await;
+
+    let claim = claim_nft_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &ah.fee_account,
+        &ah.authority.pubkey(),
+    );
+    process_tx(&mut context, &[claim], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let buyer_token_account = get_associated_token_address(&sale.buyer.pubkey(), &sale.nft.mint);
+    assert_eq!(
+        get_token_balance(&mut context, &buyer_token_account).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn only_the_buyer_can_claim() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let sale = execute_native_sale(&mut context, &ah, ONE_SOL).await;
+    let thief = Keypair::new();
+    airdrop(&mut context, &thief.pubkey(), ONE_SOL).await;
+
+    // The escrow is keyed by the buyer, so nothing is escrowed for anyone else.
+    let claim = claim_nft_ix(
+        &ah,
+        &thief.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &ah.fee_account,
+        &thief.pubkey(),
+    );
+    let result = process_tx(&mut context, &[claim], &[&thief]).await;
+    assert_anchor_error(result, ErrorCode::AccountNotInitialized);
+
+    let claim = claim_nft_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &ah.fee_account,
+        &sale.seller.pubkey(),
+    );
+    let result = process_tx(&mut context, &[claim], &[&sale.seller]).await;
+    assert_auction_house_error(result, AuctionHouseError::NoValidSignerPresent);
+}
+
+#[tokio::test]
+async fn claim_returns_rent_to_its_payer_only() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let sale = execute_native_sale(&mut context, &ah, ONE_SOL).await;
+
+    let claim = claim_nft_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &sale.buyer.pubkey(),
+        &sale.buyer.pubkey(),
+    );
+    let result = process_tx(&mut context, &[claim], &[&sale.buyer]).await;
+
+    assert_anchor_error(result, ErrorCode::ConstraintHasOne);
+}
+
+#[tokio::test]
+async fn refund_pays_the_buyer_back() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let sale = execute_native_sale(&mut context, &ah, ONE_SOL).await;
+    let escrow_nft = escrow_nft_address(&ah, &sale);
+    let escrow_rent = escrow_rent(&mut context, &ah, &sale).await;
+    let fee_account_lamports = get_lamports(&mut context, &ah.fee_account).await;
+    let seller_lamports = get_lamports(&mut context, &sale.seller.pubkey()).await;
+    let escrow_payment = find_escrow_payment_address(&ah.address, &sale.buyer.pubkey()).0;
+    let escrow_payment_lamports = get_lamports(&mut context, &escrow_payment).await;
+
+    let refund = refund_nft_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &ah.fee_account,
+    );
+    process_tx(&mut context, &[refund], &[&ah.authority, &sale.seller])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_token_balance(&mut context, &sale.nft.token_account).await,
+        1
+    );
+    assert_eq!(
+        get_lamports(&mut context, &sale.seller.pubkey()).await,
+        seller_lamports - ONE_SOL
+    );
+    assert_eq!(
+        get_lamports(&mut context, &escrow_payment).await,
+        escrow_payment_lamports + ONE_SOL
+    );
+    assert!(get_account(&mut context, &escrow_nft).await.is_none());
+    assert_eq!(
+        get_lamports(&mut context, &ah.fee_account).await,
+        fee_account_lamports + escrow_rent
+    );
+
+    // Nothing is left for the buyer to claim.
+    let claim = claim_nft_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &ah.fee_account,
+        &sale.buyer.pubkey(),
+    );
+    let result = process_tx(&mut context, &[claim], &[&sale.buyer]).await;
+    assert_anchor_error(result, ErrorCode::AccountNotInitialized);
+}
+
+#[tokio::test]
+async fn refund_pays_spl_price_back() {
+    const PRICE: u64 = 5_000_000;
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_mint(&mut context, &mint_authority.pubkey(), 6).await;
+    let ah = create_auction_house(&mut context, &treasury_mint.pubkey(), 0, false, false).await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let buyer_payment = create_ata(&mut context, &buyer.pubkey(), &ah.treasury_mint).await;
+    mint_to(
+        &mut context,
+        &ah.treasury_mint,
+        &mint_authority,
+        &buyer_payment,
+        PRICE,
+    )
+    .await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    let seller_payment = ah.payment_account(&seller.pubkey());
+    let escrow_payment = find_escrow_payment_address(&ah.address, &buyer.pubkey()).0;
+    assert_eq!(
+        get_token_balance(&mut context, &seller_payment).await,
+        PRICE
+    );
+    assert_eq!(get_token_balance(&mut context, &escrow_payment).await, 0);
+
+    let refund = refund_nft_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &ah.fee_account,
+    );
+    process_tx(&mut context, &[refund], &[&ah.authority, &seller])
+        .await
+        .unwrap();
+
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 1);
+    assert_eq!(get_token_balance(&mut context, &seller_payment).await, 0);
+    assert_eq!(
+        get_token_balance(&mut context, &escrow_payment).await,
+        PRICE
+    );
+}
+
+#[tokio::test]
+async fn refund_needs_the_authority_and_the_seller() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    let sale = execute_native_sale(&mut context, &ah, ONE_SOL).await;
+
+    // The seller alone cannot take back an NFT the buyer paid for.
+    let mut refund = refund_nft_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &ah.fee_account,
+    );
+    for meta in refund.accounts.iter_mut() {
+        meta.is_signer = meta.pubkey == sale.seller.pubkey();
+    }
+    let result = process_tx(&mut context, &[refund], &[&sale.seller]).await;
+    assert_anchor_error(result, ErrorCode::AccountNotSigner);
+
+    // Nor can the authority undo the sale without the seller paying the buyer back.
+    let mut refund = refund_nft_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        &ah.fee_account,
+    );
+    for meta in refund.accounts.iter_mut() {
+        meta.is_signer = meta.pubkey == ah.authority.pubkey();
+    }
+    let result = process_tx(&mut context, &[refund], &[&ah.authority]).await;
+    assert_anchor_error(result, ErrorCode::AccountNotSigner);
+
+    assert_eq!(
+        get_token_balance(&mut context, &escrow_nft_address(&ah, &sale)).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn seller_escrows_several_sales_at_once() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = native_house(&mut context).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 3 * ONE_SOL).await;
+
+    let mut nfts = Vec::new();
+    for _ in 0..2 {
+        let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+        let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+        process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+        let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+        process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+        let execute_sale =
+            execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+        process_tx(&mut context, &[execute_sale], &[&ah.authority])
+            .await
+            .unwrap();
+        nfts.push(nft);
+    }
+
+    for nft in &nfts {
+        let claim = claim_nft_ix(
+            &ah,
+            &buyer.pubkey(),
+            &seller.pubkey(),
+            nft,
+            &ah.fee_account,
+            &buyer.pubkey(),
+        );
+        process_tx(&mut context, &[claim], &[&buyer]).await.unwrap();
+        assert_eq!(
+            get_token_balance(&mut context, &nft.token_account_of(&buyer.pubkey())).await,
+            1
+        );
+    }
+}
@@ -0,0 +1,116 @@
+mod utils;
+
+use auction_house_v2::events::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const HOUSE_FEE_BPS: u16 = 250;
+const ROYALTY_BPS: u16 = 500;
+
+#[tokio::test]
+async fn trade_lifecycle_emits_events() {
+    let mut context = start_with_event_logs(auction_house_program_test()).await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    let creator = Keypair::new().pubkey();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    airdrop(&mut context, &creator, ONE_SOL).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let nft = create_nft(
+        &mut context,
+        &seller.pubkey(),
+        &[(creator, 100)],
+        ROYALTY_BPS,
+        1,
+    )
+    .await;
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let listings: Vec<ListingCreated> = process_tx_for_events(&mut context, &[sell], &[&seller])
+        .await
+        .unwrap();
+    assert_eq!(listings.len(), 1);
+    assert_eq!(
+        listings[0].trade_state,
+        trade_state_address(&ah, &seller.pubkey(), &nft, ONE_SOL, 1)
+    );
+    assert_eq!(listings[0].seller, seller.pubkey());
+    assert_eq!(listings[0].token_mint, nft.mint);
+    assert_eq!(listings[0].price, ONE_SOL);
+
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    let bids: Vec<BidPlaced> = process_tx_for_events(&mut context, &[buy], &[&buyer])
+        .await
+        .unwrap();
+    assert_eq!(bids.len(), 1);
+    assert_eq!(bids[0].buyer, buyer.pubkey());
+    assert_eq!(bids[0].price, ONE_SOL);
+    assert!(!bids[0].public);
+
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    let sales: Vec<SaleExecuted> =
+        process_tx_for_events(&mut context, &[execute_sale], &[&ah.authority])
+            .await
+            .unwrap();
+    assert_eq!(sales.len(), 1);
+    let sale = &sales[0];
+    assert_eq!(sale.buyer, buyer.pubkey());
+    assert_eq!(sale.seller, seller.pubkey());
+    assert_eq!(sale.token_size, 1);
+    assert_eq!(sale.royalties, ONE_SOL * ROYALTY_BPS as u64 / 10_000);
+    assert_eq!(
+        sale.auction_house_fee,
+        ONE_SOL * HOUSE_FEE_BPS as u64 / 10_000
+    );
+    assert_eq!(
+        sale.royalties + sale.auction_house_fee + sale.seller_proceeds,
+        sale.price
+    );
+}
+
+#[tokio::test]
+async fn cancel_deposit_and_withdraw_emit_events() {
+    let mut context = start_with_event_logs(auction_house_program_test()).await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let seller = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), 2 * ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+
+    let cancel = cancel_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let cancels: Vec<OrderCanceled> = process_tx_for_events(&mut context, &[cancel], &[&seller])
+        .await
+        .unwrap();
+    assert_eq!(cancels.len(), 1);
+    assert_eq!(cancels[0].wallet, seller.pubkey());
+    assert_eq!(cancels[0].token_mint, nft.mint);
+    assert_eq!(cancels[0].price, ONE_SOL);
+
+    let deposit = deposit_ix(&ah, &seller.pubkey(), ONE_SOL);
+    let deposits: Vec<Deposited> = process_tx_for_events(&mut context, &[deposit], &[&seller])
+        .await
+        .unwrap();
+    assert_eq!(deposits.len(), 1);
+    assert_eq!(deposits[0].wallet, seller.pubkey());
+    assert_eq!(deposits[0].amount, ONE_SOL);
+
+    let withdraw = withdraw_ix(&ah, &seller.pubkey(), ONE_SOL / 2);
+    let withdrawals: Vec<Withdrew> = process_tx_for_events(&mut context, &[withdraw], &[&seller])
+        .await
+        .unwrap();
+    assert_eq!(withdrawals.len(), 1);
+    assert_eq!(withdrawals[0].wallet, seller.pubkey());
+    assert_eq!(withdrawals[0].amount, ONE_SOL / 2);
+}
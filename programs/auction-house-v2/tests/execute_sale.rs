@@ -0,0 +1,357 @@
+mod utils;
+
+use anchor_lang::error::ErrorCode;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use spl_associated_token_account::get_associated_token_address;
+use utils::*;
+
+const HOUSE_FEE_BPS: u16 = 250;
+const ROYALTY_BPS: u16 = 500;
+
+struct Balances {
+    creators: Vec<u64>,
+    treasury: u64,
+    seller: u64,
+    escrow: u64,
+}
+
+async fn balance_of(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    address: &solana_sdk::pubkey::Pubkey,
+) -> u64 {
+    if ah.is_native() {
+        get_lamports(context, address).await
+    } else {
+        get_token_balance(context, address).await
+    }
+}
+
+async fn snapshot(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    nft: &TestNft,
+    seller: &Keypair,
+    listing: &solana_sdk::pubkey::Pubkey,
+    buyer: &Keypair,
+) -> Balances {
+    let mut creators = vec![];
+    for creator in &nft.creators {
+        let account = ah.payment_account(creator);
+        creators.push(balance_of(context, ah, &account).await);
+    }
+    let treasury = balance_of(context, ah, &ah.treasury).await;
+    let seller_account = ah.payment_account(&seller.pubkey());
+    let mut seller_balance = balance_of(context, ah, &seller_account).await;
+    // Native proceeds go to the seller wallet, which also gets its listing rent back, so the listing counts as the
+    // seller's until then.
+    if ah.is_native() {
+        seller_balance += get_lamports(context, listing).await;
+    }
+    let (escrow_address, _) =
+        auction_house_v2::pda::find_escrow_payment_address(&ah.address, &buyer.pubkey());
+    let escrow = balance_of(context, ah, &escrow_address).await;
+    Balances {
+        creators,
+        treasury,
+        seller: seller_balance,
+        escrow,
+    }
+}
+
+/// Lists the NFT, places a matching bid and settles it, returning the balances before and after.
+async fn settle(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    treasury_mint_authority: Option<&Keypair>,
+    price: u64,
+) -> (TestNft, Balances, Balances) {
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+
+    let creators = [(Keypair::new().pubkey(), 60), (Keypair::new().pubkey(), 40)];
+    if ah.is_native() {
+        airdrop(context, &creators[0].0, ONE_SOL).await;
+        airdrop(context, &creators[1].0, ONE_SOL).await;
+        airdrop(context, &buyer.pubkey(), price).await;
+    } else {
+        let buyer_payment_account = create_ata(context, &buyer.pubkey(), &ah.treasury_mint).await;
+        let mint_authority = treasury_mint_authority.unwrap();
+        mint_to(
+            context,
+            &ah.treasury_mint,
+            mint_authority,
+            &buyer_payment_account,
+            price,
+        )
+        .await;
+    }
+    let nft = create_nft(context, &seller.pubkey(), &creators, ROYALTY_BPS, 1).await;
+
+    let sell = sell_ix(ah, &seller.pubkey(), &nft, price, 1);
+    process_tx(context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(ah, &buyer.pubkey(), &nft, price, 1);
+    process_tx(context, &[buy], &[&buyer]).await.unwrap();
+
+    let listing = trade_state_address(ah, &seller.pubkey(), &nft, price, 1);
+    let before = snapshot(context, ah, &nft, &seller, &listing, &buyer).await;
+    let execute_sale = execute_sale_ix(ah, &buyer.pubkey(), &seller.pubkey(), &nft, price, 1);
+    process_tx(context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    let after = snapshot(context, ah, &nft, &seller, &listing, &buyer).await;
+
+    let (escrow_nft, _) =
+        find_escrow_nft_address(&ah.address, &seller.pubkey(), &nft.mint, &buyer.pubkey());
+    assert_eq!(get_token_balance(context, &escrow_nft).await, 1);
+
+    (nft, before, after)
+}
+
+fn assert_settlement(before: &Balances, after: &Balances, price: u64) {
+    let royalties = price * ROYALTY_BPS as u64 / 10000;
+    let creator_paid: Vec<u64> = after
+        .creators
+        .iter()
+        .zip(&before.creators)
+        .map(|(after, before)| after - before)
+        .collect();
+    assert_eq!(creator_paid[0], royalties * 60 / 100);
+    assert_eq!(creator_paid[1], royalties * 40 / 100);
+
+    let house_fee = after.treasury - before.treasury;
+    assert_eq!(house_fee, price * HOUSE_FEE_BPS as u64 / 10000);
+
+    let seller_paid = after.seller - before.seller;
+    assert_eq!(
+        creator_paid.iter().sum::<u64>() + house_fee + seller_paid,
+        price
+    );
+    assert_eq!(before.escrow - after.escrow, price);
+}
+
+#[tokio::test]
+async fn execute_sale_settles_native_payment() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+
+    let price = ONE_SOL;
+    let (_, before, after) = settle(&mut context, &ah, None, price).await;
+    assert_settlement(&before, &after, price);
+}
+
+#[tokio::test]
+async fn execute_sale_settles_spl_payment() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_mint(&mut context, &mint_authority.pubkey(), 6).await;
+    let ah = create_auction_house(
+        &mut context,
+        &treasury_mint.pubkey(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+
+    let price = 5_000_000;
+    let (_, before, after) = settle(&mut context, &ah, Some(&mint_authority), price).await;
+    assert_settlement(&before, &after, price);
+}
+
+#[tokio::test]
+async fn execute_sale_returns_rounding_dust_to_seller() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_mint(&mut context, &mint_authority.pubkey(), 0).await;
+    let ah = create_auction_house(
+        &mut context,
+        &treasury_mint.pubkey(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+
+    // Neither fee divides evenly, so the remainder must end up with the seller.
+    let price = 1_003;
+    let (_, before, after) = settle(&mut context, &ah, Some(&mint_authority), price).await;
+
+    let creator_paid: u64 = after
+        .creators
+        .iter()
+        .zip(&before.creators)
+        .map(|(after, before)| after - before)
+        .sum();
+    let house_fee = after.treasury - before.treasury;
+    let seller_paid = after.seller - before.seller;
+    assert_eq!(creator_paid + house_fee + seller_paid, price);
+    assert_eq!(before.escrow - after.escrow, price);
+}
+
+#[tokio::test]
+async fn execute_sale_closes_trade_states() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+
+    let trade_states = [
+        trade_state_address(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1),
+        trade_state_address(&ah, &seller.pubkey(), &nft, ONE_SOL, 1),
+    ];
+    let trade_state_rent = [
+        get_lamports(&mut context, &trade_states[0]).await,
+        get_lamports(&mut context, &trade_states[1]).await,
+    ];
+    let fee_account_lamports = get_lamports(&mut context, &ah.fee_account).await;
+    let buyer_lamports = get_lamports(&mut context, &buyer.pubkey()).await;
+    let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    for trade_state in &trade_states {
+        assert!(get_account(&mut context, trade_state).await.is_none());
+    }
+    // The authority settled, so the fee account paid for the new token accounts, the escrow record and trader stats.
+    // The trade state rent goes back to the wallets that paid it.
+    let (escrow_nft, _) =
+        find_escrow_nft_address(&ah.address, &seller.pubkey(), &nft.mint, &buyer.pubkey());
+    let escrow_nft_record = find_escrow_nft_record_address(&escrow_nft).0;
+    let buyer_receipt = get_associated_token_address(&buyer.pubkey(), &nft.mint);
+    let mut new_account_rent = get_lamports(&mut context, &escrow_nft).await
+        + get_lamports(&mut context, &escrow_nft_record).await
+        + get_lamports(&mut context, &buyer_receipt).await;
+    for wallet in [buyer.pubkey(), seller.pubkey()] {
+        new_account_rent += get_lamports(&mut context, &trader_stats_address(&ah, &wallet)).await;
+    }
+    assert_eq!(
+        get_lamports(&mut context, &ah.fee_account).await,
+        fee_account_lamports - new_account_rent
+    );
+    assert_eq!(
+        get_lamports(&mut context, &buyer.pubkey()).await,
+        buyer_lamports + trade_state_rent[0]
+    );
+    let house_fee = ONE_SOL * HOUSE_FEE_BPS as u64 / 10000;
+    assert_eq!(
+        get_lamports(&mut context, &seller.pubkey()).await,
+        seller_lamports + ONE_SOL - house_fee + trade_state_rent[1]
+    );
+}
+
+#[tokio::test]
+async fn buyer_signed_sale_refunds_listing_rent_to_seller() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+
+    let seller_trade_state = trade_state_address(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let listing_rent = get_lamports(&mut context, &seller_trade_state).await;
+    let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+
+    // The buyer settles and pays for the new accounts, but the listing rent is the seller's.
+    let mut execute_sale =
+        execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    for meta in execute_sale.accounts.iter_mut() {
+        meta.is_signer = meta.pubkey == buyer.pubkey();
+    }
+    process_tx(&mut context, &[execute_sale], &[&buyer])
+        .await
+        .unwrap();
+
+    assert!(get_account(&mut context, &seller_trade_state)
+        .await
+        .is_none());
+    let house_fee = ONE_SOL * HOUSE_FEE_BPS as u64 / 10000;
+    assert_eq!(
+        get_lamports(&mut context, &seller.pubkey()).await,
+        seller_lamports + ONE_SOL - house_fee + listing_rent
+    );
+}
+
+#[tokio::test]
+async fn filled_order_cannot_execute_twice() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let sale = execute_native_sale(&mut context, &ah, ONE_SOL).await;
+
+    // Replaying the settlement finds no seller trade state to match.
+    let execute_sale = execute_sale_ix(
+        &ah,
+        &sale.buyer.pubkey(),
+        &sale.seller.pubkey(),
+        &sale.nft,
+        ONE_SOL,
+        1,
+    );
+    let result = process_tx(
+        &mut context,
+        std::slice::from_ref(&execute_sale),
+        &[&ah.authority],
+    )
+    .await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    // A fresh bid at the same price cannot revive the filled listing either.
+    airdrop(&mut context, &sale.buyer.pubkey(), ONE_SOL).await;
+    let buy = buy_ix(&ah, &sale.buyer.pubkey(), &sale.nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&sale.buyer])
+        .await
+        .unwrap();
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+}
@@ -0,0 +1,146 @@
+mod utils;
+
+use auction_house_v2::errors::AuctionHouseError;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const PRICE: u64 = ONE_SOL;
+const LIFETIME: i64 = 100;
+
+struct TestOrders {
+    ah: TestAuctionHouse,
+    seller: Keypair,
+    buyer: Keypair,
+    nft: TestNft,
+    expiry: i64,
+}
+
+/// List a fresh royalty-free NFT with an expiry and bid on it without one.
+async fn place_orders(context: &mut ProgramTestContext) -> TestOrders {
+    let ah = create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), ONE_SOL + PRICE).await;
+    let nft = create_nft(context, &seller.pubkey(), &[], 0, 1).await;
+
+    let expiry = get_clock(context).await.unix_timestamp + LIFETIME;
+    let sell = sell_with_expiry_ix(&ah, &seller.pubkey(), &nft, PRICE, 1, Some(expiry));
+    process_tx(context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    process_tx(context, &[buy], &[&buyer]).await.unwrap();
+
+    TestOrders {
+        ah,
+        seller,
+        buyer,
+        nft,
+        expiry,
+    }
+}
+
+#[tokio::test]
+async fn sale_rejected_after_expiry() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let orders = place_orders(&mut context).await;
+
+    set_clock(&mut context, orders.expiry).await;
+    let execute_sale = execute_sale_ix(
+        &orders.ah,
+        &orders.buyer.pubkey(),
+        &orders.seller.pubkey(),
+        &orders.nft,
+        PRICE,
+        1,
+    );
+    let result = process_tx(&mut context, &[execute_sale], &[&orders.ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::TradeStateExpired);
+
+    // An expiry that has already passed cannot be set.
+    let buy = buy_with_expiry_ix(
+        &orders.ah,
+        &orders.buyer.pubkey(),
+        &orders.nft,
+        PRICE,
+        1,
+        Some(orders.expiry),
+    );
+    let result = process_tx(&mut context, &[buy], &[&orders.buyer]).await;
+    assert_auction_house_error(result, AuctionHouseError::InvalidExpiry);
+}
+
+#[tokio::test]
+async fn anyone_closes_expired_listing() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let orders = place_orders(&mut context).await;
+    let seller_trade_state =
+        trade_state_address(&orders.ah, &orders.seller.pubkey(), &orders.nft, PRICE, 1);
+    let rent = get_lamports(&mut context, &seller_trade_state).await;
+    let seller_lamports = get_lamports(&mut context, &orders.seller.pubkey()).await;
+
+    set_clock(&mut context, orders.expiry).await;
+    let close = close_expired_trade_state_ix(
+        &orders.ah,
+        &orders.seller.pubkey(),
+        &orders.nft,
+        &orders.seller.pubkey(),
+        PRICE,
+        1,
+    );
+    process_tx(&mut context, &[close], &[]).await.unwrap();
+
+    assert!(get_account(&mut context, &seller_trade_state)
+        .await
+        .is_none());
+    assert_eq!(
+        get_lamports(&mut context, &orders.seller.pubkey()).await,
+        seller_lamports + rent
+    );
+}
+
+#[tokio::test]
+async fn close_requires_expired_trade_state() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let orders = place_orders(&mut context).await;
+
+    let close = close_expired_trade_state_ix(
+        &orders.ah,
+        &orders.seller.pubkey(),
+        &orders.nft,
+        &orders.seller.pubkey(),
+        PRICE,
+        1,
+    );
+    let result = process_tx(&mut context, std::slice::from_ref(&close), &[]).await;
+    assert_auction_house_error(result, AuctionHouseError::TradeStateNotExpired);
+
+    set_clock(&mut context, orders.expiry).await;
+
+    // The bid never expires.
+    let close_bid = close_expired_trade_state_ix(
+        &orders.ah,
+        &orders.buyer.pubkey(),
+        &orders.nft,
+        &orders.buyer.pubkey(),
+        PRICE,
+        1,
+    );
+    let result = process_tx(&mut context, &[close_bid], &[]).await;
+    assert_auction_house_error(result, AuctionHouseError::TradeStateNotExpired);
+
+    // The rent only goes back to the account that paid it.
+    let wrong_payer = close_expired_trade_state_ix(
+        &orders.ah,
+        &orders.seller.pubkey(),
+        &orders.nft,
+        &orders.buyer.pubkey(),
+        PRICE,
+        1,
+    );
+    let result = process_tx(&mut context, &[wrong_payer], &[]).await;
+    assert_auction_house_error(result, AuctionHouseError::PublicKeyMismatch);
+
+    process_tx(&mut context, &[close], &[]).await.unwrap();
+}
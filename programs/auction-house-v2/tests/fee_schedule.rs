@@ -0,0 +1,109 @@
+mod utils;
+
+use auction_house_v2::{errors::AuctionHouseError, FeeSchedule};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const HOUSE_FEE_BPS: u16 = 250;
+const PRICE: u64 = ONE_SOL;
+
+/// Sell a fresh verified member of `collection` from `seller` to `buyer` at `PRICE`, returning what the treasury
+/// collected.
+async fn sell_member(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    collection: &TestCollection,
+    seller: &Keypair,
+    buyer: &Keypair,
+) -> u64 {
+    let nft = create_collection_nft(context, &seller.pubkey(), collection, true).await;
+    list_and_bid(context, ah, seller, buyer, &nft, PRICE).await;
+    let treasury_before = get_lamports(context, &ah.treasury).await;
+    let execute_sale = execute_sale_ix(ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    process_tx(context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    get_lamports(context, &ah.treasury).await - treasury_before
+}
+
+#[tokio::test]
+async fn fee_schedule_overrides_house_rate_for_collection() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, HOUSE_FEE_BPS).await;
+    let collection = create_collection(&mut context).await;
+    let create = create_fee_schedule_ix(&ah, &collection.mint, 100);
+    process_tx(&mut context, &[create], &[&ah.authority])
+        .await
+        .unwrap();
+    let fee_schedule: FeeSchedule =
+        get_anchor_account(&mut context, &fee_schedule_address(&ah, &collection.mint)).await;
+    assert_eq!(fee_schedule.auction_house, ah.address);
+    assert_eq!(fee_schedule.collection, collection.mint);
+    assert_eq!(fee_schedule.seller_fee_basis_points, 100);
+
+    let house_fee = sell_member(&mut context, &ah, &collection, &seller, &buyer).await;
+    assert_eq!(house_fee, PRICE * 100 / 10_000);
+
+    let update = update_fee_schedule_ix(&ah, &collection.mint, 0);
+    process_tx(&mut context, &[update], &[&ah.authority])
+        .await
+        .unwrap();
+    let house_fee = sell_member(&mut context, &ah, &collection, &seller, &buyer).await;
+    assert_eq!(house_fee, 0);
+
+    let close = close_fee_schedule_ix(&ah, &collection.mint);
+    process_tx(&mut context, &[close], &[&ah.authority])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_lamports(&mut context, &fee_schedule_address(&ah, &collection.mint)).await,
+        0
+    );
+    let house_fee = sell_member(&mut context, &ah, &collection, &seller, &buyer).await;
+    assert_eq!(house_fee, PRICE * HOUSE_FEE_BPS as u64 / 10_000);
+}
+
+#[tokio::test]
+async fn sale_requires_fee_schedule_address_for_verified_collection() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, HOUSE_FEE_BPS).await;
+    let collection = create_collection(&mut context).await;
+    let create = create_fee_schedule_ix(&ah, &collection.mint, 500);
+    process_tx(&mut context, &[create], &[&ah.authority])
+        .await
+        .unwrap();
+    let nft = create_collection_nft(&mut context, &seller.pubkey(), &collection, true).await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, PRICE).await;
+
+    // Leaving the schedule out would settle at the house rate instead of the collection's.
+    let fee_schedule = fee_schedule_address(&ah, &collection.mint);
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    execute_sale
+        .accounts
+        .retain(|account| account.pubkey != fee_schedule);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::FeeScheduleMismatch);
+
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    let other = create_collection(&mut context).await;
+    for account in execute_sale.accounts.iter_mut() {
+        if account.pubkey == fee_schedule {
+            account.pubkey = fee_schedule_address(&ah, &other.mint);
+        }
+    }
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::FeeScheduleMismatch);
+}
+
+#[tokio::test]
+async fn create_fee_schedule_rejects_invalid_basis_points() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, _, _) = setup_native_sale(&mut context, HOUSE_FEE_BPS).await;
+    let collection = create_collection(&mut context).await;
+
+    let create = create_fee_schedule_ix(&ah, &collection.mint, 10_001);
+    let result = process_tx(&mut context, &[create], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InvalidBasisPoints);
+}
@@ -0,0 +1,284 @@
+mod utils;
+
+use auction_house_v2::{
+    pda::*,
+    receipt::{BidReceipt, ListingReceipt, PurchaseReceipt},
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const HOUSE_FEE_BPS: u16 = 250;
+const ROYALTY_BPS: u16 = 500;
+
+async fn balance_of(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    address: &Pubkey,
+) -> u64 {
+    if ah.is_native() {
+        get_lamports(context, address).await
+    } else {
+        get_token_balance(context, address).await
+    }
+}
+
+/// Give `wallet` `amount` of the house's treasury mint on top of lamports for rent.
+async fn fund(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    treasury_mint_authority: Option<&Keypair>,
+    wallet: &Pubkey,
+    amount: u64,
+) {
+    airdrop(context, wallet, ONE_SOL).await;
+    match treasury_mint_authority {
+        None => airdrop(context, wallet, amount).await,
+        Some(mint_authority) => {
+            let payment_account = create_ata(context, wallet, &ah.treasury_mint).await;
+            mint_to(
+                context,
+                &ah.treasury_mint,
+                mint_authority,
+                &payment_account,
+                amount,
+            )
+            .await;
+        }
+    }
+}
+
+/// Deposit, list, bid, settle and claim an NFT with receipts for every step, withdraw what is
+/// left in the buyer's escrow, then have the buyer relist the NFT and cancel that listing.
+async fn run_lifecycle(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    treasury_mint_authority: Option<&Keypair>,
+    price: u64,
+) {
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    let creator = Keypair::new().pubkey();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &creator, ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    airdrop(context, &ah.authority.pubkey(), ONE_SOL).await;
+    fund(
+        context,
+        ah,
+        treasury_mint_authority,
+        &buyer.pubkey(),
+        2 * price,
+    )
+    .await;
+    let nft = create_nft(context, &seller.pubkey(), &[(creator, 100)], ROYALTY_BPS, 1).await;
+
+    // Deposit
+    let (escrow, _) = find_escrow_payment_address(&ah.address, &buyer.pubkey());
+    let deposit = deposit_ix(ah, &buyer.pubkey(), 2 * price);
+    process_tx(context, &[deposit], &[&buyer]).await.unwrap();
+    let escrow_funded = balance_of(context, ah, &escrow).await;
+    assert!(escrow_funded >= 2 * price);
+
+    // Sell
+    let seller_trade_state = trade_state_address(ah, &seller.pubkey(), &nft, price, 1);
+    let sell = sell_ix(ah, &seller.pubkey(), &nft, price, 1);
+    let listing_receipt = print_listing_receipt_ix(&seller.pubkey(), &seller_trade_state);
+    process_tx(context, &[sell, listing_receipt], &[&seller])
+        .await
+        .unwrap();
+
+    // Buy
+    let buyer_trade_state = trade_state_address(ah, &buyer.pubkey(), &nft, price, 1);
+    let buy = buy_ix(ah, &buyer.pubkey(), &nft, price, 1);
+    let bid_receipt = print_bid_receipt_ix(&buyer.pubkey(), &buyer_trade_state);
+    process_tx(context, &[buy, bid_receipt], &[&buyer])
+        .await
+        .unwrap();
+    // The deposit already covers the bid, so nothing more is pulled from the buyer.
+    assert_eq!(balance_of(context, ah, &escrow).await, escrow_funded);
+
+    // Execute sale
+    let seller_payment_account = ah.payment_account(&seller.pubkey());
+    let creator_payment_account = ah.payment_account(&creator);
+    let mut seller_before = balance_of(context, ah, &seller_payment_account).await;
+    // Native proceeds go to the seller wallet, which also gets its listing rent back.
+    if ah.is_native() {
+        seller_before += get_lamports(context, &seller_trade_state).await;
+    }
+    let creator_before = balance_of(context, ah, &creator_payment_account).await;
+    let treasury_before = balance_of(context, ah, &ah.treasury).await;
+    let execute_sale = execute_sale_ix(ah, &buyer.pubkey(), &seller.pubkey(), &nft, price, 1);
+    let purchase_receipt = print_purchase_receipt_ix(
+        &ah.authority.pubkey(),
+        &seller_trade_state,
+        &buyer_trade_state,
+        None,
+    );
+    process_tx(context, &[execute_sale, purchase_receipt], &[&ah.authority])
+        .await
+        .unwrap();
+    let claim = claim_nft_ix(
+        ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &ah.fee_account,
+        &buyer.pubkey(),
+    );
+    process_tx(context, &[claim], &[&buyer]).await.unwrap();
+
+    let royalties = price * ROYALTY_BPS as u64 / 10_000;
+    let house_fee = price * HOUSE_FEE_BPS as u64 / 10_000;
+    assert_eq!(
+        balance_of(context, ah, &creator_payment_account).await - creator_before,
+        royalties
+    );
+    assert_eq!(
+        balance_of(context, ah, &ah.treasury).await - treasury_before,
+        house_fee
+    );
+    assert_eq!(
+        balance_of(context, ah, &seller_payment_account).await - seller_before,
+        price - royalties - house_fee
+    );
+    assert_eq!(
+        balance_of(context, ah, &escrow).await,
+        escrow_funded - price
+    );
+    assert_eq!(get_token_balance(context, &nft.token_account).await, 0);
+    let buyer_token_account = nft.token_account_of(&buyer.pubkey());
+    assert_eq!(get_token_balance(context, &buyer_token_account).await, 1);
+    assert_eq!(get_lamports(context, &seller_trade_state).await, 0);
+    assert_eq!(get_lamports(context, &buyer_trade_state).await, 0);
+
+    // Receipts
+    let (purchase_receipt, _) =
+        find_purchase_receipt_address(&seller_trade_state, &buyer_trade_state);
+    let purchase: PurchaseReceipt = get_anchor_account(context, &purchase_receipt).await;
+    assert_eq!(purchase.auction_house, ah.address);
+    assert_eq!(purchase.seller, seller.pubkey());
+    assert_eq!(purchase.buyer, buyer.pubkey());
+    assert_eq!(purchase.metadata, nft.metadata);
+    assert_eq!(purchase.price, price);
+    assert_eq!(purchase.token_size, 1);
+    let (listing_receipt, _) = find_listing_receipt_address(&seller_trade_state);
+    let listing: ListingReceipt = get_anchor_account(context, &listing_receipt).await;
+    assert_eq!(listing.seller, seller.pubkey());
+    assert_eq!(listing.price, price);
+    assert_eq!(listing.purchase_receipt, Some(purchase_receipt));
+    let (bid_receipt, _) = find_bid_receipt_address(&buyer_trade_state);
+    let bid: BidReceipt = get_anchor_account(context, &bid_receipt).await;
+    assert_eq!(bid.buyer, buyer.pubkey());
+    assert_eq!(bid.price, price);
+    assert_eq!(bid.purchase_receipt, Some(purchase_receipt));
+
+    // Withdraw
+    let buyer_payment_account = ah.payment_account(&buyer.pubkey());
+    let buyer_before = balance_of(context, ah, &buyer_payment_account).await;
+    let withdraw = withdraw_ix(ah, &buyer.pubkey(), price);
+    process_tx(context, &[withdraw], &[&buyer]).await.unwrap();
+    assert_eq!(
+        balance_of(context, ah, &buyer_payment_account).await - buyer_before,
+        price
+    );
+    assert_eq!(
+        balance_of(context, ah, &escrow).await,
+        escrow_funded - 2 * price
+    );
+
+    // Cancel
+    let owned = TestNft {
+        token_account: buyer_token_account,
+        ..nft
+    };
+    let relist_trade_state = trade_state_address(ah, &buyer.pubkey(), &owned, 2 * price, 1);
+    let relist = sell_ix(ah, &buyer.pubkey(), &owned, 2 * price, 1);
+    let relist_receipt = print_listing_receipt_ix(&buyer.pubkey(), &relist_trade_state);
+    process_tx(context, &[relist, relist_receipt], &[&buyer])
+        .await
+        .unwrap();
+    let cancel = cancel_ix(ah, &buyer.pubkey(), &owned, 2 * price, 1);
+    let cancel_receipt = cancel_listing_receipt_ix(&relist_trade_state);
+    process_tx(context, &[cancel, cancel_receipt], &[&buyer])
+        .await
+        .unwrap();
+
+    assert_eq!(get_lamports(context, &relist_trade_state).await, 0);
+    let (relist_receipt, _) = find_listing_receipt_address(&relist_trade_state);
+    let listing: ListingReceipt = get_anchor_account(context, &relist_receipt).await;
+    assert!(listing.canceled_at.is_some());
+    assert_eq!(listing.purchase_receipt, None);
+    assert_eq!(get_token_balance(context, &buyer_token_account).await, 1);
+}
+
+#[tokio::test]
+async fn native_trade_lifecycle() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+
+    run_lifecycle(&mut context, &ah, None, ONE_SOL).await;
+}
+
+#[tokio::test]
+async fn spl_trade_lifecycle() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_mint(&mut context, &mint_authority.pubkey(), 6).await;
+    let ah = create_auction_house(
+        &mut context,
+        &treasury_mint.pubkey(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+
+    run_lifecycle(&mut context, &ah, Some(&mint_authority), 5_000_000).await;
+}
+
+#[tokio::test]
+async fn authority_cancel_refunds_the_rent_payer() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    airdrop(&mut context, &ah.authority.pubkey(), ONE_SOL).await;
+    let seller = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    // The seller lists on their own, so their wallet pays the trade state rent.
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let trade_state = trade_state_address(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    let rent = get_lamports(&mut context, &trade_state).await;
+    let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+    let fee_account_lamports = get_lamports(&mut context, &ah.fee_account).await;
+
+    let mut cancel = cancel_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    for meta in cancel.accounts.iter_mut() {
+        meta.is_signer = meta.pubkey == ah.authority.pubkey();
+    }
+    process_tx(&mut context, &[cancel], &[&ah.authority])
+        .await
+        .unwrap();
+
+    assert!(get_account(&mut context, &trade_state).await.is_none());
+    assert_eq!(
+        get_lamports(&mut context, &seller.pubkey()).await,
+        seller_lamports + rent
+    );
+    assert_eq!(
+        get_lamports(&mut context, &ah.fee_account).await,
+        fee_account_lamports
+    );
+}
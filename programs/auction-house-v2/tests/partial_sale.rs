@@ -0,0 +1,180 @@
+mod utils;
+
+use auction_house_v2::{errors::AuctionHouseError, pda::*, receipt::PurchaseReceipt};
+use solana_program::program_pack::Pack;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const SUPPLY: u64 = 10;
+
+/// Native house and a seller holding `SUPPLY` tokens of a royalty-free semi-fungible mint.
+async fn setup(context: &mut ProgramTestContext) -> (TestAuctionHouse, Keypair, TestNft) {
+    let ah = create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await;
+    let seller = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    airdrop(context, &ah.authority.pubkey(), ONE_SOL).await;
+    let nft = create_nft(context, &seller.pubkey(), &[], 0, SUPPLY).await;
+    (ah, seller, nft)
+}
+
+async fn funded_buyer(context: &mut ProgramTestContext) -> Keypair {
+    let buyer = Keypair::new();
+    airdrop(context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    buyer
+}
+
+async fn delegated_amount(context: &mut ProgramTestContext, nft: &TestNft) -> u64 {
+    let account = get_account(context, &nft.token_account).await.unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .delegated_amount
+}
+
+#[tokio::test]
+async fn partial_sales_fill_listing_until_sold_out() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, nft) = setup(&mut context).await;
+    let price = ONE_SOL;
+    let seller_trade_state = trade_state_address(&ah, &seller.pubkey(), &nft, price, SUPPLY);
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, price, SUPPLY);
+    let listing_receipt = print_listing_receipt_ix(&seller.pubkey(), &seller_trade_state);
+    process_tx(&mut context, &[sell, listing_receipt], &[&seller])
+        .await
+        .unwrap();
+
+    let first = funded_buyer(&mut context).await;
+    let first_trade_state = trade_state_address(&ah, &first.pubkey(), &nft, 3 * price / 10, 3);
+    let buy = buy_ix(&ah, &first.pubkey(), &nft, 3 * price / 10, 3);
+    let bid_receipt = print_bid_receipt_ix(&first.pubkey(), &first_trade_state);
+    process_tx(&mut context, &[buy, bid_receipt], &[&first])
+        .await
+        .unwrap();
+    let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+    let execute_sale = execute_partial_sale_ix(
+        &ah,
+        &first.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        price,
+        SUPPLY,
+        3,
+        3 * price / 10,
+    );
+    let purchase_receipt = print_purchase_receipt_ix(
+        &ah.authority.pubkey(),
+        &seller_trade_state,
+        &first_trade_state,
+        None,
+    );
+    process_tx(
+        &mut context,
+        &[execute_sale, purchase_receipt],
+        &[&ah.authority],
+    )
+    .await
+    .unwrap();
+    let claim = claim_nft_ix(
+        &ah,
+        &first.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &ah.fee_account,
+        &first.pubkey(),
+    );
+    process_tx(&mut context, &[claim], &[&first]).await.unwrap();
+
+    assert_eq!(
+        get_lamports(&mut context, &seller.pubkey()).await,
+        seller_lamports + 3 * price / 10
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &nft.token_account_of(&first.pubkey())).await,
+        3
+    );
+    assert_eq!(delegated_amount(&mut context, &nft).await, SUPPLY - 3);
+    assert!(get_lamports(&mut context, &seller_trade_state).await > 0);
+    let (purchase_receipt, _) =
+        find_purchase_receipt_address(&seller_trade_state, &first_trade_state);
+    let purchase: PurchaseReceipt = get_anchor_account(&mut context, &purchase_receipt).await;
+    assert_eq!(purchase.price, 3 * price / 10);
+    assert_eq!(purchase.token_size, 3);
+
+    let second = funded_buyer(&mut context).await;
+    let buy = buy_ix(&ah, &second.pubkey(), &nft, 7 * price / 10, 7);
+    process_tx(&mut context, &[buy], &[&second]).await.unwrap();
+    let execute_sale = execute_partial_sale_ix(
+        &ah,
+        &second.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        price,
+        SUPPLY,
+        7,
+        7 * price / 10,
+    );
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 0);
+    assert_eq!(get_lamports(&mut context, &seller_trade_state).await, 0);
+}
+
+#[tokio::test]
+async fn partial_sale_rejects_rounded_down_price() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, nft) = setup(&mut context).await;
+    // A third of the listing is 333_333_333.33 lamports, which truncating division rounds down.
+    let price = ONE_SOL;
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, price, 3);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buyer = funded_buyer(&mut context).await;
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, price / 3, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+
+    let execute_sale = execute_partial_sale_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        price,
+        3,
+        1,
+        price / 3,
+    );
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::PartialPriceMismatch);
+}
+
+#[tokio::test]
+async fn partial_sale_rejects_more_than_listed() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, nft) = setup(&mut context).await;
+    let price = ONE_SOL / 2;
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, price, 2);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buyer = funded_buyer(&mut context).await;
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, 2 * price, 4);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+
+    let execute_sale = execute_partial_sale_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        price,
+        2,
+        4,
+        2 * price,
+    );
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+
+    assert_auction_house_error(
+        result,
+        AuctionHouseError::NotEnoughTokensAvailableForPurchase,
+    );
+    assert_eq!(delegated_amount(&mut context, &nft).await, 2);
+}
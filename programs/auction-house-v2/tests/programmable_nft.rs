@@ -0,0 +1,245 @@
+mod utils;
+
+use anchor_lang::error::ErrorCode;
+use auction_house_v2::{
+    errors::AuctionHouseError,
+    pda::{find_escrow_nft_address, find_program_as_signer_address},
+    AuthorityScope,
+};
+use mpl_token_metadata::{
+    error::MetadataError,
+    state::{TokenDelegateRole, TokenMetadataAccount, TokenRecord, TokenState},
+};
+use solana_program::{
+    instruction::AccountMeta, program_option::COption, program_pack::Pack, pubkey::Pubkey,
+};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+async fn get_token_record(context: &mut ProgramTestContext, nft: &TestNft) -> TokenRecord {
+    let address = programmable_accounts(nft, &nft.token_account)[2].pubkey;
+    let account = get_account(context, &address).await.unwrap();
+    TokenRecord::safe_deserialize(&account.data).unwrap()
+}
+
+#[tokio::test]
+async fn programmable_nft_sells_to_buyer() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let nft = create_programmable_nft(&mut context, &seller.pubkey()).await;
+
+    let sell = programmable_sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let record = get_token_record(&mut context, &nft).await;
+    assert_eq!(record.delegate, Some(find_program_as_signer_address().0));
+    assert_eq!(record.delegate_role, Some(TokenDelegateRole::Sale));
+    assert_eq!(record.state, TokenState::Listed);
+
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+    let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+    let execute_sale =
+        programmable_execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    assert!(get_lamports(&mut context, &seller.pubkey()).await >= seller_lamports + ONE_SOL);
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 0);
+    assert_eq!(
+        get_token_balance(&mut context, &nft.token_account_of(&buyer.pubkey())).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn cancelling_programmable_listing_revokes_sale_delegate() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _) = setup_native_sale(&mut context, 0).await;
+    let nft = create_programmable_nft(&mut context, &seller.pubkey()).await;
+    let sell = programmable_sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+
+    let cancel = programmable_cancel_ix(&ah, &seller.pubkey(), &nft, ONE_SOL);
+    process_tx(&mut context, &[cancel], &[&seller])
+        .await
+        .unwrap();
+
+    let record = get_token_record(&mut context, &nft).await;
+    assert_eq!(record.delegate, None);
+    assert_eq!(record.state, TokenState::Unlocked);
+    let account = get_account(&mut context, &nft.token_account).await.unwrap();
+    let token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+    assert_eq!(token_account.delegate, COption::None);
+}
+
+#[tokio::test]
+async fn programmable_cancel_rejects_regular_nft() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _) = setup_native_sale(&mut context, 0).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+
+    let cancel = programmable_cancel_ix(&ah, &seller.pubkey(), &nft, ONE_SOL);
+    let result = process_tx(&mut context, &[cancel], &[&seller]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::NotProgrammableNft);
+}
+
+#[tokio::test]
+async fn listing_must_pass_the_rule_set() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, _) = setup_native_sale(&mut context, 0).await;
+    let nft = create_programmable_nft_with_rule_set(
+        &mut context,
+        &seller.pubkey(),
+        Some(Pubkey::new_unique()),
+    )
+    .await;
+
+    // Token Metadata checks the rule set before asking Token Auth Rules to validate the delegation, so a listing
+    // can't skip it by passing the placeholder accounts of an NFT without one. The validation itself isn't run here,
+    // as the natively run Token Metadata leaves out accounts of that call the test runtime requires.
+    let sell = programmable_sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL);
+    let result = process_tx(&mut context, &[sell], &[&seller]).await;
+
+    assert_metadata_error(result, MetadataError::MissingAuthorizationRules);
+    assert_eq!(
+        get_token_record(&mut context, &nft).await.state,
+        TokenState::Unlocked
+    );
+}
+
+#[tokio::test]
+async fn programmable_nft_fills_through_partial_sale() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let nft = create_programmable_nft(&mut context, &seller.pubkey()).await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, ONE_SOL).await;
+
+    // A programmable NFT is a single token, so the only partial fill takes all of it.
+    let mut execute_sale = execute_partial_sale_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        ONE_SOL,
+        1,
+        1,
+        ONE_SOL,
+    );
+    execute_sale
+        .accounts
+        .extend(programmable_sale_accounts(&nft, &buyer.pubkey()));
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 0);
+    assert_eq!(
+        get_token_balance(&mut context, &nft.token_account_of(&buyer.pubkey())).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn programmable_sale_skips_the_nft_escrow() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let nft = create_programmable_nft(&mut context, &seller.pubkey()).await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, ONE_SOL).await;
+    let execute_sale =
+        programmable_execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    // The buyer already holds the NFT, so there is nothing to claim or refund.
+    let escrow_nft =
+        find_escrow_nft_address(&ah.address, &seller.pubkey(), &nft.mint, &buyer.pubkey()).0;
+    assert!(get_account(&mut context, &escrow_nft).await.is_none());
+    let claim = claim_nft_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &ah.fee_account,
+        &buyer.pubkey(),
+    );
+    let result = process_tx(&mut context, &[claim], &[&buyer]).await;
+    assert_anchor_error(result, ErrorCode::AccountNotInitialized);
+    let refund = refund_nft_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &ah.fee_account,
+    );
+    let result = process_tx(&mut context, &[refund], &[&ah.authority, &seller]).await;
+    assert_anchor_error(result, ErrorCode::AccountNotInitialized);
+    assert_eq!(
+        get_token_balance(&mut context, &nft.token_account_of(&buyer.pubkey())).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn auctioneer_sells_programmable_nft() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let auctioneer = Keypair::new();
+    let delegate = delegate_auctioneer_ix(
+        &ah,
+        &auctioneer.pubkey(),
+        vec![
+            AuthorityScope::Sell,
+            AuthorityScope::Buy,
+            AuthorityScope::ExecuteSale,
+        ],
+    );
+    process_tx(&mut context, &[delegate], &[&ah.authority])
+        .await
+        .unwrap();
+    let nft = create_programmable_nft(&mut context, &seller.pubkey()).await;
+
+    let mut sell = auctioneer_sell_ix(&ah, &auctioneer.pubkey(), &seller.pubkey(), &nft, 1);
+    sell.accounts
+        .extend(programmable_accounts(&nft, &nft.token_account));
+    sell.accounts
+        .push(AccountMeta::new_readonly(nft.mint, false));
+    process_tx(&mut context, &[sell], &[&seller, &auctioneer])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_token_record(&mut context, &nft).await.state,
+        TokenState::Listed
+    );
+    let buy = auctioneer_buy_ix(&ah, &auctioneer.pubkey(), &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer, &auctioneer])
+        .await
+        .unwrap();
+
+    let mut execute_sale = auctioneer_execute_sale_ix(
+        &ah,
+        &auctioneer.pubkey(),
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        ONE_SOL,
+        1,
+    );
+    execute_sale
+        .accounts
+        .extend(programmable_sale_accounts(&nft, &buyer.pubkey()));
+    process_tx(&mut context, &[execute_sale], &[&ah.authority, &auctioneer])
+        .await
+        .unwrap();
+
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 0);
+    assert_eq!(
+        get_token_balance(&mut context, &nft.token_account_of(&buyer.pubkey())).await,
+        1
+    );
+}
@@ -0,0 +1,88 @@
+mod utils;
+
+use auction_house_v2::{
+    pda::{find_bid_receipt_address, find_public_bid_trade_state_address},
+    receipt::BidReceipt,
+};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+#[tokio::test]
+async fn public_bid_matches_listing() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let buy = public_buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+    let (public_trade_state, _) = find_public_bid_trade_state_address(
+        &buyer.pubkey(),
+        &ah.address,
+        &ah.treasury_mint,
+        &nft.mint,
+        ONE_SOL,
+        1,
+    );
+    assert!(get_account(&mut context, &public_trade_state)
+        .await
+        .is_some());
+
+    // The bid was placed before the listing, so it is not tied to the seller's token account.
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+
+    let execute_sale =
+        execute_public_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let (escrow_nft, _) =
+        find_escrow_nft_address(&ah.address, &seller.pubkey(), &nft.mint, &buyer.pubkey());
+    assert_eq!(get_token_balance(&mut context, &escrow_nft).await, 1);
+    assert!(get_account(&mut context, &public_trade_state)
+        .await
+        .is_none());
+    assert!(get_lamports(&mut context, &seller.pubkey()).await > seller_lamports);
+}
+
+#[tokio::test]
+async fn public_bid_receipt_has_no_token_account() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let holder = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let nft = create_nft(&mut context, &holder.pubkey(), &[], 0, 1).await;
+
+    let (public_trade_state, _) = find_public_bid_trade_state_address(
+        &buyer.pubkey(),
+        &ah.address,
+        &ah.treasury_mint,
+        &nft.mint,
+        ONE_SOL,
+        1,
+    );
+    let buy = public_buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    let print_receipt = print_bid_receipt_ix(&context.payer.pubkey(), &public_trade_state);
+    process_tx(&mut context, &[buy, print_receipt], &[&buyer])
+        .await
+        .unwrap();
+
+    let (receipt_address, _) = find_bid_receipt_address(&public_trade_state);
+    let receipt: BidReceipt = get_anchor_account(&mut context, &receipt_address).await;
+    assert_eq!(receipt.trade_state, public_trade_state);
+    assert_eq!(receipt.buyer, buyer.pubkey());
+    assert_eq!(receipt.metadata, nft.metadata);
+    assert_eq!(receipt.token_account, None);
+    assert_eq!(receipt.price, ONE_SOL);
+}
@@ -0,0 +1,126 @@
+mod utils;
+
+use auction_house_v2::{
+    pda::*,
+    receipt::{
+        BidReceipt, ListingReceipt, BID_RECEIPT_SIZE, LISTING_RECEIPT_SIZE, PURCHASE_RECEIPT_SIZE,
+    },
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const PRICE: u64 = ONE_SOL;
+
+async fn data_len(context: &mut ProgramTestContext, address: &Pubkey) -> usize {
+    get_account(context, address).await.unwrap().data.len()
+}
+
+// Receipts used to be written through the account's own data slice, which shrinks as it is written to.
+#[tokio::test]
+async fn rewritten_receipts_keep_their_size() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    airdrop(&mut context, &ah.authority.pubkey(), ONE_SOL).await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let seller_trade_state = trade_state_address(&ah, &seller.pubkey(), &nft, PRICE, 1);
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, PRICE, 1);
+    let listing_receipt = print_listing_receipt_ix(&seller.pubkey(), &seller_trade_state);
+    process_tx(&mut context, &[sell, listing_receipt], &[&seller])
+        .await
+        .unwrap();
+    let buyer_trade_state = trade_state_address(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    let bid_receipt = print_bid_receipt_ix(&buyer.pubkey(), &buyer_trade_state);
+    process_tx(&mut context, &[buy, bid_receipt], &[&buyer])
+        .await
+        .unwrap();
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    let purchase_receipt = print_purchase_receipt_ix(
+        &ah.authority.pubkey(),
+        &seller_trade_state,
+        &buyer_trade_state,
+        None,
+    );
+    process_tx(
+        &mut context,
+        &[execute_sale, purchase_receipt],
+        &[&ah.authority],
+    )
+    .await
+    .unwrap();
+
+    let (purchase_receipt, _) =
+        find_purchase_receipt_address(&seller_trade_state, &buyer_trade_state);
+    let (listing_receipt, _) = find_listing_receipt_address(&seller_trade_state);
+    let (bid_receipt, _) = find_bid_receipt_address(&buyer_trade_state);
+    assert_eq!(
+        data_len(&mut context, &purchase_receipt).await,
+        PURCHASE_RECEIPT_SIZE
+    );
+    assert_eq!(
+        data_len(&mut context, &listing_receipt).await,
+        LISTING_RECEIPT_SIZE
+    );
+    assert_eq!(data_len(&mut context, &bid_receipt).await, BID_RECEIPT_SIZE);
+    let listing: ListingReceipt = get_anchor_account(&mut context, &listing_receipt).await;
+    assert_eq!(listing.purchase_receipt, Some(purchase_receipt));
+    let bid: BidReceipt = get_anchor_account(&mut context, &bid_receipt).await;
+    assert_eq!(bid.purchase_receipt, Some(purchase_receipt));
+}
+
+#[tokio::test]
+async fn canceled_receipts_keep_their_size() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let seller_trade_state = trade_state_address(&ah, &seller.pubkey(), &nft, PRICE, 1);
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, PRICE, 1);
+    let listing_receipt = print_listing_receipt_ix(&seller.pubkey(), &seller_trade_state);
+    process_tx(&mut context, &[sell, listing_receipt], &[&seller])
+        .await
+        .unwrap();
+    let cancel = cancel_ix(&ah, &seller.pubkey(), &nft, PRICE, 1);
+    let cancel_receipt = cancel_listing_receipt_ix(&seller_trade_state);
+    process_tx(&mut context, &[cancel, cancel_receipt], &[&seller])
+        .await
+        .unwrap();
+
+    let buyer_trade_state = trade_state_address(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    let bid_receipt = print_bid_receipt_ix(&buyer.pubkey(), &buyer_trade_state);
+    process_tx(&mut context, &[buy, bid_receipt], &[&buyer])
+        .await
+        .unwrap();
+    let cancel = cancel_ix(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    let cancel_receipt = cancel_bid_receipt_ix(&buyer_trade_state);
+    process_tx(&mut context, &[cancel, cancel_receipt], &[&buyer])
+        .await
+        .unwrap();
+
+    let (listing_receipt, _) = find_listing_receipt_address(&seller_trade_state);
+    let (bid_receipt, _) = find_bid_receipt_address(&buyer_trade_state);
+    assert_eq!(
+        data_len(&mut context, &listing_receipt).await,
+        LISTING_RECEIPT_SIZE
+    );
+    assert_eq!(data_len(&mut context, &bid_receipt).await, BID_RECEIPT_SIZE);
+    let listing: ListingReceipt = get_anchor_account(&mut context, &listing_receipt).await;
+    assert!(listing.canceled_at.is_some());
+    let bid: BidReceipt = get_anchor_account(&mut context, &bid_receipt).await;
+    assert!(bid.canceled_at.is_some());
+}
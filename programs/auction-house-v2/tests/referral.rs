@@ -0,0 +1,400 @@
+mod utils;
+
+use auction_house_v2::{errors::AuctionHouseError, pda::*, receipt::PurchaseReceipt, Referral};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const HOUSE_FEE_BPS: u16 = 250;
+const MAX_REFERRAL_BPS: u16 = 3_000;
+
+/// `ah` with referrals of up to `MAX_REFERRAL_BPS` of the house fee, and a seller holding a royalty-free NFT.
+async fn setup(context: &mut ProgramTestContext, ah: &TestAuctionHouse) -> (Keypair, TestNft) {
+    airdrop(context, &ah.authority.pubkey(), ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let update = AuctionHouseUpdate {
+        max_referral_basis_points: Some(MAX_REFERRAL_BPS),
+        ..AuctionHouseUpdate::unchanged(ah)
+    };
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(ah, &payer, &ah.authority.pubkey(), &update);
+    process_tx(context, &[ix], &[&ah.authority]).await.unwrap();
+
+    let seller = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(context, &seller.pubkey(), &[], 0, 1).await;
+    (seller, nft)
+}
+
+fn referral(referrer: &Pubkey, basis_points: u16) -> Referral {
+    Referral {
+        referrer: *referrer,
+        basis_points,
+    }
+}
+
+#[tokio::test]
+async fn sale_pays_both_referrers_out_of_house_fee() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let (seller, nft) = setup(&mut context, &ah).await;
+    let buyer = Keypair::new();
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let listing_referrer = Keypair::new().pubkey();
+    let bid_referrer = Keypair::new().pubkey();
+    let price = ONE_SOL;
+
+    let seller_trade_state = trade_state_address(&ah, &seller.pubkey(), &nft, price, 1);
+    let buyer_trade_state = trade_state_address(&ah, &buyer.pubkey(), &nft, price, 1);
+    let sell = sell_with_referral_ix(
+        &ah,
+        &seller.pubkey(),
+        &nft,
+        price,
+        1,
+        referral(&listing_referrer, 2_000),
+    );
+    let listing_receipt = print_listing_receipt_ix(&seller.pubkey(), &seller_trade_state);
+    process_tx(&mut context, &[sell, listing_receipt], &[&seller])
+        .await
+        .unwrap();
+    let buy = buy_with_referral_ix(
+        &ah,
+        &buyer.pubkey(),
+        &nft,
+        price,
+        1,
+        referral(&bid_referrer, 1_000),
+    );
+    let bid_receipt = print_bid_receipt_ix(&buyer.pubkey(), &buyer_trade_state);
+    process_tx(&mut context, &[buy, bid_receipt], &[&buyer])
+        .await
+        .unwrap();
+
+    let treasury_before = get_lamports(&mut context, &ah.treasury).await;
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, price, 1);
+    execute_sale.accounts.extend(referrer_remaining_accounts(
+        &ah,
+        &[listing_referrer, bid_referrer],
+    ));
+    let purchase_receipt = print_purchase_receipt_ix(
+        &ah.authority.pubkey(),
+        &seller_trade_state,
+        &buyer_trade_state,
+        None,
+    );
+    process_tx(
+        &mut context,
+        &[execute_sale, purchase_receipt],
+        &[&ah.authority],
+    )
+    .await
+    .unwrap();
+
+    let house_fee = price * HOUSE_FEE_BPS as u64 / 10_000;
+    let listing_referral_fee = house_fee * 2_000 / 10_000;
+    let bid_referral_fee = house_fee * 1_000 / 10_000;
+    assert_eq!(
+        get_lamports(&mut context, &listing_referrer).await,
+        listing_referral_fee
+    );
+    assert_eq!(
+        get_lamports(&mut context, &bid_referrer).await,
+        bid_referral_fee
+    );
+    assert_eq!(
+        get_lamports(&mut context, &ah.treasury).await - treasury_before,
+        house_fee - listing_referral_fee - bid_referral_fee
+    );
+    let (purchase_receipt, _) =
+        find_purchase_receipt_address(&seller_trade_state, &buyer_trade_state);
+    let purchase: PurchaseReceipt = get_anchor_account(&mut context, &purchase_receipt).await;
+    assert_eq!(
+        purchase.seller_referral,
+        Some(referral(&listing_referrer, 2_000))
+    );
+    assert_eq!(
+        purchase.buyer_referral,
+        Some(referral(&bid_referrer, 1_000))
+    );
+}
+
+#[tokio::test]
+async fn spl_sale_creates_referrer_token_account() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_mint(&mut context, &mint_authority.pubkey(), 6).await;
+    let ah = create_auction_house(
+        &mut context,
+        &treasury_mint.pubkey(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let (seller, nft) = setup(&mut context, &ah).await;
+    let buyer = Keypair::new();
+    airdrop(&mut context, &buyer.pubkey(), ONE_SOL).await;
+    let price = 8_000_000;
+    let buyer_payment_account = create_ata(&mut context, &buyer.pubkey(), &ah.treasury_mint).await;
+    mint_to(
+        &mut context,
+        &ah.treasury_mint,
+        &mint_authority,
+        &buyer_payment_account,
+        price,
+    )
+    .await;
+    let bid_referrer = Keypair::new().pubkey();
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, price, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_with_referral_ix(
+        &ah,
+        &buyer.pubkey(),
+        &nft,
+        price,
+        1,
+        referral(&bid_referrer, MAX_REFERRAL_BPS),
+    );
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+
+    let treasury_before = get_token_balance(&mut context, &ah.treasury).await;
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, price, 1);
+    execute_sale
+        .accounts
+        .extend(referrer_remaining_accounts(&ah, &[bid_referrer]));
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let house_fee = price * HOUSE_FEE_BPS as u64 / 10_000;
+    let referral_fee = house_fee * MAX_REFERRAL_BPS as u64 / 10_000;
+    assert_eq!(
+        get_token_balance(&mut context, &ah.payment_account(&bid_referrer)).await,
+        referral_fee
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &ah.treasury).await - treasury_before,
+        house_fee - referral_fee
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &ah.payment_account(&seller.pubkey())).await,
+        price - house_fee
+    );
+}
+
+#[tokio::test]
+async fn execute_sale_rejects_other_referrer_account() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let (seller, nft) = setup(&mut context, &ah).await;
+    let buyer = Keypair::new();
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let listing_referrer = Keypair::new().pubkey();
+
+    let sell = sell_with_referral_ix(
+        &ah,
+        &seller.pubkey(),
+        &nft,
+        ONE_SOL,
+        1,
+        referral(&listing_referrer, 1_000),
+    );
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+
+    let mut execute_sale =
+        execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    execute_sale
+        .accounts
+        .extend(referrer_remaining_accounts(&ah, &[buyer.pubkey()]));
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::PublicKeyMismatch);
+}
+
+#[tokio::test]
+async fn referral_above_house_max_is_rejected() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let (seller, nft) = setup(&mut context, &ah).await;
+
+    let sell = sell_with_referral_ix(
+        &ah,
+        &seller.pubkey(),
+        &nft,
+        ONE_SOL,
+        1,
+        referral(&Keypair::new().pubkey(), MAX_REFERRAL_BPS + 1),
+    );
+    let result = process_tx(&mut context, &[sell], &[&seller]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InvalidReferral);
+}
+
+#[tokio::test]
+async fn referrals_are_disabled_until_house_sets_max() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+
+    let buy = buy_with_referral_ix(
+        &ah,
+        &buyer.pubkey(),
+        &nft,
+        ONE_SOL,
+        1,
+        referral(&Keypair::new().pubkey(), 1),
+    );
+    let result = process_tx(&mut context, &[buy], &[&buyer]).await;
+    assert_auction_house_error(result, AuctionHouseError::InvalidReferral);
+
+    // Referrals on both sides may take at most the whole house fee.
+    let update = AuctionHouseUpdate {
+        max_referral_basis_points: Some(5_001),
+        ..AuctionHouseUpdate::unchanged(&ah)
+    };
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(&ah, &payer, &ah.authority.pubkey(), &update);
+    let result = process_tx(&mut context, &[ix], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::InvalidBasisPoints);
+}
+
+#[tokio::test]
+async fn lowered_house_max_caps_placed_referrals() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let (seller, nft) = setup(&mut context, &ah).await;
+    let buyer = Keypair::new();
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let listing_referrer = Keypair::new().pubkey();
+
+    let sell = sell_with_referral_ix(
+        &ah,
+        &seller.pubkey(),
+        &nft,
+        ONE_SOL,
+        1,
+        referral(&listing_referrer, MAX_REFERRAL_BPS),
+    );
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+    let lowered_max = MAX_REFERRAL_BPS / 3;
+    let update = AuctionHouseUpdate {
+        max_referral_basis_points: Some(lowered_max),
+        ..AuctionHouseUpdate::unchanged(&ah)
+    };
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(&ah, &payer, &ah.authority.pubkey(), &update);
+    process_tx(&mut context, &[ix], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let mut execute_sale =
+        execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    execute_sale
+        .accounts
+        .extend(referrer_remaining_accounts(&ah, &[listing_referrer]));
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let house_fee = ONE_SOL * HOUSE_FEE_BPS as u64 / 10_000;
+    assert_eq!(
+        get_lamports(&mut context, &listing_referrer).await,
+        house_fee * lowered_max as u64 / 10_000
+    );
+}
+
+#[tokio::test]
+async fn parties_to_the_sale_cannot_be_referrers() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let (seller, nft) = setup(&mut context, &ah).await;
+    let buyer = Keypair::new();
+    airdrop(&mut context, &buyer.pubkey(), 2 * ONE_SOL).await;
+
+    let sell = sell_with_referral_ix(
+        &ah,
+        &seller.pubkey(),
+        &nft,
+        ONE_SOL,
+        1,
+        referral(&seller.pubkey(), 1_000),
+    );
+    let result = process_tx(&mut context, &[sell], &[&seller]).await;
+    assert_auction_house_error(result, AuctionHouseError::InvalidReferral);
+
+    // The buyer isn't known when the listing is placed, so a listing referring them settles with the referral dropped
+    // and the whole house fee going to the treasury.
+    let sell = sell_with_referral_ix(
+        &ah,
+        &seller.pubkey(),
+        &nft,
+        ONE_SOL,
+        1,
+        referral(&buyer.pubkey(), 1_000),
+    );
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+    let treasury_before = get_lamports(&mut context, &ah.treasury).await;
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_lamports(&mut context, &ah.treasury).await - treasury_before,
+        ONE_SOL * HOUSE_FEE_BPS as u64 / 10_000
+    );
+}
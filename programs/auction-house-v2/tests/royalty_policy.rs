@@ -0,0 +1,204 @@
+mod utils;
+
+use auction_house_v2::{
+    constants::MAX_ROYALTY_WAIVED_COLLECTIONS, errors::AuctionHouseError, pda::*,
+    receipt::PurchaseReceipt, RoyaltyPolicy,
+};
+use mpl_token_metadata::state::{Creator, Metadata, TokenMetadataAccount};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const ROYALTY_BPS: u16 = 1_000;
+
+/// Native fee-free house running `policy`, plus a funded seller and buyer.
+async fn setup(
+    context: &mut ProgramTestContext,
+    policy: RoyaltyPolicy,
+) -> (TestAuctionHouse, Keypair, Keypair) {
+    let ah = create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(context, &ah.authority.pubkey(), ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let update = AuctionHouseUpdate {
+        royalty_policy: Some(policy),
+        ..AuctionHouseUpdate::unchanged(&ah)
+    };
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(&ah, &payer, &ah.authority.pubkey(), &update);
+    process_tx(context, &[ix], &[&ah.authority]).await.unwrap();
+
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    (ah, seller, buyer)
+}
+
+/// NFT owned by `owner` with a single funded creator taking `ROYALTY_BPS`.
+async fn create_royalty_nft(context: &mut ProgramTestContext, owner: &Pubkey) -> TestNft {
+    let creator = Keypair::new().pubkey();
+    airdrop(context, &creator, ONE_SOL).await;
+    create_nft(context, owner, &[(creator, 100)], ROYALTY_BPS, 1).await
+}
+
+/// Give a royalty-free collection member a single funded creator taking `ROYALTY_BPS`.
+async fn add_royalty(context: &mut ProgramTestContext, nft: &mut TestNft) {
+    let creator = Keypair::new().pubkey();
+    airdrop(context, &creator, ONE_SOL).await;
+    let mut account = get_account(context, &nft.metadata).await.unwrap();
+    let mut metadata = Metadata::safe_deserialize(&account.data).unwrap();
+    metadata.data.seller_fee_basis_points = ROYALTY_BPS;
+    metadata.data.creators = Some(vec![Creator {
+        address: creator,
+        verified: false,
+        share: 100,
+    }]);
+    metadata.save(&mut account.data).unwrap();
+    context.set_account(&nft.metadata, &account.into());
+    nft.creators = vec![creator];
+}
+
+#[tokio::test]
+async fn optional_policy_pays_the_buyers_chosen_share() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup(
+        &mut context,
+        RoyaltyPolicy::Optional {
+            min_basis_points: 2_500,
+        },
+    )
+    .await;
+    let nft = create_royalty_nft(&mut context, &seller.pubkey()).await;
+    let price = ONE_SOL;
+
+    let seller_trade_state = trade_state_address(&ah, &seller.pubkey(), &nft, price, 1);
+    let buyer_trade_state = trade_state_address(&ah, &buyer.pubkey(), &nft, price, 1);
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, price, 1);
+    let listing_receipt = print_listing_receipt_ix(&seller.pubkey(), &seller_trade_state);
+    process_tx(&mut context, &[sell, listing_receipt], &[&seller])
+        .await
+        .unwrap();
+    let buy = buy_with_royalty_choice_ix(&ah, &buyer.pubkey(), &nft, price, 1, 5_000);
+    let bid_receipt = print_bid_receipt_ix(&buyer.pubkey(), &buyer_trade_state);
+    process_tx(&mut context, &[buy, bid_receipt], &[&buyer])
+        .await
+        .unwrap();
+
+    let creator_before = get_lamports(&mut context, &nft.creators[0]).await;
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, price, 1);
+    let purchase_receipt = print_purchase_receipt_ix(
+        &ah.authority.pubkey(),
+        &seller_trade_state,
+        &buyer_trade_state,
+        None,
+    );
+    process_tx(
+        &mut context,
+        &[execute_sale, purchase_receipt],
+        &[&ah.authority],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        get_lamports(&mut context, &nft.creators[0]).await - creator_before,
+        price * ROYALTY_BPS as u64 / 2 / 10_000
+    );
+    let (purchase_receipt, _) =
+        find_purchase_receipt_address(&seller_trade_state, &buyer_trade_state);
+    let purchase: PurchaseReceipt = get_anchor_account(&mut context, &purchase_receipt).await;
+    assert_eq!(purchase.royalty_choice, Some(5_000));
+}
+
+#[tokio::test]
+async fn optional_policy_rejects_choice_below_floor() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup(
+        &mut context,
+        RoyaltyPolicy::Optional {
+            min_basis_points: 2_500,
+        },
+    )
+    .await;
+    let nft = create_royalty_nft(&mut context, &seller.pubkey()).await;
+
+    let buy = buy_with_royalty_choice_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1, 1_000);
+    let result = process_tx(&mut context, &[buy], &[&buyer]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InvalidRoyaltyChoice);
+}
+
+#[tokio::test]
+async fn full_policy_rejects_royalty_choice() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup(&mut context, RoyaltyPolicy::Full).await;
+    let nft = create_royalty_nft(&mut context, &seller.pubkey()).await;
+
+    let buy = buy_with_royalty_choice_ix(&ah, &buyer.pubkey(), &nft, ONE_SOL, 1, 10_000);
+    let result = process_tx(&mut context, &[buy], &[&buyer]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InvalidRoyaltyChoice);
+}
+
+#[tokio::test]
+async fn waived_collection_pays_no_royalties() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let collection = create_collection(&mut context).await;
+    let mut collections = [Pubkey::default(); MAX_ROYALTY_WAIVED_COLLECTIONS];
+    collections[0] = collection.mint;
+    let (ah, seller, buyer) = setup(
+        &mut context,
+        RoyaltyPolicy::WaivedForCollections { collections },
+    )
+    .await;
+    let mut member = create_collection_nft(&mut context, &seller.pubkey(), &collection, true).await;
+    add_royalty(&mut context, &mut member).await;
+    // The NFT escrow is keyed by seller, so the outsider comes from a second one.
+    let other_seller = Keypair::new();
+    airdrop(&mut context, &other_seller.pubkey(), ONE_SOL).await;
+    let outsider = create_royalty_nft(&mut context, &other_seller.pubkey()).await;
+    let price = ONE_SOL;
+    airdrop(&mut context, &buyer.pubkey(), ONE_SOL).await;
+
+    let member_creator_before = get_lamports(&mut context, &member.creators[0]).await;
+    let outsider_creator_before = get_lamports(&mut context, &outsider.creators[0]).await;
+    for (seller, nft) in [(&seller, &member), (&other_seller, &outsider)] {
+        let sell = sell_ix(&ah, &seller.pubkey(), nft, price, 1);
+        process_tx(&mut context, &[sell], &[seller]).await.unwrap();
+        let buy = buy_ix(&ah, &buyer.pubkey(), nft, price, 1);
+        process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+        let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), nft, price, 1);
+        process_tx(&mut context, &[execute_sale], &[&ah.authority])
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        get_lamports(&mut context, &member.creators[0]).await,
+        member_creator_before
+    );
+    assert_eq!(
+        get_lamports(&mut context, &outsider.creators[0]).await - outsider_creator_before,
+        price * ROYALTY_BPS as u64 / 10_000
+    );
+}
+
+#[tokio::test]
+async fn update_rejects_royalty_floor_above_full() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let update = AuctionHouseUpdate {
+        royalty_policy: Some(RoyaltyPolicy::Optional {
+            min_basis_points: 10_001,
+        }),
+        ..AuctionHouseUpdate::unchanged(&ah)
+    };
+
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(&ah, &payer, &ah.authority.pubkey(), &update);
+    let result = process_tx(&mut context, &[ix], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::InvalidBasisPoints);
+}
@@ -0,0 +1,305 @@
+mod utils;
+
+use auction_house_v2::{errors::AuctionHouseError, pda::*, SwapOffer, SwapTarget};
+use solana_program::{program_option::COption, program_pack::Pack};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const MAKER_ROYALTY_BPS: u16 = 500;
+const TAKER_ROYALTY_BPS: u16 = 1_000;
+
+/// Funded maker and taker on `ah`, each holding an NFT with a single creator.
+async fn setup(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+) -> (Keypair, TestNft, Keypair, TestNft) {
+    let maker = Keypair::new();
+    let taker = Keypair::new();
+    let maker_creator = Keypair::new().pubkey();
+    let taker_creator = Keypair::new().pubkey();
+    airdrop(context, &maker.pubkey(), 2 * ONE_SOL).await;
+    airdrop(context, &taker.pubkey(), 2 * ONE_SOL).await;
+    airdrop(context, &maker_creator, ONE_SOL).await;
+    airdrop(context, &taker_creator, ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    airdrop(context, &ah.authority.pubkey(), ONE_SOL).await;
+    let maker_nft = create_nft(
+        context,
+        &maker.pubkey(),
+        &[(maker_creator, 100)],
+        MAKER_ROYALTY_BPS,
+        1,
+    )
+    .await;
+    let taker_nft = create_nft(
+        context,
+        &taker.pubkey(),
+        &[(taker_creator, 100)],
+        TAKER_ROYALTY_BPS,
+        1,
+    )
+    .await;
+    (maker, maker_nft, taker, taker_nft)
+}
+
+async fn delegate_of(
+    context: &mut ProgramTestContext,
+    nft: &TestNft,
+) -> COption<solana_program::pubkey::Pubkey> {
+    let account = get_account(context, &nft.token_account).await.unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .delegate
+}
+
+#[tokio::test]
+async fn swap_with_maker_extra_pays_taker_royalties() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let (maker, maker_nft, taker, taker_nft) = setup(&mut context, &ah).await;
+    let extra = ONE_SOL / 2;
+
+    let offer = create_swap_offer_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        1,
+        SwapTarget::Mint(taker_nft.mint),
+        1,
+        extra,
+        0,
+    );
+    process_tx(&mut context, &[offer], &[&maker]).await.unwrap();
+    let swap_offer = swap_offer_address(&ah, &maker.pubkey(), &maker_nft);
+    let recorded: SwapOffer = get_anchor_account(&mut context, &swap_offer).await;
+    assert_eq!(recorded.wanted, SwapTarget::Mint(taker_nft.mint));
+    assert_eq!(recorded.maker_extra, extra);
+    let (program_as_signer, _) = find_program_as_signer_address();
+    assert_eq!(
+        delegate_of(&mut context, &maker_nft).await,
+        COption::Some(program_as_signer)
+    );
+
+    let (escrow, _) = find_escrow_payment_address(&ah.address, &maker.pubkey());
+    let escrow_before = get_lamports(&mut context, &escrow).await;
+    let taker_before = get_lamports(&mut context, &taker.pubkey()).await;
+    let creator_before = get_lamports(&mut context, &taker_nft.creators[0]).await;
+    // The authority pays for the receipt token accounts so the taker's balance only moves by the extra.
+    let mut swap = execute_swap_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        &taker.pubkey(),
+        &taker_nft,
+        extra,
+        0,
+    );
+    mark_signer(&mut swap.accounts, &ah.authority.pubkey());
+    process_tx(&mut context, &[swap], &[&taker, &ah.authority])
+        .await
+        .unwrap();
+
+    let royalties = extra * TAKER_ROYALTY_BPS as u64 / 10_000;
+    assert_eq!(
+        get_lamports(&mut context, &taker_nft.creators[0]).await - creator_before,
+        royalties
+    );
+    assert_eq!(
+        get_lamports(&mut context, &taker.pubkey()).await - taker_before,
+        extra - royalties
+    );
+    assert_eq!(
+        get_lamports(&mut context, &escrow).await,
+        escrow_before - extra
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &maker_nft.token_account_of(&taker.pubkey())).await,
+        1
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &taker_nft.token_account_of(&maker.pubkey())).await,
+        1
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &maker_nft.token_account).await,
+        0
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &taker_nft.token_account).await,
+        0
+    );
+    assert_eq!(get_lamports(&mut context, &swap_offer).await, 0);
+}
+
+#[tokio::test]
+async fn swap_with_taker_extra_in_spl_pays_maker_royalties() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_mint(&mut context, &mint_authority.pubkey(), 6).await;
+    let ah = create_auction_house(&mut context, &treasury_mint.pubkey(), 0, false, false).await;
+    let (maker, maker_nft, taker, taker_nft) = setup(&mut context, &ah).await;
+    let extra = 4_000_000;
+    let taker_payment_account = create_ata(&mut context, &taker.pubkey(), &ah.treasury_mint).await;
+    mint_to(
+        &mut context,
+        &ah.treasury_mint,
+        &mint_authority,
+        &taker_payment_account,
+        extra,
+    )
+    .await;
+    let deposit = deposit_ix(&ah, &taker.pubkey(), extra);
+    process_tx(&mut context, &[deposit], &[&taker])
+        .await
+        .unwrap();
+
+    let offer = create_swap_offer_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        1,
+        SwapTarget::TokenAccount(taker_nft.token_account),
+        1,
+        0,
+        extra,
+    );
+    process_tx(&mut context, &[offer], &[&maker]).await.unwrap();
+    let swap = execute_swap_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        &taker.pubkey(),
+        &taker_nft,
+        0,
+        extra,
+    );
+    process_tx(&mut context, &[swap], &[&taker]).await.unwrap();
+
+    let royalties = extra * MAKER_ROYALTY_BPS as u64 / 10_000;
+    assert_eq!(
+        get_token_balance(&mut context, &ah.payment_account(&maker_nft.creators[0])).await,
+        royalties
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &ah.payment_account(&maker.pubkey())).await,
+        extra - royalties
+    );
+    let (escrow, _) = find_escrow_payment_address(&ah.address, &taker.pubkey());
+    assert_eq!(get_token_balance(&mut context, &escrow).await, 0);
+    assert_eq!(
+        get_token_balance(&mut context, &maker_nft.token_account_of(&taker.pubkey())).await,
+        1
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &taker_nft.token_account_of(&maker.pubkey())).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn swap_accepts_any_token_in_wanted_collection() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let (maker, maker_nft, taker, _) = setup(&mut context, &ah).await;
+    let collection = create_collection(&mut context).await;
+    let member = create_collection_nft(&mut context, &taker.pubkey(), &collection, true).await;
+
+    let offer = create_swap_offer_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        1,
+        SwapTarget::Collection(collection.mint),
+        1,
+        0,
+        0,
+    );
+    process_tx(&mut context, &[offer], &[&maker]).await.unwrap();
+    let swap = execute_swap_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        &taker.pubkey(),
+        &member,
+        0,
+        0,
+    );
+    process_tx(&mut context, &[swap], &[&taker]).await.unwrap();
+
+    assert_eq!(
+        get_token_balance(&mut context, &member.token_account_of(&maker.pubkey())).await,
+        1
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &maker_nft.token_account_of(&taker.pubkey())).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn swap_rejects_unwanted_token() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let (maker, maker_nft, taker, taker_nft) = setup(&mut context, &ah).await;
+    let other = create_nft(&mut context, &taker.pubkey(), &[], 0, 1).await;
+
+    let offer = create_swap_offer_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        1,
+        SwapTarget::Mint(taker_nft.mint),
+        1,
+        0,
+        0,
+    );
+    process_tx(&mut context, &[offer], &[&maker]).await.unwrap();
+    let swap = execute_swap_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        &taker.pubkey(),
+        &other,
+        0,
+        0,
+    );
+    let result = process_tx(&mut context, &[swap], &[&taker]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::SwapTargetMismatch);
+    assert_eq!(
+        get_token_balance(&mut context, &maker_nft.token_account).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn cancel_swap_offer_revokes_delegate() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    let (maker, maker_nft, _, taker_nft) = setup(&mut context, &ah).await;
+    let offer = create_swap_offer_ix(
+        &ah,
+        &maker.pubkey(),
+        &maker_nft,
+        1,
+        SwapTarget::Mint(taker_nft.mint),
+        1,
+        0,
+        0,
+    );
+    process_tx(&mut context, &[offer], &[&maker]).await.unwrap();
+
+    let cancel = cancel_swap_offer_ix(&ah, &maker.pubkey(), &maker_nft);
+    process_tx(&mut context, &[cancel], &[&maker])
+        .await
+        .unwrap();
+
+    assert_eq!(delegate_of(&mut context, &maker_nft).await, COption::None);
+    let swap_offer = swap_offer_address(&ah, &maker.pubkey(), &maker_nft);
+    assert_eq!(get_lamports(&mut context, &swap_offer).await, 0);
+}
@@ -0,0 +1,202 @@
+mod utils;
+
+use auction_house_v2::constants::TRANSFER_HOOK_EXTENSION_TYPE;
+use auction_house_v2::errors::AuctionHouseError;
+use solana_program_test::*;
+use solana_sdk::{
+    account::AccountSharedData,
+    program_pack::Pack,
+    signature::{Keypair, Signer},
+};
+use spl_token_2022::{extension::ExtensionType, state::Account as Token2022Account};
+use utils::*;
+
+#[tokio::test]
+async fn token_2022_nft_sells_for_sol() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let nft = create_token_2022_nft(
+        &mut context,
+        &seller.pubkey(),
+        &[ExtensionType::MintCloseAuthority],
+    )
+    .await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, ONE_SOL).await;
+    let seller_lamports = get_lamports(&mut context, &seller.pubkey()).await;
+
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    let claim = claim_nft_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &ah.fee_account,
+        &buyer.pubkey(),
+    );
+    process_tx(&mut context, &[claim], &[&buyer]).await.unwrap();
+
+    assert!(get_lamports(&mut context, &seller.pubkey()).await >= seller_lamports + ONE_SOL);
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 0);
+    assert_eq!(
+        get_token_balance(&mut context, &nft.token_account_of(&buyer.pubkey())).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn transfer_fee_mint_cannot_be_sold() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let nft = create_token_2022_nft(
+        &mut context,
+        &seller.pubkey(),
+        &[ExtensionType::TransferFeeConfig],
+    )
+    .await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, ONE_SOL).await;
+
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::UnsupportedMintExtension);
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 1);
+}
+
+#[tokio::test]
+async fn token_2022_nft_sells_for_token_2022_treasury() {
+    const HOUSE_FEE_BPS: u16 = 250;
+    const PRICE: u64 = 5_000_000;
+
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_token_2022_mint(&mut context, &mint_authority.pubkey(), 6).await;
+    let ah = create_auction_house(
+        &mut context,
+        &treasury_mint.pubkey(),
+        HOUSE_FEE_BPS,
+        false,
+        false,
+    )
+    .await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(&mut context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &buyer.pubkey(), ONE_SOL).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let payment_account = create_ata(&mut context, &buyer.pubkey(), &ah.treasury_mint).await;
+    mint_to(
+        &mut context,
+        &ah.treasury_mint,
+        &mint_authority,
+        &payment_account,
+        PRICE,
+    )
+    .await;
+    let nft = create_token_2022_nft(&mut context, &seller.pubkey(), &[]).await;
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[buy], &[&buyer]).await.unwrap();
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let house_fee = PRICE * HOUSE_FEE_BPS as u64 / 10_000;
+    assert_eq!(get_token_balance(&mut context, &payment_account).await, 0);
+    assert_eq!(
+        get_token_balance(&mut context, &ah.payment_account(&seller.pubkey())).await,
+        PRICE - house_fee
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &ah.treasury).await,
+        house_fee
+    );
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 0);
+}
+
+#[tokio::test]
+async fn token_2022_escrowed_nft_round_trips() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let nft = create_token_2022_nft(
+        &mut context,
+        &seller.pubkey(),
+        &[ExtensionType::MintCloseAuthority],
+    )
+    .await;
+    let escrow_nft =
+        find_escrow_nft_address(&ah.address, &seller.pubkey(), &nft.mint, &buyer.pubkey()).0;
+
+    // The authority sends the first escrowed sale back to the seller.
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, ONE_SOL).await;
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    assert_eq!(get_token_balance(&mut context, &escrow_nft).await, 1);
+    let refund = refund_nft_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &ah.fee_account,
+    );
+    process_tx(&mut context, &[refund], &[&ah.authority, &seller])
+        .await
+        .unwrap();
+    assert!(get_account(&mut context, &escrow_nft).await.is_none());
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 1);
+
+    // The same escrow is opened again for the next sale and the buyer claims it.
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, ONE_SOL).await;
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    let claim = claim_nft_ix(
+        &ah,
+        &buyer.pubkey(),
+        &seller.pubkey(),
+        &nft,
+        &ah.fee_account,
+        &buyer.pubkey(),
+    );
+    process_tx(&mut context, &[claim], &[&buyer]).await.unwrap();
+
+    assert!(get_account(&mut context, &escrow_nft).await.is_none());
+    assert_eq!(
+        get_token_balance(&mut context, &nft.token_account_of(&buyer.pubkey())).await,
+        1
+    );
+}
+
+#[tokio::test]
+async fn transfer_hook_mint_cannot_be_sold() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let nft = create_token_2022_nft(
+        &mut context,
+        &seller.pubkey(),
+        &[ExtensionType::MintCloseAuthority],
+    )
+    .await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, ONE_SOL).await;
+
+    // The pinned Token-2022 can't create a transfer hook, so retype the mint's only extension as one.
+    let mut mint = get_account(&mut context, &nft.mint).await.unwrap();
+    let type_start = Token2022Account::LEN + 1;
+    mint.data[type_start..type_start + 2]
+        .copy_from_slice(&TRANSFER_HOOK_EXTENSION_TYPE.to_le_bytes());
+    context.set_account(&nft.mint, &AccountSharedData::from(mint));
+
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, ONE_SOL, 1);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+
+    assert_auction_house_error(result, AuctionHouseError::UnsupportedMintExtension);
+    assert_eq!(get_token_balance(&mut context, &nft.token_account).await, 1);
+}
@@ -0,0 +1,124 @@
+mod utils;
+
+use auction_house_v2::{errors::AuctionHouseError, TraderStats};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const HOUSE_FEE_BPS: u16 = 250;
+const ROYALTY_BPS: u16 = 500;
+const PRICE: u64 = ONE_SOL;
+
+#[tokio::test]
+async fn sales_update_both_traders_stats() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, first_seller, buyer) = setup_native_sale(&mut context, HOUSE_FEE_BPS).await;
+
+    let nft = create_nft(&mut context, &first_seller.pubkey(), &[], 0, 1).await;
+    list_and_bid(&mut context, &ah, &first_seller, &buyer, &nft, PRICE).await;
+    let execute_sale =
+        execute_sale_ix(&ah, &buyer.pubkey(), &first_seller.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    let first_sale_at = get_clock(&mut context).await.unix_timestamp;
+
+    let seller_stats: TraderStats = get_anchor_account(
+        &mut context,
+        &trader_stats_address(&ah, &first_seller.pubkey()),
+    )
+    .await;
+    assert_eq!(seller_stats.auction_house, ah.address);
+    assert_eq!(seller_stats.wallet, first_seller.pubkey());
+    assert_eq!(seller_stats.buy_volume, 0);
+    assert_eq!(seller_stats.sell_volume, PRICE);
+    assert_eq!(seller_stats.trade_count, 1);
+    assert_eq!(
+        seller_stats.fees_paid,
+        PRICE * HOUSE_FEE_BPS as u64 / 10_000
+    );
+    assert_eq!(seller_stats.first_trade_at, first_sale_at);
+
+    // The buyer's stats accumulate across sales.
+    let later = first_sale_at + 60;
+    set_clock(&mut context, later).await;
+    let second_seller = Keypair::new();
+    airdrop(&mut context, &second_seller.pubkey(), ONE_SOL).await;
+    let nft = create_nft(&mut context, &second_seller.pubkey(), &[], 0, 1).await;
+    list_and_bid(&mut context, &ah, &second_seller, &buyer, &nft, PRICE).await;
+    let execute_sale = execute_sale_ix(
+        &ah,
+        &buyer.pubkey(),
+        &second_seller.pubkey(),
+        &nft,
+        PRICE,
+        1,
+    );
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let buyer_stats: TraderStats =
+        get_anchor_account(&mut context, &trader_stats_address(&ah, &buyer.pubkey())).await;
+    assert_eq!(buyer_stats.buy_volume, 2 * PRICE);
+    assert_eq!(buyer_stats.sell_volume, 0);
+    assert_eq!(buyer_stats.trade_count, 2);
+    assert_eq!(buyer_stats.fees_paid, 0);
+    assert_eq!(buyer_stats.first_trade_at, first_sale_at);
+    assert_eq!(buyer_stats.last_trade_at, later);
+}
+
+#[tokio::test]
+async fn buyer_fees_count_royalties() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let creator = Keypair::new().pubkey();
+    let nft = create_nft(
+        &mut context,
+        &seller.pubkey(),
+        &[(creator, 100)],
+        ROYALTY_BPS,
+        1,
+    )
+    .await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, PRICE).await;
+
+    let execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    process_tx(&mut context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let buyer_stats: TraderStats =
+        get_anchor_account(&mut context, &trader_stats_address(&ah, &buyer.pubkey())).await;
+    assert_eq!(buyer_stats.fees_paid, PRICE * ROYALTY_BPS as u64 / 10_000);
+    let seller_stats: TraderStats =
+        get_anchor_account(&mut context, &trader_stats_address(&ah, &seller.pubkey())).await;
+    assert_eq!(seller_stats.fees_paid, 0);
+}
+
+#[tokio::test]
+async fn sale_requires_both_trader_stats_in_order() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer) = setup_native_sale(&mut context, 0).await;
+    let nft = create_nft(&mut context, &seller.pubkey(), &[], 0, 1).await;
+    list_and_bid(&mut context, &ah, &seller, &buyer, &nft, PRICE).await;
+
+    let buyer_stats = trader_stats_address(&ah, &buyer.pubkey());
+    let seller_stats = trader_stats_address(&ah, &seller.pubkey());
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    execute_sale
+        .accounts
+        .retain(|account| account.pubkey != buyer_stats && account.pubkey != seller_stats);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::TraderStatsMismatch);
+
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    let position = execute_sale
+        .accounts
+        .iter()
+        .position(|account| account.pubkey == buyer_stats)
+        .unwrap();
+    execute_sale.accounts.swap(position, position + 1);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::TraderStatsMismatch);
+}
@@ -0,0 +1,185 @@
+mod utils;
+
+use anchor_lang::error::ErrorCode;
+use auction_house_v2::{errors::AuctionHouseError, RoyaltyPolicy, UnverifiedCreatorPolicy};
+use solana_program_test::*;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use spl_associated_token_account::get_associated_token_address;
+use utils::*;
+
+#[tokio::test]
+async fn authority_updates_fees_and_rotates_authority() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        250,
+        false,
+        false,
+    )
+    .await;
+    let new_authority = Keypair::new();
+    let update = AuctionHouseUpdate {
+        new_authority: new_authority.pubkey(),
+        fee_withdrawal_destination: Pubkey::new_unique(),
+        treasury_withdrawal_destination_owner: Pubkey::new_unique(),
+        seller_fee_basis_points: Some(500),
+        requires_sign_off: Some(true),
+        can_change_sale_price: Some(true),
+        max_referral_basis_points: Some(2000),
+        royalty_policy: Some(RoyaltyPolicy::Optional {
+            min_basis_points: 5000,
+        }),
+        unverified_creator_policy: Some(UnverifiedCreatorPolicy::Redistribute),
+    };
+
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(&ah, &payer, &ah.authority.pubkey(), &update);
+    process_tx(&mut context, &[ix], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let house = get_auction_house(&mut context, &ah.address).await;
+    assert_eq!(house.authority, new_authority.pubkey());
+    assert_eq!(house.creator, ah.authority.pubkey());
+    assert_eq!(house.seller_fee_basis_points, 500);
+    assert!(house.requires_sign_off);
+    assert!(house.can_change_sale_price);
+    assert_eq!(house.max_referral_basis_points, 2000);
+    assert_eq!(
+        house.royalty_policy,
+        RoyaltyPolicy::Optional {
+            min_basis_points: 5000
+        }
+    );
+    assert_eq!(
+        house.unverified_creator_policy,
+        UnverifiedCreatorPolicy::Redistribute
+    );
+    assert_eq!(
+        house.fee_withdrawal_destination,
+        update.fee_withdrawal_destination
+    );
+    assert_eq!(
+        house.treasury_withdrawal_destination,
+        update.treasury_withdrawal_destination_owner
+    );
+
+    // The old authority is locked out and the new one takes over at the same address.
+    let stale = update_auction_house_ix(
+        &ah,
+        &payer,
+        &ah.authority.pubkey(),
+        &AuctionHouseUpdate::unchanged(&ah),
+    );
+    let result = process_tx(&mut context, &[stale], &[&ah.authority]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintHasOne);
+
+    let update = AuctionHouseUpdate {
+        seller_fee_basis_points: Some(100),
+        ..update
+    };
+    let ix = update_auction_house_ix(&ah, &payer, &new_authority.pubkey(), &update);
+    process_tx(&mut context, &[ix], &[&new_authority])
+        .await
+        .unwrap();
+    let house = get_auction_house(&mut context, &ah.address).await;
+    assert_eq!(house.seller_fee_basis_points, 100);
+}
+
+#[tokio::test]
+async fn update_keeps_unset_fields() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 250, true, true).await;
+
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(
+        &ah,
+        &payer,
+        &ah.authority.pubkey(),
+        &AuctionHouseUpdate::unchanged(&ah),
+    );
+    process_tx(&mut context, &[ix], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let house = get_auction_house(&mut context, &ah.address).await;
+    assert_eq!(house.seller_fee_basis_points, 250);
+    assert!(house.requires_sign_off);
+    assert!(house.can_change_sale_price);
+}
+
+#[tokio::test]
+async fn update_rejects_invalid_basis_points() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        250,
+        false,
+        false,
+    )
+    .await;
+    let update = AuctionHouseUpdate {
+        seller_fee_basis_points: Some(10001),
+        ..AuctionHouseUpdate::unchanged(&ah)
+    };
+
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(&ah, &payer, &ah.authority.pubkey(), &update);
+    let result = process_tx(&mut context, &[ix], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::InvalidBasisPoints);
+}
+
+#[tokio::test]
+async fn update_creates_spl_treasury_destination() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_mint(&mut context, &mint_authority.pubkey(), 6).await;
+    let ah = create_auction_house(&mut context, &treasury_mint.pubkey(), 250, false, false).await;
+    let new_owner = Pubkey::new_unique();
+    let update = AuctionHouseUpdate {
+        treasury_withdrawal_destination_owner: new_owner,
+        ..AuctionHouseUpdate::unchanged(&ah)
+    };
+
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(&ah, &payer, &ah.authority.pubkey(), &update);
+    process_tx(&mut context, &[ix], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let destination = get_associated_token_address(&new_owner, &ah.treasury_mint);
+    let house = get_auction_house(&mut context, &ah.address).await;
+    assert_eq!(house.treasury_withdrawal_destination, destination);
+    assert_eq!(get_token_balance(&mut context, &destination).await, 0);
+}
+
+#[tokio::test]
+async fn update_rejects_native_destination_not_owned_by_owner() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah = create_auction_house(
+        &mut context,
+        &spl_token::native_mint::id(),
+        250,
+        false,
+        false,
+    )
+    .await;
+
+    let payer = context.payer.pubkey();
+    let mut ix = update_auction_house_ix(
+        &ah,
+        &payer,
+        &ah.authority.pubkey(),
+        &AuctionHouseUpdate::unchanged(&ah),
+    );
+    // A native treasury destination must be the owner wallet itself.
+    ix.accounts[5].pubkey = Pubkey::new_unique();
+    let result = process_tx(&mut context, &[ix], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::PublicKeyMismatch);
+}
@@ -0,0 +1,3211 @@
+#![allow(dead_code)]
+
+use anchor_lang::{
+    prelude::{AccountInfo, AccountMeta, Pubkey},
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction, system_instruction},
+    AccountDeserialize, AnchorDeserialize, AnchorSerialize, Discriminator, InstructionData,
+    ToAccountMetas,
+};
+pub use auction_house_v2::pda::{find_escrow_nft_address, find_escrow_nft_record_address};
+use auction_house_v2::{
+    constants::{AUCTION, AUCTION_BID, COLLECTION_BID, DUTCH},
+    pda::*,
+    AuctionHouse, AuthorityScope, DecayCurve, Referral, RoyaltyPolicy, SwapTarget,
+    UnverifiedCreatorPolicy,
+};
+use mpl_token_metadata::{
+    pda::{find_master_edition_account, find_token_record_account},
+    state::{
+        Collection, Creator, Data, Key, MasterEditionV2, Metadata, ProgrammableConfig,
+        TokenMetadataAccount, TokenRecord, TokenStandard, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN,
+        TOKEN_RECORD_SIZE,
+    },
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::Transaction,
+    transport::TransportError,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+};
+use spl_token_2022::{
+    extension::{ExtensionType, StateWithExtensions},
+    state::{Account as Token2022Account, Mint as Token2022Mint},
+};
+
+use std::sync::Once;
+
+pub const ONE_SOL: u64 = 1_000_000_000;
+
+struct DefaultSyscallStubs;
+
+impl SyscallStubs for DefaultSyscallStubs {}
+
+fn process_metadata_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    // Token Metadata ties the program id, the account slice and the accounts' data to one lifetime, which the
+    // shorter-lived borrows the test runtime hands us can't meet. Leaking copies of the id and the account infos
+    // lets them live as long as the data instead. The copies share the data with the runtime's own account infos,
+    // so Token Metadata's writes still land, and each call leaks only a few hundred bytes.
+    let program_id: &Pubkey = Box::leak(Box::new(*program_id));
+    let accounts: &[AccountInfo] = Box::leak(accounts.to_vec().into_boxed_slice());
+    mpl_token_metadata::processor::process_instruction(program_id, accounts, data)
+}
+
+pub fn auction_house_program_test() -> ProgramTest {
+    let mut program = ProgramTest::new(
+        "auction_house_v2",
+        auction_house_v2::id(),
+        processor!(auction_house_v2::entry),
+    );
+    program.add_program(
+        "mpl_token_metadata",
+        mpl_token_metadata::id(),
+        processor!(process_metadata_instruction),
+    );
+    // Run the SPL programs natively as well so the whole suite stays on one runtime.
+    program.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process),
+    );
+    program.add_program(
+        "spl_token_2022",
+        spl_token_2022::id(),
+        processor!(spl_token_2022::processor::Processor::process),
+    );
+    program.add_program(
+        "spl_associated_token_account",
+        spl_associated_token_account::id(),
+        processor!(spl_associated_token_account::processor::process_instruction),
+    );
+    program
+}
+
+/// Flag `signer` as a signer on accounts the program only checks at runtime.
+pub fn mark_signer(accounts: &mut [AccountMeta], signer: &Pubkey) {
+    for meta in accounts.iter_mut().filter(|meta| meta.pubkey == *signer) {
+        meta.is_signer = true;
+    }
+}
+
+/// Wallets pay rent for the trade states they create. Frontends make the wallet the
+/// transaction fee payer, which makes it writable; the tests pay from a separate key.
+pub fn mark_fee_payer(accounts: &mut [AccountMeta], wallet: &Pubkey) {
+    for meta in accounts.iter_mut().filter(|meta| meta.pubkey == *wallet) {
+        meta.is_signer = true;
+        meta.is_writable = true;
+    }
+}
+
+pub async fn process_tx(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransportError> {
+    process_tx_for_logs(context, instructions, signers)
+        .await
+        .map(drop)
+}
+
+/// Process a transaction on a fresh blockhash and return its log messages. Every test transaction goes through
+/// here and is executed by the banks server before it answers, so no transaction can find the accounts of the
+/// previous one still locked, as it could when some were queued with `process_transaction`.
+async fn process_tx_for_logs(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<Vec<String>, TransportError> {
+    let blockhash = context.get_new_latest_blockhash().await?;
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    let processed = context
+        .banks_client
+        .process_transaction_with_metadata(tx)
+        .await?;
+    processed.result.map_err(TransportError::TransactionError)?;
+    Ok(processed
+        .metadata
+        .map(|metadata| metadata.log_messages)
+        .unwrap_or_default())
+}
+
+/// Forwards every syscall to the program test stubs, except that event data, which the natively
+/// run programs cannot log, is logged as a message the way the runtime would log it.
+struct EventLogStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for EventLogStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields.iter().map(base64::encode).collect();
+        self.0
+            .sol_log(&format!("Program data: {}", fields.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
+
+/// Start `program_test` with Anchor events showing up in the transaction logs. Every test in a
+/// binary that reads events must start through here, as swapping the syscall stubs while another
+/// test is mid-transaction would deadlock.
+pub async fn start_with_event_logs(program_test: ProgramTest) -> ProgramTestContext {
+    static INSTALL: Once = Once::new();
+    let context = program_test.start_with_context().await;
+    INSTALL.call_once(|| {
+        let stubs = set_syscall_stubs(Box::new(DefaultSyscallStubs));
+        set_syscall_stubs(Box::new(EventLogStubs(stubs)));
+    });
+    context
+}
+
+/// Process a transaction and decode the `T` events it emitted, in order.
+pub async fn process_tx_for_events<T: AnchorDeserialize + Discriminator>(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<Vec<T>, TransportError> {
+    let logs = process_tx_for_logs(context, instructions, signers).await?;
+    Ok(logs
+        .iter()
+        .filter_map(|log| log.strip_prefix("Program log: Program data: "))
+        .map(|data| base64::decode(data).unwrap())
+        .filter(|data| data.starts_with(&T::discriminator()))
+        .map(|data| T::deserialize(&mut &data[8..]).unwrap())
+        .collect())
+}
+
+pub async fn get_account(context: &mut ProgramTestContext, address: &Pubkey) -> Option<Account> {
+    context.banks_client.get_account(*address).await.unwrap()
+}
+
+pub async fn get_lamports(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    get_account(context, address)
+        .await
+        .map(|account| account.lamports)
+        .unwrap_or_default()
+}
+
+pub async fn get_token_balance(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    match get_account(context, address).await {
+        Some(account) => {
+            StateWithExtensions::<Token2022Account>::unpack(&account.data)
+                .unwrap()
+                .base
+                .amount
+        }
+        None => 0,
+    }
+}
+
+pub async fn get_anchor_account<T: AccountDeserialize>(
+    context: &mut ProgramTestContext,
+    address: &Pubkey,
+) -> T {
+    let account = get_account(context, address).await.unwrap();
+    T::try_deserialize(&mut account.data.as_ref()).unwrap()
+}
+
+pub async fn get_auction_house(context: &mut ProgramTestContext, address: &Pubkey) -> AuctionHouse {
+    get_anchor_account(context, address).await
+}
+
+pub async fn airdrop(context: &mut ProgramTestContext, receiver: &Pubkey, amount: u64) {
+    let ix = system_instruction::transfer(&context.payer.pubkey(), receiver, amount);
+    process_tx(context, &[ix], &[]).await.unwrap();
+}
+
+pub async fn create_mint(
+    context: &mut ProgramTestContext,
+    authority: &Pubkey,
+    decimals: u8,
+) -> Keypair {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &context.payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            authority,
+            Some(authority),
+            decimals,
+        )
+        .unwrap(),
+    ];
+    process_tx(context, &ixs, &[&mint]).await.unwrap();
+    mint
+}
+
+/// Extension-free Token-2022 mint, usable as a treasury mint.
+pub async fn create_token_2022_mint(
+    context: &mut ProgramTestContext,
+    authority: &Pubkey,
+    decimals: u8,
+) -> Keypair {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &context.payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(Token2022Mint::LEN),
+            Token2022Mint::LEN as u64,
+            &spl_token_2022::id(),
+        ),
+        spl_token_2022::instruction::initialize_mint2(
+            &spl_token_2022::id(),
+            &mint.pubkey(),
+            authority,
+            Some(authority),
+            decimals,
+        )
+        .unwrap(),
+    ];
+    process_tx(context, &ixs, &[&mint]).await.unwrap();
+    mint
+}
+
+/// Token program owning `mint`.
+pub async fn mint_token_program(context: &mut ProgramTestContext, mint: &Pubkey) -> Pubkey {
+    get_account(context, mint).await.unwrap().owner
+}
+
+pub async fn create_ata(
+    context: &mut ProgramTestContext,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+) -> Pubkey {
+    let token_program = mint_token_program(context, mint).await;
+    let ix = spl_associated_token_account::instruction::create_associated_token_account(
+        &context.payer.pubkey(),
+        wallet,
+        mint,
+        &token_program,
+    );
+    process_tx(context, &[ix], &[]).await.unwrap();
+    get_associated_token_address_with_program_id(wallet, mint, &token_program)
+}
+
+pub async fn mint_to(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    authority: &Keypair,
+    destination: &Pubkey,
+    amount: u64,
+) {
+    let token_program = mint_token_program(context, mint).await;
+    let ix = spl_token_2022::instruction::mint_to(
+        &token_program,
+        mint,
+        destination,
+        &authority.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    process_tx(context, &[ix], &[authority]).await.unwrap();
+}
+
+/// Metaplex NFT minted into the owner's ATA.
+pub struct TestNft {
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub metadata: Pubkey,
+    pub creators: Vec<Pubkey>,
+    pub token_program: Pubkey,
+    /// Verified collection, whose fee schedule address sales pass after the creator accounts.
+    pub verified_collection: Option<Pubkey>,
+}
+
+impl TestNft {
+    /// ATA of `wallet` for the NFT mint under the NFT's token program.
+    pub fn token_account_of(&self, wallet: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(wallet, &self.mint, &self.token_program)
+    }
+}
+
+pub fn find_metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            mpl_token_metadata::state::PREFIX.as_bytes(),
+            mpl_token_metadata::id().as_ref(),
+            mint.as_ref(),
+        ],
+        &mpl_token_metadata::id(),
+    )
+    .0
+}
+
+/// Mint `supply` tokens to `owner` and decorate the mint with metadata paying `creators`
+/// (address, share) a royalty of `seller_fee_basis_points`.
+pub async fn create_nft(
+    context: &mut ProgramTestContext,
+    owner: &Pubkey,
+    creators: &[(Pubkey, u8)],
+    seller_fee_basis_points: u16,
+    supply: u64,
+) -> TestNft {
+    create_nft_with_collection(
+        context,
+        owner,
+        creators,
+        seller_fee_basis_points,
+        supply,
+        None,
+    )
+    .await
+}
+
+/// `create_nft` in the (unverified) `collection`.
+async fn create_nft_with_collection(
+    context: &mut ProgramTestContext,
+    owner: &Pubkey,
+    creators: &[(Pubkey, u8)],
+    seller_fee_basis_points: u16,
+    supply: u64,
+    collection: Option<Pubkey>,
+) -> TestNft {
+    let mint_authority = Keypair::new();
+    let mint = create_mint(context, &mint_authority.pubkey(), 0).await;
+    let token_account = create_ata(context, owner, &mint.pubkey()).await;
+    mint_to(
+        context,
+        &mint.pubkey(),
+        &mint_authority,
+        &token_account,
+        supply,
+    )
+    .await;
+
+    let metadata = find_metadata_address(&mint.pubkey());
+    let metadata_creators = if creators.is_empty() {
+        None
+    } else {
+        Some(
+            creators
+                .iter()
+                .map(|(address, share)| Creator {
+                    address: *address,
+                    verified: false,
+                    share: *share,
+                })
+                .collect(),
+        )
+    };
+    let ix = mpl_token_metadata::instruction::create_metadata_accounts_v3(
+        mpl_token_metadata::id(),
+        metadata,
+        mint.pubkey(),
+        mint_authority.pubkey(),
+        context.payer.pubkey(),
+        mint_authority.pubkey(),
+        "Test".to_string(),
+        "TST".to_string(),
+        "https://example.com".to_string(),
+        metadata_creators,
+        seller_fee_basis_points,
+        true,
+        true,
+        collection.map(|key| Collection {
+            verified: false,
+            key,
+        }),
+        None,
+        None,
+    );
+    process_tx(context, &[ix], &[&mint_authority])
+        .await
+        .unwrap();
+
+    TestNft {
+        mint: mint.pubkey(),
+        token_account,
+        metadata,
+        creators: creators.iter().map(|(address, _)| *address).collect(),
+        token_program: spl_token::id(),
+        verified_collection: None,
+    }
+}
+
+/// Metaplex collection parent NFT.
+pub struct TestCollection {
+    pub mint: Pubkey,
+    pub metadata: Pubkey,
+}
+
+pub async fn create_collection(context: &mut ProgramTestContext) -> TestCollection {
+    let owner = Keypair::new().pubkey();
+    let nft = create_nft(context, &owner, &[], 0, 1).await;
+    TestCollection {
+        mint: nft.mint,
+        metadata: nft.metadata,
+    }
+}
+
+/// Royalty-free NFT owned by `owner` in `collection`, flagged as verified if `verified`.
+pub async fn create_collection_nft(
+    context: &mut ProgramTestContext,
+    owner: &Pubkey,
+    collection: &TestCollection,
+    verified: bool,
+) -> TestNft {
+    let mut nft =
+        create_nft_with_collection(context, owner, &[], 0, 1, Some(collection.mint)).await;
+    if verified {
+        // Master edition and collection verification reallocate metadata, which the natively
+        // run Token Metadata cannot do, so set the flag directly.
+        let mut account = get_account(context, &nft.metadata).await.unwrap();
+        let mut metadata = Metadata::safe_deserialize(&account.data).unwrap();
+        metadata.collection = Some(Collection {
+            verified: true,
+            key: collection.mint,
+        });
+        metadata.save(&mut account.data).unwrap();
+        context.set_account(&nft.metadata, &account.into());
+        nft.verified_collection = Some(collection.mint);
+    }
+    nft
+}
+
+/// Royalty-free Token-2022 NFT owned by `owner` whose mint carries `extensions`.
+pub async fn create_token_2022_nft(
+    context: &mut ProgramTestContext,
+    owner: &Pubkey,
+    extensions: &[ExtensionType],
+) -> TestNft {
+    let mint_authority = Keypair::new();
+    let mint = Keypair::new();
+    let space = ExtensionType::get_account_len::<Token2022Mint>(extensions);
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut ixs = vec![system_instruction::create_account(
+        &context.payer.pubkey(),
+        &mint.pubkey(),
+        rent.minimum_balance(space),
+        space as u64,
+        &spl_token_2022::id(),
+    )];
+    for extension in extensions {
+        ixs.push(match extension {
+            ExtensionType::TransferFeeConfig => {
+                spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                    &spl_token_2022::id(),
+                    &mint.pubkey(),
+                    None,
+                    None,
+                    100,
+                    u64::MAX,
+                )
+                .unwrap()
+            }
+            ExtensionType::NonTransferable => {
+                spl_token_2022::instruction::initialize_non_transferable_mint(
+                    &spl_token_2022::id(),
+                    &mint.pubkey(),
+                )
+                .unwrap()
+            }
+            ExtensionType::MintCloseAuthority => {
+                spl_token_2022::instruction::initialize_mint_close_authority(
+                    &spl_token_2022::id(),
+                    &mint.pubkey(),
+                    Some(&mint_authority.pubkey()),
+                )
+                .unwrap()
+            }
+            other => panic!("unsupported test extension {:?}", other),
+        });
+    }
+    ixs.push(
+        spl_token_2022::instruction::initialize_mint2(
+            &spl_token_2022::id(),
+            &mint.pubkey(),
+            &mint_authority.pubkey(),
+            None,
+            0,
+        )
+        .unwrap(),
+    );
+    process_tx(context, &ixs, &[&mint]).await.unwrap();
+
+    let token_account =
+        get_associated_token_address_with_program_id(owner, &mint.pubkey(), &spl_token_2022::id());
+    let ixs = [
+        spl_associated_token_account::instruction::create_associated_token_account(
+            &context.payer.pubkey(),
+            owner,
+            &mint.pubkey(),
+            &spl_token_2022::id(),
+        ),
+        spl_token_2022::instruction::mint_to(
+            &spl_token_2022::id(),
+            &mint.pubkey(),
+            &token_account,
+            &mint_authority.pubkey(),
+            &[],
+            1,
+        )
+        .unwrap(),
+    ];
+    process_tx(context, &ixs, &[&mint_authority]).await.unwrap();
+
+    // Token Metadata 1.8 only decorates SPL Token mints, so write the metadata directly.
+    let metadata = find_metadata_address(&mint.pubkey());
+    let mut data = vec![0; MAX_METADATA_LEN];
+    Metadata {
+        key: Key::MetadataV1,
+        update_authority: mint_authority.pubkey(),
+        mint: mint.pubkey(),
+        data: Data {
+            name: "Test".to_string(),
+            symbol: "TST".to_string(),
+            uri: "https://example.com".to_string(),
+            seller_fee_basis_points: 0,
+            creators: None,
+        },
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: None,
+        collection: None,
+        uses: None,
+        collection_details: None,
+        programmable_config: None,
+    }
+    .save(&mut data)
+    .unwrap();
+    let account = Account {
+        lamports: rent.minimum_balance(data.len()),
+        data,
+        owner: mpl_token_metadata::id(),
+        executable: false,
+        rent_epoch: 0,
+    };
+    context.set_account(&metadata, &account.into());
+
+    TestNft {
+        mint: mint.pubkey(),
+        token_account,
+        metadata,
+        creators: vec![],
+        token_program: spl_token_2022::id(),
+        verified_collection: None,
+    }
+}
+
+/// Programmable NFT owned by `owner`, frozen in its ATA under its master edition with an empty
+/// token record and no rule set.
+pub async fn create_programmable_nft(context: &mut ProgramTestContext, owner: &Pubkey) -> TestNft {
+    create_programmable_nft_with_rule_set(context, owner, None).await
+}
+
+/// Programmable NFT whose transfers and delegations Token Metadata checks against `rule_set`.
+pub async fn create_programmable_nft_with_rule_set(
+    context: &mut ProgramTestContext,
+    owner: &Pubkey,
+    rule_set: Option<Pubkey>,
+) -> TestNft {
+    // Token Metadata `Create` and `Mint` reallocate accounts, which the natively run program
+    // cannot do, so lay out the accounts they would produce directly.
+    let mint = Keypair::new().pubkey();
+    let token_account = get_associated_token_address(owner, &mint);
+    let metadata = find_metadata_address(&mint);
+    let (edition, _) = find_master_edition_account(&mint);
+    let (token_record, token_record_bump) = find_token_record_account(&mint, &token_account);
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut set_account = |address: &Pubkey, owner: Pubkey, data: Vec<u8>| {
+        let account = Account {
+            lamports: rent.minimum_balance(data.len()),
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        context.set_account(address, &account.into());
+    };
+
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: Some(edition).into(),
+        supply: 1,
+        decimals: 0,
+        is_initialized: true,
+        freeze_authority: Some(edition).into(),
+    }
+    .pack_into_slice(&mut data);
+    set_account(&mint, spl_token::id(), data);
+
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner: *owner,
+        amount: 1,
+        state: spl_token::state::AccountState::Frozen,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    set_account(&token_account, spl_token::id(), data);
+
+    let mut data = vec![0; MAX_METADATA_LEN];
+    Metadata {
+        key: Key::MetadataV1,
+        update_authority: Pubkey::new_unique(),
+        mint,
+        data: Data {
+            name: "Test".to_string(),
+            symbol: "TST".to_string(),
+            uri: "https://example.com".to_string(),
+            seller_fee_basis_points: 0,
+            creators: None,
+        },
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::ProgrammableNonFungible),
+        collection: None,
+        uses: None,
+        collection_details: None,
+        programmable_config: Some(ProgrammableConfig::V1 { rule_set }),
+    }
+    .save(&mut data)
+    .unwrap();
+    set_account(&metadata, mpl_token_metadata::id(), data);
+
+    let mut data = MasterEditionV2 {
+        key: Key::MasterEditionV2,
+        supply: 0,
+        max_supply: Some(0),
+    }
+    .try_to_vec()
+    .unwrap();
+    data.resize(MAX_MASTER_EDITION_LEN, 0);
+    data[MAX_MASTER_EDITION_LEN - 1] = TokenStandard::ProgrammableNonFungible as u8;
+    set_account(&edition, mpl_token_metadata::id(), data);
+
+    let mut data = TokenRecord {
+        bump: token_record_bump,
+        ..Default::default()
+    }
+    .try_to_vec()
+    .unwrap();
+    data.resize(TOKEN_RECORD_SIZE, 0);
+    set_account(&token_record, mpl_token_metadata::id(), data);
+
+    TestNft {
+        mint,
+        token_account,
+        metadata,
+        creators: vec![],
+        token_program: spl_token::id(),
+        verified_collection: None,
+    }
+}
+
+/// Programmable NFT accounts for the token record of `token_account`, with no rule set.
+pub fn programmable_accounts(nft: &TestNft, token_account: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(mpl_token_metadata::id(), false),
+        AccountMeta::new_readonly(find_master_edition_account(&nft.mint).0, false),
+        AccountMeta::new(find_token_record_account(&nft.mint, token_account).0, false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+        AccountMeta::new_readonly(mpl_token_metadata::id(), false),
+        AccountMeta::new_readonly(mpl_token_metadata::id(), false),
+    ]
+}
+
+/// `sell_ix` for a programmable NFT.
+pub fn programmable_sell_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+) -> Instruction {
+    let mut ix = sell_ix(ah, wallet, nft, buyer_price, 1);
+    ix.accounts
+        .extend(programmable_accounts(nft, &nft.token_account));
+    ix.accounts.push(AccountMeta::new_readonly(nft.mint, false));
+    ix
+}
+
+/// `cancel_ix` for a programmable NFT listing.
+pub fn programmable_cancel_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+) -> Instruction {
+    let mut ix = cancel_ix(ah, wallet, nft, buyer_price, 1);
+    ix.accounts
+        .extend(programmable_accounts(nft, &nft.token_account));
+    ix.accounts.extend([
+        AccountMeta::new_readonly(nft.metadata, false),
+        AccountMeta::new_readonly(find_program_as_signer_address().0, false),
+        AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+    ]);
+    ix
+}
+
+/// `execute_sale_ix` for a programmable NFT, which goes straight to the buyer's ATA.
+pub fn programmable_execute_sale_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+) -> Instruction {
+    let mut ix = execute_sale_ix(ah, buyer, seller, nft, buyer_price, 1);
+    ix.accounts.extend(programmable_sale_accounts(nft, buyer));
+    ix
+}
+
+/// Programmable NFT accounts any sale of `nft` to `buyer` passes after its other remaining accounts.
+pub fn programmable_sale_accounts(nft: &TestNft, buyer: &Pubkey) -> Vec<AccountMeta> {
+    let mut accounts = programmable_accounts(nft, &nft.token_account);
+    accounts.push(AccountMeta::new(
+        find_token_record_account(&nft.mint, &nft.token_account_of(buyer)).0,
+        false,
+    ));
+    accounts
+}
+
+/// Auction House instance and the addresses derived from it.
+pub struct TestAuctionHouse {
+    pub address: Pubkey,
+    pub authority: Keypair,
+    pub treasury_mint: Pubkey,
+    pub treasury_token_program: Pubkey,
+    pub fee_account: Pubkey,
+    pub treasury: Pubkey,
+    pub fee_withdrawal_destination: Pubkey,
+    pub treasury_withdrawal_destination: Pubkey,
+    pub treasury_withdrawal_destination_owner: Pubkey,
+}
+
+impl TestAuctionHouse {
+    pub fn is_native(&self) -> bool {
+        self.treasury_mint == spl_token::native_mint::id()
+    }
+
+    /// Account used to pay or receive `treasury_mint` for `wallet`.
+    pub fn payment_account(&self, wallet: &Pubkey) -> Pubkey {
+        if self.is_native() {
+            *wallet
+        } else {
+            get_associated_token_address_with_program_id(
+                wallet,
+                &self.treasury_mint,
+                &self.treasury_token_program,
+            )
+        }
+    }
+}
+
+pub async fn create_auction_house(
+    context: &mut ProgramTestContext,
+    treasury_mint: &Pubkey,
+    seller_fee_basis_points: u16,
+    requires_sign_off: bool,
+    can_change_sale_price: bool,
+) -> TestAuctionHouse {
+    let authority = Keypair::new();
+    let treasury_token_program = mint_token_program(context, treasury_mint).await;
+    let treasury_withdrawal_destination_owner = Pubkey::new_unique();
+    let fee_withdrawal_destination = Pubkey::new_unique();
+    let treasury_withdrawal_destination = if *treasury_mint == spl_token::native_mint::id() {
+        treasury_withdrawal_destination_owner
+    } else {
+        get_associated_token_address_with_program_id(
+            &treasury_withdrawal_destination_owner,
+            treasury_mint,
+            &treasury_token_program,
+        )
+    };
+
+    let (address, bump) = find_auction_house_address(&authority.pubkey(), treasury_mint);
+    let (fee_account, fee_payer_bump) = find_auction_house_fee_account_address(&address);
+    let (treasury, treasury_bump) = find_auction_house_treasury_address(&address);
+
+    let accounts = auction_house_v2::accounts::CreateAuctionHouse {
+        treasury_mint: *treasury_mint,
+        payer: context.payer.pubkey(),
+        authority: authority.pubkey(),
+        fee_withdrawal_destination,
+        treasury_withdrawal_destination,
+        treasury_withdrawal_destination_owner,
+        auction_house: address,
+        auction_house_fee_account: fee_account,
+        auction_house_treasury: treasury,
+        token_program: treasury_token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::CreateAuctionHouse {
+        _bump: bump,
+        fee_payer_bump,
+        treasury_bump,
+        seller_fee_basis_points,
+        requires_sign_off,
+        can_change_sale_price,
+    }
+    .data();
+    let ix = Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    };
+    process_tx(context, &[ix], &[]).await.unwrap();
+
+    TestAuctionHouse {
+        address,
+        authority,
+        treasury_mint: *treasury_mint,
+        treasury_token_program,
+        fee_account,
+        treasury,
+        fee_withdrawal_destination,
+        treasury_withdrawal_destination,
+        treasury_withdrawal_destination_owner,
+    }
+}
+
+/// New settings passed to `update_auction_house`.
+pub struct AuctionHouseUpdate {
+    pub new_authority: Pubkey,
+    pub fee_withdrawal_destination: Pubkey,
+    pub treasury_withdrawal_destination_owner: Pubkey,
+    pub seller_fee_basis_points: Option<u16>,
+    pub requires_sign_off: Option<bool>,
+    pub can_change_sale_price: Option<bool>,
+    pub max_referral_basis_points: Option<u16>,
+    pub royalty_policy: Option<RoyaltyPolicy>,
+    pub unverified_creator_policy: Option<UnverifiedCreatorPolicy>,
+}
+
+impl AuctionHouseUpdate {
+    /// An update that keeps every setting of `ah`.
+    pub fn unchanged(ah: &TestAuctionHouse) -> Self {
+        Self {
+            new_authority: ah.authority.pubkey(),
+            fee_withdrawal_destination: ah.fee_withdrawal_destination,
+            treasury_withdrawal_destination_owner: ah.treasury_withdrawal_destination_owner,
+            seller_fee_basis_points: None,
+            requires_sign_off: None,
+            can_change_sale_price: None,
+            max_referral_basis_points: None,
+            royalty_policy: None,
+            unverified_creator_policy: None,
+        }
+    }
+}
+
+pub fn update_auction_house_ix(
+    ah: &TestAuctionHouse,
+    payer: &Pubkey,
+    authority: &Pubkey,
+    update: &AuctionHouseUpdate,
+) -> Instruction {
+    let treasury_withdrawal_destination = if ah.is_native() {
+        update.treasury_withdrawal_destination_owner
+    } else {
+        get_associated_token_address(
+            &update.treasury_withdrawal_destination_owner,
+            &ah.treasury_mint,
+        )
+    };
+    let accounts = auction_house_v2::accounts::UpdateAuctionHouse {
+        treasury_mint: ah.treasury_mint,
+        payer: *payer,
+        authority: *authority,
+        new_authority: update.new_authority,
+        fee_withdrawal_destination: update.fee_withdrawal_destination,
+        treasury_withdrawal_destination,
+        treasury_withdrawal_destination_owner: update.treasury_withdrawal_destination_owner,
+        auction_house: ah.address,
+        token_program: spl_token::id(),
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::UpdateAuctionHouse {
+        seller_fee_basis_points: update.seller_fee_basis_points,
+        requires_sign_off: update.requires_sign_off,
+        can_change_sale_price: update.can_change_sale_price,
+        max_referral_basis_points: update.max_referral_basis_points,
+        royalty_policy: update.royalty_policy,
+        unverified_creator_policy: update.unverified_creator_policy,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn withdraw_from_fee_ix(ah: &TestAuctionHouse, authority: &Pubkey, amount: u64) -> Instruction {
+    let accounts = auction_house_v2::accounts::WithdrawFromFee {
+        authority: *authority,
+        fee_withdrawal_destination: ah.fee_withdrawal_destination,
+        auction_house_fee_account: ah.fee_account,
+        auction_house: ah.address,
+        system_program: solana_sdk::system_program::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::WithdrawFromFee { amount }.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn withdraw_from_treasury_ix(
+    ah: &TestAuctionHouse,
+    authority: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let accounts = auction_house_v2::accounts::WithdrawFromTreasury {
+        treasury_mint: ah.treasury_mint,
+        authority: *authority,
+        treasury_withdrawal_destination: ah.treasury_withdrawal_destination,
+        auction_house_treasury: ah.treasury,
+        auction_house: ah.address,
+        token_program: spl_token::id(),
+        system_program: solana_sdk::system_program::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::WithdrawFromTreasury { amount }.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn sell_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    sell_with_expiry_ix(ah, wallet, nft, buyer_price, token_size, None)
+}
+
+pub fn sell_with_expiry_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+    expiry: Option<i64>,
+) -> Instruction {
+    sell_order_ix(ah, wallet, nft, buyer_price, token_size, expiry, None)
+}
+
+pub fn sell_with_referral_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+    referral: Referral,
+) -> Instruction {
+    sell_order_ix(
+        ah,
+        wallet,
+        nft,
+        buyer_price,
+        token_size,
+        None,
+        Some(referral),
+    )
+}
+
+fn sell_order_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
+) -> Instruction {
+    let (seller_trade_state, trade_state_bump) = find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        buyer_price,
+        token_size,
+    );
+    let (free_seller_trade_state, free_trade_state_bump) = find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        token_size,
+    );
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+
+    let mut accounts = auction_house_v2::accounts::Sell {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        metadata: nft.metadata,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        seller_trade_state,
+        free_seller_trade_state,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_fee_payer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::Sell {
+        trade_state_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        buyer_price,
+        token_size,
+        expiry,
+        referral,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn buy_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    buy_with_expiry_ix(ah, wallet, nft, buyer_price, token_size, None)
+}
+
+/// Native house charging `seller_fee_basis_points`, with a funded fee account and authority, and a funded seller and
+/// buyer. The buyer can cover several bids at `ONE_SOL`.
+pub async fn setup_native_sale(
+    context: &mut ProgramTestContext,
+    seller_fee_basis_points: u16,
+) -> (TestAuctionHouse, Keypair, Keypair) {
+    let ah = create_auction_house(
+        context,
+        &spl_token::native_mint::id(),
+        seller_fee_basis_points,
+        false,
+        false,
+    )
+    .await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    airdrop(context, &ah.authority.pubkey(), ONE_SOL).await;
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), 10 * ONE_SOL).await;
+    (ah, seller, buyer)
+}
+
+/// List one token of `nft` from `seller` and bid on it from `buyer`, both at `price`. Programmable NFTs are listed
+/// with `programmable_sell_ix`.
+pub async fn list_and_bid(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    seller: &Keypair,
+    buyer: &Keypair,
+    nft: &TestNft,
+    price: u64,
+) {
+    let metadata = get_account(context, &nft.metadata).await.unwrap();
+    let sell = match Metadata::safe_deserialize(&metadata.data)
+        .unwrap()
+        .token_standard
+    {
+        Some(TokenStandard::ProgrammableNonFungible) => {
+            programmable_sell_ix(ah, &seller.pubkey(), nft, price)
+        }
+        _ => sell_ix(ah, &seller.pubkey(), nft, price, 1),
+    };
+    process_tx(context, &[sell], &[seller]).await.unwrap();
+    let buy = buy_ix(ah, &buyer.pubkey(), nft, price, 1);
+    process_tx(context, &[buy], &[buyer]).await.unwrap();
+}
+
+pub fn buy_with_expiry_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+    expiry: Option<i64>,
+) -> Instruction {
+    buy_order_ix(ah, wallet, nft, buyer_price, token_size, expiry, None, None)
+}
+
+pub fn buy_with_referral_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+    referral: Referral,
+) -> Instruction {
+    buy_order_ix(
+        ah,
+        wallet,
+        nft,
+        buyer_price,
+        token_size,
+        None,
+        Some(referral),
+        None,
+    )
+}
+
+/// Private bid paying creators `royalty_choice` basis points of the metadata royalty.
+pub fn buy_with_royalty_choice_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+    royalty_choice: u16,
+) -> Instruction {
+    buy_order_ix(
+        ah,
+        wallet,
+        nft,
+        buyer_price,
+        token_size,
+        None,
+        None,
+        Some(royalty_choice),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn buy_order_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+    expiry: Option<i64>,
+    referral: Option<Referral>,
+    royalty_choice: Option<u16>,
+) -> Instruction {
+    let (buyer_trade_state, trade_state_bump) = find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        buyer_price,
+        token_size,
+    );
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, wallet);
+
+    let mut accounts = auction_house_v2::accounts::Buy {
+        wallet: *wallet,
+        payment_account: ah.payment_account(wallet),
+        transfer_authority: *wallet,
+        treasury_mint: ah.treasury_mint,
+        token_account: nft.token_account,
+        metadata: nft.metadata,
+        escrow_payment_account,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        buyer_trade_state,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_fee_payer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::Buy {
+        trade_state_bump,
+        escrow_payment_bump,
+        buyer_price,
+        token_size,
+        expiry,
+        referral,
+        royalty_choice,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Public bid on `nft.mint`; `nft.token_account` only supplies the mint and metadata.
+pub fn public_buy_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    let (buyer_trade_state, trade_state_bump) = find_public_bid_trade_state_address(
+        wallet,
+        &ah.address,
+        &ah.treasury_mint,
+        &nft.mint,
+        buyer_price,
+        token_size,
+    );
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, wallet);
+
+    let mut accounts = auction_house_v2::accounts::PublicBuy {
+        wallet: *wallet,
+        payment_account: ah.payment_account(wallet),
+        transfer_authority: *wallet,
+        treasury_mint: ah.treasury_mint,
+        token_account: nft.token_account,
+        metadata: nft.metadata,
+        escrow_payment_account,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        buyer_trade_state,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_fee_payer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::PublicBuy {
+        trade_state_bump,
+        escrow_payment_bump,
+        buyer_price,
+        token_size,
+        expiry: None,
+        referral: None,
+        royalty_choice: None,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Receipt for the bid placed by the preceding instruction in the same transaction.
+pub fn print_bid_receipt_ix(bookkeeper: &Pubkey, buyer_trade_state: &Pubkey) -> Instruction {
+    let (receipt, receipt_bump) = find_bid_receipt_address(buyer_trade_state);
+    let accounts = auction_house_v2::accounts::PrintBidReceipt {
+        receipt,
+        bookkeeper: *bookkeeper,
+        system_program: solana_sdk::system_program::id(),
+        rent: sysvar::rent::id(),
+        instruction: sysvar::instructions::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::PrintBidReceipt { receipt_bump }.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Mark the receipt of the bid canceled by the preceding instruction in the same transaction.
+pub fn cancel_bid_receipt_ix(buyer_trade_state: &Pubkey) -> Instruction {
+    let accounts = auction_house_v2::accounts::CancelBidReceipt {
+        receipt: find_bid_receipt_address(buyer_trade_state).0,
+        system_program: solana_sdk::system_program::id(),
+        instruction: sysvar::instructions::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::CancelBidReceipt {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Creator accounts `pay_creator_fees` expects after the named accounts.
+pub fn creator_remaining_accounts(ah: &TestAuctionHouse, nft: &TestNft) -> Vec<AccountMeta> {
+    let mut metas = vec![];
+    for creator in &nft.creators {
+        metas.push(AccountMeta::new(*creator, false));
+        if !ah.is_native() {
+            metas.push(AccountMeta::new(ah.payment_account(creator), false));
+        }
+    }
+    metas
+}
+
+/// Fee schedule account `execute_sale` takes after the creator accounts for tokens in a verified collection.
+pub fn fee_schedule_remaining_accounts(ah: &TestAuctionHouse, nft: &TestNft) -> Vec<AccountMeta> {
+    nft.verified_collection
+        .iter()
+        .map(|collection| AccountMeta::new_readonly(fee_schedule_address(ah, collection), false))
+        .collect()
+}
+
+/// Collection stats account `execute_sale` takes after the fee schedule for tokens in a verified collection.
+pub fn collection_stats_remaining_accounts(
+    ah: &TestAuctionHouse,
+    nft: &TestNft,
+) -> Vec<AccountMeta> {
+    nft.verified_collection
+        .iter()
+        .map(|collection| AccountMeta::new(collection_stats_address(ah, collection), false))
+        .collect()
+}
+
+pub fn collection_stats_address(ah: &TestAuctionHouse, collection: &Pubkey) -> Pubkey {
+    find_collection_stats_address(&ah.address, collection).0
+}
+
+/// Trader stats accounts `execute_sale` takes after the collection accounts: the buyer's, then the seller's.
+pub fn trader_stats_remaining_accounts(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(trader_stats_address(ah, buyer), false),
+        AccountMeta::new(trader_stats_address(ah, seller), false),
+    ]
+}
+
+pub fn trader_stats_address(ah: &TestAuctionHouse, wallet: &Pubkey) -> Pubkey {
+    find_trader_stats_address(&ah.address, wallet).0
+}
+
+/// Referrer accounts `execute_sale` takes after the trader stats accounts: the listing's referrer, then the bid's.
+pub fn referrer_remaining_accounts(
+    ah: &TestAuctionHouse,
+    referrers: &[Pubkey],
+) -> Vec<AccountMeta> {
+    let mut metas = vec![];
+    for referrer in referrers {
+        metas.push(AccountMeta::new(*referrer, false));
+        if !ah.is_native() {
+            metas.push(AccountMeta::new(ah.payment_account(referrer), false));
+        }
+    }
+    metas
+}
+
+/// Trade state PDA of `wallet`'s order for `nft` on `ah`.
+pub fn trade_state_address(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    price: u64,
+    token_size: u64,
+) -> Pubkey {
+    find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        price,
+        token_size,
+    )
+    .0
+}
+
+pub fn execute_sale_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    let (buyer_trade_state, _) = find_trade_state_address(
+        buyer,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        buyer_price,
+        token_size,
+    );
+    let (seller_trade_state, _) = find_trade_state_address(
+        seller,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        buyer_price,
+        token_size,
+    );
+    let (free_trade_state, free_trade_state_bump) = find_trade_state_address(
+        seller,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        token_size,
+    );
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, buyer);
+    let (escrow_nft_account, escrow_nft_bump) =
+        find_escrow_nft_address(&ah.address, seller, &nft.mint, buyer);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+
+    let mut accounts = auction_house_v2::accounts::ExecuteSale {
+        buyer: *buyer,
+        seller: *seller,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        escrow_nft_account,
+        escrow_nft_record: find_escrow_nft_record_address(&escrow_nft_account).0,
+        metadata: nft.metadata,
+        treasury_mint: ah.treasury_mint,
+        escrow_payment_account,
+        seller_payment_receipt_account: ah.payment_account(seller),
+        buyer_receipt_token_account: nft.token_account_of(buyer),
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        auction_house_treasury: ah.treasury,
+        buyer_trade_state,
+        seller_trade_state,
+        free_trade_state,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    // The authority signs to settle on behalf of both parties.
+    mark_signer(&mut accounts, &ah.authority.pubkey());
+    accounts.extend(creator_remaining_accounts(ah, nft));
+    accounts.extend(fee_schedule_remaining_accounts(ah, nft));
+    accounts.extend(collection_stats_remaining_accounts(ah, nft));
+    accounts.extend(trader_stats_remaining_accounts(ah, buyer, seller));
+
+    let data = auction_house_v2::instruction::ExecuteSale {
+        escrow_payment_bump,
+        _free_trade_state_bump: free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price,
+        token_size,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// `execute_sale_ix` matching the listing against `buyer`'s public bid instead of a private one.
+pub fn execute_public_sale_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    let mut ix = execute_sale_ix(ah, buyer, seller, nft, buyer_price, token_size);
+    let private_trade_state = trade_state_address(ah, buyer, nft, buyer_price, token_size);
+    let (public_trade_state, _) = find_public_bid_trade_state_address(
+        buyer,
+        &ah.address,
+        &ah.treasury_mint,
+        &nft.mint,
+        buyer_price,
+        token_size,
+    );
+    for meta in ix.accounts.iter_mut() {
+        if meta.pubkey == private_trade_state {
+            meta.pubkey = public_trade_state;
+        }
+    }
+    ix
+}
+
+/// `execute_sale_ix` filling `partial_order_size` of the listing's `token_size` tokens for
+/// `partial_order_price`, matched with `buyer`'s bid for that size and price.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_partial_sale_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+    partial_order_size: u64,
+    partial_order_price: u64,
+) -> Instruction {
+    let mut ix = execute_sale_ix(ah, buyer, seller, nft, buyer_price, token_size);
+    let full_trade_state = trade_state_address(ah, buyer, nft, buyer_price, token_size);
+    let partial_trade_state =
+        trade_state_address(ah, buyer, nft, partial_order_price, partial_order_size);
+    for meta in ix.accounts.iter_mut() {
+        if meta.pubkey == full_trade_state {
+            meta.pubkey = partial_trade_state;
+        }
+    }
+    let data = auction_house_v2::instruction::ExecuteSale::try_from_slice(&ix.data[8..]).unwrap();
+    ix.data = auction_house_v2::instruction::ExecutePartialSale {
+        escrow_payment_bump: data.escrow_payment_bump,
+        _free_trade_state_bump: data._free_trade_state_bump,
+        program_as_signer_bump: data.program_as_signer_bump,
+        escrow_nft_bump: data.escrow_nft_bump,
+        buyer_price,
+        token_size,
+        partial_order_size,
+        partial_order_price,
+    }
+    .data();
+    ix
+}
+
+/// Cancel `wallet`'s order at `buyer_price`, signed by the wallet.
+pub fn cancel_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    let trade_state = trade_state_address(ah, wallet, nft, buyer_price, token_size);
+    let mut accounts = auction_house_v2::accounts::Cancel {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        trade_state,
+        token_program: nft.token_program,
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::Cancel {
+        buyer_price,
+        token_size,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Permissionless close of an expired bid or ask; nobody but the fee payer signs.
+pub fn close_expired_trade_state_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    rent_payer: &Pubkey,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    let trade_state = trade_state_address(ah, wallet, nft, buyer_price, token_size);
+    let accounts = auction_house_v2::accounts::CloseExpiredTradeState {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        auction_house: ah.address,
+        trade_state,
+        rent_payer: *rent_payer,
+        token_program: nft.token_program,
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::CloseExpiredTradeState {
+        buyer_price,
+        token_size,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Claim the NFT escrowed by a sale whose escrow account rent `rent_payer` paid.
+pub fn claim_nft_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    rent_payer: &Pubkey,
+    signer: &Pubkey,
+) -> Instruction {
+    let (escrow_nft_account, escrow_nft_bump) =
+        find_escrow_nft_address(&ah.address, seller, &nft.mint, buyer);
+    let mut accounts = auction_house_v2::accounts::ClaimNft {
+        buyer: *buyer,
+        seller: *seller,
+        token_mint: nft.mint,
+        escrow_nft_account,
+        escrow_nft_record: find_escrow_nft_record_address(&escrow_nft_account).0,
+        rent_payer: *rent_payer,
+        buyer_receipt_token_account: nft.token_account_of(buyer),
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, signer);
+    let data = auction_house_v2::instruction::ClaimNft {
+        _escrow_nft_bump: escrow_nft_bump,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Return the NFT escrowed by a sale whose escrow account rent `rent_payer` paid to the seller, as the authority. The
+/// seller signs to pay the sale price back to the buyer.
+pub fn refund_nft_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    rent_payer: &Pubkey,
+) -> Instruction {
+    let (escrow_nft_account, escrow_nft_bump) =
+        find_escrow_nft_address(&ah.address, seller, &nft.mint, buyer);
+    let accounts = auction_house_v2::accounts::RefundNft {
+        buyer: *buyer,
+        seller: *seller,
+        token_mint: nft.mint,
+        escrow_nft_account,
+        escrow_nft_record: find_escrow_nft_record_address(&escrow_nft_account).0,
+        rent_payer: *rent_payer,
+        token_account: nft.token_account_of(seller),
+        treasury_mint: ah.treasury_mint,
+        seller_payment_account: ah.payment_account(seller),
+        escrow_payment_account: find_escrow_payment_address(&ah.address, buyer).0,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::RefundNft {
+        _escrow_nft_bump: escrow_nft_bump,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn delegate_auctioneer_ix(
+    ah: &TestAuctionHouse,
+    auctioneer_authority: &Pubkey,
+    scopes: Vec<AuthorityScope>,
+) -> Instruction {
+    let (ah_auctioneer_pda, _) = find_auctioneer_pda(&ah.address, auctioneer_authority);
+    let accounts = auction_house_v2::accounts::DelegateAuctioneer {
+        auction_house: ah.address,
+        authority: ah.authority.pubkey(),
+        auctioneer_authority: *auctioneer_authority,
+        ah_auctioneer_pda,
+        system_program: solana_sdk::system_program::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::DelegateAuctioneer { scopes }.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn update_auctioneer_ix(
+    ah: &TestAuctionHouse,
+    auctioneer_authority: &Pubkey,
+    scopes: Vec<AuthorityScope>,
+) -> Instruction {
+    let (ah_auctioneer_pda, _) = find_auctioneer_pda(&ah.address, auctioneer_authority);
+    let accounts = auction_house_v2::accounts::UpdateAuctioneer {
+        auction_house: ah.address,
+        authority: ah.authority.pubkey(),
+        auctioneer_authority: *auctioneer_authority,
+        ah_auctioneer_pda,
+        system_program: solana_sdk::system_program::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::UpdateAuctioneer { scopes }.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn revoke_auctioneer_ix(ah: &TestAuctionHouse, auctioneer_authority: &Pubkey) -> Instruction {
+    let (ah_auctioneer_pda, _) = find_auctioneer_pda(&ah.address, auctioneer_authority);
+    let accounts = auction_house_v2::accounts::RevokeAuctioneer {
+        auction_house: ah.address,
+        authority: ah.authority.pubkey(),
+        auctioneer_authority: *auctioneer_authority,
+        ah_auctioneer_pda,
+        system_program: solana_sdk::system_program::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::RevokeAuctioneer {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn fee_schedule_address(ah: &TestAuctionHouse, collection: &Pubkey) -> Pubkey {
+    find_fee_schedule_address(&ah.address, collection).0
+}
+
+pub fn create_fee_schedule_ix(
+    ah: &TestAuctionHouse,
+    collection: &Pubkey,
+    seller_fee_basis_points: u16,
+) -> Instruction {
+    let accounts = auction_house_v2::accounts::CreateFeeSchedule {
+        auction_house: ah.address,
+        authority: ah.authority.pubkey(),
+        collection: *collection,
+        fee_schedule: fee_schedule_address(ah, collection),
+        system_program: solana_sdk::system_program::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::CreateFeeSchedule {
+        seller_fee_basis_points,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn update_fee_schedule_ix(
+    ah: &TestAuctionHouse,
+    collection: &Pubkey,
+    seller_fee_basis_points: u16,
+) -> Instruction {
+    let accounts = auction_house_v2::accounts::UpdateFeeSchedule {
+        auction_house: ah.address,
+        authority: ah.authority.pubkey(),
+        fee_schedule: fee_schedule_address(ah, collection),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::UpdateFeeSchedule {
+        seller_fee_basis_points,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn close_fee_schedule_ix(ah: &TestAuctionHouse, collection: &Pubkey) -> Instruction {
+    let accounts = auction_house_v2::accounts::CloseFeeSchedule {
+        auction_house: ah.address,
+        authority: ah.authority.pubkey(),
+        fee_schedule: fee_schedule_address(ah, collection),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::CloseFeeSchedule {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Auctioneer listing; the seller trade state is derived at `u64::MAX` rather than a price.
+pub fn auctioneer_sell_ix(
+    ah: &TestAuctionHouse,
+    auctioneer_authority: &Pubkey,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    token_size: u64,
+) -> Instruction {
+    let (seller_trade_state, trade_state_bump) = find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        u64::MAX,
+        token_size,
+    );
+    let (free_seller_trade_state, free_trade_state_bump) = find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        token_size,
+    );
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let (ah_auctioneer_pda, _) = find_auctioneer_pda(&ah.address, auctioneer_authority);
+
+    let mut accounts = auction_house_v2::accounts::AuctioneerSell {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        metadata: nft.metadata,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        seller_trade_state,
+        free_seller_trade_state,
+        auctioneer_authority: *auctioneer_authority,
+        ah_auctioneer_pda,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_fee_payer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::AuctioneerSell {
+        trade_state_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        token_size,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn auctioneer_buy_ix(
+    ah: &TestAuctionHouse,
+    auctioneer_authority: &Pubkey,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    let (buyer_trade_state, trade_state_bump) = find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        buyer_price,
+        token_size,
+    );
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, wallet);
+    let (ah_auctioneer_pda, _) = find_auctioneer_pda(&ah.address, auctioneer_authority);
+
+    let mut accounts = auction_house_v2::accounts::AuctioneerBuy {
+        wallet: *wallet,
+        payment_account: ah.payment_account(wallet),
+        transfer_authority: *wallet,
+        treasury_mint: ah.treasury_mint,
+        token_account: nft.token_account,
+        metadata: nft.metadata,
+        escrow_payment_account,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        buyer_trade_state,
+        auctioneer_authority: *auctioneer_authority,
+        ah_auctioneer_pda,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_fee_payer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::AuctioneerBuy {
+        trade_state_bump,
+        escrow_payment_bump,
+        buyer_price,
+        token_size,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Settle `buyer`'s bid at `buyer_price` against the seller's Auctioneer listing.
+pub fn auctioneer_execute_sale_ix(
+    ah: &TestAuctionHouse,
+    auctioneer_authority: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    let (buyer_trade_state, _) = find_trade_state_address(
+        buyer,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        buyer_price,
+        token_size,
+    );
+    let (seller_trade_state, _) = find_trade_state_address(
+        seller,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        u64::MAX,
+        token_size,
+    );
+    let (free_trade_state, free_trade_state_bump) = find_trade_state_address(
+        seller,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        token_size,
+    );
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, buyer);
+    let (escrow_nft_account, escrow_nft_bump) =
+        find_escrow_nft_address(&ah.address, seller, &nft.mint, buyer);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let (ah_auctioneer_pda, _) = find_auctioneer_pda(&ah.address, auctioneer_authority);
+
+    let mut accounts = auction_house_v2::accounts::AuctioneerExecuteSale {
+        buyer: *buyer,
+        seller: *seller,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        escrow_nft_account,
+        escrow_nft_record: find_escrow_nft_record_address(&escrow_nft_account).0,
+        metadata: nft.metadata,
+        treasury_mint: ah.treasury_mint,
+        escrow_payment_account,
+        seller_payment_receipt_account: ah.payment_account(seller),
+        buyer_receipt_token_account: nft.token_account_of(buyer),
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        auction_house_treasury: ah.treasury,
+        buyer_trade_state,
+        seller_trade_state,
+        free_trade_state,
+        auctioneer_authority: *auctioneer_authority,
+        ah_auctioneer_pda,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    // The authority signs to settle on behalf of both parties.
+    mark_signer(&mut accounts, &ah.authority.pubkey());
+    accounts.extend(creator_remaining_accounts(ah, nft));
+    accounts.extend(fee_schedule_remaining_accounts(ah, nft));
+    accounts.extend(collection_stats_remaining_accounts(ah, nft));
+    accounts.extend(trader_stats_remaining_accounts(ah, buyer, seller));
+
+    let data = auction_house_v2::instruction::AuctioneerExecuteSale {
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price,
+        token_size,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Cancel `wallet`'s order at `buyer_price`; pass `u64::MAX` for an Auctioneer listing.
+pub fn auctioneer_cancel_ix(
+    ah: &TestAuctionHouse,
+    auctioneer_authority: &Pubkey,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+    token_size: u64,
+) -> Instruction {
+    let trade_state = trade_state_address(ah, wallet, nft, buyer_price, token_size);
+    let (ah_auctioneer_pda, _) = find_auctioneer_pda(&ah.address, auctioneer_authority);
+    let mut accounts = auction_house_v2::accounts::AuctioneerCancel {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        trade_state,
+        auctioneer_authority: *auctioneer_authority,
+        ah_auctioneer_pda,
+        token_program: nft.token_program,
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::AuctioneerCancel {
+        buyer_price,
+        token_size,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn deposit_ix(ah: &TestAuctionHouse, wallet: &Pubkey, amount: u64) -> Instruction {
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, wallet);
+    let mut accounts = auction_house_v2::accounts::Deposit {
+        wallet: *wallet,
+        payment_account: ah.payment_account(wallet),
+        transfer_authority: *wallet,
+        escrow_payment_account,
+        treasury_mint: ah.treasury_mint,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        token_program: spl_token::id(),
+        system_program: solana_sdk::system_program::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    // Paying rent for a new SPL escrow makes the wallet writable.
+    mark_fee_payer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::Deposit {
+        escrow_payment_bump,
+        amount,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn withdraw_ix(ah: &TestAuctionHouse, wallet: &Pubkey, amount: u64) -> Instruction {
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, wallet);
+    let mut accounts = auction_house_v2::accounts::Withdraw {
+        wallet: *wallet,
+        receipt_account: ah.payment_account(wallet),
+        escrow_payment_account,
+        treasury_mint: ah.treasury_mint,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        token_program: spl_token::id(),
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::Withdraw {
+        escrow_payment_bump,
+        amount,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Move the bank clock to `unix_timestamp`.
+pub async fn set_clock(context: &mut ProgramTestContext, unix_timestamp: i64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    context.set_sysvar(&clock);
+}
+
+pub async fn get_clock(context: &mut ProgramTestContext) -> Clock {
+    context.banks_client.get_sysvar().await.unwrap()
+}
+
+pub struct AuctionTerms {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub reserve_price: u64,
+    pub min_increment: u64,
+    pub extension_window: i64,
+}
+
+/// Trade state of `wallet`'s order on `nft` keyed by `seed` in place of a price.
+pub fn seeded_trade_state_address(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    seed: &str,
+) -> Pubkey {
+    find_seeded_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        seed,
+        1,
+    )
+    .0
+}
+
+/// Seller trade state of `seller`'s auction listing of `nft`.
+pub fn auction_trade_state_address(
+    ah: &TestAuctionHouse,
+    seller: &Pubkey,
+    nft: &TestNft,
+) -> Pubkey {
+    seeded_trade_state_address(ah, seller, nft, AUCTION)
+}
+
+/// Auction state PDA of `seller`'s auction listing of `nft`.
+pub fn auction_state_address(ah: &TestAuctionHouse, seller: &Pubkey, nft: &TestNft) -> Pubkey {
+    find_auction_state_address(&auction_trade_state_address(ah, seller, nft)).0
+}
+
+pub fn create_auction_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    terms: &AuctionTerms,
+) -> Instruction {
+    let (seller_trade_state, trade_state_bump) = find_seeded_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        AUCTION,
+        1,
+    );
+    let (free_seller_trade_state, free_trade_state_bump) = find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        1,
+    );
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let (auction_state, _) = find_auction_state_address(&seller_trade_state);
+    let (auction_escrow_payment_account, _) =
+        find_auction_escrow_payment_address(&ah.address, &auction_state);
+
+    let mut accounts = auction_house_v2::accounts::CreateAuction {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        metadata: nft.metadata,
+        authority: ah.authority.pubkey(),
+        treasury_mint: ah.treasury_mint,
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        seller_trade_state,
+        free_seller_trade_state,
+        auction_state,
+        auction_escrow_payment_account,
+        dutch_trade_state: dutch_trade_state_address(ah, wallet, nft),
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_fee_payer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::CreateAuction {
+        trade_state_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        token_size: 1,
+        start_time: terms.start_time,
+        end_time: terms.end_time,
+        reserve_price: terms.reserve_price,
+        min_increment: terms.min_increment,
+        extension_window: terms.extension_window,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Bid on `seller`'s auction of `nft`; `previous_bidder` is the current highest bidder, if any.
+pub fn place_auction_bid_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    previous_bidder: Option<&Pubkey>,
+    bid_price: u64,
+) -> Instruction {
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, wallet);
+    let (previous_bidder_escrow_payment_account, _) =
+        find_escrow_payment_address(&ah.address, previous_bidder.unwrap_or(wallet));
+    let seller_trade_state = auction_trade_state_address(ah, seller, nft);
+    let (auction_state, _) = find_auction_state_address(&seller_trade_state);
+    let (auction_escrow_payment_account, _) =
+        find_auction_escrow_payment_address(&ah.address, &auction_state);
+
+    let accounts = auction_house_v2::accounts::PlaceAuctionBid {
+        wallet: *wallet,
+        escrow_payment_account,
+        previous_bidder_escrow_payment_account,
+        treasury_mint: ah.treasury_mint,
+        auction_house: ah.address,
+        seller_trade_state,
+        token_account: nft.token_account,
+        auction_state,
+        auction_escrow_payment_account,
+        token_program: spl_token::id(),
+        system_program: solana_sdk::system_program::id(),
+        program_as_signer: find_program_as_signer_address().0,
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::PlaceAuctionBid {
+        escrow_payment_bump,
+        bid_price,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Settle `seller`'s auction of `nft` won by `buyer`; pass the seller as `buyer` if nobody bid.
+pub fn settle_auction_ix(
+    ah: &TestAuctionHouse,
+    payer: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+) -> Instruction {
+    let buyer_trade_state = seeded_trade_state_address(ah, buyer, nft, AUCTION_BID);
+    let seller_trade_state = auction_trade_state_address(ah, seller, nft);
+    let (free_trade_state, free_trade_state_bump) = find_trade_state_address(
+        seller,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        1,
+    );
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, buyer);
+    let (escrow_nft_account, escrow_nft_bump) =
+        find_escrow_nft_address(&ah.address, seller, &nft.mint, buyer);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let (auction_state, _) = find_auction_state_address(&seller_trade_state);
+    let (auction_escrow_payment_account, _) =
+        find_auction_escrow_payment_address(&ah.address, &auction_state);
+
+    let mut accounts = auction_house_v2::accounts::SettleAuction {
+        buyer: *buyer,
+        seller: *seller,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        escrow_nft_account,
+        escrow_nft_record: find_escrow_nft_record_address(&escrow_nft_account).0,
+        metadata: nft.metadata,
+        treasury_mint: ah.treasury_mint,
+        escrow_payment_account,
+        seller_payment_receipt_account: ah.payment_account(seller),
+        buyer_receipt_token_account: nft.token_account_of(buyer),
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        auction_house_treasury: ah.treasury,
+        buyer_trade_state,
+        seller_trade_state,
+        free_trade_state,
+        auction_state,
+        auction_escrow_payment_account,
+        payer: *payer,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    accounts.extend(creator_remaining_accounts(ah, nft));
+    accounts.extend(fee_schedule_remaining_accounts(ah, nft));
+    accounts.extend(collection_stats_remaining_accounts(ah, nft));
+    accounts.extend(trader_stats_remaining_accounts(ah, buyer, seller));
+
+    let data = auction_house_v2::instruction::SettleAuction {
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Cancel `wallet`'s auction listing of `nft`, signed by the wallet.
+pub fn cancel_auction_ix(ah: &TestAuctionHouse, wallet: &Pubkey, nft: &TestNft) -> Instruction {
+    let trade_state = auction_trade_state_address(ah, wallet, nft);
+    let (auction_state, _) = find_auction_state_address(&trade_state);
+    let mut accounts = auction_house_v2::accounts::CancelAuction {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        trade_state,
+        auction_state,
+        token_program: nft.token_program,
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::CancelAuction {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub struct DutchTerms {
+    pub start_price: u64,
+    pub end_price: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub curve: DecayCurve,
+}
+
+/// Seller trade state of `seller`'s dutch listing of `nft`.
+pub fn dutch_trade_state_address(ah: &TestAuctionHouse, seller: &Pubkey, nft: &TestNft) -> Pubkey {
+    seeded_trade_state_address(ah, seller, nft, DUTCH)
+}
+
+/// Listing config PDA of `seller`'s dutch listing of `nft`.
+pub fn listing_config_address(ah: &TestAuctionHouse, seller: &Pubkey, nft: &TestNft) -> Pubkey {
+    find_listing_config_address(&dutch_trade_state_address(ah, seller, nft)).0
+}
+
+pub fn create_dutch_listing_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+    terms: &DutchTerms,
+) -> Instruction {
+    let (seller_trade_state, trade_state_bump) = find_seeded_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        DUTCH,
+        1,
+    );
+    let (free_seller_trade_state, free_trade_state_bump) = find_trade_state_address(
+        wallet,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        1,
+    );
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let (listing_config, _) = find_listing_config_address(&seller_trade_state);
+
+    let mut accounts = auction_house_v2::accounts::CreateDutchListing {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        metadata: nft.metadata,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        seller_trade_state,
+        free_seller_trade_state,
+        listing_config,
+        auction_trade_state: auction_trade_state_address(ah, wallet, nft),
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_fee_payer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::CreateDutchListing {
+        trade_state_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        token_size: 1,
+        start_price: terms.start_price,
+        end_price: terms.end_price,
+        start_time: terms.start_time,
+        end_time: terms.end_time,
+        curve: terms.curve,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Sell `seller`'s dutch listing of `nft` to `buyer`'s bid at `buyer_price`, signed by the authority.
+pub fn execute_dutch_sale_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    buyer_price: u64,
+) -> Instruction {
+    let buyer_trade_state = trade_state_address(ah, buyer, nft, buyer_price, 1);
+    let seller_trade_state = dutch_trade_state_address(ah, seller, nft);
+    let (free_trade_state, free_trade_state_bump) = find_trade_state_address(
+        seller,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        1,
+    );
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, buyer);
+    let (escrow_nft_account, escrow_nft_bump) =
+        find_escrow_nft_address(&ah.address, seller, &nft.mint, buyer);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let (listing_config, _) = find_listing_config_address(&seller_trade_state);
+
+    let mut accounts = auction_house_v2::accounts::ExecuteDutchSale {
+        buyer: *buyer,
+        seller: *seller,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        escrow_nft_account,
+        escrow_nft_record: find_escrow_nft_record_address(&escrow_nft_account).0,
+        metadata: nft.metadata,
+        treasury_mint: ah.treasury_mint,
+        escrow_payment_account,
+        seller_payment_receipt_account: ah.payment_account(seller),
+        buyer_receipt_token_account: nft.token_account_of(buyer),
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        auction_house_treasury: ah.treasury,
+        buyer_trade_state,
+        seller_trade_state,
+        free_trade_state,
+        listing_config,
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, &ah.authority.pubkey());
+    accounts.extend(creator_remaining_accounts(ah, nft));
+    accounts.extend(fee_schedule_remaining_accounts(ah, nft));
+    accounts.extend(collection_stats_remaining_accounts(ah, nft));
+    accounts.extend(trader_stats_remaining_accounts(ah, buyer, seller));
+
+    let data = auction_house_v2::instruction::ExecuteDutchSale {
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price,
+        token_size: 1,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Cancel `wallet`'s dutch listing of `nft`, signed by the wallet.
+pub fn cancel_dutch_listing_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    nft: &TestNft,
+) -> Instruction {
+    let trade_state = dutch_trade_state_address(ah, wallet, nft);
+    let (listing_config, _) = find_listing_config_address(&trade_state);
+    let mut accounts = auction_house_v2::accounts::CancelDutchListing {
+        wallet: *wallet,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        trade_state,
+        listing_config,
+        token_program: nft.token_program,
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::CancelDutchListing {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Receipt for the listing created by the preceding instruction in the same transaction.
+pub fn print_listing_receipt_ix(bookkeeper: &Pubkey, seller_trade_state: &Pubkey) -> Instruction {
+    let (receipt, receipt_bump) = find_listing_receipt_address(seller_trade_state);
+    let accounts = auction_house_v2::accounts::PrintListingReceipt {
+        receipt,
+        bookkeeper: *bookkeeper,
+        system_program: solana_sdk::system_program::id(),
+        rent: sysvar::rent::id(),
+        instruction: sysvar::instructions::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::PrintListingReceipt { receipt_bump }.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Mark the receipt of the listing canceled by the preceding instruction in the same transaction.
+pub fn cancel_listing_receipt_ix(seller_trade_state: &Pubkey) -> Instruction {
+    let accounts = auction_house_v2::accounts::CancelListingReceipt {
+        receipt: find_listing_receipt_address(seller_trade_state).0,
+        system_program: solana_sdk::system_program::id(),
+        instruction: sysvar::instructions::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::CancelListingReceipt {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Receipt for the sale settled by the preceding instruction in the same transaction.
+/// Dutch sales also pass their `listing_config`.
+pub fn print_purchase_receipt_ix(
+    bookkeeper: &Pubkey,
+    seller_trade_state: &Pubkey,
+    buyer_trade_state: &Pubkey,
+    listing_config: Option<&Pubkey>,
+) -> Instruction {
+    let (purchase_receipt, purchase_receipt_bump) =
+        find_purchase_receipt_address(seller_trade_state, buyer_trade_state);
+    let (listing_receipt, _) = find_listing_receipt_address(seller_trade_state);
+    let (bid_receipt, _) = find_bid_receipt_address(buyer_trade_state);
+    let mut accounts = auction_house_v2::accounts::PrintPurchaseReceipt {
+        purchase_receipt,
+        listing_receipt,
+        bid_receipt,
+        bookkeeper: *bookkeeper,
+        system_program: solana_sdk::system_program::id(),
+        rent: sysvar::rent::id(),
+        instruction: sysvar::instructions::id(),
+    }
+    .to_account_metas(None);
+    if let Some(listing_config) = listing_config {
+        accounts.push(AccountMeta::new_readonly(*listing_config, false));
+    }
+    let data = auction_house_v2::instruction::PrintPurchaseReceipt {
+        purchase_receipt_bump,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn collection_bid_address(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    collection: &Pubkey,
+    price: u64,
+) -> Pubkey {
+    find_collection_bid_address(wallet, &ah.address, collection, price).0
+}
+
+/// Bid `price` per token for `quantity` tokens of `collection`, signed by the wallet.
+pub fn collection_buy_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    collection: &Pubkey,
+    price: u64,
+    quantity: u64,
+) -> Instruction {
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, wallet);
+    let accounts = auction_house_v2::accounts::CollectionBuy {
+        wallet: *wallet,
+        payment_account: ah.payment_account(wallet),
+        transfer_authority: *wallet,
+        treasury_mint: ah.treasury_mint,
+        collection_mint: *collection,
+        escrow_payment_account,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        collection_bid: collection_bid_address(ah, wallet, collection, price),
+        token_program: spl_token::id(),
+        system_program: solana_sdk::system_program::id(),
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::CollectionBuy {
+        escrow_payment_bump,
+        price,
+        quantity,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Sell `seller`'s listing of `nft` at `price` to `buyer`'s collection bid at that price, signed by the authority.
+pub fn execute_collection_sale_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nft: &TestNft,
+    collection: &Pubkey,
+    price: u64,
+) -> Instruction {
+    let buyer_trade_state = seeded_trade_state_address(ah, buyer, nft, COLLECTION_BID);
+    let seller_trade_state = trade_state_address(ah, seller, nft, price, 1);
+    let (free_trade_state, free_trade_state_bump) = find_trade_state_address(
+        seller,
+        &ah.address,
+        &nft.token_account,
+        &ah.treasury_mint,
+        &nft.mint,
+        0,
+        1,
+    );
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, buyer);
+    let (escrow_nft_account, escrow_nft_bump) =
+        find_escrow_nft_address(&ah.address, seller, &nft.mint, buyer);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+
+    let mut accounts = auction_house_v2::accounts::ExecuteCollectionSale {
+        buyer: *buyer,
+        seller: *seller,
+        token_account: nft.token_account,
+        token_mint: nft.mint,
+        escrow_nft_account,
+        escrow_nft_record: find_escrow_nft_record_address(&escrow_nft_account).0,
+        metadata: nft.metadata,
+        treasury_mint: ah.treasury_mint,
+        escrow_payment_account,
+        seller_payment_receipt_account: ah.payment_account(seller),
+        buyer_receipt_token_account: nft.token_account_of(buyer),
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        auction_house_treasury: ah.treasury,
+        buyer_trade_state,
+        seller_trade_state,
+        free_trade_state,
+        collection_bid: collection_bid_address(ah, buyer, collection, price),
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, &ah.authority.pubkey());
+    accounts.extend(creator_remaining_accounts(ah, nft));
+    accounts.extend(fee_schedule_remaining_accounts(ah, nft));
+    accounts.extend(collection_stats_remaining_accounts(ah, nft));
+    accounts.extend(trader_stats_remaining_accounts(ah, buyer, seller));
+
+    let data = auction_house_v2::instruction::ExecuteCollectionSale {
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        escrow_nft_bump,
+        buyer_price: price,
+        token_size: 1,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Cancel `wallet`'s collection bid at `price`, signed by the wallet.
+pub fn cancel_collection_bid_ix(
+    ah: &TestAuctionHouse,
+    wallet: &Pubkey,
+    collection: &Pubkey,
+    price: u64,
+) -> Instruction {
+    let mut accounts = auction_house_v2::accounts::CancelCollectionBid {
+        wallet: *wallet,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        collection_bid: collection_bid_address(ah, wallet, collection, price),
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, wallet);
+    let data = auction_house_v2::instruction::CancelCollectionBid {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Swap offer PDA of `maker`'s offer of `nft` on `ah`.
+pub fn swap_offer_address(ah: &TestAuctionHouse, maker: &Pubkey, nft: &TestNft) -> Pubkey {
+    find_swap_offer_address(maker, &ah.address, &nft.token_account).0
+}
+
+/// Offer `token_size` of `maker`'s `nft` for `wanted_size` of `wanted`, signed by the maker.
+#[allow(clippy::too_many_arguments)]
+pub fn create_swap_offer_ix(
+    ah: &TestAuctionHouse,
+    maker: &Pubkey,
+    nft: &TestNft,
+    token_size: u64,
+    wanted: SwapTarget,
+    wanted_size: u64,
+    maker_extra: u64,
+    taker_extra: u64,
+) -> Instruction {
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, maker);
+    let (program_as_signer, _) = find_program_as_signer_address();
+    let accounts = auction_house_v2::accounts::CreateSwapOffer {
+        wallet: *maker,
+        token_account: nft.token_account,
+        metadata: nft.metadata,
+        payment_account: ah.payment_account(maker),
+        transfer_authority: *maker,
+        treasury_mint: ah.treasury_mint,
+        escrow_payment_account,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        swap_offer: swap_offer_address(ah, maker, nft),
+        token_program: nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    let data = auction_house_v2::instruction::CreateSwapOffer {
+        escrow_payment_bump,
+        token_size,
+        wanted,
+        wanted_size,
+        maker_extra,
+        taker_extra,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Accept `maker`'s swap offer of `maker_nft` with `taker_nft`, signed by the taker. The extra amounts decide
+/// which creators are passed for royalties.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_swap_ix(
+    ah: &TestAuctionHouse,
+    maker: &Pubkey,
+    maker_nft: &TestNft,
+    taker: &Pubkey,
+    taker_nft: &TestNft,
+    maker_extra: u64,
+    taker_extra: u64,
+) -> Instruction {
+    let (maker_escrow_payment_account, maker_escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, maker);
+    let (taker_escrow_payment_account, taker_escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, taker);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let mut accounts = auction_house_v2::accounts::ExecuteSwap {
+        maker: *maker,
+        taker: *taker,
+        maker_token_account: maker_nft.token_account,
+        maker_token_mint: maker_nft.mint,
+        maker_metadata: maker_nft.metadata,
+        taker_token_account: taker_nft.token_account,
+        taker_token_mint: taker_nft.mint,
+        taker_metadata: taker_nft.metadata,
+        maker_receipt_token_account: taker_nft.token_account_of(maker),
+        taker_receipt_token_account: maker_nft.token_account_of(taker),
+        maker_escrow_payment_account,
+        taker_escrow_payment_account,
+        maker_payment_receipt_account: ah.payment_account(maker),
+        taker_payment_receipt_account: ah.payment_account(taker),
+        treasury_mint: ah.treasury_mint,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        swap_offer: swap_offer_address(ah, maker, maker_nft),
+        token_program: maker_nft.token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    if maker_extra > 0 {
+        accounts.extend(creator_remaining_accounts(ah, taker_nft));
+    }
+    if taker_extra > 0 {
+        accounts.extend(creator_remaining_accounts(ah, maker_nft));
+    }
+    let data = auction_house_v2::instruction::ExecuteSwap {
+        maker_escrow_payment_bump,
+        taker_escrow_payment_bump,
+        program_as_signer_bump,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Cancel `maker`'s swap offer of `nft`, signed by the maker.
+pub fn cancel_swap_offer_ix(ah: &TestAuctionHouse, maker: &Pubkey, nft: &TestNft) -> Instruction {
+    let mut accounts = auction_house_v2::accounts::CancelSwapOffer {
+        maker: *maker,
+        token_account: nft.token_account,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        swap_offer: swap_offer_address(ah, maker, nft),
+        token_program: nft.token_program,
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, maker);
+    let data = auction_house_v2::instruction::CancelSwapOffer {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Bundle listing PDA of `seller`'s bundle starting with `first`.
+pub fn bundle_listing_address(ah: &TestAuctionHouse, seller: &Pubkey, first: &TestNft) -> Pubkey {
+    find_bundle_listing_address(seller, &ah.address, &first.token_account).0
+}
+
+/// List one of each of `nfts` together at `price`, weighted by `weights`, signed by the seller.
+pub fn create_bundle_listing_ix(
+    ah: &TestAuctionHouse,
+    seller: &Pubkey,
+    nfts: &[&TestNft],
+    weights: Vec<u64>,
+    price: u64,
+) -> Instruction {
+    let (program_as_signer, _) = find_program_as_signer_address();
+    let mut accounts = auction_house_v2::accounts::CreateBundleListing {
+        wallet: *seller,
+        first_token_account: nfts[0].token_account,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        bundle_listing: bundle_listing_address(ah, seller, nfts[0]),
+        token_program: nfts[0].token_program,
+        system_program: solana_sdk::system_program::id(),
+        program_as_signer,
+    }
+    .to_account_metas(None);
+    for nft in nfts {
+        accounts.push(AccountMeta::new(nft.token_account, false));
+        accounts.push(AccountMeta::new_readonly(nft.metadata, false));
+    }
+    let data = auction_house_v2::instruction::CreateBundleListing { price, weights }.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Buy `seller`'s bundle of `nfts` at `price`, signed by the buyer.
+pub fn execute_bundle_sale_ix(
+    ah: &TestAuctionHouse,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    nfts: &[&TestNft],
+    price: u64,
+) -> Instruction {
+    let (escrow_payment_account, escrow_payment_bump) =
+        find_escrow_payment_address(&ah.address, buyer);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let mut accounts = auction_house_v2::accounts::ExecuteBundleSale {
+        buyer: *buyer,
+        payment_account: ah.payment_account(buyer),
+        transfer_authority: *buyer,
+        seller: *seller,
+        escrow_payment_account,
+        seller_payment_receipt_account: ah.payment_account(seller),
+        treasury_mint: ah.treasury_mint,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account,
+        auction_house_treasury: ah.treasury,
+        bundle_listing: bundle_listing_address(ah, seller, nfts[0]),
+        token_program: nfts[0].token_program,
+        system_program: solana_sdk::system_program::id(),
+        ata_program: spl_associated_token_account::id(),
+        program_as_signer,
+        rent: sysvar::rent::id(),
+    }
+    .to_account_metas(None);
+    for nft in nfts {
+        accounts.push(AccountMeta::new(nft.token_account, false));
+        accounts.push(AccountMeta::new_readonly(nft.mint, false));
+        accounts.push(AccountMeta::new_readonly(nft.metadata, false));
+        accounts.push(AccountMeta::new(nft.token_account_of(buyer), false));
+        accounts.extend(creator_remaining_accounts(ah, nft));
+    }
+    let data = auction_house_v2::instruction::ExecuteBundleSale {
+        escrow_payment_bump,
+        program_as_signer_bump,
+        price,
+    }
+    .data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Cancel `seller`'s bundle of `nfts`, signed by the seller.
+pub fn cancel_bundle_listing_ix(
+    ah: &TestAuctionHouse,
+    seller: &Pubkey,
+    nfts: &[&TestNft],
+) -> Instruction {
+    let mut accounts = auction_house_v2::accounts::CancelBundleListing {
+        seller: *seller,
+        authority: ah.authority.pubkey(),
+        auction_house: ah.address,
+        bundle_listing: bundle_listing_address(ah, seller, nfts[0]),
+        token_program: nfts[0].token_program,
+    }
+    .to_account_metas(None);
+    mark_signer(&mut accounts, seller);
+    for nft in nfts {
+        accounts.push(AccountMeta::new(nft.token_account, false));
+    }
+    let data = auction_house_v2::instruction::CancelBundleListing {}.data();
+    Instruction {
+        program_id: auction_house_v2::id(),
+        accounts,
+        data,
+    }
+}
+
+/// Parties and NFT of a sale settled through `execute_sale`.
+pub struct TestSale {
+    pub seller: Keypair,
+    pub buyer: Keypair,
+    pub nft: TestNft,
+}
+
+/// List a fresh royalty-free NFT on a native house, bid on it and settle the sale as the authority.
+pub async fn execute_native_sale(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    price: u64,
+) -> TestSale {
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), ONE_SOL + price).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let nft = create_nft(context, &seller.pubkey(), &[], 0, 1).await;
+
+    let sell = sell_ix(ah, &seller.pubkey(), &nft, price, 1);
+    process_tx(context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(ah, &buyer.pubkey(), &nft, price, 1);
+    process_tx(context, &[buy], &[&buyer]).await.unwrap();
+    let execute_sale = execute_sale_ix(ah, &buyer.pubkey(), &seller.pubkey(), &nft, price, 1);
+    process_tx(context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+
+    TestSale { seller, buyer, nft }
+}
+
+/// Assert that a transaction failed with the given `AuctionHouseError`.
+pub fn assert_auction_house_error(
+    result: Result<(), TransportError>,
+    error: auction_house_v2::errors::AuctionHouseError,
+) {
+    assert_custom_error(result, anchor_lang::error::ERROR_CODE_OFFSET + error as u32);
+}
+
+/// Assert that a transaction failed with the given Token Metadata error.
+pub fn assert_metadata_error(
+    result: Result<(), TransportError>,
+    error: mpl_token_metadata::error::MetadataError,
+) {
+    assert_custom_error(result, error as u32);
+}
+
+/// Assert that a transaction failed with the given Anchor framework error.
+pub fn assert_anchor_error(
+    result: Result<(), TransportError>,
+    error: anchor_lang::error::ErrorCode,
+) {
+    assert_custom_error(result, error as u32);
+}
+
+fn assert_custom_error(result: Result<(), TransportError>, expected: u32) {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+    match result {
+        Err(TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, expected),
+        other => panic!("expected custom error {}, got {:?}", expected, other),
+    }
+}
@@ -0,0 +1,139 @@
+mod utils;
+
+use auction_house_v2::{errors::AuctionHouseError, UnverifiedCreatorPolicy};
+use mpl_token_metadata::state::{Metadata, TokenMetadataAccount};
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use utils::*;
+
+const ROYALTY_BPS: u16 = 1_000;
+const PRICE: u64 = ONE_SOL;
+
+/// Native fee-free house handling unverified creators with `policy`, a funded buyer, and a seller holding an NFT
+/// whose royalty is split between a verified creator (60%) and an unverified one (40%).
+async fn setup(
+    context: &mut ProgramTestContext,
+    policy: UnverifiedCreatorPolicy,
+) -> (TestAuctionHouse, Keypair, Keypair, TestNft) {
+    let ah = create_auction_house(context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(context, &ah.authority.pubkey(), ONE_SOL).await;
+    airdrop(context, &ah.fee_account, ONE_SOL).await;
+    let update = AuctionHouseUpdate {
+        unverified_creator_policy: Some(policy),
+        ..AuctionHouseUpdate::unchanged(&ah)
+    };
+    let payer = context.payer.pubkey();
+    let ix = update_auction_house_ix(&ah, &payer, &ah.authority.pubkey(), &update);
+    process_tx(context, &[ix], &[&ah.authority]).await.unwrap();
+
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    airdrop(context, &seller.pubkey(), ONE_SOL).await;
+    airdrop(context, &buyer.pubkey(), 2 * ONE_SOL).await;
+    let verified = Keypair::new().pubkey();
+    let unverified = Keypair::new().pubkey();
+    airdrop(context, &verified, ONE_SOL).await;
+    airdrop(context, &unverified, ONE_SOL).await;
+    let nft = create_nft(
+        context,
+        &seller.pubkey(),
+        &[(verified, 60), (unverified, 40)],
+        ROYALTY_BPS,
+        1,
+    )
+    .await;
+    // Signing the metadata as a creator needs the creator's key, so set the flag directly.
+    let mut account = get_account(context, &nft.metadata).await.unwrap();
+    let mut metadata = Metadata::safe_deserialize(&account.data).unwrap();
+    metadata.data.creators.as_mut().unwrap()[0].verified = true;
+    metadata.save(&mut account.data).unwrap();
+    context.set_account(&nft.metadata, &account.into());
+
+    let sell = sell_ix(&ah, &seller.pubkey(), &nft, PRICE, 1);
+    process_tx(context, &[sell], &[&seller]).await.unwrap();
+    let buy = buy_ix(&ah, &buyer.pubkey(), &nft, PRICE, 1);
+    process_tx(context, &[buy], &[&buyer]).await.unwrap();
+    (ah, seller, buyer, nft)
+}
+
+/// Lamports each creator of `nft` and the seller gained from settling the sale, leaving out the listing rent refunded
+/// to the seller.
+async fn settle(
+    context: &mut ProgramTestContext,
+    ah: &TestAuctionHouse,
+    seller: &Keypair,
+    buyer: &Keypair,
+    nft: &TestNft,
+) -> (u64, u64, u64) {
+    let verified_before = get_lamports(context, &nft.creators[0]).await;
+    let unverified_before = get_lamports(context, &nft.creators[1]).await;
+    let seller_before = get_lamports(context, &seller.pubkey()).await;
+    let listing = trade_state_address(ah, &seller.pubkey(), nft, PRICE, 1);
+    let listing_rent = get_lamports(context, &listing).await;
+    let execute_sale = execute_sale_ix(ah, &buyer.pubkey(), &seller.pubkey(), nft, PRICE, 1);
+    process_tx(context, &[execute_sale], &[&ah.authority])
+        .await
+        .unwrap();
+    (
+        get_lamports(context, &nft.creators[0]).await - verified_before,
+        get_lamports(context, &nft.creators[1]).await - unverified_before,
+        get_lamports(context, &seller.pubkey()).await - seller_before - listing_rent,
+    )
+}
+
+#[tokio::test]
+async fn pay_all_pays_unverified_creators() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nft) = setup(&mut context, UnverifiedCreatorPolicy::PayAll).await;
+
+    let (verified, unverified, _) = settle(&mut context, &ah, &seller, &buyer, &nft).await;
+
+    let royalty = PRICE * ROYALTY_BPS as u64 / 10_000;
+    assert_eq!(verified, royalty * 60 / 100);
+    assert_eq!(unverified, royalty * 40 / 100);
+}
+
+#[tokio::test]
+async fn redistribute_pays_verified_creators_the_whole_royalty() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nft) = setup(&mut context, UnverifiedCreatorPolicy::Redistribute).await;
+
+    let (verified, unverified, _) = settle(&mut context, &ah, &seller, &buyer, &nft).await;
+
+    assert_eq!(verified, PRICE * ROYALTY_BPS as u64 / 10_000);
+    assert_eq!(unverified, 0);
+}
+
+#[tokio::test]
+async fn refund_seller_returns_unverified_shares() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nft) = setup(&mut context, UnverifiedCreatorPolicy::RefundSeller).await;
+    let (verified, unverified, seller_gain) =
+        settle(&mut context, &ah, &seller, &buyer, &nft).await;
+
+    let royalty = PRICE * ROYALTY_BPS as u64 / 10_000;
+    assert_eq!(verified, royalty * 60 / 100);
+    assert_eq!(unverified, 0);
+    assert_eq!(seller_gain, PRICE - royalty * 60 / 100);
+}
+
+#[tokio::test]
+async fn sale_rejects_creator_accounts_out_of_order() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let (ah, seller, buyer, nft) = setup(&mut context, UnverifiedCreatorPolicy::PayAll).await;
+
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    let first_creator = execute_sale
+        .accounts
+        .iter()
+        .position(|account| account.pubkey == nft.creators[0])
+        .unwrap();
+    execute_sale.accounts.swap(first_creator, first_creator + 1);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::CreatorAccountMismatch);
+
+    let mut execute_sale = execute_sale_ix(&ah, &buyer.pubkey(), &seller.pubkey(), &nft, PRICE, 1);
+    execute_sale.accounts.truncate(first_creator + 1);
+    let result = process_tx(&mut context, &[execute_sale], &[&ah.authority]).await;
+    assert_auction_house_error(result, AuctionHouseError::CreatorAccountMismatch);
+}
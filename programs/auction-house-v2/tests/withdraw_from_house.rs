@@ -0,0 +1,123 @@
+mod utils;
+
+use anchor_lang::error::ErrorCode;
+use solana_program_test::*;
+use solana_sdk::{
+    rent::Rent,
+    signature::{Keypair, Signer},
+};
+use utils::*;
+
+#[tokio::test]
+async fn authority_withdraws_from_fee_account() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(&mut context, &ah.fee_account, 2 * ONE_SOL).await;
+
+    let withdraw = withdraw_from_fee_ix(&ah, &ah.authority.pubkey(), ONE_SOL);
+    process_tx(&mut context, &[withdraw], &[&ah.authority])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_lamports(&mut context, &ah.fee_withdrawal_destination).await,
+        ONE_SOL
+    );
+    assert_eq!(get_lamports(&mut context, &ah.fee_account).await, ONE_SOL);
+}
+
+#[tokio::test]
+async fn fee_withdrawal_leaves_rent_exempt_minimum() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    let balance = get_lamports(&mut context, &ah.fee_account).await;
+
+    let withdraw = withdraw_from_fee_ix(&ah, &ah.authority.pubkey(), balance);
+    process_tx(&mut context, &[withdraw], &[&ah.authority])
+        .await
+        .unwrap();
+
+    let rent_minimum = Rent::default().minimum_balance(0);
+    assert_eq!(
+        get_lamports(&mut context, &ah.fee_account).await,
+        rent_minimum
+    );
+    assert_eq!(
+        get_lamports(&mut context, &ah.fee_withdrawal_destination).await,
+        balance - rent_minimum
+    );
+}
+
+#[tokio::test]
+async fn authority_withdraws_native_treasury() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(&mut context, &ah.treasury, 2 * ONE_SOL).await;
+    let balance = get_lamports(&mut context, &ah.treasury).await;
+
+    let withdraw = withdraw_from_treasury_ix(&ah, &ah.authority.pubkey(), ONE_SOL);
+    process_tx(&mut context, &[withdraw], &[&ah.authority])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_lamports(&mut context, &ah.treasury_withdrawal_destination).await,
+        ONE_SOL
+    );
+    assert_eq!(
+        get_lamports(&mut context, &ah.treasury).await,
+        balance - ONE_SOL
+    );
+}
+
+#[tokio::test]
+async fn authority_withdraws_spl_treasury() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let mint_authority = Keypair::new();
+    let treasury_mint = create_mint(&mut context, &mint_authority.pubkey(), 6).await;
+    let ah = create_auction_house(&mut context, &treasury_mint.pubkey(), 0, false, false).await;
+    mint_to(
+        &mut context,
+        &ah.treasury_mint,
+        &mint_authority,
+        &ah.treasury,
+        5_000_000,
+    )
+    .await;
+
+    let withdraw = withdraw_from_treasury_ix(&ah, &ah.authority.pubkey(), 3_000_000);
+    process_tx(&mut context, &[withdraw], &[&ah.authority])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_token_balance(&mut context, &ah.treasury_withdrawal_destination).await,
+        3_000_000
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &ah.treasury).await,
+        2_000_000
+    );
+}
+
+#[tokio::test]
+async fn only_the_authority_can_withdraw() {
+    let mut context = auction_house_program_test().start_with_context().await;
+    let ah =
+        create_auction_house(&mut context, &spl_token::native_mint::id(), 0, false, false).await;
+    airdrop(&mut context, &ah.fee_account, ONE_SOL).await;
+    airdrop(&mut context, &ah.treasury, ONE_SOL).await;
+    let impostor = Keypair::new();
+
+    let withdraw = withdraw_from_fee_ix(&ah, &impostor.pubkey(), ONE_SOL / 2);
+    let result = process_tx(&mut context, &[withdraw], &[&impostor]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintHasOne);
+
+    let withdraw = withdraw_from_treasury_ix(&ah, &impostor.pubkey(), ONE_SOL / 2);
+    let result = process_tx(&mut context, &[withdraw], &[&impostor]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintHasOne);
+}
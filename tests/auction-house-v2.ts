@@ -1,16 +1,76 @@
 import * as anchor from "@project-serum/anchor";
 import { Program } from "@project-serum/anchor";
+import { PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
+import { expect } from "chai";
 import { AuctionHouseV2 } from "../target/types/auction_house_v2";
 
+// The full trade lifecycle is covered by the solana-program-test suite in
+// programs/auction-house-v2/tests; this only smoke-tests a deployed build.
+const TOKEN_PROGRAM_ID = new PublicKey(
+  "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+);
+const ASSOCIATED_TOKEN_PROGRAM_ID = new PublicKey(
+  "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
+);
+const NATIVE_MINT = new PublicKey(
+  "So11111111111111111111111111111111111111112"
+);
+
 describe("auction-house-v2", () => {
   // Configure the client to use the local cluster.
   anchor.setProvider(anchor.AnchorProvider.env());
 
   const program = anchor.workspace.AuctionHouseV2 as Program<AuctionHouseV2>;
+  const wallet = (program.provider as anchor.AnchorProvider).wallet;
+
+  it("creates a native auction house", async () => {
+    const [auctionHouse, bump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("auction_house"),
+        wallet.publicKey.toBuffer(),
+        NATIVE_MINT.toBuffer(),
+      ],
+      program.programId
+    );
+    const [feeAccount, feePayerBump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("auction_house"),
+        auctionHouse.toBuffer(),
+        Buffer.from("fee_payer"),
+      ],
+      program.programId
+    );
+    const [treasury, treasuryBump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("auction_house"),
+        auctionHouse.toBuffer(),
+        Buffer.from("treasury"),
+      ],
+      program.programId
+    );
+
+    await program.methods
+      .createAuctionHouse(bump, feePayerBump, treasuryBump, 250, false, false)
+      .accounts({
+        treasuryMint: NATIVE_MINT,
+        payer: wallet.publicKey,
+        authority: wallet.publicKey,
+        feeWithdrawalDestination: wallet.publicKey,
+        treasuryWithdrawalDestination: wallet.publicKey,
+        treasuryWithdrawalDestinationOwner: wallet.publicKey,
+        auctionHouse,
+        auctionHouseFeeAccount: feeAccount,
+        auctionHouseTreasury: treasury,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        ataProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+    const account = await program.account.auctionHouse.fetch(auctionHouse);
+    expect(account.authority.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(account.treasuryMint.toBase58()).to.equal(NATIVE_MINT.toBase58());
+    expect(account.sellerFeeBasisPoints).to.equal(250);
   });
 });